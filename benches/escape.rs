@@ -0,0 +1,93 @@
+//! Benchmarks for string escaping.
+//!
+//! Run with `cargo bench --bench escape`, add `--features simd` for the SSE2 version.
+
+use json_writer::{
+	AsciiOnly, DefaultEscaping, EscapeProfile, JSONWriter, JSONWriterValue, PreEscaped,
+	ProfiledWriter,
+};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+fn bench(name: &str, bytes_per_iteration: usize, mut f: impl FnMut()) {
+	// Warm up
+	for _ in 0..100 {
+		f();
+	}
+	let mut iterations: u64 = 0;
+	let start = Instant::now();
+	while start.elapsed() < Duration::from_secs(1) {
+		for _ in 0..100 {
+			f();
+		}
+		iterations += 100;
+	}
+	let elapsed = start.elapsed();
+	let per_iteration = elapsed / iterations as u32;
+	let throughput = (bytes_per_iteration as f64 * iterations as f64)
+		/ elapsed.as_secs_f64()
+		/ (1024.0 * 1024.0);
+	println!("{name:<32} {per_iteration:>12?}/iter {throughput:>10.1} MiB/s");
+}
+
+fn main() {
+	let clean: String = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. "
+		.chars()
+		.cycle()
+		.take(4096)
+		.collect();
+	let escapes: String = "\"\\\n\t\u{1}".chars().cycle().take(4096).collect();
+	let mixed: String = "Grüße aus Köln, 中文 \"quoted\"\n"
+		.chars()
+		.cycle()
+		.take(4096)
+		.collect();
+
+	let large_clean = clean.repeat(256);
+	let large_mixed = mixed.repeat(256);
+
+	let mut buffer = String::with_capacity(4 * 1024 * 1024);
+	for (name, input) in [
+		("clean 4 KiB", &clean),
+		("all escapes 4 KiB", &escapes),
+		("mixed utf-8 4 KiB", &mixed),
+		("clean 1 MiB", &large_clean),
+		("mixed utf-8 1 MiB", &large_mixed),
+	] {
+		bench(name, input.len(), || {
+			buffer.clear();
+			json_writer::write_string(&mut buffer, black_box(input));
+			black_box(&buffer);
+		});
+		bench_profile(&format!("{name} DefaultEscaping"), input, DefaultEscaping);
+		bench_profile(&format!("{name} AsciiOnly"), input, AsciiOnly);
+	}
+
+	let ids: Vec<String> = (0..1000u64)
+		.map(|i| format!("{:016x}", i.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+		.collect();
+	let ids_len = ids.iter().map(String::len).sum();
+	bench("1000 hex ids", ids_len, || {
+		buffer.clear();
+		for id in &ids {
+			buffer.json_string(black_box(id));
+		}
+		black_box(&buffer);
+	});
+	bench("1000 hex ids PreEscaped", ids_len, || {
+		buffer.clear();
+		for id in &ids {
+			PreEscaped(black_box(id)).write_json(&mut buffer);
+		}
+		black_box(&buffer);
+	});
+}
+
+fn bench_profile<P: EscapeProfile + Copy>(name: &str, input: &str, profile: P) {
+	let mut buffer = String::with_capacity(8 * 1024 * 1024);
+	bench(name, input.len(), || {
+		buffer.clear();
+		ProfiledWriter::new(&mut buffer, profile).json_string(black_box(input));
+		black_box(&buffer);
+	});
+}
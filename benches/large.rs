@@ -0,0 +1,154 @@
+//! Benchmarks for writing large values.
+//!
+//! Run with `cargo bench --bench large`.
+
+use json_writer::{
+	to_json_string, write_string, JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue,
+	Matrix, PrettyJSONWriter, StringCache,
+};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn bench(name: &str, mut f: impl FnMut()) {
+	f();
+	let mut iterations: u32 = 0;
+	let start = Instant::now();
+	while start.elapsed() < Duration::from_secs(2) {
+		f();
+		iterations += 1;
+	}
+	println!("{name:<40} {:>12?}/iter", start.elapsed() / iterations);
+}
+
+fn write_nested<W: JSONWriter>(array: &mut JSONArrayWriter<'_, W>, depth: u32) {
+	for i in 0..2000 / depth {
+		array.value(i);
+	}
+	if depth > 1 {
+		write_nested(&mut array.array(), depth - 1);
+	}
+}
+
+fn write_fixed_shape<W: JSONWriter>(object: &mut JSONObjectWriter<'_, W>, i: u32) {
+	object.value("id", i);
+	object.value("name", "sensor");
+	object.value("active", i.is_multiple_of(2));
+	object.value("value", f64::from(i) * 0.5);
+	object.value("unit", "celsius");
+}
+
+fn main() {
+	let large: String = "Lorem ipsum dolor sit amet,\n\"consectetur\" adipiscing elit. "
+		.chars()
+		.cycle()
+		.take(16 * 1024 * 1024)
+		.collect();
+	bench("16 MiB string", || {
+		let mut buffer = String::new();
+		write_string(&mut buffer, black_box(&large));
+		black_box(buffer);
+	});
+
+	let medium: Vec<String> = (0..100_000)
+		.map(|i| format!("medium sized string number {i} with a \"quote\""))
+		.collect();
+	bench("100k medium strings", || {
+		black_box(to_json_string(black_box(&medium)));
+	});
+
+	let label: Arc<str> = Arc::from("a repeated category label with \"quotes\"");
+	bench("100k repeated value", || {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		for _ in 0..100_000 {
+			array.value(black_box(&*label));
+		}
+		array.end();
+		black_box(buffer);
+	});
+	bench("100k repeated value interned", || {
+		let mut cache = StringCache::new();
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		for _ in 0..100_000 {
+			array.value_interned(black_box(&label), &mut cache);
+		}
+		array.end();
+		black_box(buffer);
+	});
+
+	let numbers: Vec<u32> = (0..10_000_000u32)
+		.map(|i| i.wrapping_mul(2_654_435_761))
+		.collect();
+	let mut buffer = String::new();
+	bench("10M u32 slice", || {
+		buffer.clear();
+		black_box(&numbers[..]).write_json(&mut buffer);
+		black_box(&buffer);
+	});
+
+	let matrix: Vec<Vec<f64>> = (0..1000)
+		.map(|row| {
+			(0..1000)
+				.map(|col| (row * 1000 + col) as f64 / 7.0)
+				.collect()
+		})
+		.collect();
+	bench("1000x1000 f64 nested slices", || {
+		buffer.clear();
+		black_box(&matrix).write_json(&mut buffer);
+		black_box(&buffer);
+	});
+	bench("1000x1000 f64 Matrix", || {
+		buffer.clear();
+		Matrix(black_box(&matrix)).write_json(&mut buffer);
+		black_box(&buffer);
+	});
+
+	bench("100k fixed-shape objects", || {
+		for i in 0..100_000u32 {
+			let mut buffer = String::new();
+			write_fixed_shape(&mut JSONObjectWriter::new(&mut buffer), black_box(i));
+			black_box(buffer);
+		}
+	});
+	bench("100k fixed-shape objects with hint", || {
+		for i in 0..100_000u32 {
+			let mut buffer = String::new();
+			write_fixed_shape(
+				&mut JSONObjectWriter::with_field_hint(&mut buffer, 5),
+				black_box(i),
+			);
+			black_box(buffer);
+		}
+	});
+
+	bench("1M small nested writers", || {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		for i in 0..1_000_000u32 {
+			let mut object = array.object();
+			object.array("tags").value(black_box(i));
+			object.string_writer("name").push_str("n");
+		}
+		drop(array);
+		black_box(buffer);
+	});
+
+	bench("pretty deep and wide document", || {
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		write_nested(&mut JSONArrayWriter::new(&mut pretty), 30);
+		black_box(buffer);
+	});
+
+	let small = "small";
+	bench("small string x 1000", || {
+		for _ in 0..1000 {
+			let mut buffer = String::new();
+			write_string(&mut buffer, black_box(small));
+			black_box(buffer);
+		}
+	});
+}
@@ -0,0 +1,61 @@
+//! Benchmarks for parallel array serialization.
+//!
+//! Run with `cargo bench --features rayon --bench parallel`.
+
+use json_writer::{JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+fn bench(name: &str, mut f: impl FnMut()) {
+	f();
+	let mut iterations: u32 = 0;
+	let start = Instant::now();
+	while start.elapsed() < Duration::from_secs(2) {
+		f();
+		iterations += 1;
+	}
+	println!("{name:<40} {:>12?}/iter", start.elapsed() / iterations);
+}
+
+struct Reading {
+	sensor: u32,
+	label: String,
+	values: [f64; 4],
+}
+
+impl JSONWriterValue for &Reading {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("sensor", self.sensor);
+		object.value("label", &self.label);
+		object.value("values", &self.values[..]);
+	}
+}
+
+fn main() {
+	let readings: Vec<Reading> = (0..1_000_000u32)
+		.map(|i| Reading {
+			sensor: i,
+			label: format!("sensor \"{}\"", i % 100),
+			values: [i as f64 / 3.0, i as f64 * 1.5, -(i as f64), 0.1],
+		})
+		.collect();
+	println!("threads: {}", rayon::current_num_threads());
+
+	bench("1M structs sequential", || {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		for reading in black_box(&readings) {
+			array.value(reading);
+		}
+		array.end();
+		black_box(buffer);
+	});
+	bench("1M structs par_values", || {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.par_values(black_box(&readings));
+		array.end();
+		black_box(buffer);
+	});
+}
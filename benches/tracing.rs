@@ -0,0 +1,133 @@
+//! Benchmarks for JSON log lines, compared to the serde based formatter of tracing-subscriber.
+//!
+//! Run with `cargo bench --features tracing --bench tracing`.
+
+use json_writer::{format_event, ElapsedMicros, SpanFields};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::hint::black_box;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tracing::span;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Counts all allocations to compare the formatters
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout);
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+	static LINE: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Formats events with `format_event` into a reused buffer and writes them to a sink
+struct JsonLayer {
+	start: Instant,
+}
+
+impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for JsonLayer {
+	fn on_new_span(&self, attributes: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+		let span = ctx.span(id).unwrap();
+		span.extensions_mut().insert(SpanFields::new(attributes));
+	}
+
+	fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+		let scope: Vec<_> = ctx
+			.event_scope(event)
+			.into_iter()
+			.flat_map(|scope| scope.from_root())
+			.collect();
+		let extensions: Vec<_> = scope.iter().map(|span| span.extensions()).collect();
+		let spans = extensions
+			.iter()
+			.filter_map(|extensions| extensions.get::<SpanFields>());
+		LINE.with_borrow_mut(|line| {
+			line.clear();
+			format_event(line, ElapsedMicros(self.start.elapsed()), spans, event);
+			line.push('\n');
+			std::io::sink().write_all(line.as_bytes()).unwrap();
+		});
+	}
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Request {
+	method: &'static str,
+	retries: u32,
+}
+
+fn log_events() {
+	let span = tracing::info_span!("request", id = 42u64, path = "/api/items");
+	let _span = span.enter();
+	for i in 0..1000u64 {
+		tracing::info!(
+			count = i,
+			ok = true,
+			ratio = 0.25f64,
+			name = "item \"x\"",
+			request = ?Request { method: "GET", retries: 2 },
+			"processed item {}", i
+		);
+	}
+}
+
+fn bench(name: &str, subscriber: impl tracing::Subscriber + Send + Sync + 'static) {
+	tracing::subscriber::with_default(subscriber, || {
+		log_events();
+		let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+		log_events();
+		let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+		let mut iterations: u32 = 0;
+		let start = Instant::now();
+		while start.elapsed() < Duration::from_secs(2) {
+			log_events();
+			iterations += 1;
+		}
+		let per_event = start.elapsed() / iterations / 1000;
+		println!(
+			"{name:<40} {per_event:>10?}/event {:>6.1} allocations/event",
+			allocations as f64 / 1000.0
+		);
+	});
+}
+
+fn main() {
+	bench(
+		"json_writer format_event",
+		tracing_subscriber::registry().with(JsonLayer {
+			start: Instant::now(),
+		}),
+	);
+	bench(
+		"tracing-subscriber json",
+		tracing_subscriber::fmt()
+			.json()
+			.with_current_span(false)
+			.with_span_list(true)
+			.with_writer(|| black_box(std::io::sink()))
+			.finish(),
+	);
+}
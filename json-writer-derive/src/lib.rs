@@ -0,0 +1,195 @@
+//! Derive macros for [json-writer](https://crates.io/crates/json-writer).
+//!
+//! Use them through the `derive` feature of `json-writer`, which re-exports them next to the traits they implement.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+///
+/// Implements `JSONEnumString` and `JSONWriterValue` for an enum with unit variants.
+///
+/// Each variant is written as its name, converted with `#[json(rename_all = "...")]` on the enum
+/// or replaced with `#[json(rename = "...")]` on the variant. `rename_all` accepts the cases of serde:
+/// `lowercase`, `UPPERCASE`, `PascalCase`, `camelCase`, `snake_case`, `SCREAMING_SNAKE_CASE`,
+/// `kebab-case` and `SCREAMING-KEBAB-CASE`.
+///
+/// Variants with fields are rejected with a compile error.
+///
+#[proc_macro_derive(JSONEnumString, attributes(json))]
+pub fn derive_json_enum_string(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	expand_json_enum_string(&input)
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}
+
+fn expand_json_enum_string(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+	let Data::Enum(data) = &input.data else {
+		return Err(syn::Error::new_spanned(
+			&input.ident,
+			"JSONEnumString can only be derived for enums",
+		));
+	};
+	let rename_all = match json_attribute(&input.attrs, "rename_all")? {
+		Some(rule) => Some(RenameRule::parse(&rule)?),
+		None => None,
+	};
+	let mut arms = Vec::with_capacity(data.variants.len());
+	for variant in &data.variants {
+		if !matches!(variant.fields, Fields::Unit) {
+			return Err(syn::Error::new_spanned(
+				variant,
+				format!(
+					"JSONEnumString can only be derived for enums with unit variants, `{}` has fields",
+					variant.ident
+				),
+			));
+		}
+		let ident = &variant.ident;
+		let name = match json_attribute(&variant.attrs, "rename")? {
+			Some(name) => name.value(),
+			None => match rename_all {
+				Some(rule) => rule.apply(&ident.to_string()),
+				None => ident.to_string(),
+			},
+		};
+		let name = LitStr::new(&name, Span::call_site());
+		arms.push(quote!(Self::#ident => #name,));
+	}
+
+	let ident = &input.ident;
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	Ok(quote! {
+		impl #impl_generics ::json_writer::JSONEnumString for #ident #type_generics #where_clause {
+			fn as_json_str(&self) -> &'static str {
+				match self {
+					#(#arms)*
+				}
+			}
+		}
+
+		impl #impl_generics ::json_writer::JSONWriterValue for #ident #type_generics #where_clause {
+			#[inline(always)]
+			fn write_json<W: ::json_writer::JSONWriter>(self, writer: &mut W) {
+				writer.json_string(::json_writer::JSONEnumString::as_json_str(&self));
+			}
+		}
+	})
+}
+
+/// Returns the value of `#[json(name = "...")]`, rejecting unknown and repeated options
+fn json_attribute(attrs: &[syn::Attribute], name: &str) -> syn::Result<Option<LitStr>> {
+	let mut result = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+		attr.parse_nested_meta(|meta| {
+			if !meta.path.is_ident(name) {
+				return Err(meta.error(format!("unknown json option, expected `{name}`")));
+			}
+			if result.is_some() {
+				return Err(meta.error(format!("duplicate json option `{name}`")));
+			}
+			result = Some(meta.value()?.parse::<LitStr>()?);
+			Ok(())
+		})?;
+	}
+	Ok(result)
+}
+
+/// Case conversion of `rename_all`
+#[derive(Copy, Clone)]
+enum RenameRule {
+	Lower,
+	Upper,
+	Pascal,
+	Camel,
+	Snake,
+	ScreamingSnake,
+	Kebab,
+	ScreamingKebab,
+}
+
+impl RenameRule {
+	fn parse(rule: &LitStr) -> syn::Result<RenameRule> {
+		Ok(match rule.value().as_str() {
+			"lowercase" => RenameRule::Lower,
+			"UPPERCASE" => RenameRule::Upper,
+			"PascalCase" => RenameRule::Pascal,
+			"camelCase" => RenameRule::Camel,
+			"snake_case" => RenameRule::Snake,
+			"SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+			"kebab-case" => RenameRule::Kebab,
+			"SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+			_ => return Err(syn::Error::new_spanned(
+				rule,
+				"unknown rename_all case, expected one of `lowercase`, `UPPERCASE`, `PascalCase`, \
+					 `camelCase`, `snake_case`, `SCREAMING_SNAKE_CASE`, `kebab-case` or `SCREAMING-KEBAB-CASE`",
+			)),
+		})
+	}
+
+	/// Converts a variant name, which is expected to be in PascalCase
+	fn apply(self, variant: &str) -> String {
+		match self {
+			RenameRule::Lower => variant.to_ascii_lowercase(),
+			RenameRule::Upper => variant.to_ascii_uppercase(),
+			RenameRule::Pascal => variant.to_owned(),
+			RenameRule::Camel => {
+				let mut chars = variant.chars();
+				chars
+					.next()
+					.map(|first| first.to_ascii_lowercase())
+					.into_iter()
+					.chain(chars)
+					.collect()
+			}
+			RenameRule::Snake => snake_case(variant),
+			RenameRule::ScreamingSnake => snake_case(variant).to_ascii_uppercase(),
+			RenameRule::Kebab => snake_case(variant).replace('_', "-"),
+			RenameRule::ScreamingKebab => {
+				snake_case(variant).to_ascii_uppercase().replace('_', "-")
+			}
+		}
+	}
+}
+
+/// Inserts '_' before every uppercase letter but the first and lowercases them, like serde
+fn snake_case(variant: &str) -> String {
+	let mut result = String::with_capacity(variant.len() + 4);
+	for (index, c) in variant.char_indices() {
+		if c.is_uppercase() {
+			if index > 0 {
+				result.push('_');
+			}
+			result.push(c.to_ascii_lowercase());
+		} else {
+			result.push(c);
+		}
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rename_rules() {
+		let cases = [
+			(RenameRule::Lower, "httpstatus"),
+			(RenameRule::Upper, "HTTPSTATUS"),
+			(RenameRule::Pascal, "HttpStatus"),
+			(RenameRule::Camel, "httpStatus"),
+			(RenameRule::Snake, "http_status"),
+			(RenameRule::ScreamingSnake, "HTTP_STATUS"),
+			(RenameRule::Kebab, "http-status"),
+			(RenameRule::ScreamingKebab, "HTTP-STATUS"),
+		];
+		for (rule, expected) in cases {
+			assert_eq!(rule.apply("HttpStatus"), expected);
+		}
+		assert_eq!(RenameRule::Snake.apply("A"), "a");
+		assert_eq!(RenameRule::Camel.apply(""), "");
+	}
+}
@@ -0,0 +1,234 @@
+//!
+//! Base64 encoded string values streamed from a reader.
+//!
+//! Enabled with the `base64` feature.
+//!
+
+use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriter};
+use std::io::Read;
+
+static ALPHABET: [u8; 64] = *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Bytes read from the reader at once, a multiple of 3 so full chunks encode without padding
+const CHUNK_LEN: usize = 3 * 1024;
+
+impl<W: JSONWriter> JSONObjectWriter<'_, W> {
+	///
+	/// Reads `reader` to the end and writes its content as base64 encoded string value with the given key.
+	///
+	/// The input is read and encoded in small chunks, so it never has to be held in memory.
+	///
+	/// If reading fails, the string is closed with the data encoded so far, the writer is poisoned
+	/// (see `JsonDocument::is_poisoned`) and the error is returned.
+	///
+	pub fn base64_value_from_reader<R: Read + ?Sized>(
+		&mut self,
+		key: &str,
+		reader: &mut R,
+	) -> Result<(), std::io::Error> {
+		let value = self.string_writer(key);
+		write_base64(value.writer, reader, &mut [0u8; CHUNK_LEN])
+	}
+}
+
+impl<W: JSONWriter> JSONArrayWriter<'_, W> {
+	///
+	/// Reads `reader` to the end and writes its content as base64 encoded string array entry.
+	///
+	/// See `JSONObjectWriter::base64_value_from_reader`.
+	///
+	pub fn base64_value_from_reader<R: Read + ?Sized>(
+		&mut self,
+		reader: &mut R,
+	) -> Result<(), std::io::Error> {
+		let value = self.string_writer();
+		write_base64(value.writer, reader, &mut [0u8; CHUNK_LEN])
+	}
+}
+
+///
+/// Encodes everything read from `reader` and writes it as part of a string.
+///
+/// `chunk` is the read buffer and must be at least 3 bytes long.
+///
+fn write_base64<W: JSONWriter + ?Sized, R: Read + ?Sized>(
+	writer: &mut W,
+	reader: &mut R,
+	chunk: &mut [u8],
+) -> Result<(), std::io::Error> {
+	// Bytes at the start of `chunk` that are left over from the previous read
+	let mut carry = 0;
+	loop {
+		let read = match reader.read(&mut chunk[carry..]) {
+			Ok(0) => break,
+			Ok(read) => read,
+			Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+			Err(err) => {
+				writer.json_poison();
+				return Err(err);
+			}
+		};
+		let len = carry + read;
+		let encoded_len = len - len % 3;
+		encode_part(writer, &chunk[..encoded_len]);
+		chunk.copy_within(encoded_len..len, 0);
+		carry = len - encoded_len;
+	}
+	encode_part(writer, &chunk[..carry]);
+	Ok(())
+}
+
+///
+/// Encodes `input` and writes it as part of a string. Only the last part may have a length that is not a multiple of 3.
+///
+fn encode_part<W: JSONWriter + ?Sized>(writer: &mut W, input: &[u8]) {
+	let mut output = [0u8; 1024];
+	for block in input.chunks(output.len() / 4 * 3) {
+		let mut output_len = 0;
+		for group in block.chunks(3) {
+			let b0 = group[0] as usize;
+			let b1 = group.get(1).copied().unwrap_or(0) as usize;
+			let b2 = group.get(2).copied().unwrap_or(0) as usize;
+			output[output_len] = ALPHABET[b0 >> 2];
+			output[output_len + 1] = ALPHABET[((b0 & 0x3) << 4) | (b1 >> 4)];
+			output[output_len + 2] = if group.len() > 1 {
+				ALPHABET[((b1 & 0xF) << 2) | (b2 >> 6)]
+			} else {
+				b'='
+			};
+			output[output_len + 3] = if group.len() > 2 {
+				ALPHABET[b2 & 0x3F]
+			} else {
+				b'='
+			};
+			output_len += 4;
+		}
+		// Checks can be omitted here: the output only contains characters of the alphabet and '='
+		writer.json_string_part(unsafe { core::str::from_utf8_unchecked(&output[..output_len]) });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONStringWriter, JsonDocument};
+
+	/// Reader that returns at most `max_read` bytes per call and fails after `fail_after` bytes
+	struct TestReader<'a> {
+		data: &'a [u8],
+		max_read: usize,
+		fail_after: Option<usize>,
+		position: usize,
+	}
+
+	impl Read for TestReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+			if self.fail_after.is_some_and(|limit| self.position >= limit) {
+				return Err(std::io::Error::other("disk gone"));
+			}
+			let len = buf
+				.len()
+				.min(self.max_read)
+				.min(self.data.len() - self.position);
+			buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+			self.position += len;
+			Ok(len)
+		}
+	}
+
+	#[test]
+	fn test_base64_padding() {
+		let cases = [
+			("", ""),
+			("f", "Zg=="),
+			("fo", "Zm8="),
+			("foo", "Zm9v"),
+			("foob", "Zm9vYg=="),
+			("fooba", "Zm9vYmE="),
+			("foobar", "Zm9vYmFy"),
+		];
+		for (input, expected) in cases {
+			let mut buffer = String::new();
+			let mut object = JSONObjectWriter::new(&mut buffer);
+			object
+				.base64_value_from_reader("data", &mut input.as_bytes())
+				.unwrap();
+			object.value("next", 1u8);
+			object.end();
+			assert_eq!(buffer, format!("{{\"data\":\"{expected}\",\"next\":1}}"));
+		}
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array
+			.base64_value_from_reader(&mut &[0xFBu8, 0xFF][..])
+			.unwrap();
+		array.end();
+		// '/' is escaped like in any other string
+		assert_eq!(buffer, "[\"+\\/8=\"]");
+	}
+
+	#[test]
+	fn test_base64_chunks() {
+		let data: Vec<u8> = (0..10000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+		let mut expected = String::new();
+		encode_part(&mut expected, &data);
+		assert_eq!(
+			expected.len(),
+			data.len().div_ceil(3) * 4 + expected.matches('\\').count()
+		);
+
+		// Tiny read buffers and short reads exercise the carried bytes between chunks
+		for (chunk_len, max_read) in [(3, 1), (4, 2), (5, 5), (7, 3), (3072, 100000)] {
+			let mut reader = TestReader {
+				data: &data,
+				max_read,
+				fail_after: None,
+				position: 0,
+			};
+			let mut actual = String::new();
+			let mut chunk = vec![0u8; chunk_len];
+			write_base64(&mut actual, &mut reader, &mut chunk).unwrap();
+			assert_eq!(
+				actual, expected,
+				"chunk_len: {chunk_len}, max_read: {max_read}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_base64_read_error() {
+		let data = [1u8; 100];
+		let mut reader = TestReader {
+			data: &data,
+			max_read: 10,
+			fail_after: Some(30),
+			position: 0,
+		};
+		let mut document = JsonDocument::new(String::new());
+		{
+			let mut object = document.root().object();
+			let err = object
+				.base64_value_from_reader("data", &mut reader)
+				.unwrap_err();
+			assert_eq!(err.to_string(), "disk gone");
+		}
+		assert!(document.is_poisoned());
+		assert_eq!(
+			document.into_inner(),
+			format!("{{\"data\":\"{}\"}}", "AQEB".repeat(10))
+		);
+
+		let mut buffer = String::new();
+		let value = JSONStringWriter::new(&mut buffer);
+		let mut reader = TestReader {
+			data: &data,
+			max_read: 10,
+			fail_after: Some(0),
+			position: 0,
+		};
+		assert!(write_base64(value.writer, &mut reader, &mut [0u8; 3]).is_err());
+		value.end();
+		assert_eq!(buffer, "\"\"");
+	}
+}
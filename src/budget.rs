@@ -0,0 +1,282 @@
+use crate::JSONWriter;
+use alloc::string::String;
+
+///
+/// Writes to a `String` until the output reaches a byte budget, then drops everything else
+/// and marks the output as truncated, e.g. to log "the first N bytes" of a large value.
+///
+/// The output is cut exactly at the budget, or before it if the budget ends inside a multi-byte
+/// character. Long strings and fragments are shortened before escaping them, so writing a huge value
+/// does not grow the buffer beyond the budget. Truncated output is not valid JSON.
+///
+/// ```rust
+/// use json_writer::{BudgetedJSONWriter, JSONArrayWriter};
+/// let mut buffer = String::new();
+/// let mut writer = BudgetedJSONWriter::new(&mut buffer, 8);
+/// let mut array = JSONArrayWriter::new(&mut writer);
+/// for i in 0..100u32 {
+///     array.value(i);
+/// }
+/// array.end();
+/// assert!(writer.is_truncated());
+/// assert_eq!(buffer, "[0,1,2,3");
+/// ```
+///
+pub struct BudgetedJSONWriter<'a> {
+	output: &'a mut String,
+	/// Length of `output` when the writer was created
+	start: usize,
+	budget: usize,
+	truncated: bool,
+}
+
+impl<'a> BudgetedJSONWriter<'a> {
+	///
+	/// Creates a new writer that appends at most `budget` bytes to `output`.
+	///
+	pub fn new(output: &'a mut String, budget: usize) -> BudgetedJSONWriter<'a> {
+		let start = output.len();
+		BudgetedJSONWriter {
+			output,
+			start,
+			budget,
+			truncated: false,
+		}
+	}
+
+	///
+	/// Returns true if output was dropped because the budget was reached
+	///
+	#[inline(always)]
+	pub fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+
+	///
+	/// Returns the number of bytes written by this writer
+	///
+	#[inline(always)]
+	pub fn written(&self) -> usize {
+		self.output.len() - self.start
+	}
+
+	#[inline(always)]
+	fn remaining(&self) -> usize {
+		self.budget - self.written()
+	}
+
+	/// Returns the part of `value` that is enough to fill the rest of the budget
+	fn prefix<'v>(&self, value: &'v str) -> &'v str {
+		let mut end = self.remaining().min(value.len());
+		if end < value.len() {
+			// One more byte than the budget allows, so that `check` notices the truncation
+			end += 1;
+		}
+		while !value.is_char_boundary(end) {
+			end += 1;
+		}
+		&value[..end]
+	}
+
+	/// Cuts the output back to the budget after a write
+	#[inline(always)]
+	fn check(&mut self) {
+		if self.written() > self.budget {
+			let mut end = self.start + self.budget;
+			while !self.output.is_char_boundary(end) {
+				end -= 1;
+			}
+			self.output.truncate(end);
+			self.truncated = true;
+		}
+	}
+}
+
+impl JSONWriter for BudgetedJSONWriter<'_> {
+	forward_json_writer!(|this| this.output; json_poison);
+
+	fn json_null(&mut self) {
+		if !self.truncated {
+			self.output.json_null();
+			self.check();
+		}
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		if !self.truncated {
+			self.output.json_bool(value);
+			self.check();
+		}
+	}
+
+	fn json_string(&mut self, value: &str) {
+		if !self.truncated {
+			let prefix = self.prefix(value);
+			if prefix.len() == value.len() {
+				self.output.json_string(value);
+			} else {
+				self.output.json_begin_string();
+				self.output.json_string_part(prefix);
+			}
+			self.check();
+		}
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		if !self.truncated {
+			let prefix = self.prefix(value);
+			self.output.json_string_part(prefix);
+			self.check();
+		}
+	}
+
+	fn json_number_f64(&mut self, value: f64) {
+		if !self.truncated {
+			self.output.json_number_f64(value);
+			self.check();
+		}
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		if !self.truncated {
+			self.output.json_number_str(value);
+			self.check();
+		}
+	}
+
+	fn json_begin_object(&mut self) {
+		if !self.truncated {
+			self.output.json_begin_object();
+			self.check();
+		}
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		if !self.truncated {
+			self.output.json_end_object(empty);
+			self.check();
+		}
+	}
+
+	fn json_begin_array(&mut self) {
+		if !self.truncated {
+			self.output.json_begin_array();
+			self.check();
+		}
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		if !self.truncated {
+			self.output.json_end_array(empty);
+			self.check();
+		}
+	}
+
+	fn json_begin_string(&mut self) {
+		if !self.truncated {
+			self.output.json_begin_string();
+			self.check();
+		}
+	}
+
+	fn json_end_string(&mut self) {
+		if !self.truncated {
+			self.output.json_end_string();
+			self.check();
+		}
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		if !self.truncated {
+			self.output.json_begin_array_value(first);
+			self.check();
+		}
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		if !self.truncated {
+			let prefix = self.prefix(key);
+			if prefix.len() == key.len() {
+				self.output.json_object_key(key, first);
+			} else {
+				if !first {
+					self.output.push(',');
+				}
+				self.output.json_string(prefix);
+			}
+			self.check();
+		}
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		if !self.truncated {
+			let prefix = self.prefix(value);
+			self.output.json_fragment(prefix);
+			self.check();
+		}
+	}
+
+	#[inline(always)]
+	fn json_reserve(&mut self, additional: usize) {
+		self.output.json_reserve(additional.min(self.remaining()));
+	}
+
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		// Dropped values don't change the length, which must not be mistaken for missing values
+		if self.truncated {
+			None
+		} else {
+			self.output.json_debug_len()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::write_items;
+	use crate::JSONObjectWriter;
+
+	#[test]
+	fn test_budget_truncation() {
+		let mut expected = String::new();
+		write_items(&mut expected, 100);
+
+		for budget in [0, 1, 7, 100, 1001, expected.len() - 1] {
+			let mut buffer = String::from("prefix");
+			let mut writer = BudgetedJSONWriter::new(&mut buffer, budget);
+			write_items(&mut writer, 100);
+			assert!(writer.is_truncated());
+			assert!(writer.written() <= budget && writer.written() + 4 > budget);
+			assert!(expected.starts_with(&buffer["prefix".len()..]));
+		}
+
+		let mut buffer = String::new();
+		let mut writer = BudgetedJSONWriter::new(&mut buffer, expected.len());
+		write_items(&mut writer, 100);
+		assert!(!writer.is_truncated());
+		assert_eq!(buffer, expected);
+	}
+
+	#[test]
+	fn test_budget_long_values() {
+		let long = "x\"".repeat(100_000);
+		let mut buffer = String::new();
+		let mut writer = BudgetedJSONWriter::new(&mut buffer, 10);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("value", long.as_str());
+		object.end();
+		assert!(writer.is_truncated());
+		assert_eq!(buffer, "{\"value\":\"");
+		assert!(buffer.capacity() < 1000);
+
+		let mut buffer = String::new();
+		let mut writer = BudgetedJSONWriter::new(&mut buffer, 10);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value(&long, 1u8);
+		object.end();
+		assert!(writer.is_truncated());
+		assert_eq!(buffer, r#"{"x\"x\"x\"#);
+	}
+}
@@ -0,0 +1,521 @@
+//!
+//! Builders that are closed explicitly with `end()` or `build()` instead of when dropped.
+//!
+
+use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+use alloc::string::String;
+use core::mem::ManuallyDrop;
+
+///
+/// Error returned by `end()` when a nested builder was dropped without calling `end()`.
+///
+/// The nested object or array was left open, so the output is not valid JSON.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnendedBuilder;
+
+impl core::fmt::Display for UnendedBuilder {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str("a nested builder was dropped without calling end()")
+	}
+}
+
+impl core::error::Error for UnendedBuilder {}
+
+///
+/// Alternative to `JSONObjectWriter` that must be closed by calling `end()`.
+///
+/// Appends '{' on creation and '}' on `end()`. Dropping the builder without calling `end()`
+/// panics in debug builds. In release builds the object is left open, the writer is poisoned
+/// and `end()` of the enclosing builder returns an error.
+///
+/// ```rust
+/// use json_writer::ObjectBuilder;
+/// let mut buffer = String::new();
+/// let mut object = ObjectBuilder::new(&mut buffer);
+/// object.value("a", 1u8);
+/// let mut list = object.array("list");
+/// list.value(true);
+/// list.end()?;
+/// object.end()?;
+/// assert_eq!(buffer, "{\"a\":1,\"list\":[true]}");
+/// # Ok::<(), json_writer::UnendedBuilder>(())
+/// ```
+///
+/// Ignoring a builder is a warning:
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// let mut buffer = String::new();
+/// json_writer::ObjectBuilder::new(&mut buffer);
+/// ```
+///
+#[must_use = "the object is only closed by calling end()"]
+pub struct ObjectBuilder<'a, W: JSONWriter = String> {
+	inner: ManuallyDrop<JSONObjectWriter<'a, W>>,
+	ended: bool,
+	/// Set when a nested builder was dropped without calling `end()`
+	unended_child: bool,
+	parent: Option<&'a mut bool>,
+}
+
+///
+/// Alternative to `JSONArrayWriter` that must be closed by calling `end()`.
+///
+/// See `ObjectBuilder`.
+///
+#[must_use = "the array is only closed by calling end()"]
+pub struct ArrayBuilder<'a, W: JSONWriter = String> {
+	inner: ManuallyDrop<JSONArrayWriter<'a, W>>,
+	ended: bool,
+	/// Set when a nested builder was dropped without calling `end()`
+	unended_child: bool,
+	parent: Option<&'a mut bool>,
+}
+
+impl<'a, W: JSONWriter> ObjectBuilder<'a, W> {
+	///
+	/// Creates a new ObjectBuilder that writes to the given buffer. Writes '{' to the buffer immediately.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> ObjectBuilder<'a, W> {
+		ObjectBuilder::with_parent(JSONObjectWriter::new(writer), None)
+	}
+
+	fn with_parent(inner: JSONObjectWriter<'a, W>, parent: Option<&'a mut bool>) -> Self {
+		ObjectBuilder {
+			inner: ManuallyDrop::new(inner),
+			ended: false,
+			unended_child: false,
+			parent,
+		}
+	}
+
+	///
+	/// Starts writing a nested object with given key
+	///
+	#[inline(always)]
+	pub fn object(&mut self, key: &str) -> ObjectBuilder<'_, W> {
+		ObjectBuilder::with_parent(self.inner.object(key), Some(&mut self.unended_child))
+	}
+
+	///
+	/// Starts writing a nested array with given key
+	///
+	#[inline(always)]
+	pub fn array(&mut self, key: &str) -> ArrayBuilder<'_, W> {
+		ArrayBuilder::with_parent(self.inner.array(key), Some(&mut self.unended_child))
+	}
+
+	///
+	/// Escapes and appends key:value to the buffer
+	///
+	#[inline(always)]
+	pub fn value<T: JSONWriterValue>(&mut self, key: &str, value: T) {
+		self.inner.value(key, value);
+	}
+
+	///
+	/// Writes '}' and returns an error if a nested builder was dropped without calling `end()`.
+	///
+	pub fn end(mut self) -> Result<(), UnendedBuilder> {
+		self.ended = true;
+		if self.unended_child {
+			Err(UnendedBuilder)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+impl<'a, W: JSONWriter> ArrayBuilder<'a, W> {
+	///
+	/// Creates a new ArrayBuilder that writes to the given buffer. Writes '[' to the buffer immediately.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> ArrayBuilder<'a, W> {
+		ArrayBuilder::with_parent(JSONArrayWriter::new(writer), None)
+	}
+
+	fn with_parent(inner: JSONArrayWriter<'a, W>, parent: Option<&'a mut bool>) -> Self {
+		ArrayBuilder {
+			inner: ManuallyDrop::new(inner),
+			ended: false,
+			unended_child: false,
+			parent,
+		}
+	}
+
+	///
+	/// Starts writing a nested object as array entry
+	///
+	#[inline(always)]
+	pub fn object(&mut self) -> ObjectBuilder<'_, W> {
+		ObjectBuilder::with_parent(self.inner.object(), Some(&mut self.unended_child))
+	}
+
+	///
+	/// Starts writing a nested array as array entry
+	///
+	#[inline(always)]
+	pub fn array(&mut self) -> ArrayBuilder<'_, W> {
+		ArrayBuilder::with_parent(self.inner.array(), Some(&mut self.unended_child))
+	}
+
+	///
+	/// Writes given value as array entry
+	///
+	#[inline(always)]
+	pub fn value<T: JSONWriterValue>(&mut self, value: T) {
+		self.inner.value(value);
+	}
+
+	///
+	/// Writes ']' and returns an error if a nested builder was dropped without calling `end()`.
+	///
+	pub fn end(mut self) -> Result<(), UnendedBuilder> {
+		self.ended = true;
+		if self.unended_child {
+			Err(UnendedBuilder)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+impl<W: JSONWriter> Drop for ObjectBuilder<'_, W> {
+	fn drop(&mut self) {
+		if !self.ended && !crate::thread_panicking() {
+			forgotten_end(self.inner.writer, &mut self.parent, "ObjectBuilder");
+			return;
+		}
+		// Safety: the inner writer is only dropped here, which closes the object
+		unsafe { ManuallyDrop::drop(&mut self.inner) }
+	}
+}
+
+impl<W: JSONWriter> Drop for ArrayBuilder<'_, W> {
+	fn drop(&mut self) {
+		if !self.ended && !crate::thread_panicking() {
+			forgotten_end(self.inner.writer, &mut self.parent, "ArrayBuilder");
+			return;
+		}
+		// Safety: the inner writer is only dropped here, which closes the array
+		unsafe { ManuallyDrop::drop(&mut self.inner) }
+	}
+}
+
+///
+/// Chainable builder for a JSON object that owns its output, for quick construction in tests and small handlers.
+///
+/// Every method takes and returns the builder, `nested` and `array` build nested containers with closures.
+/// `build()` writes '}' and returns the output. For large documents or other writers, use `JSONObjectWriter`.
+///
+/// ```rust
+/// use json_writer::OwnedObjectBuilder;
+/// let json = OwnedObjectBuilder::new()
+///     .field("a", 1)
+///     .field("b", "x")
+///     .nested("c", |c| c.field("d", true))
+///     .array("e", |e| e.value(1).value(2))
+///     .build();
+/// assert_eq!(json, r#"{"a":1,"b":"x","c":{"d":true},"e":[1,2]}"#);
+/// ```
+///
+#[must_use = "the output is only returned by build()"]
+#[derive(Debug)]
+pub struct OwnedObjectBuilder {
+	buffer: String,
+	empty: bool,
+}
+
+///
+/// Chainable builder for a JSON array that owns its output, see `OwnedObjectBuilder`.
+///
+#[must_use = "the output is only returned by build()"]
+#[derive(Debug)]
+pub struct OwnedArrayBuilder {
+	buffer: String,
+	empty: bool,
+}
+
+impl OwnedObjectBuilder {
+	///
+	/// Creates a new builder with an empty object
+	///
+	#[inline(always)]
+	pub fn new() -> OwnedObjectBuilder {
+		OwnedObjectBuilder::append_to(String::new())
+	}
+
+	/// Continues writing to `buffer`, which is returned again by `build()`
+	fn append_to(mut buffer: String) -> OwnedObjectBuilder {
+		buffer.json_begin_object();
+		OwnedObjectBuilder {
+			buffer,
+			empty: true,
+		}
+	}
+
+	/// Writes the key of the next field
+	fn key(&mut self, key: &str) {
+		self.buffer.json_object_key(key, self.empty);
+		self.empty = false;
+	}
+
+	///
+	/// Escapes and appends key:value
+	///
+	#[inline(always)]
+	pub fn field<T: JSONWriterValue>(mut self, key: &str, value: T) -> OwnedObjectBuilder {
+		self.key(key);
+		value.write_json(&mut self.buffer);
+		self
+	}
+
+	///
+	/// Appends a nested object with given key, whose fields are added by `build`
+	///
+	pub fn nested(
+		mut self,
+		key: &str,
+		build: impl FnOnce(OwnedObjectBuilder) -> OwnedObjectBuilder,
+	) -> OwnedObjectBuilder {
+		self.key(key);
+		self.buffer = build(OwnedObjectBuilder::append_to(self.buffer)).build();
+		self
+	}
+
+	///
+	/// Appends a nested array with given key, whose values are added by `build`
+	///
+	pub fn array(
+		mut self,
+		key: &str,
+		build: impl FnOnce(OwnedArrayBuilder) -> OwnedArrayBuilder,
+	) -> OwnedObjectBuilder {
+		self.key(key);
+		self.buffer = build(OwnedArrayBuilder::append_to(self.buffer)).build();
+		self
+	}
+
+	///
+	/// Writes '}' and returns the output
+	///
+	#[inline(always)]
+	pub fn build(mut self) -> String {
+		self.buffer.json_end_object(self.empty);
+		self.buffer
+	}
+}
+
+impl Default for OwnedObjectBuilder {
+	fn default() -> Self {
+		OwnedObjectBuilder::new()
+	}
+}
+
+impl OwnedArrayBuilder {
+	///
+	/// Creates a new builder with an empty array
+	///
+	#[inline(always)]
+	pub fn new() -> OwnedArrayBuilder {
+		OwnedArrayBuilder::append_to(String::new())
+	}
+
+	/// Continues writing to `buffer`, which is returned again by `build()`
+	fn append_to(mut buffer: String) -> OwnedArrayBuilder {
+		buffer.json_begin_array();
+		OwnedArrayBuilder {
+			buffer,
+			empty: true,
+		}
+	}
+
+	/// Writes the separator before the next value
+	fn next(&mut self) {
+		self.buffer.json_begin_array_value(self.empty);
+		self.empty = false;
+	}
+
+	///
+	/// Appends a value
+	///
+	#[inline(always)]
+	pub fn value<T: JSONWriterValue>(mut self, value: T) -> OwnedArrayBuilder {
+		self.next();
+		value.write_json(&mut self.buffer);
+		self
+	}
+
+	///
+	/// Appends a nested object, whose fields are added by `build`
+	///
+	pub fn object(
+		mut self,
+		build: impl FnOnce(OwnedObjectBuilder) -> OwnedObjectBuilder,
+	) -> OwnedArrayBuilder {
+		self.next();
+		self.buffer = build(OwnedObjectBuilder::append_to(self.buffer)).build();
+		self
+	}
+
+	///
+	/// Appends a nested array, whose values are added by `build`
+	///
+	pub fn array(
+		mut self,
+		build: impl FnOnce(OwnedArrayBuilder) -> OwnedArrayBuilder,
+	) -> OwnedArrayBuilder {
+		self.next();
+		self.buffer = build(OwnedArrayBuilder::append_to(self.buffer)).build();
+		self
+	}
+
+	///
+	/// Writes ']' and returns the output
+	///
+	#[inline(always)]
+	pub fn build(mut self) -> String {
+		self.buffer.json_end_array(self.empty);
+		self.buffer
+	}
+}
+
+impl Default for OwnedArrayBuilder {
+	fn default() -> Self {
+		OwnedArrayBuilder::new()
+	}
+}
+
+/// Leaves the container open and reports the missing `end()` call
+fn forgotten_end<W: JSONWriter>(writer: &mut W, parent: &mut Option<&mut bool>, name: &str) {
+	writer.json_poison();
+	if let Some(parent) = parent {
+		**parent = true;
+	}
+	#[cfg(debug_assertions)]
+	panic!("{name} dropped without calling end()");
+	#[cfg(not(debug_assertions))]
+	let _ = name;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::JsonDocument;
+
+	#[test]
+	fn test_builder_matches_writer() {
+		let mut expected = String::new();
+		{
+			let mut object = JSONObjectWriter::new(&mut expected);
+			object.value("a", "b");
+			let mut list = object.array("list");
+			list.value(1u8);
+			list.object().value("nested", crate::NULL);
+			list.array();
+			list.end();
+			object.object("empty");
+		}
+
+		let mut buffer = String::new();
+		let mut object = ObjectBuilder::new(&mut buffer);
+		object.value("a", "b");
+		let mut list = object.array("list");
+		list.value(1u8);
+		let mut nested = list.object();
+		nested.value("nested", crate::NULL);
+		nested.end().unwrap();
+		list.array().end().unwrap();
+		list.end().unwrap();
+		object.object("empty").end().unwrap();
+		object.end().unwrap();
+		assert_eq!(buffer, expected);
+	}
+
+	#[test]
+	fn test_owned_builder_matches_writer() {
+		let mut expected = String::new();
+		{
+			let mut object = JSONObjectWriter::new(&mut expected);
+			object.value("a", 1);
+			object.value("b", "x\"y");
+			let mut c = object.object("c");
+			c.value("d", true);
+			let mut e = c.array("e");
+			e.object().object("f").value("g", crate::NULL);
+			e.array();
+			e.value(2.5);
+			e.end();
+			c.end();
+			object.object("empty");
+			object.array("empty list");
+		}
+
+		let built = OwnedObjectBuilder::new()
+			.field("a", 1)
+			.field("b", "x\"y")
+			.nested("c", |c| {
+				c.field("d", true).array("e", |e| {
+					e.object(|o| o.nested("f", |f| f.field("g", crate::NULL)))
+						.array(|a| a)
+						.value(2.5)
+				})
+			})
+			.nested("empty", |o| o)
+			.array("empty list", |a| a)
+			.build();
+		assert_eq!(built, expected);
+		assert_eq!(OwnedObjectBuilder::default().build(), "{}");
+		assert_eq!(
+			OwnedArrayBuilder::new().value("a").object(|o| o).build(),
+			r#"["a",{}]"#
+		);
+	}
+
+	fn status_response(ok: bool) -> String {
+		OwnedObjectBuilder::new()
+			.field("ok", ok)
+			.nested("details", |d| d.field("code", if ok { 200 } else { 500 }))
+			.build()
+	}
+
+	#[test]
+	fn test_owned_builder_as_expression() {
+		assert_eq!(
+			status_response(true),
+			r#"{"ok":true,"details":{"code":200}}"#
+		);
+		assert_eq!(
+			status_response(false),
+			r#"{"ok":false,"details":{"code":500}}"#
+		);
+	}
+
+	#[test]
+	fn test_forgotten_end() {
+		let result = std::panic::catch_unwind(|| {
+			let mut document = JsonDocument::new(String::new());
+			let mut array = ArrayBuilder::new(&mut document);
+			array.value(1u8);
+			// Dropped immediately, e.g. by an early return
+			drop(array.object());
+			let result = array.end();
+			(result, document.is_poisoned(), document.into_inner())
+		});
+		if cfg!(debug_assertions) {
+			let message = result.unwrap_err();
+			assert_eq!(
+				message.downcast_ref::<String>().map(String::as_str),
+				Some("ObjectBuilder dropped without calling end()")
+			);
+		} else {
+			let (result, poisoned, output) = result.unwrap();
+			assert_eq!(result, Err(UnendedBuilder));
+			assert!(poisoned);
+			assert_eq!(output, "[1,{]");
+		}
+	}
+}
@@ -0,0 +1,354 @@
+use crate::JSONWriter;
+use alloc::vec::Vec;
+
+///
+/// Writes CBOR (RFC 8949) instead of JSON, so the same code can generate both formats.
+///
+/// Objects and arrays are written as indefinite-length maps and arrays, because their number of entries
+/// is not known when they are started. Strings are definite-length text strings, except for strings written
+/// in parts with `JSONStringWriter`, which become indefinite-length text strings with one chunk per part.
+///
+/// Numbers are written as the smallest CBOR integer that can hold them, or as a bignum (tag 2 or 3)
+/// if they don't fit into 64 bits. Numbers with a fraction or exponent are written as single precision float
+/// if that is lossless, otherwise as double precision float. Like in JSON, NaN and infinity are written as null
+/// and integral floats as integers. Number strings that are not numbers at all are written as text strings.
+///
+/// Raw JSON can't be spliced into CBOR, so every `json_fragment` is written as null.
+///
+pub struct CborWriter<'a> {
+	/// Result
+	pub buffer: &'a mut Vec<u8>,
+}
+
+/// Major type of unsigned integers
+const UNSIGNED: u8 = 0;
+/// Major type of negative integers
+const NEGATIVE: u8 = 1;
+/// Major type of byte strings
+const BYTES: u8 = 2;
+/// Major type of text strings
+const TEXT: u8 = 3;
+/// Major type of tags
+const TAG: u8 = 6;
+/// Tag of positive bignums
+const POSITIVE_BIGNUM: u64 = 2;
+/// Tag of negative bignums
+const NEGATIVE_BIGNUM: u64 = 3;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const FLOAT32: u8 = 0xfa;
+const FLOAT64: u8 = 0xfb;
+const BEGIN_TEXT: u8 = 0x7f;
+const BEGIN_ARRAY: u8 = 0x9f;
+const BEGIN_MAP: u8 = 0xbf;
+const BREAK: u8 = 0xff;
+
+/// Converts ASCII digits to the big-endian bytes of the number, without leading zeros
+fn decimal_to_bytes(digits: &str) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(digits.len() / 2 + 1);
+	for digit in digits.bytes() {
+		let mut carry = (digit - b'0') as u32;
+		for byte in bytes.iter_mut().rev() {
+			let value = *byte as u32 * 10 + carry;
+			*byte = value as u8;
+			carry = value >> 8;
+		}
+		if carry > 0 {
+			bytes.insert(0, carry as u8);
+		}
+	}
+	bytes
+}
+
+/// Subtracts 1 from a positive big-endian number
+fn decrement(bytes: &mut Vec<u8>) {
+	for byte in bytes.iter_mut().rev() {
+		let (value, borrow) = byte.overflowing_sub(1);
+		*byte = value;
+		if !borrow {
+			break;
+		}
+	}
+	if bytes.first() == Some(&0) {
+		bytes.remove(0);
+	}
+}
+
+impl CborWriter<'_> {
+	/// Creates a new CBOR writer that appends to `buffer`
+	pub fn new(buffer: &mut Vec<u8>) -> CborWriter<'_> {
+		CborWriter { buffer }
+	}
+
+	/// Writes the initial byte of a data item and the argument in the shortest form
+	fn write_head(&mut self, major: u8, argument: u64) {
+		let major = major << 5;
+		match argument {
+			0..=23 => self.buffer.push(major | argument as u8),
+			24..=0xff => self.buffer.extend_from_slice(&[major | 24, argument as u8]),
+			0x100..=0xffff => {
+				self.buffer.push(major | 25);
+				self.buffer
+					.extend_from_slice(&(argument as u16).to_be_bytes());
+			}
+			0x1_0000..=0xffff_ffff => {
+				self.buffer.push(major | 26);
+				self.buffer
+					.extend_from_slice(&(argument as u32).to_be_bytes());
+			}
+			_ => {
+				self.buffer.push(major | 27);
+				self.buffer.extend_from_slice(&argument.to_be_bytes());
+			}
+		}
+	}
+
+	fn write_text(&mut self, value: &str) {
+		self.write_head(TEXT, value.len() as u64);
+		self.buffer.extend_from_slice(value.as_bytes());
+	}
+
+	/// Writes an integer of any size, `digits` must only consist of ASCII digits
+	fn write_integer(&mut self, negative: bool, digits: &str) {
+		if let Ok(magnitude) = digits.parse::<u64>() {
+			match (negative, magnitude) {
+				(true, 1..) => self.write_head(NEGATIVE, magnitude - 1),
+				_ => self.write_head(UNSIGNED, magnitude),
+			}
+			return;
+		}
+		let mut bytes = decimal_to_bytes(digits);
+		if negative {
+			decrement(&mut bytes);
+			if bytes.len() <= 8 {
+				// -2^64 still fits, because negative integers store -1 - n
+				let argument = bytes
+					.iter()
+					.fold(0, |argument, &b| (argument << 8) | b as u64);
+				self.write_head(NEGATIVE, argument);
+				return;
+			}
+			self.write_head(TAG, NEGATIVE_BIGNUM);
+		} else {
+			self.write_head(TAG, POSITIVE_BIGNUM);
+		}
+		self.write_head(BYTES, bytes.len() as u64);
+		self.buffer.extend_from_slice(&bytes);
+	}
+
+	fn write_float(&mut self, value: f64) {
+		if value as f32 as f64 == value {
+			self.buffer.push(FLOAT32);
+			self.buffer.extend_from_slice(&(value as f32).to_be_bytes());
+		} else {
+			self.buffer.push(FLOAT64);
+			self.buffer.extend_from_slice(&value.to_be_bytes());
+		}
+	}
+}
+
+impl JSONWriter for CborWriter<'_> {
+	fn json_null(&mut self) {
+		self.buffer.push(NULL);
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		self.buffer.push(if value { TRUE } else { FALSE });
+	}
+
+	fn json_string(&mut self, value: &str) {
+		self.write_text(value);
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		if !value.is_empty() {
+			self.write_text(value);
+		}
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		let (negative, digits) = match value.strip_prefix('-') {
+			Some(digits) => (true, digits),
+			None => (false, value.strip_prefix('+').unwrap_or(value)),
+		};
+		if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+			self.write_integer(negative, digits);
+		} else {
+			match value.parse::<f64>() {
+				Ok(number) if number.is_finite() => self.write_float(number),
+				_ => self.write_text(value),
+			}
+		}
+	}
+
+	fn json_begin_object(&mut self) {
+		self.buffer.push(BEGIN_MAP);
+	}
+
+	fn json_end_object(&mut self, _empty: bool) {
+		self.buffer.push(BREAK);
+	}
+
+	fn json_begin_array(&mut self) {
+		self.buffer.push(BEGIN_ARRAY);
+	}
+
+	fn json_end_array(&mut self, _empty: bool) {
+		self.buffer.push(BREAK);
+	}
+
+	fn json_begin_string(&mut self) {
+		self.buffer.push(BEGIN_TEXT);
+	}
+
+	fn json_end_string(&mut self) {
+		self.buffer.push(BREAK);
+	}
+
+	#[inline(always)]
+	fn json_begin_array_value(&mut self, _first: bool) {}
+
+	fn json_object_key(&mut self, key: &str, _first: bool) {
+		self.write_text(key);
+	}
+
+	fn json_fragment(&mut self, _value: &str) {
+		self.buffer.push(NULL);
+	}
+
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.buffer.len())
+	}
+
+	fn json_reserve(&mut self, additional: usize) {
+		self.buffer.reserve(additional);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{BigUintBytes, JSONArrayWriter, JSONObjectWriter, JSONWriterValue};
+
+	fn write_document<W: JSONWriter>(writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("name", "json-writer");
+		object.value("unicode ✓", "ünïcödé 😀");
+		object.value("empty", "");
+		object.value("missing", ());
+		object.value("flags", [true, false].as_slice());
+		let mut numbers = object.array("numbers");
+		for number in [
+			0,
+			23,
+			24,
+			255,
+			256,
+			65535,
+			65536,
+			u32::MAX as i64 + 1,
+			-1,
+			-24,
+			-25,
+			-256,
+			-257,
+		] {
+			numbers.value(number);
+		}
+		numbers.value(u64::MAX);
+		numbers.value(i64::MIN);
+		numbers.value(0.5);
+		numbers.value(0.1);
+		numbers.value(-2.5e-300);
+		numbers.value(3.0);
+		numbers.value(f64::NAN);
+		numbers.end();
+		let mut nested = object.object("nested");
+		nested.object("empty");
+		nested.array("empty array");
+		nested
+			.array("deep")
+			.array()
+			.object()
+			.value("x", [1u8, 2].as_slice());
+		nested.end();
+		let mut string = object.string_writer("streamed");
+		string.push_str("a");
+		string.push_str("");
+		string.push_str("ü");
+		string.end();
+	}
+
+	#[test]
+	fn test_cbor_matches_json() {
+		let mut json = String::new();
+		write_document(&mut json);
+		let mut cbor = Vec::new();
+		write_document(&mut CborWriter::new(&mut cbor));
+
+		let expected: serde_json::Value = serde_json::from_str(&json).unwrap();
+		let decoded: serde_json::Value = ciborium::from_reader(cbor.as_slice()).unwrap();
+		assert_eq!(decoded, expected);
+	}
+
+	#[test]
+	fn test_cbor_encoding() {
+		let encode = |write: &dyn Fn(&mut CborWriter<'_>)| {
+			let mut buffer = Vec::new();
+			write(&mut CborWriter::new(&mut buffer));
+			buffer
+		};
+		// Examples of RFC 8949 appendix A
+		assert_eq!(encode(&|w| 500u32.write_json(w)), [0x19, 0x01, 0xf4]);
+		assert_eq!(encode(&|w| (-1000i32).write_json(w)), [0x39, 0x03, 0xe7]);
+		assert_eq!(
+			encode(&|w| 1.5.write_json(w)),
+			[0xfa, 0x3f, 0xc0, 0x00, 0x00]
+		);
+		assert_eq!(
+			encode(&|w| 1.1.write_json(w)),
+			[0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]
+		);
+		assert_eq!(
+			encode(&|w| u64::MAX.write_json(w)),
+			[0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+		);
+		assert_eq!(
+			encode(&|w| w.json_number_str("18446744073709551616")),
+			[0xc2, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+		);
+		assert_eq!(
+			encode(&|w| w.json_number_str("-18446744073709551616")),
+			[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+		);
+		assert_eq!(
+			encode(&|w| w.json_number_str("-18446744073709551617")),
+			[0xc3, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+		);
+		assert_eq!(encode(&|w| w.json_number_str("+42")), [0x18, 0x2a]);
+		assert_eq!(encode(&|w| w.json_number_str("-0")), [0x00]);
+		assert_eq!(
+			encode(&|w| w.json_number_str("0x1")),
+			[0x63, b'0', b'x', b'1']
+		);
+		assert_eq!(
+			encode(&|w| {
+				let mut array = JSONArrayWriter::new(w);
+				array.value("a");
+				array.object().value("b", ());
+				array.array();
+				array.with_raw_writer(|w| w.json_fragment("{\"raw\":1}"));
+			}),
+			[0x9f, 0x61, b'a', 0xbf, 0x61, b'b', 0xf6, 0xff, 0x9f, 0xff, 0xf6, 0xff]
+		);
+
+		let bytes = encode(&|w| BigUintBytes(&[0xff; 32]).as_number().write_json(w));
+		let decoded: ciborium::Value = ciborium::from_reader(bytes.as_slice()).unwrap();
+		assert_eq!(
+			decoded,
+			ciborium::Value::Tag(2, Box::new(ciborium::Value::Bytes(vec![0xff; 32])))
+		);
+	}
+}
@@ -0,0 +1,415 @@
+use crate::JSONWriter;
+use std::sync::mpsc::{Sender, SyncSender, TrySendError};
+
+///
+/// Message sent by a `ChannelWriter`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonChunk {
+	/// The next part of the output
+	Data(String),
+	/// The output is complete. Not sent if the writer was dropped during a panic,
+	/// so a disconnect without `Done` means the output is incomplete.
+	Done,
+}
+
+///
+/// What a `ChannelWriter` with a bounded channel does when the channel is full
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backpressure {
+	/// Blocks the writing thread until the receiver has made room
+	Block,
+	/// Stops writing and reports `ChannelError::Full` from `finish`
+	Error,
+}
+
+///
+/// Error of `ChannelWriter::finish`. Output written after the error has been dropped.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChannelError {
+	/// The receiver has been dropped
+	Disconnected,
+	/// The bounded channel was full and the writer was created with `Backpressure::Error`
+	Full,
+}
+
+impl core::fmt::Display for ChannelError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(match self {
+			ChannelError::Disconnected => "JSON channel receiver disconnected",
+			ChannelError::Full => "JSON channel full",
+		})
+	}
+}
+
+impl std::error::Error for ChannelError {}
+
+enum ChunkSender {
+	Unbounded(Sender<JsonChunk>),
+	Bounded(SyncSender<JsonChunk>, Backpressure),
+}
+
+///
+/// Writer that sends its output over an `std::sync::mpsc` channel in chunks, e.g. to serialize on a worker thread
+/// while another thread writes to the network, without holding the whole document in memory.
+///
+/// A chunk is sent every time at least `chunk_size` bytes have been written. `finish` sends the rest of the output
+/// followed by `JsonChunk::Done`, dropping the writer does the same unless it is dropped during a panic.
+/// The receiver can concatenate the chunks until `Done`.
+///
+/// `JSONWriter` methods can't return errors, so when a chunk can't be sent, the writer stops writing
+/// and `finish` returns the error.
+///
+/// ```rust
+/// use json_writer::{ChannelWriter, JSONArrayWriter, JsonChunk};
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// let worker = std::thread::spawn(move || {
+///     let mut writer = ChannelWriter::new(sender, 4);
+///     let mut array = JSONArrayWriter::new(&mut writer);
+///     for i in 0..10u32 {
+///         array.value(i);
+///     }
+///     array.end();
+///     writer.finish()
+/// });
+/// let mut output = String::new();
+/// for chunk in receiver {
+///     match chunk {
+///         JsonChunk::Data(data) => output.push_str(&data),
+///         JsonChunk::Done => break,
+///     }
+/// }
+/// worker.join().unwrap().unwrap();
+/// assert_eq!(output, "[0,1,2,3,4,5,6,7,8,9]");
+/// ```
+///
+pub struct ChannelWriter {
+	buffer: String,
+	chunk_size: usize,
+	sender: ChunkSender,
+	/// Bytes sent so far
+	sent: usize,
+	error: Option<ChannelError>,
+	finished: bool,
+}
+
+impl ChannelWriter {
+	///
+	/// Creates a new writer that sends chunks of at least `chunk_size` bytes over an unbounded channel
+	///
+	pub fn new(sender: Sender<JsonChunk>, chunk_size: usize) -> ChannelWriter {
+		ChannelWriter::with_sender(ChunkSender::Unbounded(sender), chunk_size)
+	}
+
+	///
+	/// Creates a new writer that sends chunks of at least `chunk_size` bytes over a bounded channel
+	/// and handles a full channel according to `backpressure`
+	///
+	pub fn bounded(
+		sender: SyncSender<JsonChunk>,
+		chunk_size: usize,
+		backpressure: Backpressure,
+	) -> ChannelWriter {
+		ChannelWriter::with_sender(ChunkSender::Bounded(sender, backpressure), chunk_size)
+	}
+
+	fn with_sender(sender: ChunkSender, chunk_size: usize) -> ChannelWriter {
+		ChannelWriter {
+			buffer: String::new(),
+			chunk_size,
+			sender,
+			sent: 0,
+			error: None,
+			finished: false,
+		}
+	}
+
+	///
+	/// Returns the error that stopped the writer, if any
+	///
+	#[inline(always)]
+	pub fn error(&self) -> Option<ChannelError> {
+		self.error
+	}
+
+	///
+	/// Sends the rest of the output and `JsonChunk::Done`.
+	///
+	/// Returns the first error that occurred while sending, in which case `Done` is not sent.
+	///
+	pub fn finish(mut self) -> Result<(), ChannelError> {
+		self.finished = true;
+		self.send_rest()
+	}
+
+	fn send_rest(&mut self) -> Result<(), ChannelError> {
+		if !self.buffer.is_empty() {
+			self.send_buffer();
+		}
+		if self.error.is_none() {
+			self.send(JsonChunk::Done);
+		}
+		match self.error {
+			Some(error) => Err(error),
+			None => Ok(()),
+		}
+	}
+
+	fn send(&mut self, chunk: JsonChunk) {
+		let result = match &self.sender {
+			ChunkSender::Unbounded(sender) => {
+				sender.send(chunk).map_err(|_| ChannelError::Disconnected)
+			}
+			ChunkSender::Bounded(sender, Backpressure::Block) => {
+				sender.send(chunk).map_err(|_| ChannelError::Disconnected)
+			}
+			ChunkSender::Bounded(sender, Backpressure::Error) => {
+				sender.try_send(chunk).map_err(|err| match err {
+					TrySendError::Full(_) => ChannelError::Full,
+					TrySendError::Disconnected(_) => ChannelError::Disconnected,
+				})
+			}
+		};
+		if let Err(error) = result {
+			self.error = Some(error);
+		}
+	}
+
+	fn send_buffer(&mut self) {
+		let chunk = core::mem::replace(&mut self.buffer, String::with_capacity(self.chunk_size));
+		self.sent += chunk.len();
+		self.send(JsonChunk::Data(chunk));
+	}
+
+	/// Sends the buffer after a write if it is large enough, or drops it after an error
+	#[inline(always)]
+	fn check(&mut self) {
+		if self.error.is_some() {
+			self.buffer.clear();
+		} else if self.buffer.len() >= self.chunk_size {
+			self.send_buffer();
+		}
+	}
+}
+
+impl JSONWriter for ChannelWriter {
+	fn json_null(&mut self) {
+		self.buffer.json_null();
+		self.check();
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		self.buffer.json_bool(value);
+		self.check();
+	}
+
+	fn json_string(&mut self, value: &str) {
+		self.buffer.json_string(value);
+		self.check();
+	}
+
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.buffer.json_pre_escaped_string(value);
+		self.check();
+	}
+
+	fn json_escaped_string(&mut self, _value: &str, escaped: &str) {
+		self.buffer.push_str(escaped);
+		self.check();
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		self.buffer.json_string_part(value);
+		self.check();
+	}
+
+	fn json_number_f64(&mut self, value: f64) {
+		self.buffer.json_number_f64(value);
+		self.check();
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		self.buffer.json_number_str(value);
+		self.check();
+	}
+
+	fn json_begin_object(&mut self) {
+		self.buffer.json_begin_object();
+		self.check();
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.buffer.json_end_object(empty);
+		self.check();
+	}
+
+	fn json_begin_array(&mut self) {
+		self.buffer.json_begin_array();
+		self.check();
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.buffer.json_end_array(empty);
+		self.check();
+	}
+
+	fn json_begin_string(&mut self) {
+		self.buffer.json_begin_string();
+		self.check();
+	}
+
+	fn json_end_string(&mut self) {
+		self.buffer.json_end_string();
+		self.check();
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		self.buffer.json_begin_array_value(first);
+		self.check();
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		self.buffer.json_object_key(key, first);
+		self.check();
+	}
+
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		self.buffer.json_pre_escaped_object_key(key, first);
+		self.check();
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		self.buffer.json_fragment(value);
+		self.check();
+	}
+
+	#[inline(always)]
+	fn json_reserve(&mut self, additional: usize) {
+		self.buffer.reserve(additional.min(self.chunk_size));
+	}
+
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		// Dropped output doesn't change the length, which must not be mistaken for missing values
+		match self.error {
+			Some(_) => None,
+			None => Some(self.sent + self.buffer.len()),
+		}
+	}
+}
+
+impl Drop for ChannelWriter {
+	fn drop(&mut self) {
+		// Without `Done`, the receiver can tell that the output is incomplete
+		if !self.finished && !std::thread::panicking() {
+			// Errors can only be reported by `finish`
+			let _result = self.send_rest();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::write_items;
+	use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriterValue};
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use std::sync::mpsc::{channel, sync_channel, Receiver};
+	use std::sync::Arc;
+
+	/// Receives chunks until `Done`, returns `None` if the channel was disconnected before
+	fn receive_all(receiver: &Receiver<JsonChunk>) -> Option<(String, usize)> {
+		let mut output = String::new();
+		let mut chunks = 0;
+		for chunk in receiver {
+			match chunk {
+				JsonChunk::Data(data) => {
+					output.push_str(&data);
+					chunks += 1;
+				}
+				JsonChunk::Done => return Some((output, chunks)),
+			}
+		}
+		None
+	}
+
+	#[test]
+	fn test_channel_reassembly() {
+		let mut expected = String::new();
+		write_items(&mut expected, 1000);
+
+		let (sender, receiver) = channel();
+		let worker = std::thread::spawn(move || {
+			let mut writer = ChannelWriter::new(sender, 256);
+			write_items(&mut writer, 1000);
+			writer.finish()
+		});
+		let (output, chunks) = receive_all(&receiver).unwrap();
+		worker.join().unwrap().unwrap();
+		assert_eq!(output, expected);
+		assert!(chunks >= expected.len() / 300);
+
+		// Dropping sends the rest and `Done` as well
+		let (sender, receiver) = channel();
+		write_items(&mut ChannelWriter::new(sender, 1 << 20), 1000);
+		assert_eq!(receive_all(&receiver).unwrap(), (expected, 1));
+	}
+
+	#[test]
+	fn test_channel_backpressure_blocks() {
+		let (sender, receiver) = sync_channel(1);
+		let done = Arc::new(AtomicBool::new(false));
+		let worker = std::thread::spawn({
+			let done = done.clone();
+			move || {
+				let mut writer = ChannelWriter::bounded(sender, 1, Backpressure::Block);
+				[1u8; 10].as_slice().write_json(&mut writer);
+				done.store(true, Ordering::SeqCst);
+				writer.finish()
+			}
+		});
+		std::thread::sleep(std::time::Duration::from_millis(50));
+		// The channel only holds one chunk, the producer waits for the receiver
+		assert!(!done.load(Ordering::SeqCst));
+		assert_eq!(receive_all(&receiver).unwrap().0, "[1,1,1,1,1,1,1,1,1,1]");
+		worker.join().unwrap().unwrap();
+		assert!(done.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn test_channel_errors() {
+		let (sender, receiver) = sync_channel(1);
+		let mut writer = ChannelWriter::bounded(sender, 4, Backpressure::Error);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("first", 1);
+		object.value("second", 2);
+		object.end();
+		assert_eq!(writer.error(), Some(ChannelError::Full));
+		assert_eq!(writer.finish(), Err(ChannelError::Full));
+		assert_eq!(
+			receiver.recv(),
+			Ok(JsonChunk::Data("{\"first\":".to_owned()))
+		);
+		// No `Done`, the output is incomplete
+		assert!(receiver.recv().is_err());
+
+		let (sender, receiver) = channel();
+		drop(receiver);
+		let mut writer = ChannelWriter::new(sender, 4);
+		write_items(&mut writer, 1000);
+		assert_eq!(writer.finish(), Err(ChannelError::Disconnected));
+
+		let (sender, receiver) = channel();
+		let result = std::thread::spawn(move || {
+			let mut writer = ChannelWriter::new(sender, 4);
+			let mut array = JSONArrayWriter::new(&mut writer);
+			array.value("before");
+			panic!("serializer failed");
+		})
+		.join();
+		assert!(result.is_err());
+		assert_eq!(receive_all(&receiver), None);
+	}
+}
@@ -0,0 +1,122 @@
+//!
+//! Values wrapped with a checksum of their serialized content.
+//!
+//! Enabled with the `checksum` feature.
+//!
+
+use crate::{JSONObjectWriter, JSONWriter, JSONWriterValue};
+use alloc::string::String;
+
+///
+/// Writes `{"data":<value>,"checksum":"<crc>"}`, where the checksum is the CRC-32 (IEEE) of the
+/// serialized `data` bytes as 8 lowercase hex digits.
+///
+/// The value is serialized compactly into a temporary buffer first, because the checksum has to be computed
+/// before it can be written. The data is written as is, so it stays compact with a `PrettyJSONWriter`
+/// and the checksum always covers exactly the bytes of the `data` value in the output.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Checksummed<T>(pub T);
+
+impl<T: JSONWriterValue> JSONWriterValue for Checksummed<T> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut data = String::new();
+		self.0.write_json(&mut data);
+		let crc = crc32(data.as_bytes());
+		let mut checksum = [0u8; 8];
+		for (index, digit) in checksum.iter_mut().enumerate() {
+			*digit = b"0123456789abcdef"[(crc >> (28 - index * 4)) as usize & 0xF];
+		}
+		let mut object = JSONObjectWriter::new(writer);
+		object.with_raw_writer("data", |writer| writer.json_fragment(&data));
+		// Checks can be omitted here: the checksum only contains hex digits
+		object.value("checksum", unsafe {
+			core::str::from_utf8_unchecked(&checksum)
+		});
+	}
+}
+
+/// Lookup table for the reflected CRC-32 polynomial 0xEDB88320
+static CRC_TABLE: [u32; 256] = {
+	let mut table = [0u32; 256];
+	let mut index = 0;
+	while index < 256 {
+		let mut crc = index as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 == 1 {
+				(crc >> 1) ^ 0xEDB8_8320
+			} else {
+				crc >> 1
+			};
+			bit += 1;
+		}
+		table[index] = crc;
+		index += 1;
+	}
+	table
+};
+
+/// Computes the CRC-32 (IEEE) of `bytes`, as used by zlib and PNG
+fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in bytes {
+		crc = CRC_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8);
+	}
+	!crc
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{to_json_string, AssocObject, PrettyJSONWriter};
+
+	/// Bitwise CRC-32 without the lookup table
+	fn crc32_reference(bytes: &[u8]) -> u32 {
+		let mut crc = !0u32;
+		for &byte in bytes {
+			crc ^= u32::from(byte);
+			for _ in 0..8 {
+				let mask = (crc & 1).wrapping_neg();
+				crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+			}
+		}
+		!crc
+	}
+
+	#[test]
+	fn test_crc32() {
+		assert_eq!(crc32(b""), 0);
+		assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+		let data: Vec<u8> = (0..5000u32).map(|i| (i * 31 + i / 7) as u8).collect();
+		assert_eq!(crc32(&data), crc32_reference(&data));
+	}
+
+	#[test]
+	fn test_checksum_matches_data() {
+		let values = vec![vec![1.5f64, -2.0], vec![]];
+		let data = to_json_string(&values);
+
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		Checksummed(&values).write_json(&mut pretty);
+		let expected_checksum = format!("{:08x}", crc32_reference(data.as_bytes()));
+		assert_eq!(
+			buffer,
+			format!("{{\n  \"data\": {data},\n  \"checksum\": \"{expected_checksum}\"\n}}")
+		);
+
+		let output = to_json_string(Checksummed(AssocObject(&[
+			("name", "中文 \"quoted\""),
+			("path", "a/b"),
+		])));
+		let data = output
+			.strip_prefix("{\"data\":")
+			.and_then(|rest| rest.split_once(",\"checksum\":\""))
+			.unwrap();
+		assert_eq!(
+			data.1,
+			format!("{:08x}\"}}", crc32_reference(data.0.as_bytes()))
+		);
+	}
+}
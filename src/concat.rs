@@ -0,0 +1,111 @@
+use crate::{JSONWriter, JSONWriterValue};
+use alloc::string::String;
+
+///
+/// Helper for writing multiple top-level values to the borrowed buffer (concatenated JSON).
+///
+/// Values are separated by a single space unless another separator is given.
+///
+pub struct ConcatJSONWriter<'a, W: JSONWriter = String> {
+	///
+	/// Mutable borrow of buffer
+	///
+	/// Consider using the methods instead of using this field directly.
+	/// This field should not be used unless you know what you are doing.
+	///
+	pub writer: &'a mut W,
+	separator: &'a str,
+	/// Number of values written
+	count: usize,
+}
+
+impl<'a, W: JSONWriter> ConcatJSONWriter<'a, W> {
+	///
+	/// Creates a new ConcatJSONWriter that separates values with a single space.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> ConcatJSONWriter<'a, W> {
+		ConcatJSONWriter::with_separator(writer, " ")
+	}
+
+	///
+	/// Creates a new ConcatJSONWriter that separates values with `separator`.
+	///
+	/// The separator is written as is and should only contain JSON whitespace.
+	///
+	#[inline(always)]
+	pub fn with_separator(writer: &'a mut W, separator: &'a str) -> ConcatJSONWriter<'a, W> {
+		ConcatJSONWriter {
+			writer,
+			separator,
+			count: 0,
+		}
+	}
+
+	///
+	/// Writes given value as the next top-level value
+	///
+	#[inline]
+	pub fn write_value<T: JSONWriterValue>(&mut self, value: T) {
+		if self.count > 0 {
+			self.writer.json_fragment(self.separator);
+		}
+		value.write_json(self.writer);
+		self.count += 1;
+	}
+
+	///
+	/// Returns the number of values written so far, e.g. the number of records of a JSON Lines stream.
+	///
+	/// A value only counts once it has been written completely, not if writing it panicked.
+	///
+	#[inline(always)]
+	pub fn count(&self) -> usize {
+		self.count
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONObjectWriter, NULL};
+
+	#[test]
+	fn test_concat() {
+		let mut buffer = String::new();
+		let mut concat = ConcatJSONWriter::new(&mut buffer);
+		concat.write_value(1u8);
+		concat.write_value("two");
+		concat.write_value(NULL);
+		assert_eq!(buffer, "1 \"two\" null");
+
+		let mut buffer = String::new();
+		let mut concat = ConcatJSONWriter::with_separator(&mut buffer, "\n");
+		concat.write_value(&vec![1u8]);
+		concat.write_value(true);
+		assert_eq!(buffer, "[1]\ntrue");
+	}
+
+	#[test]
+	fn test_concat_count() {
+		let mut buffer = String::new();
+		let mut lines = ConcatJSONWriter::with_separator(&mut buffer, "\n");
+		assert_eq!(lines.count(), 0);
+		for i in 0..5u32 {
+			lines.write_value(i);
+			assert_eq!(lines.count(), i as usize + 1);
+		}
+		struct Failing;
+		impl JSONWriterValue for Failing {
+			fn write_json<W: JSONWriter>(self, writer: &mut W) {
+				JSONObjectWriter::new(writer).value("partial", true);
+				panic!("record failed");
+			}
+		}
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			lines.write_value(Failing);
+		}));
+		assert!(result.is_err());
+		assert_eq!(lines.count(), 5);
+	}
+}
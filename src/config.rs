@@ -0,0 +1,745 @@
+use crate::{
+	write_escaped_byte, write_escaped_char, BufferedJSONWriter, EscapeBuffer, FragmentBuffer,
+	JSONWriter, NonFinite, NonFiniteValue, REPLACEMENTS,
+};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt::Write;
+
+///
+/// Function that replaces string values before they are escaped, see `ConfiguredWriter::string_transform`
+///
+pub type StringTransform = fn(&str) -> Cow<'_, str>;
+
+///
+/// How null is written by a `ConfiguredWriter`, see `WriterConfig::null_as`.
+///
+/// Every option except `Null` changes the meaning of the document for standard consumers,
+/// so they are only meant for consumers that expect that representation.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NullAs {
+	/// Writes `null`
+	#[default]
+	Null,
+	/// Writes the quoted string `"null"`
+	QuotedNull,
+	/// Writes an empty object `{}`
+	EmptyObject,
+	/// Writes an empty string `""`
+	EmptyString,
+}
+
+/// Largest integer that a JavaScript number represents exactly, `Number.MAX_SAFE_INTEGER`
+const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+///
+/// Escaping, number and layout options of a `ConfiguredWriter`.
+///
+/// The config is `Copy` and the presets are constants, so a config can be built once at startup,
+/// e.g. as a `static`, and shared by all writers:
+///
+/// ```rust
+/// use json_writer::{ConfiguredWriter, JSONObjectWriter, WriterConfig};
+/// static CONFIG: WriterConfig = WriterConfig::PRETTY.indent("\t").escape_slash(false);
+///
+/// let mut buffer = String::new();
+/// let mut writer = ConfiguredWriter::new(&mut buffer, &CONFIG);
+/// let mut object = JSONObjectWriter::new(&mut writer);
+/// object.value("url", "a/b");
+/// object.end();
+/// assert_eq!(buffer, "{\n\t\"url\": \"a/b\"\n}");
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WriterConfig {
+	escape_slash: bool,
+	escape_html: bool,
+	ascii_only: bool,
+	non_finite: NonFinite,
+	big_int_as_string: bool,
+	fraction_digits: Option<u8>,
+	indent: Option<&'static str>,
+	null_as: NullAs,
+}
+
+impl WriterConfig {
+	///
+	/// Compact output, identical to writing to a `String` directly
+	///
+	pub const COMPACT: WriterConfig = WriterConfig {
+		escape_slash: true,
+		escape_html: false,
+		ascii_only: false,
+		non_finite: NonFinite {
+			nan: NonFiniteValue::Null,
+			infinity: NonFiniteValue::Null,
+		},
+		big_int_as_string: false,
+		fraction_digits: None,
+		indent: None,
+		null_as: NullAs::Null,
+	};
+
+	///
+	/// Indented with two spaces, identical to `PrettyJSONWriter::new`
+	///
+	pub const PRETTY: WriterConfig = WriterConfig::COMPACT.indent("  ");
+
+	///
+	/// Compact output for JavaScript consumers: integers that `JSON.parse` cannot represent exactly
+	/// are written as strings, and `/` stays escaped, so `</script>` never appears in the output.
+	///
+	pub const JS_SAFE: WriterConfig = WriterConfig::COMPACT.big_int_as_string(true);
+
+	///
+	/// Compact output that escapes only what JSON requires, following the string rules of
+	/// JSON Canonicalization (RFC 8785). Keys are written in the order they are given;
+	/// use e.g. `SortedKeys` for a canonical key order.
+	///
+	pub const CANONICAL: WriterConfig = WriterConfig::COMPACT.escape_slash(false);
+
+	///
+	/// Whether `/` is escaped as `\/` in strings and keys. Enabled by default.
+	///
+	pub const fn escape_slash(mut self, escape_slash: bool) -> WriterConfig {
+		self.escape_slash = escape_slash;
+		self
+	}
+
+	///
+	/// Whether `<`, `>` and `&` are escaped as `\u003C`, `\u003E` and `\u0026`, so the output can be
+	/// embedded in HTML. Disabled by default.
+	///
+	pub const fn escape_html(mut self, escape_html: bool) -> WriterConfig {
+		self.escape_html = escape_html;
+		self
+	}
+
+	///
+	/// Whether non-ASCII characters are escaped as `\uXXXX`, using surrogate pairs outside the BMP.
+	/// Disabled by default.
+	///
+	pub const fn ascii_only(mut self, ascii_only: bool) -> WriterConfig {
+		self.ascii_only = ascii_only;
+		self
+	}
+
+	///
+	/// How NaN and infinite floats are written, null by default
+	///
+	pub const fn non_finite(mut self, non_finite: NonFinite) -> WriterConfig {
+		self.non_finite = non_finite;
+		self
+	}
+
+	///
+	/// Whether integers outside of ±(2^53 - 1) are written as strings. Disabled by default.
+	///
+	pub const fn big_int_as_string(mut self, big_int_as_string: bool) -> WriterConfig {
+		self.big_int_as_string = big_int_as_string;
+		self
+	}
+
+	///
+	/// Rounds floats to at most `digits` fraction digits, omitting trailing zeros.
+	///
+	/// By default, floats are written with the shortest representation that parses back to the same value.
+	/// Floats of at least 2^53 in magnitude are integers, they are written like without this option, e.g. `1e300`.
+	/// Negative numbers that round to zero are written as `0`.
+	///
+	pub const fn fraction_digits(mut self, digits: u8) -> WriterConfig {
+		self.fraction_digits = Some(digits);
+		self
+	}
+
+	///
+	/// Writes each value on its own line, indented with `indent` per nesting level, like `PrettyJSONWriter`
+	///
+	pub const fn indent(mut self, indent: &'static str) -> WriterConfig {
+		self.indent = Some(indent);
+		self
+	}
+
+	///
+	/// How null values are written, `NullAs::Null` by default.
+	///
+	/// NaN and infinity written as null because of `non_finite` are not affected.
+	///
+	pub const fn null_as(mut self, null_as: NullAs) -> WriterConfig {
+		self.null_as = null_as;
+		self
+	}
+
+	/// Returns true if strings are escaped like by the default writers
+	#[inline(always)]
+	fn default_escaping(&self) -> bool {
+		self.escape_slash && !self.escape_html && !self.ascii_only
+	}
+}
+
+impl Default for WriterConfig {
+	fn default() -> Self {
+		WriterConfig::COMPACT
+	}
+}
+
+///
+/// Wraps a writer and applies the options of a `WriterConfig` to everything written to it.
+///
+/// Writing with `WriterConfig::COMPACT` produces exactly the output of the wrapped writer.
+/// With an indentation, the wrapped writer should be compact, since the layout is written by this writer.
+///
+pub struct ConfiguredWriter<'c, W: JSONWriter = String> {
+	inner: W,
+	config: &'c WriterConfig,
+	depth: usize,
+	string_transform: Option<StringTransform>,
+}
+
+impl<'c, W: JSONWriter> ConfiguredWriter<'c, W> {
+	///
+	/// Creates a new writer that writes to `inner` according to `config`
+	///
+	#[inline(always)]
+	pub fn new(inner: W, config: &'c WriterConfig) -> ConfiguredWriter<'c, W> {
+		ConfiguredWriter {
+			inner,
+			config,
+			depth: 0,
+			string_transform: None,
+		}
+	}
+
+	///
+	/// Passes every string value through `transform` before it is escaped, e.g. to mask sensitive data
+	/// in one place instead of at every call site.
+	///
+	/// Keys and integers written as strings by `big_int_as_string` are not transformed.
+	/// Strings written in parts with `JSONStringWriter` are transformed part by part.
+	///
+	/// ```rust
+	/// use json_writer::{ConfiguredWriter, JSONObjectWriter, WriterConfig};
+	/// use std::borrow::Cow;
+	///
+	/// fn mask_digits(value: &str) -> Cow<'_, str> {
+	///     if value.bytes().any(|b| b.is_ascii_digit()) {
+	///         Cow::Owned(value.chars().map(|c| if c.is_ascii_digit() { 'X' } else { c }).collect())
+	///     } else {
+	///         Cow::Borrowed(value)
+	///     }
+	/// }
+	///
+	/// let mut buffer = String::new();
+	/// let mut writer = ConfiguredWriter::new(&mut buffer, &WriterConfig::COMPACT).string_transform(mask_digits);
+	/// let mut object = JSONObjectWriter::new(&mut writer);
+	/// object.value("card", "4111 1111 1111 1111");
+	/// object.value("amount", 12);
+	/// object.end();
+	/// assert_eq!(buffer, "{\"card\":\"XXXX XXXX XXXX XXXX\",\"amount\":12}");
+	/// ```
+	///
+	#[inline(always)]
+	pub fn string_transform(mut self, transform: StringTransform) -> ConfiguredWriter<'c, W> {
+		self.string_transform = Some(transform);
+		self
+	}
+
+	///
+	/// Returns the wrapped writer
+	///
+	#[inline(always)]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+
+	fn write_newline(&mut self, first: bool) {
+		self.inner.json_fragment(if first { "\n" } else { ",\n" });
+		self.write_indent();
+	}
+
+	fn write_indent(&mut self) {
+		if let Some(indent) = self.config.indent {
+			for _ in 0..self.depth {
+				self.inner.json_fragment(indent);
+			}
+		}
+	}
+
+	/// Escapes `value` according to the config and writes it with delimiting quotes
+	fn write_string(&mut self, value: &str) {
+		if self.config.default_escaping() {
+			self.inner.json_string(value);
+		} else {
+			self.inner.json_begin_string();
+			self.write_string_part(value);
+			self.inner.json_end_string();
+		}
+	}
+
+	/// Escapes `value` according to the config and writes it without delimiting quotes
+	fn write_string_part(&mut self, value: &str) {
+		let output = &mut FragmentBuffer(&mut self.inner);
+		let bytes = value.as_bytes();
+		let mut num_bytes_written = 0;
+		let mut index = 0;
+		while index < bytes.len() {
+			let cur_byte = bytes[index];
+			if cur_byte >= 0x80 {
+				if !self.config.ascii_only {
+					index += 1;
+					continue;
+				}
+				output.push_str(&value[num_bytes_written..index]);
+				let c = value[index..].chars().next().unwrap();
+				write_escaped_char(output, c);
+				index += c.len_utf8();
+				num_bytes_written = index;
+				continue;
+			}
+			let replacement = match cur_byte {
+				b'/' if !self.config.escape_slash => 0,
+				b'<' | b'>' | b'&' if self.config.escape_html => b'u',
+				_ => REPLACEMENTS[cur_byte as usize],
+			};
+			if replacement != 0 {
+				write_escaped_byte(
+					output,
+					value,
+					num_bytes_written,
+					index,
+					cur_byte,
+					replacement,
+				);
+				num_bytes_written = index + 1;
+			}
+			index += 1;
+		}
+		output.push_str(&value[num_bytes_written..]);
+	}
+}
+
+impl<W: JSONWriter> JSONWriter for ConfiguredWriter<'_, W> {
+	forward_json_writer!(|this| this.inner;
+		json_bool, json_begin_string, json_end_string, json_fragment, json_poison, json_reserve,
+		json_debug_len,
+	);
+
+	#[inline(always)]
+	fn json_null(&mut self) {
+		match self.config.null_as {
+			NullAs::Null => self.inner.json_null(),
+			NullAs::QuotedNull => self.inner.json_string("null"),
+			NullAs::EmptyObject => {
+				self.inner.json_begin_object();
+				self.inner.json_end_object(true);
+			}
+			NullAs::EmptyString => self.inner.json_string(""),
+		}
+	}
+
+	fn json_string(&mut self, value: &str) {
+		match self.string_transform {
+			Some(transform) => self.write_string(&transform(value)),
+			None => self.write_string(value),
+		}
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		let transformed;
+		let value = match self.string_transform {
+			Some(transform) => {
+				transformed = transform(value);
+				&*transformed
+			}
+			None => value,
+		};
+		if self.config.default_escaping() {
+			self.inner.json_string_part(value);
+		} else {
+			self.write_string_part(value);
+		}
+	}
+
+	fn json_number_f64(&mut self, value: f64) {
+		if !value.is_finite() {
+			self.config
+				.non_finite
+				.write_non_finite(&mut self.inner, value);
+			return;
+		}
+		// Floats of at least 2^53 are integers, there is nothing to round
+		let digits = self
+			.config
+			.fraction_digits
+			.filter(|_| value.abs() < 9_007_199_254_740_992.0);
+		if let Some(digits) = digits {
+			let mut buffer = NumberBuffer {
+				bytes: [0; NUMBER_BUFFER_LEN],
+				len: 0,
+			};
+			if write!(buffer, "{value:.*}", usize::from(digits)).is_ok() {
+				let mut number = buffer.as_str();
+				if number.contains('.') {
+					number = number.trim_end_matches('0').trim_end_matches('.');
+				}
+				// Negative numbers may round to zero, e.g. -0.004 with 2 digits
+				if number == "-0" {
+					number = "0";
+				}
+				self.inner.json_number_str(number);
+				return;
+			}
+		}
+		self.inner.json_number_f64(value);
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		if self.config.big_int_as_string && is_unsafe_integer(value) {
+			self.inner.json_string(value);
+		} else {
+			self.inner.json_number_str(value);
+		}
+	}
+
+	fn json_number_i64(&mut self, value: i64) {
+		if self.config.big_int_as_string && value.unsigned_abs() as u128 > MAX_SAFE_INTEGER {
+			self.inner.json_string(itoa::Buffer::new().format(value));
+		} else {
+			self.inner.json_number_i64(value);
+		}
+	}
+
+	fn json_number_u64(&mut self, value: u64) {
+		if self.config.big_int_as_string && value as u128 > MAX_SAFE_INTEGER {
+			self.inner.json_string(itoa::Buffer::new().format(value));
+		} else {
+			self.inner.json_number_u64(value);
+		}
+	}
+
+	fn json_number_i128(&mut self, value: i128) {
+		if self.config.big_int_as_string && value.unsigned_abs() > MAX_SAFE_INTEGER {
+			self.inner.json_string(itoa::Buffer::new().format(value));
+		} else {
+			self.inner.json_number_i128(value);
+		}
+	}
+
+	fn json_number_u128(&mut self, value: u128) {
+		if self.config.big_int_as_string && value > MAX_SAFE_INTEGER {
+			self.inner.json_string(itoa::Buffer::new().format(value));
+		} else {
+			self.inner.json_number_u128(value);
+		}
+	}
+
+	fn json_begin_object(&mut self) {
+		self.depth += 1;
+		self.inner.json_begin_object();
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.depth -= 1;
+		if self.config.indent.is_some() {
+			if !empty {
+				self.inner.json_fragment("\n");
+				self.write_indent();
+			}
+			self.inner.json_fragment("}");
+		} else {
+			self.inner.json_end_object(empty);
+		}
+	}
+
+	fn json_begin_array(&mut self) {
+		self.depth += 1;
+		self.inner.json_begin_array();
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.depth -= 1;
+		if self.config.indent.is_some() {
+			if !empty {
+				self.inner.json_fragment("\n");
+				self.write_indent();
+			}
+			self.inner.json_fragment("]");
+		} else {
+			self.inner.json_end_array(empty);
+		}
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		if self.config.indent.is_some() {
+			self.write_newline(first);
+		} else {
+			self.inner.json_begin_array_value(first);
+		}
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		if self.config.indent.is_none() && self.config.default_escaping() {
+			self.inner.json_object_key(key, first);
+			return;
+		}
+		if self.config.indent.is_some() {
+			self.write_newline(first);
+		} else if !first {
+			self.inner.json_fragment(",");
+		}
+		self.write_string(key);
+		self.inner.json_fragment(if self.config.indent.is_some() {
+			": "
+		} else {
+			":"
+		});
+	}
+}
+
+impl<W: BufferedJSONWriter> BufferedJSONWriter for ConfiguredWriter<'_, W> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.inner.take_buffer(out)
+	}
+}
+
+/// Returns true if `value` is an integer that a JavaScript number cannot represent exactly
+fn is_unsafe_integer(value: &str) -> bool {
+	let digits = value.strip_prefix('-').unwrap_or(value);
+	// Shorter integers are always safe
+	digits.len() >= 16
+		&& digits.bytes().all(|byte| byte.is_ascii_digit())
+		&& digits
+			.parse::<u128>()
+			.map_or(true, |number| number > MAX_SAFE_INTEGER)
+}
+
+/// Fits any float below 2^53 with 255 fraction digits: a sign, 16 integer digits, the '.' and the fraction
+const NUMBER_BUFFER_LEN: usize = 1 + 16 + 1 + 255;
+
+/// Formats a number on the stack
+struct NumberBuffer {
+	bytes: [u8; NUMBER_BUFFER_LEN],
+	len: usize,
+}
+
+impl NumberBuffer {
+	fn as_str(&self) -> &str {
+		// Checks can be omitted here: the buffer only contains complete strings written by `write_str`
+		unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+	}
+}
+
+impl Write for NumberBuffer {
+	fn write_str(&mut self, value: &str) -> core::fmt::Result {
+		let end = self.len + value.len();
+		if end > self.bytes.len() {
+			return Err(core::fmt::Error);
+		}
+		self.bytes[self.len..end].copy_from_slice(value.as_bytes());
+		self.len = end;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONArrayWriter, JSONObjectWriter, PrettyJSONWriter};
+
+	fn write_document<W: JSONWriter>(writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("text", "</a> é 😀\t");
+		object.value("a/b", 0.1f64 + 0.2);
+		object.value("big", u64::MAX);
+		object.value("small", -42i64);
+		object.value("nan", f64::NAN);
+		let mut list = object.array("list");
+		list.value(1i64);
+		list.value(9_007_199_254_740_993i64);
+		list.object();
+		list.array().value(true);
+		list.end();
+		object.object("empty");
+	}
+
+	fn render(config: &WriterConfig) -> String {
+		let mut buffer = String::new();
+		write_document(&mut ConfiguredWriter::new(&mut buffer, config));
+		buffer
+	}
+
+	#[test]
+	fn test_presets_match_writers() {
+		let mut plain = String::new();
+		write_document(&mut plain);
+		assert_eq!(render(&WriterConfig::default()), plain);
+
+		let mut pretty = String::new();
+		write_document(&mut PrettyJSONWriter::new(&mut pretty));
+		assert_eq!(render(&WriterConfig::PRETTY), pretty);
+	}
+
+	#[test]
+	fn test_config_matrix() {
+		let custom = WriterConfig::PRETTY
+			.indent("\t")
+			.ascii_only(true)
+			.escape_slash(false)
+			.escape_html(true)
+			.fraction_digits(3)
+			.big_int_as_string(true)
+			.non_finite(NonFinite {
+				nan: NonFiniteValue::String,
+				infinity: NonFiniteValue::Null,
+			});
+		let cases = [
+			(
+				WriterConfig::COMPACT,
+				r#"{"text":"<\/a> é 😀\t","a\/b":0.30000000000000004,"big":18446744073709551615,"small":-42,"nan":null,"list":[1,9007199254740993,{},[true]],"empty":{}}"#,
+			),
+			(
+				WriterConfig::JS_SAFE,
+				r#"{"text":"<\/a> é 😀\t","a\/b":0.30000000000000004,"big":"18446744073709551615","small":-42,"nan":null,"list":[1,"9007199254740993",{},[true]],"empty":{}}"#,
+			),
+			(
+				WriterConfig::CANONICAL,
+				r#"{"text":"</a> é 😀\t","a/b":0.30000000000000004,"big":18446744073709551615,"small":-42,"nan":null,"list":[1,9007199254740993,{},[true]],"empty":{}}"#,
+			),
+			(
+				custom,
+				"{\n\t\"text\": \"\\u003C/a\\u003E \\u00E9 \\uD83D\\uDE00\\t\",\n\t\"a/b\": 0.3,\n\t\"big\": \"18446744073709551615\",\n\t\"small\": -42,\n\t\"nan\": \"NaN\",\n\t\"list\": [\n\t\t1,\n\t\t\"9007199254740993\",\n\t\t{},\n\t\t[\n\t\t\ttrue\n\t\t]\n\t],\n\t\"empty\": {}\n}",
+			),
+		];
+		for (config, expected) in cases {
+			let output = render(&config);
+			assert_eq!(output, expected);
+			crate::dev::assert_valid_json(&output);
+		}
+	}
+
+	#[test]
+	fn test_fraction_digits() {
+		let config = WriterConfig::COMPACT.fraction_digits(2);
+		let mut buffer = String::new();
+		let mut writer = ConfiguredWriter::new(&mut buffer, &config);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		for value in [1.0f64, 2.5, 1.23456, -0.004, -0.0, -1.004, f64::INFINITY] {
+			array.value(value);
+		}
+		array.end();
+		assert_eq!(buffer, "[1,2.5,1.23,0,0,-1,null]");
+	}
+
+	#[test]
+	fn test_fraction_digits_large_values() {
+		let config = WriterConfig::COMPACT.fraction_digits(2);
+		let mut buffer = String::new();
+		let mut writer = ConfiguredWriter::new(&mut buffer, &config);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		for value in [
+			9_007_199_254_740_991.0f64,
+			9_007_199_254_740_992.0,
+			-1e20,
+			1e300,
+		] {
+			array.value(value);
+		}
+		array.end();
+		assert_eq!(buffer, "[9007199254740991,9007199254740992,-1e20,1e300]");
+
+		let config = WriterConfig::COMPACT.fraction_digits(255);
+		let mut buffer = String::new();
+		let mut writer = ConfiguredWriter::new(&mut buffer, &config);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		array.value(-4_503_599_627_370_495.5f64);
+		array.value(1e-300);
+		array.end();
+		assert_eq!(buffer, "[-4503599627370495.5,0]");
+	}
+
+	#[test]
+	fn test_string_transform() {
+		fn mask_digits(value: &str) -> Cow<'_, str> {
+			value.replace(|c: char| c.is_ascii_digit(), "X").into()
+		}
+
+		for (config, expected) in [
+			(
+				WriterConfig::COMPACT,
+				r#"{"card 1":"XXXX-XXXX","id":42,"big":18446744073709551615,"list":["aX",1.5,"bX\/X"]}"#,
+			),
+			(
+				WriterConfig::JS_SAFE.indent(""),
+				"{\n\"card 1\": \"XXXX-XXXX\",\n\"id\": 42,\n\"big\": \"18446744073709551615\",\n\"list\": [\n\"aX\",\n1.5,\n\"bX\\/X\"\n]\n}",
+			),
+		] {
+			let mut buffer = String::new();
+			let mut writer =
+				ConfiguredWriter::new(&mut buffer, &config).string_transform(mask_digits);
+			let mut object = JSONObjectWriter::new(&mut writer);
+			object.value("card 1", "1234-5678");
+			object.value("id", 42);
+			object.value("big", u64::MAX);
+			let mut list = object.array("list");
+			list.value("a1");
+			list.value(1.5);
+			let mut string = list.string_writer();
+			string.push_str("b2");
+			string.push_str("/3");
+			string.end();
+			list.end();
+			object.end();
+			assert_eq!(buffer, expected);
+		}
+	}
+
+	#[test]
+	fn test_null_as() {
+		for (null_as, expected) in [
+			(NullAs::Null, r#"{"a":null,"b":[null,1],"nan":null}"#),
+			(
+				NullAs::QuotedNull,
+				r#"{"a":"null","b":["null",1],"nan":null}"#,
+			),
+			(NullAs::EmptyObject, r#"{"a":{},"b":[{},1],"nan":null}"#),
+			(NullAs::EmptyString, r#"{"a":"","b":["",1],"nan":null}"#),
+		] {
+			let config = WriterConfig::COMPACT.null_as(null_as);
+			let mut buffer = String::new();
+			let mut writer = ConfiguredWriter::new(&mut buffer, &config);
+			let mut object = JSONObjectWriter::new(&mut writer);
+			object.value("a", ());
+			let mut array = object.array("b");
+			array.value(Option::<u8>::None);
+			array.value(Some(1));
+			array.end();
+			object.value("nan", f64::NAN);
+			object.end();
+			assert_eq!(buffer, expected);
+		}
+
+		let config = WriterConfig::PRETTY.null_as(NullAs::EmptyObject);
+		let mut buffer = String::new();
+		let mut writer = ConfiguredWriter::new(&mut buffer, &config);
+		JSONArrayWriter::new(&mut writer).value(Option::<u8>::None);
+		assert_eq!(buffer, "[\n  {}\n]");
+	}
+
+	#[test]
+	fn test_unsafe_integer() {
+		assert!(!is_unsafe_integer("9007199254740991"));
+		assert!(!is_unsafe_integer("-9007199254740991"));
+		assert!(is_unsafe_integer("9007199254740992"));
+		assert!(is_unsafe_integer("-9007199254740992"));
+		assert!(is_unsafe_integer("340282366920938463463374607431768211455"));
+		assert!(!is_unsafe_integer("1.2345678901234567e19"));
+	}
+}
@@ -0,0 +1,276 @@
+use crate::reformat::is_number;
+use crate::{JSONArrayWriter, JSONWriter};
+
+///
+/// Options of `write_csv_as_json_array`.
+///
+/// By default, the keys are taken from the header row of the reader, every cell is written as string
+/// and rows with fewer cells than headers are an error.
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CsvOptions<'a> {
+	infer_types: bool,
+	empty_as_null: bool,
+	pad_short_rows: bool,
+	headers: Option<&'a [&'a str]>,
+}
+
+impl<'a> CsvOptions<'a> {
+	///
+	/// Whether cells that are JSON numbers or `true`/`false` are written as numbers and booleans.
+	///
+	/// Only the exact JSON grammar is recognized, so cells like `007`, `+1`, `1.` or `True`
+	/// stay strings and nothing is lost. Disabled by default.
+	///
+	pub const fn infer_types(mut self, infer_types: bool) -> CsvOptions<'a> {
+		self.infer_types = infer_types;
+		self
+	}
+
+	///
+	/// Whether empty cells are written as null instead of `""`. Disabled by default.
+	///
+	pub const fn empty_as_null(mut self, empty_as_null: bool) -> CsvOptions<'a> {
+		self.empty_as_null = empty_as_null;
+		self
+	}
+
+	///
+	/// Whether the missing cells of rows with fewer cells than headers are written as null instead of
+	/// returning `CsvJsonError::RaggedRow`. Rows with more cells than headers are always an error.
+	///
+	/// The reader must be `flexible` for rows of different length to get here. Disabled by default.
+	///
+	pub const fn pad_short_rows(mut self, pad_short_rows: bool) -> CsvOptions<'a> {
+		self.pad_short_rows = pad_short_rows;
+		self
+	}
+
+	///
+	/// Uses `headers` as keys instead of the header row of the reader.
+	///
+	/// If the reader has a header row, it is still skipped. Readers without header row need this option,
+	/// otherwise their first row is used as keys and as the first row.
+	///
+	pub const fn headers(mut self, headers: &'a [&'a str]) -> CsvOptions<'a> {
+		self.headers = Some(headers);
+		self
+	}
+}
+
+///
+/// Error of `write_csv_as_json_array`
+///
+#[derive(Debug)]
+pub enum CsvJsonError {
+	/// Reading or parsing the CSV failed
+	Csv(csv::Error),
+	/// A row has a different number of cells than there are headers
+	RaggedRow {
+		/// Index of the row, starting at 0 for the first row after the header
+		row: u64,
+		/// Number of headers
+		expected: usize,
+		/// Number of cells in the row
+		found: usize,
+	},
+}
+
+impl core::fmt::Display for CsvJsonError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			CsvJsonError::Csv(err) => write!(f, "reading CSV failed: {err}"),
+			CsvJsonError::RaggedRow {
+				row,
+				expected,
+				found,
+			} => write!(f, "CSV row {row} has {found} cells, expected {expected}"),
+		}
+	}
+}
+
+impl std::error::Error for CsvJsonError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			CsvJsonError::Csv(err) => Some(err),
+			CsvJsonError::RaggedRow { .. } => None,
+		}
+	}
+}
+
+impl From<csv::Error> for CsvJsonError {
+	fn from(err: csv::Error) -> CsvJsonError {
+		CsvJsonError::Csv(err)
+	}
+}
+
+fn write_cell<W: JSONWriter>(writer: &mut W, cell: &str, options: &CsvOptions<'_>) {
+	if cell.is_empty() && options.empty_as_null {
+		writer.json_null();
+	} else if options.infer_types && (cell == "true" || cell == "false") {
+		writer.json_bool(cell == "true");
+	} else if options.infer_types && is_number(cell.as_bytes()) {
+		writer.json_number_str(cell);
+	} else {
+		writer.json_string(cell);
+	}
+}
+
+///
+/// Writes the rows of `reader` as an array of objects keyed by the headers, one row at a time.
+///
+/// Only the current row is held in memory, so a writer that flushes its output, like the `std::io::Write`
+/// based ones, converts CSV files of any size in bounded memory. Returns the number of rows written.
+/// On error, the array is closed after the rows written so far.
+///
+pub fn write_csv_as_json_array<R: std::io::Read, W: JSONWriter>(
+	reader: &mut csv::Reader<R>,
+	writer: &mut W,
+	options: CsvOptions<'_>,
+) -> Result<u64, CsvJsonError> {
+	let reader_headers;
+	let headers: Vec<&str> = if let Some(headers) = options.headers {
+		if reader.has_headers() {
+			// Reads the header row, so it isn't returned as record
+			reader.headers()?;
+		}
+		headers.to_vec()
+	} else {
+		reader_headers = reader.headers()?.clone();
+		reader_headers.iter().collect()
+	};
+
+	let mut array = JSONArrayWriter::new(writer);
+	let mut record = csv::StringRecord::new();
+	let mut rows = 0;
+	while reader.read_record(&mut record)? {
+		if record.len() > headers.len() || (record.len() < headers.len() && !options.pad_short_rows)
+		{
+			return Err(CsvJsonError::RaggedRow {
+				row: rows,
+				expected: headers.len(),
+				found: record.len(),
+			});
+		}
+		let mut object = array.object();
+		for (index, key) in headers.iter().enumerate() {
+			match record.get(index) {
+				Some(cell) => {
+					object.with_raw_writer(key, |writer| write_cell(writer, cell, &options));
+				}
+				None => object.value(key, ()),
+			}
+		}
+		object.end();
+		rows += 1;
+	}
+	array.end();
+	Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn convert(csv: &str, options: CsvOptions<'_>) -> Result<(String, u64), CsvJsonError> {
+		let mut reader = csv::ReaderBuilder::new()
+			.flexible(true)
+			.from_reader(csv.as_bytes());
+		let mut buffer = String::new();
+		let rows = write_csv_as_json_array(&mut reader, &mut buffer, options)?;
+		Ok((buffer, rows))
+	}
+
+	#[test]
+	fn test_csv_quoted_fields() {
+		let csv = "name,comment\n\"Doe, Jane\",\"line 1\nline \"\"2\"\"\"\nx,\n";
+		let (json, rows) = convert(csv, CsvOptions::default()).unwrap();
+		assert_eq!(rows, 2);
+		assert_eq!(
+			json,
+			r#"[{"name":"Doe, Jane","comment":"line 1\nline \"2\""},{"name":"x","comment":""}]"#
+		);
+
+		let (json, _) = convert("a\n\n", CsvOptions::default()).unwrap();
+		assert_eq!(json, "[]");
+	}
+
+	#[test]
+	fn test_csv_type_inference() {
+		let cells = [
+			("0", "0"),
+			("-12", "-12"),
+			("1.5e-3", "1.5e-3"),
+			(
+				"123456789012345678901234567890",
+				"123456789012345678901234567890",
+			),
+			("true", "true"),
+			("false", "false"),
+			("007", r#""007""#),
+			("-0", "-0"),
+			("+1", r#""+1""#),
+			("1.", r#""1.""#),
+			(".5", r#"".5""#),
+			(" 1", r#"" 1""#),
+			("0x1A", r#""0x1A""#),
+			("1_000", r#""1_000""#),
+			("NaN", r#""NaN""#),
+			("True", r#""True""#),
+			("", r#""""#),
+		];
+		let options = CsvOptions::default().infer_types(true);
+		for (cell, expected) in cells {
+			let csv = format!("v\n\"{cell}\"\n");
+			let (json, _) = convert(&csv, options).unwrap();
+			assert_eq!(json, format!(r#"[{{"v":{expected}}}]"#), "{cell}");
+		}
+
+		let (json, _) = convert("a,b\n1,\n", CsvOptions::default().empty_as_null(true)).unwrap();
+		assert_eq!(json, r#"[{"a":"1","b":null}]"#);
+	}
+
+	#[test]
+	fn test_csv_ragged_rows() {
+		let csv = "a,b,c\n1,2,3\n4,5\n";
+		let err = convert(csv, CsvOptions::default()).unwrap_err();
+		assert!(matches!(
+			err,
+			CsvJsonError::RaggedRow {
+				row: 1,
+				expected: 3,
+				found: 2
+			}
+		));
+
+		let (json, rows) = convert(csv, CsvOptions::default().pad_short_rows(true)).unwrap();
+		assert_eq!(rows, 2);
+		assert_eq!(
+			json,
+			r#"[{"a":"1","b":"2","c":"3"},{"a":"4","b":"5","c":null}]"#
+		);
+
+		let err = convert("a\n1,2\n", CsvOptions::default().pad_short_rows(true)).unwrap_err();
+		assert!(matches!(err, CsvJsonError::RaggedRow { found: 2, .. }));
+
+		// Without `flexible`, the reader rejects the row itself
+		let mut reader = csv::Reader::from_reader(csv.as_bytes());
+		let err = write_csv_as_json_array(&mut reader, &mut String::new(), CsvOptions::default());
+		assert!(matches!(err, Err(CsvJsonError::Csv(_))));
+	}
+
+	#[test]
+	fn test_csv_header_override() {
+		let options = CsvOptions::default().headers(&["x", "y"]).infer_types(true);
+		let (json, _) = convert("a,b\n1,2\n", options).unwrap();
+		assert_eq!(json, r#"[{"x":1,"y":2}]"#);
+
+		let mut reader = csv::ReaderBuilder::new()
+			.has_headers(false)
+			.from_reader("1,2\n3,4\n".as_bytes());
+		let mut buffer = String::new();
+		let rows = write_csv_as_json_array(&mut reader, &mut buffer, options).unwrap();
+		assert_eq!(rows, 2);
+		assert_eq!(buffer, r#"[{"x":1,"y":2},{"x":3,"y":4}]"#);
+	}
+}
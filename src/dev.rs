@@ -0,0 +1,270 @@
+//!
+//! Helpers for checking JSON output in tests.
+//!
+//! Enabled with the `dev` feature. The output is parsed with `serde_json`, with its `arbitrary_precision`
+//! feature so that numbers keep their text and large integers or long decimals are compared exactly.
+//!
+
+use serde_json::Value;
+
+///
+/// Panics if `json` is not a single valid JSON document.
+///
+pub fn assert_valid_json(json: &str) {
+	if let Err(err) = serde_json::from_str::<Value>(json) {
+		panic!("invalid JSON: {err}\n{json}");
+	}
+}
+
+///
+/// Panics unless `produced` and `expected` are valid JSON documents with equal values.
+///
+/// See `assert_json_eq!` for the comparison rules.
+///
+#[track_caller]
+pub fn assert_json_eq(produced: &str, expected: &str) {
+	let produced_value: Value = match serde_json::from_str(produced) {
+		Ok(value) => value,
+		Err(err) => panic!("produced invalid JSON: {err}\n{produced}"),
+	};
+	let expected_value: Value = match serde_json::from_str(expected) {
+		Ok(value) => value,
+		Err(err) => panic!("expected invalid JSON: {err}\n{expected}"),
+	};
+	let mut pointer = String::new();
+	if let Some((produced_part, expected_part)) =
+		first_difference(Some(&produced_value), Some(&expected_value), &mut pointer)
+	{
+		panic!(
+			"JSON values differ at \"{pointer}\"\nproduced: {}\nexpected: {}\nproduced document: {produced}\nexpected document: {expected}",
+			describe(produced_part),
+			describe(expected_part),
+		);
+	}
+}
+
+///
+/// Asserts that two JSON documents have equal values, ignoring whitespace and the order of object keys.
+///
+/// Both arguments can be anything that implements `AsRef<str>`. Arrays are compared in order.
+/// Numbers are equal when their canonical forms match, i.e. the same sign, significant digits and exponent,
+/// so `1`, `1.0` and `1e0` are equal, while large integers and long decimals are compared exactly, not as `f64`.
+/// If an object contains a key more than once, the last value is used.
+///
+/// On mismatch, the panic message contains the JSON Pointer of the first difference and both values there.
+///
+/// ```rust
+/// use json_writer::assert_json_eq;
+/// assert_json_eq!("{\"a\": 1, \"b\": [true]}", "{\"b\":[true],\"a\":1.0}");
+/// ```
+///
+#[macro_export]
+macro_rules! assert_json_eq {
+	($produced:expr, $expected:expr $(,)?) => {
+		$crate::dev::assert_json_eq(
+			::core::convert::AsRef::<str>::as_ref(&$produced),
+			::core::convert::AsRef::<str>::as_ref(&$expected),
+		)
+	};
+}
+
+/// Returns the values at the first difference, `pointer` is left pointing to it
+fn first_difference<'a>(
+	produced: Option<&'a Value>,
+	expected: Option<&'a Value>,
+	pointer: &mut String,
+) -> Option<(Option<&'a Value>, Option<&'a Value>)> {
+	let len = pointer.len();
+	match (produced, expected) {
+		(Some(Value::Array(produced_items)), Some(Value::Array(expected_items))) => {
+			for index in 0..produced_items.len().max(expected_items.len()) {
+				pointer.push('/');
+				pointer.push_str(&index.to_string());
+				let difference = first_difference(
+					produced_items.get(index),
+					expected_items.get(index),
+					pointer,
+				);
+				if difference.is_some() {
+					return difference;
+				}
+				pointer.truncate(len);
+			}
+			None
+		}
+		(Some(Value::Object(produced_members)), Some(Value::Object(expected_members))) => {
+			let keys: std::collections::BTreeSet<&String> = produced_members
+				.keys()
+				.chain(expected_members.keys())
+				.collect();
+			for key in keys {
+				pointer.push('/');
+				pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+				let difference = first_difference(
+					produced_members.get(key),
+					expected_members.get(key),
+					pointer,
+				);
+				if difference.is_some() {
+					return difference;
+				}
+				pointer.truncate(len);
+			}
+			None
+		}
+		(Some(Value::Number(produced_number)), Some(Value::Number(expected_number)))
+			if numbers_equal(&produced_number.to_string(), &expected_number.to_string()) =>
+		{
+			None
+		}
+		_ if produced == expected => None,
+		_ => Some((produced, expected)),
+	}
+}
+
+/// Compares two JSON numbers by their canonical form, or by their text if an exponent is out of range
+fn numbers_equal(a: &str, b: &str) -> bool {
+	match (canonical_number(a), canonical_number(b)) {
+		(Some(a), Some(b)) => a == b,
+		_ => a == b,
+	}
+}
+
+///
+/// Returns the sign, the significant digits without leading and trailing zeros and the exponent of the
+/// last digit of a JSON number, e.g. `(false, "15", -1)` for `1.50`.
+///
+/// All zeros are `(false, "", 0)`, so `-0` and `0.0` equal `0`.
+///
+fn canonical_number(text: &str) -> Option<(bool, String, i64)> {
+	let (negative, unsigned) = match text.strip_prefix('-') {
+		Some(unsigned) => (true, unsigned),
+		None => (false, text),
+	};
+	let (mantissa, mut exponent) = match unsigned.split_once(['e', 'E']) {
+		Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+		None => (unsigned, 0),
+	};
+	let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+	exponent = exponent.checked_sub(i64::try_from(fraction.len()).ok()?)?;
+	let digits = format!("{integer}{fraction}");
+	let digits = digits.trim_start_matches('0');
+	let significant = digits.trim_end_matches('0');
+	if significant.is_empty() {
+		return Some((false, String::new(), 0));
+	}
+	exponent = exponent.checked_add(i64::try_from(digits.len() - significant.len()).ok()?)?;
+	Some((negative, significant.to_owned(), exponent))
+}
+
+fn describe(value: Option<&Value>) -> String {
+	match value {
+		Some(value) => value.to_string(),
+		None => "(missing)".to_owned(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONObjectWriter, PrettyJSONWriter};
+
+	#[test]
+	fn test_output_is_valid_json() {
+		let tricky = "\"\\/\u{0}\u{1f}\u{7f}\u{8}\u{c}\n\r\t</script>中文😀";
+		let mut buffer = String::new();
+		{
+			let mut object = JSONObjectWriter::new(&mut buffer);
+			object.value(tricky, tricky);
+			let mut array = object.array("nested");
+			array.value(&vec![vec![1u8], vec![]][..]);
+			array.object().object("empty");
+			array.value(-1.5e-30f64);
+			array.value(f64::NAN);
+		}
+		assert_valid_json(&buffer);
+		assert_json_eq(
+			&buffer,
+			r#"{"nested":[[[1],[]],{"empty":{}},-1.5e-30,null],"\"\\/\u0000\u001f\u007f\b\f\n\r\t</script>中文\ud83d\ude00":"\"\\/\u0000\u001f\u007f\b\f\n\r\t</script>中文😀"}"#,
+		);
+
+		let mut compact_buffer = String::new();
+		JSONObjectWriter::new(&mut compact_buffer).value("a", &vec![tricky]);
+		let mut pretty_buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut pretty_buffer);
+		JSONObjectWriter::new(&mut pretty).value("a", &vec![tricky]);
+		assert_json_eq(&pretty_buffer, &compact_buffer);
+	}
+
+	#[test]
+	fn test_assert_json_eq_ignores_order_and_whitespace() {
+		assert_json_eq!(
+			"{\"b\": [1, {\"y\": null, \"x\": \"s\"}], \"a\": true}",
+			String::from("{\"a\":true,\"b\":[1.0,{\"x\":\"s\",\"y\":null}]}"),
+		);
+		assert_json_eq!("[1e2, -0, 0.0e5, 1.50, 25e-1]", "[100, 0, 0, 1.5, 2.5]");
+		assert_json_eq!("[0.100000000000000000001]", "[1.00000000000000000001e-1]");
+		// The last value of a duplicate key is used
+		assert_json_eq!("{\"a\": 1, \"a\": 2}", "{\"a\": 2}");
+	}
+
+	#[test]
+	fn test_assert_json_eq_reports_pointer() {
+		let cases = [
+			("[1, 2]", "[1, 3]", "\"/1\"\nproduced: 2\nexpected: 3\n"),
+			(
+				"[1]",
+				"[1, 3]",
+				"\"/1\"\nproduced: (missing)\nexpected: 3\n",
+			),
+			(
+				"{\"a\": {\"b/c\": [true]}}",
+				"{\"a\": {\"b/c\": [false]}}",
+				"\"/a/b~1c/0\"\nproduced: true\nexpected: false\n",
+			),
+			(
+				"{\"a~\": 1, \"z\": 2}",
+				"{\"z\": 2}",
+				"\"/a~0\"\nproduced: 1\nexpected: (missing)\n",
+			),
+			("{}", "[]", "\"\"\nproduced: {}\nexpected: []\n"),
+			(
+				"[9007199254740993]",
+				"[9007199254740992]",
+				"\"/0\"\nproduced: 9007199254740993\nexpected: 9007199254740992\n",
+			),
+			(
+				"{\"x\": 0.30000000000000000001}",
+				"{\"x\": 0.3}",
+				"\"/x\"\nproduced: 0.30000000000000000001\nexpected: 0.3\n",
+			),
+		];
+		for (produced, expected, message) in cases {
+			let panic =
+				std::panic::catch_unwind(|| assert_json_eq!(produced, expected)).unwrap_err();
+			let panic = panic.downcast_ref::<String>().unwrap();
+			assert!(
+				panic.starts_with(&format!("JSON values differ at {message}")),
+				"{panic}"
+			);
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "invalid JSON")]
+	fn test_invalid_json_is_detected() {
+		assert_valid_json("{\"a\":[1,]}");
+	}
+
+	#[test]
+	#[should_panic(expected = "invalid JSON")]
+	fn test_deeply_nested_json_is_rejected() {
+		assert_valid_json(&"[".repeat(100_000));
+	}
+
+	#[test]
+	#[should_panic(expected = "JSON values differ at \"/0\"")]
+	fn test_large_integers_are_compared_exactly() {
+		assert_json_eq("[12345678901234567890]", "[12345678901234567891]");
+	}
+}
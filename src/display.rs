@@ -0,0 +1,238 @@
+use crate::{write_part_of_string_impl, EscapeBuffer, JSONWriter, JSONWriterValue, PrettyLayout};
+use core::fmt;
+
+///
+/// Implements `Display` for a JSON value, writing it straight into the formatter without building a `String` first.
+///
+/// Useful in `format!`, `println!` or log macros. The value is written each time it is formatted, so it must be
+/// `Copy`; wrap a reference like `JsonDisplay(&value)` for values that are written by reference.
+/// The alternate flag `{:#}` writes the value indented like `JsonPretty`.
+///
+/// ```rust
+/// use json_writer::JsonDisplay;
+/// let scores = vec![1, 2, 3];
+/// assert_eq!(format!("scores={}", JsonDisplay(&scores)), "scores=[1,2,3]");
+/// assert_eq!(format!("{:#}", JsonDisplay(&scores[..1])), "[\n  1\n]");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct JsonDisplay<T>(pub T);
+
+///
+/// Implements `Display` for a JSON value like `JsonDisplay`, but indented with two spaces like `PrettyJSONWriter::new`
+///
+#[derive(Debug, Copy, Clone)]
+pub struct JsonPretty<T>(pub T);
+
+impl<T: JSONWriterValue + Copy> fmt::Display for JsonDisplay<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let pretty = f.alternate();
+		FormatterWriter::write(f, pretty, self.0)
+	}
+}
+
+impl<T: JSONWriterValue + Copy> fmt::Display for JsonPretty<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		FormatterWriter::write(f, true, self.0)
+	}
+}
+
+///
+/// Writes to a `Formatter`, keeping the first error because `JSONWriter` methods can't return it
+///
+struct FormatterWriter<'a, 'f> {
+	output: FormatterOutput<'a, 'f>,
+	/// Layout of `JsonPretty`, `None` for compact output
+	layout: Option<PrettyLayout<&'static str>>,
+}
+
+struct FormatterOutput<'a, 'f> {
+	f: &'a mut fmt::Formatter<'f>,
+	result: fmt::Result,
+	/// Bytes passed to the formatter, for the debug checks of the object and array writers
+	len: usize,
+}
+
+impl<'a, 'f> FormatterWriter<'a, 'f> {
+	fn write<T: JSONWriterValue>(
+		f: &'a mut fmt::Formatter<'f>,
+		pretty: bool,
+		value: T,
+	) -> fmt::Result {
+		let mut writer = FormatterWriter {
+			output: FormatterOutput {
+				f,
+				result: Ok(()),
+				len: 0,
+			},
+			// Same indentation as `PrettyJSONWriter::new`
+			layout: pretty.then(|| PrettyLayout::new("  ")),
+		};
+		value.write_json(&mut writer);
+		writer.output.result
+	}
+
+	fn begin(&mut self, bracket: &str) {
+		match &mut self.layout {
+			Some(layout) => layout.begin(&mut self.output, bracket),
+			None => self.output.push_str(bracket),
+		}
+	}
+
+	fn end(&mut self, empty: bool, bracket: &str) {
+		match &mut self.layout {
+			Some(layout) => layout.end(&mut self.output, empty, bracket),
+			None => self.output.push_str(bracket),
+		}
+	}
+
+	fn separator(&mut self, first: bool) {
+		match &self.layout {
+			Some(layout) => layout.line_start(&mut self.output, first),
+			None if !first => self.output.push_str(","),
+			None => {}
+		}
+	}
+}
+
+impl EscapeBuffer for FormatterOutput<'_, '_> {
+	#[inline(always)]
+	fn push_str(&mut self, value: &str) {
+		// Counted after errors too, so the debug checks don't mistake dropped writes for missing values
+		self.len += value.len();
+		if self.result.is_ok() {
+			self.result = self.f.write_str(value);
+		}
+	}
+}
+
+impl JSONWriter for FormatterWriter<'_, '_> {
+	fn json_string(&mut self, value: &str) {
+		self.output.push_str("\"");
+		write_part_of_string_impl(&mut self.output, value);
+		self.output.push_str("\"");
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		write_part_of_string_impl(&mut self.output, value);
+	}
+
+	fn json_begin_object(&mut self) {
+		self.begin("{");
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.end(empty, "}");
+	}
+
+	fn json_begin_array(&mut self) {
+		self.begin("[");
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.end(empty, "]");
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		self.separator(first);
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		self.separator(first);
+		self.json_string(key);
+		self.output
+			.push_str(if self.layout.is_some() { ": " } else { ":" });
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		self.output.push_str(value);
+	}
+
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.output.len)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{to_json_string, PrettyJSONWriter};
+	use std::collections::BTreeMap;
+	use std::sync::{Arc, Mutex};
+
+	fn sample() -> BTreeMap<String, Vec<&'static str>> {
+		let mut map = BTreeMap::new();
+		map.insert("a/b".to_owned(), vec!["x\"y", "ü\n"]);
+		map.insert("empty".to_owned(), Vec::new());
+		map
+	}
+
+	#[test]
+	fn test_display_matches_writers() {
+		let map = sample();
+		assert_eq!(format!("{}", JsonDisplay(&map)), to_json_string(&map));
+
+		let mut pretty = String::new();
+		(&map).write_json(&mut PrettyJSONWriter::new(&mut pretty));
+		assert_eq!(format!("{}", JsonPretty(&map)), pretty);
+		assert_eq!(format!("{:#}", JsonDisplay(&map)), pretty);
+		assert_eq!(format!("{}", JsonPretty(&Vec::<u8>::new())), "[]");
+		assert_eq!(format!("{}", JsonDisplay(1.5)), "1.5");
+	}
+
+	#[test]
+	fn test_display_error() {
+		/// Fails after accepting `capacity` bytes
+		struct Limited {
+			capacity: usize,
+		}
+
+		impl fmt::Write for Limited {
+			fn write_str(&mut self, s: &str) -> fmt::Result {
+				self.capacity = self.capacity.checked_sub(s.len()).ok_or(fmt::Error)?;
+				Ok(())
+			}
+		}
+
+		let map = sample();
+		let mut output = Limited { capacity: 10 };
+		assert!(fmt::write(&mut output, format_args!("{}", JsonDisplay(&map))).is_err());
+		let mut output = Limited { capacity: 1000 };
+		assert!(fmt::write(&mut output, format_args!("{}", JsonDisplay(&map))).is_ok());
+	}
+
+	/// Collects the output of the tracing formatter
+	#[derive(Clone, Default)]
+	struct Capture(Arc<Mutex<Vec<u8>>>);
+
+	impl std::io::Write for Capture {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().extend_from_slice(buf);
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_display_in_log_macro() {
+		let capture = Capture::default();
+		let writer = capture.clone();
+		let subscriber = tracing_subscriber::fmt()
+			.with_writer(move || writer.clone())
+			.without_time()
+			.with_target(false)
+			.finish();
+		let map = sample();
+		tracing::subscriber::with_default(subscriber, || {
+			tracing::info!(map = %JsonDisplay(&map), "loaded {}", JsonDisplay(&map["empty"]));
+		});
+		let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+		assert!(
+			output.contains(&format!("loaded [] map={}", to_json_string(&map))),
+			"{output}"
+		);
+	}
+}
@@ -0,0 +1,104 @@
+use crate::{BufferedJSONWriter, JSONWriter, JsonRoot};
+use alloc::string::String;
+
+///
+/// Owns a buffer and remembers whether writing to it was interrupted by a panic.
+///
+/// Writers that are dropped during a panic do not close their brackets.
+/// Use `is_poisoned()` after `std::panic::catch_unwind` to find out if the buffer should be discarded.
+///
+pub struct JsonDocument<W: JSONWriter = String> {
+	inner: W,
+	poisoned: bool,
+}
+
+impl<W: JSONWriter> JsonDocument<W> {
+	///
+	/// Creates a new document that writes to the given buffer.
+	///
+	#[inline(always)]
+	pub fn new(inner: W) -> JsonDocument<W> {
+		JsonDocument {
+			inner,
+			poisoned: false,
+		}
+	}
+
+	///
+	/// Returns a root for writing the single top-level value of the document.
+	///
+	#[inline(always)]
+	pub fn root(&mut self) -> JsonRoot<'_, JsonDocument<W>> {
+		JsonRoot::new(self)
+	}
+
+	///
+	/// Returns true if a writer was dropped during a panic, leaving the document incomplete.
+	///
+	#[inline(always)]
+	pub fn is_poisoned(&self) -> bool {
+		self.poisoned
+	}
+
+	///
+	/// Marks the document as incomplete.
+	///
+	/// Useful when writing was aborted in a way that did not unwind, e.g. with `panic = "abort"` handlers.
+	///
+	#[inline(always)]
+	pub fn poison(&mut self) {
+		self.poisoned = true;
+	}
+
+	///
+	/// Returns the buffer
+	///
+	#[inline(always)]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W: JSONWriter> JSONWriter for JsonDocument<W> {
+	forward_json_writer!(|this| this.inner;
+		json_null, json_bool, json_string, json_pre_escaped_string, json_escaped_string,
+		json_string_part, json_number_f64, json_numbers_f64, json_number_str, json_number_i64, json_number_u64,
+		json_number_i128, json_number_u128, json_begin_object, json_end_object, json_begin_array,
+		json_end_array, json_begin_string, json_end_string, json_begin_array_value, json_object_key,
+		json_pre_escaped_object_key, json_fragment, json_reserve, json_debug_len,
+	);
+
+	#[inline(always)]
+	fn json_poison(&mut self) {
+		self.poisoned = true;
+		self.inner.json_poison();
+	}
+}
+
+impl<W: BufferedJSONWriter> BufferedJSONWriter for JsonDocument<W> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.inner.take_buffer(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_document() {
+		let mut document = JsonDocument::new(String::new());
+		document.root().object().value("a", 1u8);
+		assert!(!document.is_poisoned());
+		document.poison();
+		assert!(document.is_poisoned());
+		assert_eq!(document.into_inner(), "{\"a\":1}");
+	}
+}
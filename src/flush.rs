@@ -0,0 +1,200 @@
+use std::io::Write;
+
+///
+/// Wraps the sink of a streamed document and flushes it every time another `interval` bytes have been written,
+/// e.g. to push the data of a large export down to the OS instead of letting it pile up.
+///
+/// Works with everything that writes to a `std::io::Write`, like `output_buffered_data`, `object_stream`
+/// and `SegmentedBuffer::write_to`. Once the interval is reached, the sink is flushed at the start of the next
+/// `write` call, so the distance between two flushes is at least `interval` bytes and at most `interval` plus one write.
+/// If that flush fails, `write` returns the error without writing anything, as the `Write` contract requires.
+///
+/// `Write::flush` of a `File` does not sync the data to disk; use `with_callback` to call
+/// `File::sync_data` instead:
+///
+/// ```rust
+/// use json_writer::{JSONArrayWriter, PeriodicFlush};
+/// use std::io::Write;
+/// let mut sink = PeriodicFlush::with_callback(Vec::new(), 64, |_buffer: &mut Vec<u8>| {
+///     // file.flush()?; file.get_ref().sync_data()
+///     Ok(())
+/// });
+/// let mut buffer = String::new();
+/// let mut array = JSONArrayWriter::new(&mut buffer);
+/// for i in 0..100u32 {
+///     array.value(i);
+///     if array.buffer_len() > 16 {
+///         array.output_buffered_data(&mut sink)?;
+///     }
+/// }
+/// array.end();
+/// sink.write_all(buffer.as_bytes())?;
+/// sink.flush()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+pub struct PeriodicFlush<S: Write, F: FnMut(&mut S) -> std::io::Result<()> = FlushFn<S>> {
+	inner: S,
+	on_flush: F,
+	interval: u64,
+	/// Bytes written since the last flush
+	pending: u64,
+}
+
+/// Callback of `PeriodicFlush::new`, calls `Write::flush`
+pub type FlushFn<S> = fn(&mut S) -> std::io::Result<()>;
+
+impl<S: Write> PeriodicFlush<S> {
+	///
+	/// Creates a new sink that calls `Write::flush` of `inner` every `interval` bytes.
+	///
+	/// Panics if `interval` is 0.
+	///
+	pub fn new(inner: S, interval: u64) -> PeriodicFlush<S> {
+		PeriodicFlush::with_callback(inner, interval, S::flush)
+	}
+}
+
+impl<S: Write, F: FnMut(&mut S) -> std::io::Result<()>> PeriodicFlush<S, F> {
+	///
+	/// Creates a new sink that calls `on_flush` with `inner` every `interval` bytes and when flushed explicitly.
+	///
+	/// Panics if `interval` is 0.
+	///
+	pub fn with_callback(inner: S, interval: u64, on_flush: F) -> PeriodicFlush<S, F> {
+		assert!(interval > 0, "flush interval must not be 0");
+		PeriodicFlush {
+			inner,
+			on_flush,
+			interval,
+			pending: 0,
+		}
+	}
+
+	///
+	/// Returns the wrapped sink
+	///
+	pub fn get_ref(&self) -> &S {
+		&self.inner
+	}
+
+	///
+	/// Returns the wrapped sink without flushing it
+	///
+	pub fn into_inner(self) -> S {
+		self.inner
+	}
+}
+
+impl<S: Write, F: FnMut(&mut S) -> std::io::Result<()>> Write for PeriodicFlush<S, F> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		// Flushing after the write would have to report an error for bytes that were already written
+		if self.pending >= self.interval {
+			self.flush()?;
+		}
+		let written = self.inner.write(buf)?;
+		self.pending += written as u64;
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		(self.on_flush)(&mut self.inner)?;
+		self.pending = 0;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{object_stream, JSONArrayWriter};
+
+	/// Records the number of bytes written at each flush
+	#[derive(Default)]
+	struct RecordingSink {
+		written: usize,
+		flushes: Vec<usize>,
+	}
+
+	impl Write for RecordingSink {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.written += buf.len();
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			self.flushes.push(self.written);
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_flush_cadence() {
+		let mut sink = PeriodicFlush::new(RecordingSink::default(), 1000);
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		let mut max_write = 0;
+		for i in 0..10_000u32 {
+			array.value(i);
+			if array.buffer_len() > 100 {
+				max_write = max_write.max(array.output_buffered_data(&mut sink).unwrap());
+			}
+		}
+		array.end();
+		sink.write_all(buffer.as_bytes()).unwrap();
+		sink.flush().unwrap();
+		let sink = sink.into_inner();
+
+		assert!(sink.flushes.len() >= sink.written / (1000 + max_write));
+		let (&last, periodic) = sink.flushes.split_last().unwrap();
+		assert_eq!(last, sink.written);
+		let mut previous = 0;
+		for &flush in periodic {
+			assert!(flush - previous >= 1000);
+			assert!(flush - previous < 1000 + max_write);
+			previous = flush;
+		}
+		assert!(last - previous < 1000 + max_write);
+	}
+
+	#[test]
+	fn test_failed_flush_writes_nothing() {
+		let mut fail = true;
+		let mut sink = PeriodicFlush::with_callback(Vec::new(), 4, |_: &mut Vec<u8>| {
+			if std::mem::take(&mut fail) {
+				Err(std::io::Error::other("disk full"))
+			} else {
+				Ok(())
+			}
+		});
+		assert_eq!(sink.write(b"12345").unwrap(), 5);
+		assert!(sink.write(b"678").is_err());
+		assert_eq!(sink.get_ref(), b"12345");
+		// The flush is retried by the next write
+		assert_eq!(sink.write(b"678").unwrap(), 3);
+		assert_eq!(sink.into_inner(), b"12345678");
+	}
+
+	#[test]
+	fn test_flush_callback() {
+		let mut syncs = 0;
+		let mut sink = PeriodicFlush::with_callback(Vec::new(), 10, |_: &mut Vec<u8>| {
+			syncs += 1;
+			Ok(())
+		});
+		object_stream(
+			&mut String::new(),
+			(0..10u32).map(|i| (i.to_string(), i)),
+			&mut sink,
+			5,
+		)
+		.unwrap();
+		sink.flush().unwrap();
+		let output = sink.into_inner();
+		assert_eq!(
+			output,
+			br#"{"0":0,"1":1,"2":2,"3":3,"4":4,"5":5,"6":6,"7":7,"8":8,"9":9}"#
+		);
+		assert_eq!(syncs, 6);
+	}
+}
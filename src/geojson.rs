@@ -0,0 +1,413 @@
+//!
+//! GeoJSON (RFC 7946) geometries and features.
+//!
+//! The geometries are values that write the coordinates of their iterators as they go, so nothing is collected
+//! into intermediate `Vec`s. Positions are `(longitude, latitude)` pairs. Coordinates are written with
+//! `JSONWriter::json_number_f64`, so their precision can be limited with `WriterConfig::fraction_digits`.
+//!
+//! The coordinates are written as given: the first and last position of polygon rings must be equal,
+//! and exterior rings should be counterclockwise and holes clockwise. Closing rings is left to the caller.
+//!
+//! ```rust
+//! use json_writer::geojson::{FeatureWriter, LineString, Point};
+//! let mut buffer = String::new();
+//! let mut features = FeatureWriter::new(&mut buffer);
+//! features.feature(Point(102.0, 0.5), |properties| properties.value("name", "a"));
+//! features.feature(LineString([(102.0, 0.0), (103.0, 1.0)]), |_| {});
+//! features.end();
+//! assert_eq!(
+//!     buffer,
+//!     concat!(
+//!         r#"{"type":"FeatureCollection","features":["#,
+//!         r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[102,0.5]},"properties":{"name":"a"}},"#,
+//!         r#"{"type":"Feature","geometry":{"type":"LineString","coordinates":[[102,0],[103,1]]},"properties":{}}"#,
+//!         "]}"
+//!     )
+//! );
+//! ```
+//!
+
+use crate::{BufferedJSONWriter, JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+use alloc::string::String;
+
+///
+/// Point geometry at longitude `.0` and latitude `.1`
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point(pub f64, pub f64);
+
+///
+/// MultiPoint geometry with the positions of an iterator
+///
+#[derive(Debug, Copy, Clone)]
+pub struct MultiPoint<I>(pub I);
+
+///
+/// LineString geometry with the positions of an iterator, which must yield at least two positions
+///
+#[derive(Debug, Copy, Clone)]
+pub struct LineString<I>(pub I);
+
+///
+/// MultiLineString geometry with the lines of an iterator, each an iterator of positions
+///
+#[derive(Debug, Copy, Clone)]
+pub struct MultiLineString<I>(pub I);
+
+///
+/// Polygon geometry with the rings of an iterator, each an iterator of positions.
+///
+/// The first ring is the exterior ring, the others are holes. Each ring must be closed by the caller,
+/// i.e. its last position must repeat the first one.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Polygon<I>(pub I);
+
+///
+/// MultiPolygon geometry with the polygons of an iterator, each an iterator of rings like `Polygon`
+///
+#[derive(Debug, Copy, Clone)]
+pub struct MultiPolygon<I>(pub I);
+
+fn write_position<W: JSONWriter>(
+	array: &mut JSONArrayWriter<'_, W>,
+	(longitude, latitude): (f64, f64),
+) {
+	let mut position = array.array();
+	position.value(longitude);
+	position.value(latitude);
+}
+
+fn write_positions<W, I>(array: &mut JSONArrayWriter<'_, W>, positions: I)
+where
+	W: JSONWriter,
+	I: IntoIterator<Item = (f64, f64)>,
+{
+	for position in positions {
+		write_position(array, position);
+	}
+}
+
+fn write_lines<W, I>(array: &mut JSONArrayWriter<'_, W>, lines: I)
+where
+	W: JSONWriter,
+	I: IntoIterator,
+	I::Item: IntoIterator<Item = (f64, f64)>,
+{
+	for line in lines {
+		write_positions(&mut array.array(), line);
+	}
+}
+
+/// Writes a geometry object of type `kind`, `coordinates` writes the items of its coordinates array
+fn write_geometry<W: JSONWriter>(
+	writer: &mut W,
+	kind: &str,
+	coordinates: impl FnOnce(&mut JSONArrayWriter<'_, W>),
+) {
+	let mut object = JSONObjectWriter::new(writer);
+	object.value("type", kind);
+	coordinates(&mut object.array("coordinates"));
+}
+
+impl JSONWriterValue for Point {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("type", "Point");
+		let mut coordinates = object.array("coordinates");
+		coordinates.value(self.0);
+		coordinates.value(self.1);
+	}
+}
+
+impl<I: IntoIterator<Item = (f64, f64)>> JSONWriterValue for MultiPoint<I> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		write_geometry(writer, "MultiPoint", |array| write_positions(array, self.0));
+	}
+}
+
+impl<I: IntoIterator<Item = (f64, f64)>> JSONWriterValue for LineString<I> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		write_geometry(writer, "LineString", |array| write_positions(array, self.0));
+	}
+}
+
+impl<I> JSONWriterValue for MultiLineString<I>
+where
+	I: IntoIterator,
+	I::Item: IntoIterator<Item = (f64, f64)>,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		write_geometry(writer, "MultiLineString", |array| {
+			write_lines(array, self.0);
+		});
+	}
+}
+
+impl<I> JSONWriterValue for Polygon<I>
+where
+	I: IntoIterator,
+	I::Item: IntoIterator<Item = (f64, f64)>,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		write_geometry(writer, "Polygon", |array| write_lines(array, self.0));
+	}
+}
+
+impl<I> JSONWriterValue for MultiPolygon<I>
+where
+	I: IntoIterator,
+	I::Item: IntoIterator,
+	<I::Item as IntoIterator>::Item: IntoIterator<Item = (f64, f64)>,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		write_geometry(writer, "MultiPolygon", |array| {
+			for polygon in self.0 {
+				write_lines(&mut array.array(), polygon);
+			}
+		});
+	}
+}
+
+///
+/// Writes a Feature object with the given geometry and the properties written by `properties`.
+///
+/// `geometry` is usually one of the geometries of this module, or `()` for a feature without location.
+/// If `properties` writes nothing, the properties are an empty object.
+///
+pub fn write_feature<W, G, P>(writer: &mut W, geometry: G, properties: P)
+where
+	W: JSONWriter,
+	G: JSONWriterValue,
+	P: FnOnce(&mut JSONObjectWriter<'_, W>),
+{
+	let mut object = JSONObjectWriter::new(writer);
+	object.value("type", "Feature");
+	object.value("geometry", geometry);
+	properties(&mut object.object("properties"));
+}
+
+///
+/// Writes a FeatureCollection object, one feature at a time.
+///
+/// Writes the start of the collection on creation and closes it when dropped.
+/// Nothing is appended when dropped during a panic, leaving the output detectably incomplete.
+/// Code that runs during unwinding, e.g. a `Drop` impl, closes the collection with `end`.
+///
+pub struct FeatureWriter<'a, W: JSONWriter = String> {
+	writer: &'a mut W,
+	empty: bool,
+}
+
+impl<'a, W: JSONWriter> FeatureWriter<'a, W> {
+	///
+	/// Creates a new feature collection. Writes `{"type":"FeatureCollection","features":[` immediately.
+	///
+	pub fn new(writer: &'a mut W) -> FeatureWriter<'a, W> {
+		writer.json_begin_object();
+		writer.json_object_key("type", true);
+		writer.json_string("FeatureCollection");
+		writer.json_object_key("features", false);
+		writer.json_begin_array();
+		FeatureWriter {
+			writer,
+			empty: true,
+		}
+	}
+
+	///
+	/// Writes a Feature with the given geometry and the properties written by `properties`, see `write_feature`
+	///
+	pub fn feature<G, P>(&mut self, geometry: G, properties: P)
+	where
+		G: JSONWriterValue,
+		P: FnOnce(&mut JSONObjectWriter<'_, W>),
+	{
+		self.writer.json_begin_array_value(self.empty);
+		self.empty = false;
+		write_feature(self.writer, geometry, properties);
+	}
+
+	///
+	/// Closes the collection and consumes the writer, also while the thread is panicking.
+	///
+	#[inline(always)]
+	pub fn end(self) {
+		let mut this = core::mem::ManuallyDrop::new(self);
+		this.close();
+	}
+
+	fn close(&mut self) {
+		self.writer.json_end_array(self.empty);
+		self.writer.json_end_object(false);
+	}
+}
+
+impl<W: BufferedJSONWriter> FeatureWriter<'_, W> {
+	///
+	/// Writes the entire buffer to given writer and clears entire buffer on success.
+	///
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	pub fn output_buffered_data<Writer: std::io::Write>(
+		&mut self,
+		writer: &mut Writer,
+	) -> Result<usize, std::io::Error> {
+		self.writer.take_buffer(writer)
+	}
+
+	///
+	/// Returns buffer length in bytes
+	///
+	#[inline(always)]
+	pub fn buffer_len(&self) -> usize {
+		self.writer.buffer_len()
+	}
+}
+
+impl<W: JSONWriter> Drop for FeatureWriter<'_, W> {
+	fn drop(&mut self) {
+		if crate::thread_panicking() {
+			self.writer.json_poison();
+			return;
+		}
+		self.close();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{to_json_string, ConfiguredWriter, WriterConfig};
+
+	#[test]
+	fn test_geometries() {
+		// The examples of RFC 7946 appendix A
+		assert_eq!(
+			to_json_string(Point(100.0, 0.0)),
+			r#"{"type":"Point","coordinates":[100,0]}"#
+		);
+		assert_eq!(
+			to_json_string(LineString([(100.0, 0.0), (101.0, 1.0)])),
+			r#"{"type":"LineString","coordinates":[[100,0],[101,1]]}"#
+		);
+		let exterior = [
+			(100.0, 0.0),
+			(101.0, 0.0),
+			(101.0, 1.0),
+			(100.0, 1.0),
+			(100.0, 0.0),
+		];
+		let hole = [
+			(100.8, 0.8),
+			(100.8, 0.2),
+			(100.2, 0.2),
+			(100.2, 0.8),
+			(100.8, 0.8),
+		];
+		assert_eq!(
+			to_json_string(Polygon(
+				[exterior.as_slice(), hole.as_slice()].map(|ring| ring.iter().copied())
+			)),
+			concat!(
+				r#"{"type":"Polygon","coordinates":["#,
+				r#"[[100,0],[101,0],[101,1],[100,1],[100,0]],"#,
+				r#"[[100.8,0.8],[100.8,0.2],[100.2,0.2],[100.2,0.8],[100.8,0.8]]"#,
+				"]}"
+			)
+		);
+		assert_eq!(
+			to_json_string(MultiPoint([(100.0, 0.0), (101.0, 1.0)])),
+			r#"{"type":"MultiPoint","coordinates":[[100,0],[101,1]]}"#
+		);
+		assert_eq!(
+			to_json_string(MultiLineString([
+				[(100.0, 0.0), (101.0, 1.0)],
+				[(102.0, 2.0), (103.0, 3.0)]
+			])),
+			r#"{"type":"MultiLineString","coordinates":[[[100,0],[101,1]],[[102,2],[103,3]]]}"#
+		);
+		let square = |x: f64, y: f64| {
+			[
+				(x, y),
+				(x + 1.0, y),
+				(x + 1.0, y + 1.0),
+				(x, y + 1.0),
+				(x, y),
+			]
+		};
+		assert_eq!(
+			to_json_string(MultiPolygon([
+				vec![square(102.0, 2.0)],
+				vec![square(100.0, 0.0)]
+			])),
+			concat!(
+				r#"{"type":"MultiPolygon","coordinates":["#,
+				r#"[[[102,2],[103,2],[103,3],[102,3],[102,2]]],"#,
+				r#"[[[100,0],[101,0],[101,1],[100,1],[100,0]]]"#,
+				"]}"
+			)
+		);
+	}
+
+	#[test]
+	fn test_feature() {
+		let mut buffer = String::new();
+		write_feature(&mut buffer, (), |_| {});
+		assert_eq!(
+			buffer,
+			r#"{"type":"Feature","geometry":null,"properties":{}}"#
+		);
+
+		let mut buffer = String::new();
+		FeatureWriter::new(&mut buffer);
+		assert_eq!(buffer, r#"{"type":"FeatureCollection","features":[]}"#);
+
+		// Precision is limited by the writer config
+		let mut buffer = String::new();
+		let config = WriterConfig::COMPACT.fraction_digits(6);
+		let mut writer = ConfiguredWriter::new(&mut buffer, &config);
+		write_feature(
+			&mut writer,
+			Point(8.123456789, 47.000000123),
+			|properties| {
+				properties.value("id", 1);
+			},
+		);
+		assert_eq!(
+			buffer,
+			r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[8.123457,47]},"properties":{"id":1}}"#
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_feature_collection_stream() {
+		let mut output = Vec::new();
+		let mut buffer = String::new();
+		let mut features = FeatureWriter::new(&mut buffer);
+		for i in 0..100_000u32 {
+			let x = (i % 360) as f64 - 180.0;
+			let y = (i % 180) as f64 / 2.0 - 45.0;
+			features.feature(LineString([(x, y), (x + 0.5, y + 0.25)]), |properties| {
+				properties.value("id", i);
+			});
+			if features.buffer_len() > 64 * 1024 {
+				features.output_buffered_data(&mut output).unwrap();
+			}
+		}
+		features.end();
+		output.extend_from_slice(buffer.as_bytes());
+
+		let parsed: serde_json::Value = serde_json::from_slice(&output).unwrap();
+		assert_eq!(parsed["type"], "FeatureCollection");
+		let features = parsed["features"].as_array().unwrap();
+		assert_eq!(features.len(), 100_000);
+		assert_eq!(features[99_999]["properties"]["id"], 99_999);
+		assert_eq!(features[361]["geometry"]["coordinates"][1][0], -178.5);
+		assert!(features
+			.iter()
+			.all(|feature| feature["type"] == "Feature"
+				&& feature["geometry"]["type"] == "LineString"));
+	}
+}
@@ -0,0 +1,158 @@
+use crate::{write_string, JSONArrayWriter, JSONObjectWriter, JSONWriter};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+///
+/// Cache of escaped string values for `value_interned`.
+///
+/// Strings are identified by the address of their `Arc`, so clones of the same `Arc` share one entry.
+/// Every distinct string is escaped once and kept, together with a reference to the original,
+/// until the cache is cleared or dropped. This trades memory for speed: it only pays off
+/// for documents where the same values repeat many times, e.g. category labels.
+///
+/// The cached form uses the default escaping of `write_string`. It is written with
+/// `JSONWriter::json_escaped_string`, which only copies it for writers with the default escaping,
+/// like `String` and `Vec<u8>`. Other writers, e.g. `ConfiguredWriter` with `ascii_only` or `CborWriter`,
+/// write the original string as usual.
+///
+#[derive(Debug, Default)]
+pub struct StringCache {
+	/// Keyed by the address of the string. The `Arc` keeps the address from being reused.
+	escaped: HashMap<usize, (Arc<str>, Box<str>)>,
+}
+
+impl StringCache {
+	///
+	/// Creates an empty cache
+	///
+	pub fn new() -> StringCache {
+		StringCache::default()
+	}
+
+	///
+	/// Returns the quoted and escaped form of `value`, escaping it on first use.
+	///
+	pub fn escaped(&mut self, value: &Arc<str>) -> &str {
+		let address = Arc::as_ptr(value).cast::<u8>() as usize;
+		let (_, escaped) = self.escaped.entry(address).or_insert_with(|| {
+			let mut result = String::with_capacity(value.len() + 2);
+			write_string(&mut result, value);
+			(Arc::clone(value), result.into_boxed_str())
+		});
+		escaped
+	}
+
+	///
+	/// Returns the number of cached strings
+	///
+	pub fn len(&self) -> usize {
+		self.escaped.len()
+	}
+
+	///
+	/// Returns true if no strings are cached
+	///
+	pub fn is_empty(&self) -> bool {
+		self.escaped.is_empty()
+	}
+
+	///
+	/// Removes all cached strings
+	///
+	pub fn clear(&mut self) {
+		self.escaped.clear();
+	}
+}
+
+impl<W: JSONWriter> JSONObjectWriter<'_, W> {
+	///
+	/// Escapes key and appends key:value to the buffer, using the escaped value from `cache`
+	///
+	#[inline]
+	pub fn value_interned(&mut self, key: &str, value: &Arc<str>, cache: &mut StringCache) {
+		self.key(key);
+		self.writer.json_escaped_string(value, cache.escaped(value));
+	}
+}
+
+impl<W: JSONWriter> JSONArrayWriter<'_, W> {
+	///
+	/// Writes given string as array entry, using the escaped value from `cache`
+	///
+	#[inline]
+	pub fn value_interned(&mut self, value: &Arc<str>, cache: &mut StringCache) {
+		self.comma();
+		self.writer.json_escaped_string(value, cache.escaped(value));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{CborWriter, ConfiguredWriter, WriterConfig};
+
+	#[test]
+	fn test_value_interned() {
+		let mut cache = StringCache::new();
+		let label: Arc<str> = Arc::from("cat\"egory");
+		let other: Arc<str> = Arc::from("other");
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		for i in 0..4 {
+			let mut object = array.object();
+			object.value("id", i);
+			object.value_interned(
+				"label",
+				if i % 2 == 0 { &label } else { &other },
+				&mut cache,
+			);
+		}
+		array.value_interned(&label, &mut cache);
+		array.end();
+
+		assert_eq!(cache.len(), 2);
+		assert_eq!(
+			buffer,
+			r#"[{"id":0,"label":"cat\"egory"},{"id":1,"label":"other"},{"id":2,"label":"cat\"egory"},{"id":3,"label":"other"},"cat\"egory"]"#
+		);
+
+		// Keyed by the Arc, not by the content
+		let copy: Arc<str> = Arc::from("other");
+		assert_eq!(cache.escaped(&copy), "\"other\"");
+		assert_eq!(cache.escaped(&Arc::clone(&label)), "\"cat\\\"egory\"");
+		assert_eq!(cache.len(), 3);
+		cache.clear();
+		assert!(cache.is_empty());
+	}
+
+	#[test]
+	fn test_value_interned_applies_writer_policies() {
+		let mut cache = StringCache::new();
+		let label: Arc<str> = Arc::from("caf\u{e9} \"</b>\"");
+		let config = WriterConfig::COMPACT.ascii_only(true).escape_html(true);
+		let write = |interned: bool, cache: &mut StringCache| {
+			let mut writer = ConfiguredWriter::new(String::new(), &config);
+			let mut array = JSONArrayWriter::new(&mut writer);
+			for _ in 0..2 {
+				if interned {
+					array.value_interned(&label, cache);
+				} else {
+					array.value(&*label);
+				}
+			}
+			array.end();
+			writer.into_inner()
+		};
+		let expected = write(false, &mut cache);
+		assert!(expected.is_ascii());
+		assert_eq!(write(true, &mut cache), expected);
+
+		let mut cbor = Vec::new();
+		JSONArrayWriter::new(&mut CborWriter::new(&mut cbor)).value_interned(&label, &mut cache);
+		let decoded: String = ciborium::from_reader::<Vec<String>, _>(cbor.as_slice())
+			.unwrap()
+			.remove(0);
+		assert_eq!(decoded, *label);
+	}
+}
@@ -0,0 +1,324 @@
+use crate::{BufferedJSONWriter, JSONWriter};
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+///
+/// Case style that a `KeyCaseWriter` converts keys to
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyCase {
+	/// `userId`
+	CamelCase,
+	/// `user_id`
+	SnakeCase,
+	/// `user-id`
+	KebabCase,
+	/// `UserId`
+	PascalCase,
+}
+
+impl KeyCase {
+	///
+	/// Converts `key` to this case, borrowing it without allocating if it is already in this case.
+	///
+	/// Words are separated by `_`, `-` and spaces, by a lowercase letter or digit followed by an uppercase letter,
+	/// and at the end of an acronym like `HTTPServer`. Acronyms are written like other words, e.g. `userID`
+	/// becomes `userId` in camel case. Converting a converted key again doesn't change it.
+	///
+	/// Different keys can convert to the same key: `a_b` and `aB` both become `aB` in camel case, and
+	/// separators at the start and end are dropped, so `__typename` becomes `typename`. An object with
+	/// such keys is written with duplicate keys.
+	///
+	/// ```rust
+	/// use json_writer::KeyCase;
+	/// assert_eq!(KeyCase::CamelCase.convert("io_uring_fd"), "ioUringFd");
+	/// assert_eq!(KeyCase::SnakeCase.convert("HTTPServer"), "http_server");
+	/// ```
+	///
+	pub fn convert(self, key: &str) -> Cow<'_, str> {
+		let mut rest = key.chars();
+		let mut unchanged = true;
+		self.convert_with(key, |c| unchanged &= rest.next() == Some(c));
+		if unchanged && rest.next().is_none() {
+			Cow::Borrowed(key)
+		} else {
+			let mut converted = String::with_capacity(key.len());
+			self.convert_into(key, &mut converted);
+			Cow::Owned(converted)
+		}
+	}
+
+	/// Appends `key` converted to this case to `output`
+	fn convert_into(self, key: &str, output: &mut String) {
+		self.convert_with(key, |c| output.push(c));
+	}
+
+	/// Calls `f` with each char of `key` converted to this case
+	fn convert_with(self, key: &str, mut f: impl FnMut(char)) {
+		let separator = match self {
+			KeyCase::CamelCase | KeyCase::PascalCase => None,
+			KeyCase::SnakeCase => Some('_'),
+			KeyCase::KebabCase => Some('-'),
+		};
+		let mut first = true;
+		for_each_word(key, |word| {
+			if let (false, Some(separator)) = (first, separator) {
+				f(separator);
+			}
+			let capitalize = match self {
+				KeyCase::CamelCase => !first,
+				KeyCase::PascalCase => true,
+				KeyCase::SnakeCase | KeyCase::KebabCase => false,
+			};
+			let mut chars = word.chars();
+			if capitalize {
+				if let Some(c) = chars.next() {
+					c.to_uppercase().for_each(&mut f);
+				}
+			}
+			for c in chars {
+				c.to_lowercase().for_each(&mut f);
+			}
+			first = false;
+		});
+	}
+}
+
+/// Calls `f` with each word of `key`, see `KeyCase::convert`
+fn for_each_word(key: &str, mut f: impl FnMut(&str)) {
+	let mut start = None;
+	let mut previous: Option<char> = None;
+	let mut chars = key.char_indices().peekable();
+	while let Some((index, c)) = chars.next() {
+		if matches!(c, '_' | '-' | ' ') {
+			if let Some(start) = start.take() {
+				f(&key[start..index]);
+			}
+			previous = None;
+			continue;
+		}
+		if let (Some(word_start), Some(previous)) = (start, previous) {
+			let next_is_lowercase = chars.peek().is_some_and(|&(_, next)| next.is_lowercase());
+			let boundary = c.is_uppercase()
+				&& (previous.is_lowercase()
+					|| previous.is_ascii_digit()
+					|| (previous.is_uppercase() && next_is_lowercase));
+			if boundary {
+				f(&key[word_start..index]);
+				start = Some(index);
+			}
+		}
+		start.get_or_insert(index);
+		previous = Some(c);
+	}
+	if let Some(start) = start {
+		f(&key[start..]);
+	}
+}
+
+///
+/// Wraps a writer and converts every object key to a case style, e.g. snake_case field names to camelCase.
+///
+/// Only keys are converted, values are written unchanged. Keys are converted into a buffer that is reused
+/// for all keys, so no allocation is needed per key. Keys that collide after the conversion, see
+/// `KeyCase::convert`, are written as duplicate keys.
+///
+/// ```rust
+/// use json_writer::{JSONObjectWriter, KeyCase, KeyCaseWriter};
+/// let mut buffer = String::new();
+/// let mut writer = KeyCaseWriter::new(&mut buffer, KeyCase::CamelCase);
+/// let mut object = JSONObjectWriter::new(&mut writer);
+/// object.value("user_id", 1);
+/// object.value("display_name", "snake_case value");
+/// object.end();
+/// assert_eq!(buffer, r#"{"userId":1,"displayName":"snake_case value"}"#);
+/// ```
+///
+pub struct KeyCaseWriter<W: JSONWriter> {
+	inner: W,
+	case: KeyCase,
+	/// Reused buffer for converted keys
+	key: String,
+}
+
+impl<W: JSONWriter> KeyCaseWriter<W> {
+	///
+	/// Creates a new writer that converts keys to `case` and writes to `inner`
+	///
+	#[inline(always)]
+	pub fn new(inner: W, case: KeyCase) -> KeyCaseWriter<W> {
+		KeyCaseWriter {
+			inner,
+			case,
+			key: String::new(),
+		}
+	}
+
+	///
+	/// Returns the wrapped writer
+	///
+	#[inline(always)]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W: JSONWriter> JSONWriter for KeyCaseWriter<W> {
+	forward_json_writer!(|this| this.inner;
+		json_null, json_bool, json_string, json_pre_escaped_string, json_escaped_string,
+		json_string_part, json_number_f64, json_numbers_f64, json_number_str, json_number_i64, json_number_u64,
+		json_number_i128, json_number_u128, json_begin_object, json_end_object, json_begin_array,
+		json_end_array, json_begin_string, json_end_string, json_begin_array_value, json_fragment,
+		json_poison, json_reserve, json_debug_len,
+	);
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		self.key.clear();
+		self.case.convert_into(key, &mut self.key);
+		self.inner.json_object_key(&self.key, first);
+	}
+}
+
+impl<W: BufferedJSONWriter> BufferedJSONWriter for KeyCaseWriter<W> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.inner.take_buffer(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriterValue};
+
+	#[test]
+	fn test_key_case_conversions() {
+		// Input, camelCase, snake_case, kebab-case, PascalCase
+		let cases = [
+			("user_id", "userId", "user_id", "user-id", "UserId"),
+			(
+				"io_uring_fd",
+				"ioUringFd",
+				"io_uring_fd",
+				"io-uring-fd",
+				"IoUringFd",
+			),
+			("userId", "userId", "user_id", "user-id", "UserId"),
+			("UserId", "userId", "user_id", "user-id", "UserId"),
+			("user-id", "userId", "user_id", "user-id", "UserId"),
+			("userID", "userId", "user_id", "user-id", "UserId"),
+			(
+				"HTTPServer",
+				"httpServer",
+				"http_server",
+				"http-server",
+				"HttpServer",
+			),
+			(
+				"parseHTTPResponse",
+				"parseHttpResponse",
+				"parse_http_response",
+				"parse-http-response",
+				"ParseHttpResponse",
+			),
+			("ID", "id", "id", "id", "Id"),
+			("x", "x", "x", "x", "X"),
+			(
+				"sha256Hash",
+				"sha256Hash",
+				"sha256_hash",
+				"sha256-hash",
+				"Sha256Hash",
+			),
+			("__private__", "private", "private", "private", "Private"),
+			(
+				"two  spaces",
+				"twoSpaces",
+				"two_spaces",
+				"two-spaces",
+				"TwoSpaces",
+			),
+			(
+				"ünïcödé_straße",
+				"ünïcödéStraße",
+				"ünïcödé_straße",
+				"ünïcödé-straße",
+				"ÜnïcödéStraße",
+			),
+			("", "", "", "", ""),
+		];
+		for (input, camel, snake, kebab, pascal) in cases {
+			for (case, expected) in [
+				(KeyCase::CamelCase, camel),
+				(KeyCase::SnakeCase, snake),
+				(KeyCase::KebabCase, kebab),
+				(KeyCase::PascalCase, pascal),
+			] {
+				let converted = case.convert(input);
+				assert_eq!(converted, expected, "{input} {case:?}");
+				// Idempotent, and converted keys are borrowed
+				assert!(
+					matches!(case.convert(&converted), Cow::Borrowed(_)),
+					"{input} {case:?}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_key_case_collisions() {
+		assert_eq!(KeyCase::CamelCase.convert("a_b"), "aB");
+		assert!(matches!(
+			KeyCase::CamelCase.convert("aB"),
+			Cow::Borrowed("aB")
+		));
+		assert_eq!(KeyCase::SnakeCase.convert("__typename"), "typename");
+		// A prefix of the converted key is not enough to borrow it
+		assert!(matches!(KeyCase::SnakeCase.convert("ab_"), Cow::Owned(_)));
+		assert!(matches!(KeyCase::CamelCase.convert("ab C"), Cow::Owned(_)));
+	}
+
+	#[test]
+	fn test_key_case_writer() {
+		let mut buffer = String::new();
+		let mut writer = KeyCaseWriter::new(&mut buffer, KeyCase::CamelCase);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("first_name", "snake_case_value");
+		let mut nested = object.object("home_address");
+		nested.value("zip_code", "1010");
+		let mut lines = nested.array("street_lines");
+		lines.object().value("line_number", 1);
+		lines.end();
+		nested.end();
+		object.value(
+			"extra_fields",
+			&std::collections::BTreeMap::from([("created_at", 1), ("updated_at", 2)]),
+		);
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"firstName":"snake_case_value","homeAddress":{"zipCode":"1010","streetLines":[{"lineNumber":1}]},"extraFields":{"createdAt":1,"updatedAt":2}}"#
+		);
+
+		let mut writer = KeyCaseWriter::new(String::new(), KeyCase::KebabCase);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		array.value("camelCase");
+		array.object().value("camelCase", ["snake_case"].as_slice());
+		array.end();
+		assert_eq!(
+			writer.into_inner(),
+			r#"["camelCase",{"camel-case":["snake_case"]}]"#
+		);
+
+		let mut buffer = String::new();
+		let map = std::collections::BTreeMap::from([("requestCount", 3)]);
+		(&map).write_json(&mut KeyCaseWriter::new(&mut buffer, KeyCase::SnakeCase));
+		assert_eq!(buffer, r#"{"request_count":3}"#);
+	}
+}
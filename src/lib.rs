@@ -1,12 +1,323 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt;
 
+///
+/// Implements the listed `JSONWriter` hooks of a wrapper by passing them on unchanged, e.g.
+/// `forward_json_writer!(|this| this.inner; json_null, json_bool);` inside the `impl JSONWriter` block.
+///
+/// Wrappers list every hook they don't change, so hooks with a default implementation
+/// still reach the wrapped writer.
+///
+macro_rules! forward_json_writer {
+	(|$this:ident| $target:expr; $($hook:ident),+ $(,)?) => {
+		$(forward_json_writer!(@hook $this, $target, $hook);)+
+	};
+	(@hook $this:ident, $target:expr, json_null) => {
+		#[inline(always)]
+		fn json_null(&mut self) {
+			let $this = self;
+			$target.json_null();
+		}
+	};
+	(@hook $this:ident, $target:expr, json_bool) => {
+		#[inline(always)]
+		fn json_bool(&mut self, value: bool) {
+			let $this = self;
+			$target.json_bool(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_string) => {
+		#[inline(always)]
+		fn json_string(&mut self, value: &str) {
+			let $this = self;
+			$target.json_string(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_pre_escaped_string) => {
+		#[inline(always)]
+		fn json_pre_escaped_string(&mut self, value: &str) {
+			let $this = self;
+			$target.json_pre_escaped_string(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_escaped_string) => {
+		#[inline(always)]
+		fn json_escaped_string(&mut self, value: &str, escaped: &str) {
+			let $this = self;
+			$target.json_escaped_string(value, escaped);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_string_part) => {
+		#[inline(always)]
+		fn json_string_part(&mut self, value: &str) {
+			let $this = self;
+			$target.json_string_part(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_number_f64) => {
+		#[inline(always)]
+		fn json_number_f64(&mut self, value: f64) {
+			let $this = self;
+			$target.json_number_f64(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_numbers_f64) => {
+		#[inline(always)]
+		fn json_numbers_f64(&mut self, values: &[f64], first: bool) {
+			let $this = self;
+			$target.json_numbers_f64(values, first);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_number_str) => {
+		#[inline(always)]
+		fn json_number_str(&mut self, value: &str) {
+			let $this = self;
+			$target.json_number_str(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_number_i64) => {
+		#[inline(always)]
+		fn json_number_i64(&mut self, value: i64) {
+			let $this = self;
+			$target.json_number_i64(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_number_u64) => {
+		#[inline(always)]
+		fn json_number_u64(&mut self, value: u64) {
+			let $this = self;
+			$target.json_number_u64(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_number_i128) => {
+		#[inline(always)]
+		fn json_number_i128(&mut self, value: i128) {
+			let $this = self;
+			$target.json_number_i128(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_number_u128) => {
+		#[inline(always)]
+		fn json_number_u128(&mut self, value: u128) {
+			let $this = self;
+			$target.json_number_u128(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_begin_object) => {
+		#[inline(always)]
+		fn json_begin_object(&mut self) {
+			let $this = self;
+			$target.json_begin_object();
+		}
+	};
+	(@hook $this:ident, $target:expr, json_end_object) => {
+		#[inline(always)]
+		fn json_end_object(&mut self, empty: bool) {
+			let $this = self;
+			$target.json_end_object(empty);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_begin_array) => {
+		#[inline(always)]
+		fn json_begin_array(&mut self) {
+			let $this = self;
+			$target.json_begin_array();
+		}
+	};
+	(@hook $this:ident, $target:expr, json_end_array) => {
+		#[inline(always)]
+		fn json_end_array(&mut self, empty: bool) {
+			let $this = self;
+			$target.json_end_array(empty);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_begin_string) => {
+		#[inline(always)]
+		fn json_begin_string(&mut self) {
+			let $this = self;
+			$target.json_begin_string();
+		}
+	};
+	(@hook $this:ident, $target:expr, json_end_string) => {
+		#[inline(always)]
+		fn json_end_string(&mut self) {
+			let $this = self;
+			$target.json_end_string();
+		}
+	};
+	(@hook $this:ident, $target:expr, json_begin_array_value) => {
+		#[inline(always)]
+		fn json_begin_array_value(&mut self, first: bool) {
+			let $this = self;
+			$target.json_begin_array_value(first);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_object_key) => {
+		#[inline(always)]
+		fn json_object_key(&mut self, key: &str, first: bool) {
+			let $this = self;
+			$target.json_object_key(key, first);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_pre_escaped_object_key) => {
+		#[inline(always)]
+		fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+			let $this = self;
+			$target.json_pre_escaped_object_key(key, first);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_fragment) => {
+		#[inline(always)]
+		fn json_fragment(&mut self, value: &str) {
+			let $this = self;
+			$target.json_fragment(value);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_poison) => {
+		#[inline(always)]
+		fn json_poison(&mut self) {
+			let $this = self;
+			$target.json_poison();
+		}
+	};
+	(@hook $this:ident, $target:expr, json_reserve) => {
+		#[inline(always)]
+		fn json_reserve(&mut self, additional: usize) {
+			let $this = self;
+			$target.json_reserve(additional);
+		}
+	};
+	(@hook $this:ident, $target:expr, json_debug_len) => {
+		#[inline(always)]
+		fn json_debug_len(&self) -> Option<usize> {
+			let $this = self;
+			$target.json_debug_len()
+		}
+	};
+}
+
+#[cfg(any(test, feature = "base64"))]
+mod base64;
+mod budget;
+mod builder;
+#[cfg(any(test, feature = "cbor"))]
+mod cbor;
+#[cfg(feature = "std")]
+mod channel;
+#[cfg(any(test, feature = "checksum"))]
+mod checksum;
+mod concat;
+mod config;
+#[cfg(any(test, feature = "csv"))]
+mod csv_json;
+#[cfg(any(test, feature = "dev"))]
+pub mod dev;
+mod display;
+mod document;
+#[cfg(feature = "std")]
+mod flush;
+pub mod geojson;
+#[cfg(feature = "std")]
+mod interned;
+mod key_case;
+mod non_finite;
+mod ordered;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+mod position;
+mod profile;
+mod progress;
+mod reformat;
+mod root;
+mod segmented;
+#[cfg(any(test, feature = "serde"))]
+mod ser;
+#[cfg(any(test, feature = "http-body"))]
+mod stream_body;
+#[cfg(any(test, feature = "chrono"))]
+mod system_time;
+#[cfg(any(test, feature = "tracing"))]
+mod tracing_json;
+mod validating;
+mod wrapped;
+#[cfg(any(test, feature = "yaml"))]
+mod yaml;
+
+pub use budget::BudgetedJSONWriter;
+pub use builder::{
+	ArrayBuilder, ObjectBuilder, OwnedArrayBuilder, OwnedObjectBuilder, UnendedBuilder,
+};
+#[cfg(any(test, feature = "cbor"))]
+pub use cbor::CborWriter;
+#[cfg(feature = "std")]
+pub use channel::{Backpressure, ChannelError, ChannelWriter, JsonChunk};
+#[cfg(any(test, feature = "checksum"))]
+pub use checksum::Checksummed;
+pub use concat::ConcatJSONWriter;
+pub use config::{ConfiguredWriter, NullAs, StringTransform, WriterConfig};
+#[cfg(any(test, feature = "csv"))]
+pub use csv_json::{write_csv_as_json_array, CsvJsonError, CsvOptions};
+pub use display::{JsonDisplay, JsonPretty};
+pub use document::JsonDocument;
+#[cfg(feature = "std")]
+pub use flush::{FlushFn, PeriodicFlush};
+#[cfg(feature = "std")]
+pub use interned::StringCache;
+#[cfg(feature = "derive")]
+pub use json_writer_derive::JSONEnumString;
+pub use key_case::{KeyCase, KeyCaseWriter};
+pub use non_finite::{NonFinite, NonFiniteJSONWriter, NonFiniteValue};
+pub use ordered::OrderedObjectWriter;
+pub use patch::{write_merge_patch, JsonPatchWriter};
+pub use position::{Position, PositionTrackingWriter};
+pub use profile::{
+	replacement_table, AsciiOnly, DefaultEscaping, EscapeProfile, HtmlSafe, NoSlashEscape,
+	ProfiledWriter,
+};
+pub use progress::ProgressWriter;
+pub use reformat::{minify_json, minify_json_into, prettify_json, JsonSyntaxError};
+pub use root::{JsonRoot, JsonRootArray, JsonRootObject};
+pub use segmented::SegmentedBuffer;
+#[cfg(any(test, feature = "serde"))]
+pub use ser::{serde_to_string, Compound, JSONSerializer, Serde, SerdeError};
+#[cfg(any(test, feature = "http-body"))]
+pub use stream_body::{JsonStreamBody, JsonStreamWriter, SerializationPanicked};
+#[cfg(any(test, feature = "chrono"))]
+pub use system_time::SystemTimeRfc3339;
+#[cfg(any(test, feature = "tracing"))]
+pub use tracing_json::{format_event, JsonVisitor, SpanFields};
+pub use validating::{IncompleteDocument, ValidatingJSONWriter};
+pub use wrapped::{InvalidCallbackName, WrappedDocument, WrappedWriter};
+#[cfg(any(test, feature = "yaml"))]
+pub use yaml::YamlWriter;
+
+///
+/// Returns whether the current thread is unwinding, so that `Drop` impls can leave their output incomplete.
+/// Always `false` without the `std` feature, where dropped writers are closed as usual.
+///
+#[inline(always)]
+fn thread_panicking() -> bool {
+	#[cfg(any(feature = "std", test))]
+	return std::thread::panicking();
+	#[cfg(not(any(feature = "std", test)))]
+	return false;
+}
+
 ///
 /// Helper for appending a JSON object to the borrowed buffer.
 ///
 /// Appends '{' on creation.
 /// Appends '}' when dropped.
+/// Nothing is appended when dropped during a panic, leaving the output detectably incomplete.
+/// Code that runs during unwinding, e.g. a `Drop` impl, closes its writers with `end`.
 ///
 pub struct JSONObjectWriter<'a, Writer: JSONWriter = String> {
 	///
@@ -17,6 +328,8 @@ pub struct JSONObjectWriter<'a, Writer: JSONWriter = String> {
 	///
 	pub writer: &'a mut Writer,
 	empty: bool,
+	#[cfg(debug_assertions)]
+	slot: SlotState,
 }
 
 ///
@@ -24,6 +337,8 @@ pub struct JSONObjectWriter<'a, Writer: JSONWriter = String> {
 ///
 /// Appends '[' on creation.
 /// Appends ']' when dropped.
+/// Nothing is appended when dropped during a panic, leaving the output detectably incomplete.
+/// Code that runs during unwinding, e.g. a `Drop` impl, closes its writers with `end`.
 ///
 pub struct JSONArrayWriter<'a, Writer: JSONWriter = String> {
 	///
@@ -34,6 +349,8 @@ pub struct JSONArrayWriter<'a, Writer: JSONWriter = String> {
 	///
 	pub writer: &'a mut Writer,
 	empty: bool,
+	#[cfg(debug_assertions)]
+	slot: SlotState,
 }
 
 /// Build a string using the `fmt::Write` impl
@@ -51,12 +368,55 @@ impl<'a, Writer: JSONWriter> JSONStringWriter<'a, Writer> {
 	}
 
 	///
-	/// Drops the JSONStringWriter.
-	/// Dropping causes '"' to be appended to the buffer.
+	/// Escapes and appends a chunk of the string content.
+	///
+	/// Every chunk is a complete `&str` and each character is escaped on its own,
+	/// so splitting the content into chunks at any char boundary produces the same output.
+	///
+	#[inline(always)]
+	pub fn push_str(&mut self, chunk: &str) {
+		self.writer.json_string_part(chunk);
+	}
+
+	///
+	/// Escapes and appends a single character of the string content.
+	///
+	#[inline(always)]
+	pub fn push_char(&mut self, c: char) {
+		self.push_str(c.encode_utf8(&mut [0u8; 4]));
+	}
+
+	///
+	/// Appends '"' to the buffer and consumes the JSONStringWriter, also while the thread is panicking.
 	///
 	#[inline(always)]
 	pub fn end(self) {
-		drop(self)
+		let mut this = core::mem::ManuallyDrop::new(self);
+		this.writer.json_end_string();
+	}
+}
+
+impl<Writer: BufferedJSONWriter> JSONStringWriter<'_, Writer> {
+	///
+	/// Writes the entire buffer to given writer and clears entire buffer on success.
+	///
+	/// Allows writing string values that are too large to be held in memory.
+	///
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	pub fn output_buffered_data<W: std::io::Write>(
+		&mut self,
+		writer: &mut W,
+	) -> Result<usize, std::io::Error> {
+		self.writer.take_buffer(writer)
+	}
+
+	///
+	/// Returns buffer length in bytes
+	///
+	#[inline(always)]
+	pub fn buffer_len(&self) -> usize {
+		self.writer.buffer_len()
 	}
 }
 
@@ -66,12 +426,22 @@ impl<'a, Writer: JSONWriter> fmt::Write for JSONStringWriter<'a, Writer> {
 		self.writer.json_string_part(s);
 		Ok(())
 	}
+
+	#[inline(always)]
+	fn write_char(&mut self, c: char) -> fmt::Result {
+		self.push_char(c);
+		Ok(())
+	}
 }
 
 impl<Writer: JSONWriter> Drop for JSONStringWriter<'_, Writer> {
 	#[inline(always)]
 	fn drop(&mut self) {
-		self.writer.json_end_string()
+		if thread_panicking() {
+			self.writer.json_poison();
+			return;
+		}
+		self.writer.json_end_string();
 	}
 }
 
@@ -102,6 +472,24 @@ pub trait JSONWriter {
 	/// Quotes and escapes the given string and writes the result to output without delimiting quotes.
 	fn json_string_part(&mut self, value: &str);
 
+	/// Writes a string that contains no characters that need escaping, see `PreEscaped`.
+	///
+	/// Calls `json_string` by default, which writes the same output for such strings.
+	/// Writers with the default escaping may override it to write the string without scanning it.
+	#[inline(always)]
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.json_string(value);
+	}
+
+	/// Writes the string `value`, whose quoted form with the default escaping is `escaped`, see `StringCache`.
+	///
+	/// Calls `json_string(value)` by default, so writers with their own string handling still apply it.
+	/// Writers with the default escaping may override it to copy `escaped` instead.
+	#[inline(always)]
+	fn json_escaped_string(&mut self, value: &str, _escaped: &str) {
+		self.json_string(value);
+	}
+
 	/// Converts number to string and writes it. Writes null for NaN and infinity
 	#[inline(never)]
 	fn json_number_f64(&mut self, value: f64) {
@@ -119,12 +507,63 @@ pub trait JSONWriter {
 		self.json_number_str(result);
 	}
 
+	/// Writes floats as array items. Called by `JSONArrayWriter::values_f64` and `Matrix`.
+	///
+	/// `first` is `true` when the array has no items yet. Calls `json_begin_array_value` and `json_number_f64`
+	/// for each value by default, so writers that override them keep their output. Writers with the default
+	/// formatting may override it to format all values with one buffer.
+	#[inline]
+	fn json_numbers_f64(&mut self, values: &[f64], first: bool) {
+		for (index, &value) in values.iter().enumerate() {
+			self.json_begin_array_value(first && index == 0);
+			self.json_number_f64(value);
+		}
+	}
+
 	/// Writes a number that has already been converted to string
 	#[inline(always)]
 	fn json_number_str(&mut self, value: &str) {
 		self.json_fragment(value);
 	}
 
+	/// Writes a signed integer. Called for all signed integer types up to 64 bits.
+	///
+	/// Converts the number to string and calls `json_number_str` by default.
+	/// Override it to change how integers are written, e.g. to quote them.
+	#[inline]
+	fn json_number_i64(&mut self, value: i64) {
+		let mut buf = itoa::Buffer::new();
+		self.json_number_str(buf.format(value));
+	}
+
+	/// Writes an unsigned integer. Called for all unsigned integer types up to 64 bits.
+	///
+	/// Converts the number to string and calls `json_number_str` by default.
+	/// Override it to change how integers are written, e.g. to quote them.
+	#[inline]
+	fn json_number_u64(&mut self, value: u64) {
+		let mut buf = itoa::Buffer::new();
+		self.json_number_str(buf.format(value));
+	}
+
+	/// Writes a 128 bit signed integer.
+	///
+	/// Converts the number to string and calls `json_number_str` by default.
+	#[inline]
+	fn json_number_i128(&mut self, value: i128) {
+		let mut buf = itoa::Buffer::new();
+		self.json_number_str(buf.format(value));
+	}
+
+	/// Writes a 128 bit unsigned integer.
+	///
+	/// Converts the number to string and calls `json_number_str` by default.
+	#[inline]
+	fn json_number_u128(&mut self, value: u128) {
+		let mut buf = itoa::Buffer::new();
+		self.json_number_str(buf.format(value));
+	}
+
 	/// Called at the start of writing an object. Writes the opening bracket
 	#[inline(always)]
 	fn json_begin_object(&mut self) {
@@ -187,8 +626,76 @@ pub trait JSONWriter {
 		self.json_fragment(":");
 	}
 
+	/// Writes an object key that contains no characters that need escaping, like the digits of
+	/// `JSONObjectWriter::value_int_key`.
+	///
+	/// Calls `json_object_key` by default. Writers with the default escaping may override it to write
+	/// the key without scanning it.
+	#[inline(always)]
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		self.json_object_key(key, first);
+	}
+
 	/// write a raw json fragment
 	fn json_fragment(&mut self, value: &str);
+
+	/// Called instead of closing an object, array or string when its writer is dropped during a panic.
+	///
+	/// Nothing is written by default, so the output is left detectably incomplete.
+	#[inline(always)]
+	fn json_poison(&mut self) {}
+
+	/// Hint that at least `additional` more bytes are about to be written.
+	///
+	/// Buffered writers may use this to reserve capacity up front. Does nothing by default.
+	#[inline(always)]
+	fn json_reserve(&mut self, _additional: usize) {}
+
+	/// Returns the number of bytes written so far, if known.
+	///
+	/// Only used in debug builds, to detect a missing value after `write_key` or `write_comma` by the output
+	/// length not changing. Writers that return `None`, the default, are not checked, so custom writers must
+	/// implement it to get these checks. Wrappers should return the length of the wrapped writer, and `None`
+	/// whenever writing a value may leave the length unchanged, e.g. while output is dropped.
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		None
+	}
+}
+
+///
+/// Tracks whether the last key or comma has been followed by a value. Only exists in debug builds.
+///
+/// Methods that write the value themselves mark the slot as complete right away.
+/// Only `write_key` and `write_comma` leave it awaiting a value that the caller writes through the
+/// `writer` field, which is detected by the output length changing.
+///
+#[cfg(debug_assertions)]
+#[derive(Debug, Copy, Clone)]
+enum SlotState {
+	/// No key or comma is waiting for a value
+	Complete,
+	/// A key or comma has been written with `write_key` or `write_comma` and the buffer had the given length afterwards
+	AwaitingValue(usize),
+}
+
+#[cfg(debug_assertions)]
+impl SlotState {
+	/// Panics with `message` if nothing has been written since the last key or comma
+	fn check<W: JSONWriter>(&self, writer: &W, message: &str) {
+		if let SlotState::AwaitingValue(len) = *self {
+			if writer.json_debug_len() == Some(len) {
+				panic!("{}", message);
+			}
+		}
+	}
+
+	fn await_value<W: JSONWriter>(&mut self, writer: &W) {
+		*self = match writer.json_debug_len() {
+			Some(len) => SlotState::AwaitingValue(len),
+			None => SlotState::Complete,
+		};
+	}
 }
 
 ///
@@ -198,6 +705,84 @@ pub trait JSONWriter {
 ///
 pub static NULL: Null = Null();
 
+///
+/// Represents an empty object in json.
+///
+/// Useful as placeholder where an explicit `{}` is required.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct EmptyObject;
+
+///
+/// Error of `JSONObjectWriter::checked_raw_value` for fragments that are obviously not a JSON object or array.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RawError {
+	/// The fragment is empty or only contains whitespace
+	Empty,
+	/// The fragment does not start with '{' or '[', or is closed by the other kind of bracket, e.g. `[}`
+	NotContainer,
+	/// The brackets outside of strings are not balanced, e.g. `{"a":[1}`, or the fragment is truncated, e.g. `{"a":1`
+	Unbalanced,
+}
+
+impl fmt::Display for RawError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			RawError::Empty => "raw JSON is empty",
+			RawError::NotContainer => "raw JSON is not enclosed in matching brackets",
+			RawError::Unbalanced => "raw JSON has unbalanced brackets or is truncated",
+		})
+	}
+}
+
+impl core::error::Error for RawError {}
+
+/// Checks that `raw` is enclosed in matching brackets that are balanced in between, ignoring their kind and the content of strings
+fn check_raw_container(raw: &str) -> Result<(), RawError> {
+	let bytes = raw.as_bytes();
+	let close = match bytes.first() {
+		None => return Err(RawError::Empty),
+		Some(b'{') => b'}',
+		Some(b'[') => b']',
+		Some(_) => return Err(RawError::NotContainer),
+	};
+	let mut depth = 0usize;
+	let mut in_string = false;
+	let mut escaped = false;
+	for (index, &byte) in bytes.iter().enumerate() {
+		if in_string {
+			if escaped {
+				escaped = false;
+			} else if byte == b'\\' {
+				escaped = true;
+			} else if byte == b'"' {
+				in_string = false;
+			}
+			continue;
+		}
+		match byte {
+			b'"' => in_string = true,
+			b'{' | b'[' => depth += 1,
+			b'}' | b']' => {
+				depth -= 1;
+				// The outer brackets must only be closed by the last byte
+				if depth == 0 && index + 1 != bytes.len() {
+					return Err(RawError::Unbalanced);
+				}
+			}
+			_ => {}
+		}
+	}
+	if depth != 0 || in_string {
+		Err(RawError::Unbalanced)
+	} else if bytes[bytes.len() - 1] != close {
+		Err(RawError::NotContainer)
+	} else {
+		Ok(())
+	}
+}
+
 impl<W: JSONWriter> JSONObjectWriter<'_, W> {
 	///
 	/// Creates a new JSONObjectWriter that writes to the given buffer. Writes '{' to the buffer immediately.
@@ -208,9 +793,24 @@ impl<W: JSONWriter> JSONObjectWriter<'_, W> {
 		JSONObjectWriter {
 			writer,
 			empty: true,
+			#[cfg(debug_assertions)]
+			slot: SlotState::Complete,
 		}
 	}
 
+	///
+	/// Creates a new JSONObjectWriter like `new`, but first reserves room for about `fields` key:value pairs,
+	/// which saves reallocations when writing many small objects of a known shape.
+	///
+	/// The hint only affects the capacity, the output is identical to `new`.
+	///
+	#[inline(always)]
+	pub fn with_field_hint(writer: &mut W, fields: usize) -> JSONObjectWriter<'_, W> {
+		// Rough estimate: a short key with quotes, colon, comma and a short value per field
+		writer.json_reserve(fields * 16 + 2);
+		JSONObjectWriter::new(writer)
+	}
+
 	///
 	/// Starts writing a nested object with given key:
 	///
@@ -218,7 +818,7 @@ impl<W: JSONWriter> JSONObjectWriter<'_, W> {
 	///
 	#[inline(always)]
 	pub fn object<'a>(&'a mut self, key: &str) -> JSONObjectWriter<'a, W> {
-		self.write_key(key);
+		self.key(key);
 		JSONObjectWriter::new(self.writer)
 	}
 
@@ -229,7 +829,7 @@ impl<W: JSONWriter> JSONObjectWriter<'_, W> {
 	///
 	#[inline(always)]
 	pub fn array<'a>(&'a mut self, key: &str) -> JSONArrayWriter<'a, W> {
-		self.write_key(key);
+		self.key(key);
 		JSONArrayWriter::new(self.writer)
 	}
 
@@ -238,164 +838,616 @@ impl<W: JSONWriter> JSONObjectWriter<'_, W> {
 	///
 	#[inline(always)]
 	pub fn value<T: JSONWriterValue>(&mut self, key: &str, value: T) {
-		self.write_key(key);
+		self.key(key);
 		value.write_json(self.writer);
 	}
 
-	/// Write string with the given key, where the body of the string is built up using the
-	/// `JSONStringWriter` that impls the `fmt::Write` trait and so can be used in the `write!` macro.
-	#[inline(always)]
-	pub fn string_writer(&mut self, key: &str) -> JSONStringWriter<'_, W> {
-		self.write_key(key);
-		JSONStringWriter::new(self.writer)
-	}
-
 	///
-	/// Writes a key without any value.
-	///
-	/// Consider using the methods value(key, value), object(key) and array(key) instead of using this method directly.
+	/// Appends key:value to the buffer, where the key is the decimal representation of `key`.
 	///
-	/// <p style="background:rgba(255,181,77,0.16);padding:0.75em;">
-	/// <strong>Warning:</strong>
-	/// If you use this method, you will have to write the value to the buffer yourself afterwards.
-	/// </p>
+	/// Useful for sparse arrays written as objects keyed by index, e.g. `{"0":true,"42":false}`.
+	/// The key is formatted on the stack, without allocating, and written without scanning it for escapes.
 	///
-	pub fn write_key(&mut self, key: &str) {
-		self.writer.json_object_key(key, self.empty);
+	#[inline(always)]
+	pub fn value_int_key<T: JSONWriterValue>(&mut self, key: u64, value: T) {
+		let mut buf = itoa::Buffer::new();
+		self.fill_slot();
+		self.writer
+			.json_pre_escaped_object_key(buf.format(key), self.empty);
 		self.empty = false;
+		value.write_json(self.writer);
 	}
 
 	///
-	/// Drops the writer.
-	/// Dropping causes '}' to be appended to the buffer.
+	/// Writes the fields of `value` as nested object with given key
 	///
 	#[inline(always)]
-	pub fn end(self) {
-		drop(self);
+	pub fn object_value<T: JSONObjectFields + ?Sized>(&mut self, key: &str, value: &T) {
+		value.write_fields(&mut self.object(key));
 	}
-}
 
-impl JSONObjectWriter<'_, String> {
 	///
-	/// Writes the entire buffer to given writer and clears entire buffer on success.
+	/// Writes the fields of `value` into this object instead of nesting them under a key
 	///
 	#[inline(always)]
-	pub fn output_buffered_data<Writer: std::io::Write>(
-		&mut self,
-		writer: &mut Writer,
-	) -> Result<usize, std::io::Error> {
-		output_buffer_to(self.writer, writer)
+	pub fn flatten<T: JSONObjectFields + ?Sized>(&mut self, value: &T) {
+		value.write_fields(self);
 	}
 
 	///
-	/// Returns buffer length in bytes
+	/// Writes the discriminant of an internally or adjacently tagged enum, e.g. `"type":"Circle"`.
+	///
+	/// The fields of the variant follow as values of this object for internally tagged enums,
+	/// or nested under a key like `"content"` for adjacently tagged enums.
+	/// Use `ExternallyTagged` to write the `{"Circle":{...}}` shape instead.
+	///
+	/// ```rust
+	/// use json_writer::JSONObjectWriter;
+	/// let mut buffer = String::new();
+	/// let mut object = JSONObjectWriter::new(&mut buffer);
+	/// object.tagged("type", "Circle");
+	/// object.value("radius", 1.5);
+	/// object.end();
+	/// assert_eq!(buffer, "{\"type\":\"Circle\",\"radius\":1.5}");
+	/// ```
 	///
 	#[inline(always)]
-	pub fn buffer_len(&self) -> usize {
-		self.writer.len()
+	pub fn tagged(&mut self, tag_key: &str, tag_value: &str) {
+		self.value(tag_key, tag_value);
 	}
-}
 
-impl<'a, W: JSONWriter> Drop for JSONObjectWriter<'a, W> {
+	///
+	/// Formats `args` directly into an escaped string value with the given key, without allocating.
+	///
+	/// ```rust
+	/// use json_writer::JSONObjectWriter;
+	/// let (host, port) = ("localhost", 8080);
+	/// let mut buffer = String::new();
+	/// let mut object = JSONObjectWriter::new(&mut buffer);
+	/// object.value_fmt("addr", format_args!("{host}:{port}"));
+	/// object.end();
+	/// assert_eq!(buffer, "{\"addr\":\"localhost:8080\"}");
+	/// ```
+	///
 	#[inline(always)]
-	fn drop(&mut self) {
-		self.writer.json_end_object(self.empty);
+	pub fn value_fmt(&mut self, key: &str, args: fmt::Arguments<'_>) {
+		self.value(key, args);
 	}
-}
 
-impl<W: JSONWriter> JSONArrayWriter<'_, W> {
 	///
-	/// Creates a new JSONArrayWriter that writes to the given buffer. Writes '[' to the buffer immediately.
+	/// Escapes and appends key:value to the buffer if `condition` is true, otherwise writes nothing
 	///
 	#[inline(always)]
-	pub fn new(writer: &mut W) -> JSONArrayWriter<'_, W> {
-		writer.json_begin_array();
-		JSONArrayWriter {
-			writer,
-			empty: true,
+	pub fn value_if<T: JSONWriterValue>(&mut self, condition: bool, key: &str, value: T) {
+		if condition {
+			self.value(key, value);
 		}
 	}
 
 	///
-	/// Starts writing a nested object as array entry.
+	/// Starts writing a nested object with given key if `condition` is true, otherwise writes nothing, not even the key
 	///
-	/// Writes '{' and returns a JSONObjectWriter
+	#[inline(always)]
+	pub fn object_if<'a>(
+		&'a mut self,
+		condition: bool,
+		key: &str,
+	) -> Option<JSONObjectWriter<'a, W>> {
+		condition.then(|| self.object(key))
+	}
+
+	///
+	/// Starts writing a nested array with given key if `condition` is true, otherwise writes nothing, not even the key
 	///
 	#[inline(always)]
-	pub fn object(&mut self) -> JSONObjectWriter<'_, W> {
-		self.write_comma();
-		JSONObjectWriter::new(self.writer)
+	pub fn array_if<'a>(
+		&'a mut self,
+		condition: bool,
+		key: &str,
+	) -> Option<JSONArrayWriter<'a, W>> {
+		condition.then(|| self.array(key))
 	}
 
 	///
-	/// Starts writing a nested array as array entry.
+	/// Starts writing a string value with the given key whose content is appended in chunks
+	/// with `JSONStringWriter::push_str`.
 	///
-	/// Writes '[' and returns a JSONArrayWriter
+	/// Writes "\"key\":\"" immediately and the closing '"' when the returned writer is dropped.
+	///
+	/// The returned writer also implements `fmt::Write`, so the content can be formatted
+	/// directly into the escaped string with `write!`.
+	///
+	/// ```rust
+	/// use core::fmt::Write;
+	/// use json_writer::JSONObjectWriter;
+	/// let mut buffer = String::new();
+	/// let mut object = JSONObjectWriter::new(&mut buffer);
+	/// write!(object.string_writer("msg"), "user \"{}\" failed {} times", "bob", 3).unwrap();
+	/// object.end();
+	/// assert_eq!(buffer, r#"{"msg":"user \"bob\" failed 3 times"}"#);
+	/// ```
 	///
 	#[inline(always)]
-	pub fn array(&mut self) -> JSONArrayWriter<'_, W> {
-		self.write_comma();
-		JSONArrayWriter::new(self.writer)
+	pub fn string_writer(&mut self, key: &str) -> JSONStringWriter<'_, W> {
+		self.key(key);
+		JSONStringWriter::new(self.writer)
 	}
 
 	///
-	/// Writes given value as array entry
+	/// Writes the key and calls `f` with the underlying writer to write the value.
 	///
-	#[inline(always)]
-	pub fn value<T: JSONWriterValue>(&mut self, value: T) {
-		self.write_comma();
-		value.write_json(self.writer);
+	/// `f` must write exactly one complete value, e.g. with `JSONWriter::json_fragment` or `JSONWriterValue::write_json`.
+	/// In debug builds, writing nothing panics.
+	///
+	pub fn with_raw_writer<F: FnOnce(&mut W)>(&mut self, key: &str, f: F) {
+		self.write_key(key);
+		f(self.writer);
+		#[cfg(debug_assertions)]
+		{
+			self.slot.check(
+				self.writer,
+				"JSONObjectWriter: with_raw_writer closure did not write a value",
+			);
+			self.slot = SlotState::Complete;
+		}
 	}
 
-	/// Write string with the given key, where the body of the string is built up using the
-	/// `JSONStringWriter` that impls the `fmt::Write` trait and so can be used in the `write!` macro.
-	#[inline(always)]
-	pub fn string_writer(&mut self) -> JSONStringWriter<'_, W> {
-		self.write_comma();
-		JSONStringWriter::new(self.writer)
+	///
+	/// Writes the key and `raw` as value, after checking that it looks like a JSON object or array.
+	///
+	/// The check is shallow: `raw` must start with '{' or '[', end with the matching bracket and contain balanced
+	/// brackets outside of strings. Surrounding whitespace is removed. Anything else, like the syntax
+	/// between the brackets, is not validated; use `minify_json_into` with `with_raw_writer` for a full check.
+	/// On error, nothing is written.
+	///
+	/// ```rust
+	/// use json_writer::{JSONObjectWriter, RawError};
+	/// let mut buffer = String::new();
+	/// let mut object = JSONObjectWriter::new(&mut buffer);
+	/// object.checked_raw_value("cached", " {\"a\":[1,2]}\n").unwrap();
+	/// assert_eq!(object.checked_raw_value("broken", "{\"a\":1"), Err(RawError::Unbalanced));
+	/// assert_eq!(object.checked_raw_value("number", "1"), Err(RawError::NotContainer));
+	/// object.end();
+	/// assert_eq!(buffer, "{\"cached\":{\"a\":[1,2]}}");
+	/// ```
+	///
+	pub fn checked_raw_value(&mut self, key: &str, raw: &str) -> Result<(), RawError> {
+		let raw = raw.trim_matches(|c| matches!(c, ' ' | '\t' | '\n' | '\r'));
+		check_raw_container(raw)?;
+		self.with_raw_writer(key, |writer| writer.json_fragment(raw));
+		Ok(())
 	}
 
 	///
-	/// Writes a comma unless at the beginning of the array
+	/// Writes a key without any value.
+	///
+	/// Consider using the methods value(key, value), object(key) and array(key) instead of using this method directly.
 	///
 	/// <p style="background:rgba(255,181,77,0.16);padding:0.75em;">
 	/// <strong>Warning:</strong>
 	/// If you use this method, you will have to write the value to the buffer yourself afterwards.
 	/// </p>
 	///
-	#[inline]
-	pub fn write_comma(&mut self) {
-		self.writer.json_begin_array_value(self.empty);
-		self.empty = false;
+	/// In debug builds, writing another key or dropping the writer before the value has been written panics.
+	/// This check relies on `JSONWriter::json_debug_len`, writers that don't implement it are not checked.
+	///
+	pub fn write_key(&mut self, key: &str) {
+		self.key(key);
+		#[cfg(debug_assertions)]
+		self.slot.await_value(self.writer);
 	}
 
-	///
-	/// Drops the writer.
-	/// Dropping causes ']' to be appended to the buffer.
-	///
+	/// Writes a key whose value the caller writes or starts right away, which fills the slot
 	#[inline(always)]
-	pub fn end(self) {
-		drop(self);
+	fn key(&mut self, key: &str) {
+		self.fill_slot();
+		self.writer.json_object_key(key, self.empty);
+		self.empty = false;
+	}
+
+	/// Checks that the previous key has its value and marks the slot as filled by the key written next
+	#[inline(always)]
+	fn fill_slot(&mut self) {
+		#[cfg(debug_assertions)]
+		{
+			self.slot.check(
+				self.writer,
+				"JSONObjectWriter: key written while the previous key is still awaiting its value",
+			);
+			self.slot = SlotState::Complete;
+		}
+	}
+
+	///
+	/// Appends '}' to the buffer and consumes the writer, also while the thread is panicking.
+	///
+	#[inline(always)]
+	pub fn end(self) {
+		self.into_writer();
+	}
+}
+
+impl<W: BufferedJSONWriter> JSONObjectWriter<'_, W> {
+	///
+	/// Writes the entire buffer to given writer and clears entire buffer on success.
+	///
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	pub fn output_buffered_data<Writer: std::io::Write>(
+		&mut self,
+		writer: &mut Writer,
+	) -> Result<usize, std::io::Error> {
+		#[cfg(debug_assertions)]
+		{
+			self.slot = SlotState::Complete;
+		}
+		self.writer.take_buffer(writer)
+	}
+
+	///
+	/// Returns buffer length in bytes
+	///
+	#[inline(always)]
+	pub fn buffer_len(&self) -> usize {
+		self.writer.buffer_len()
+	}
+}
+
+impl JSONObjectWriter<'_, String> {
+	///
+	/// Returns up to the last `n` bytes written to the buffer, for debugging.
+	///
+	/// Fewer bytes are returned if the `n`th last byte is inside a multi byte character.
+	///
+	pub fn tail(&self, n: usize) -> &str {
+		buffer_tail(self.writer, n)
+	}
+}
+
+impl<'a, W: JSONWriter> JSONObjectWriter<'a, W> {
+	/// Closes the object and returns the borrowed writer
+	fn into_writer(self) -> &'a mut W {
+		#[cfg(debug_assertions)]
+		self.slot.check(
+			self.writer,
+			"JSONObjectWriter finished with a key that is still awaiting its value",
+		);
+		let empty = self.empty;
+		let mut this = core::mem::ManuallyDrop::new(self);
+		this.writer.json_end_object(empty);
+		// Safety: `this` is never dropped, so the writer borrow is moved out exactly once
+		unsafe { core::ptr::read(&this.writer) }
+	}
+}
+
+impl<'a, W: JSONWriter> Drop for JSONObjectWriter<'a, W> {
+	#[inline(always)]
+	fn drop(&mut self) {
+		if thread_panicking() {
+			self.writer.json_poison();
+			return;
+		}
+		#[cfg(debug_assertions)]
+		self.slot.check(
+			self.writer,
+			"JSONObjectWriter dropped with a key that is still awaiting its value",
+		);
+		self.writer.json_end_object(self.empty);
+	}
+}
+
+impl<W: JSONWriter> JSONArrayWriter<'_, W> {
+	///
+	/// Creates a new JSONArrayWriter that writes to the given buffer. Writes '[' to the buffer immediately.
+	///
+	#[inline(always)]
+	pub fn new(writer: &mut W) -> JSONArrayWriter<'_, W> {
+		writer.json_begin_array();
+		JSONArrayWriter {
+			writer,
+			empty: true,
+			#[cfg(debug_assertions)]
+			slot: SlotState::Complete,
+		}
+	}
+
+	///
+	/// Starts writing a nested object as array entry.
+	///
+	/// Writes '{' and returns a JSONObjectWriter
+	///
+	#[inline(always)]
+	pub fn object(&mut self) -> JSONObjectWriter<'_, W> {
+		self.comma();
+		JSONObjectWriter::new(self.writer)
+	}
+
+	///
+	/// Writes the fields of each item as nested object, one array entry per item.
+	///
+	/// ```rust
+	/// use json_writer::{JSONArrayWriter, JSONObjectFields, JSONObjectWriter, JSONWriter};
+	/// struct Row {
+	///     id: u32,
+	///     name: &'static str,
+	/// }
+	///
+	/// impl JSONObjectFields for Row {
+	///     fn write_fields<W: JSONWriter>(&self, object: &mut JSONObjectWriter<'_, W>) {
+	///         object.value("id", self.id);
+	///         object.value("name", self.name);
+	///     }
+	/// }
+	///
+	/// let rows = [Row { id: 1, name: "a" }, Row { id: 2, name: "b" }];
+	/// let mut buffer = String::new();
+	/// JSONArrayWriter::new(&mut buffer).objects(&rows);
+	/// assert_eq!(buffer, r#"[{"id":1,"name":"a"},{"id":2,"name":"b"}]"#);
+	/// ```
+	///
+	pub fn objects<I>(&mut self, items: I)
+	where
+		I: IntoIterator,
+		I::Item: JSONObjectFields,
+	{
+		for item in items {
+			item.write_fields(&mut self.object());
+		}
+	}
+
+	///
+	/// Starts writing a nested array as array entry.
+	///
+	/// Writes '[' and returns a JSONArrayWriter
+	///
+	#[inline(always)]
+	pub fn array(&mut self) -> JSONArrayWriter<'_, W> {
+		self.comma();
+		JSONArrayWriter::new(self.writer)
+	}
+
+	///
+	/// Writes given value as array entry
+	///
+	#[inline(always)]
+	pub fn value<T: JSONWriterValue>(&mut self, value: T) {
+		self.comma();
+		value.write_json(self.writer);
+	}
+
+	///
+	/// Writes the `Some` values of `values` as array entries and skips the `None`s
+	///
+	#[inline]
+	pub fn values_flatten<I>(&mut self, values: I)
+	where
+		I: IntoIterator,
+		I::Item: OptionValue,
+	{
+		for value in values.into_iter().filter_map(OptionValue::into_option) {
+			self.value(value);
+		}
+	}
+
+	///
+	/// Formats `args` directly into an escaped string array entry, without allocating
+	///
+	#[inline(always)]
+	pub fn push_fmt(&mut self, args: fmt::Arguments<'_>) {
+		self.value(args);
+	}
+
+	///
+	/// Writes all floats of the slice as array entries.
+	///
+	/// Produces the same output as writing each value separately with `value`, but `String` and `Vec<u8>`
+	/// format the whole slice with one `ryu::Buffer`. Other writers see every value through
+	/// `JSONWriter::json_number_f64`, see `JSONWriter::json_numbers_f64`.
+	///
+	pub fn values_f64(&mut self, values: &[f64]) {
+		if values.is_empty() {
+			return;
+		}
+		#[cfg(debug_assertions)]
+		{
+			self.slot.check(
+				self.writer,
+				"JSONArrayWriter: value slot opened while the previous slot is still awaiting its value",
+			);
+			self.slot = SlotState::Complete;
+		}
+		self.writer.json_numbers_f64(values, self.empty);
+		self.empty = false;
+	}
+
+	///
+	/// Writes given value as array entry if `condition` is true, otherwise writes nothing
+	///
+	#[inline(always)]
+	pub fn value_if<T: JSONWriterValue>(&mut self, condition: bool, value: T) {
+		if condition {
+			self.value(value);
+		}
+	}
+
+	///
+	/// Starts writing a nested object as array entry if `condition` is true, otherwise writes nothing
+	///
+	#[inline(always)]
+	pub fn object_if(&mut self, condition: bool) -> Option<JSONObjectWriter<'_, W>> {
+		condition.then(|| self.object())
+	}
+
+	///
+	/// Starts writing a nested array as array entry if `condition` is true, otherwise writes nothing
+	///
+	#[inline(always)]
+	pub fn array_if(&mut self, condition: bool) -> Option<JSONArrayWriter<'_, W>> {
+		condition.then(|| self.array())
+	}
+
+	///
+	/// Starts writing a string array entry whose content is appended in chunks
+	/// with `JSONStringWriter::push_str`.
+	///
+	/// Writes '"' immediately and the closing '"' when the returned writer is dropped.
+	/// The returned writer also implements `fmt::Write`, so it can be used with `write!`.
+	///
+	#[inline(always)]
+	pub fn string_writer(&mut self) -> JSONStringWriter<'_, W> {
+		self.comma();
+		JSONStringWriter::new(self.writer)
+	}
+
+	///
+	/// Calls `f` with the underlying writer to write an array entry.
+	///
+	/// `f` must write exactly one complete value, e.g. with `JSONWriter::json_fragment` or `JSONWriterValue::write_json`.
+	/// In debug builds, writing nothing panics.
+	///
+	pub fn with_raw_writer<F: FnOnce(&mut W)>(&mut self, f: F) {
+		self.write_comma();
+		f(self.writer);
+		#[cfg(debug_assertions)]
+		{
+			self.slot.check(
+				self.writer,
+				"JSONArrayWriter: with_raw_writer closure did not write a value",
+			);
+			self.slot = SlotState::Complete;
+		}
+	}
+
+	///
+	/// Writes a comma unless at the beginning of the array
+	///
+	/// <p style="background:rgba(255,181,77,0.16);padding:0.75em;">
+	/// <strong>Warning:</strong>
+	/// If you use this method, you will have to write the value to the buffer yourself afterwards.
+	/// </p>
+	///
+	/// In debug builds, writing another comma or dropping the writer before the value has been written panics.
+	/// This check relies on `JSONWriter::json_debug_len`, writers that don't implement it are not checked.
+	///
+	#[inline]
+	pub fn write_comma(&mut self) {
+		self.comma();
+		#[cfg(debug_assertions)]
+		self.slot.await_value(self.writer);
+	}
+
+	/// Opens a slot whose value the caller writes or starts right away, which fills the slot
+	#[inline(always)]
+	fn comma(&mut self) {
+		#[cfg(debug_assertions)]
+		{
+			self.slot.check(
+				self.writer,
+				"JSONArrayWriter: value slot opened while the previous slot is still awaiting its value",
+			);
+			self.slot = SlotState::Complete;
+		}
+		self.writer.json_begin_array_value(self.empty);
+		self.empty = false;
+	}
+
+	///
+	/// Appends ']' to the buffer and consumes the writer, also while the thread is panicking.
+	///
+	#[inline(always)]
+	pub fn end(self) {
+		self.into_writer();
+	}
+}
+
+impl JSONArrayWriter<'_, String> {
+	///
+	/// Returns up to the last `n` bytes written to the buffer, for debugging.
+	///
+	/// Fewer bytes are returned if the `n`th last byte is inside a multi byte character.
+	///
+	pub fn tail(&self, n: usize) -> &str {
+		buffer_tail(self.writer, n)
+	}
+}
+
+impl<'a, W: JSONWriter> JSONArrayWriter<'a, W> {
+	/// Closes the array and returns the borrowed writer
+	fn into_writer(self) -> &'a mut W {
+		#[cfg(debug_assertions)]
+		self.slot.check(
+			self.writer,
+			"JSONArrayWriter finished with a slot that is still awaiting its value",
+		);
+		let empty = self.empty;
+		let mut this = core::mem::ManuallyDrop::new(self);
+		this.writer.json_end_array(empty);
+		// Safety: `this` is never dropped, so the writer borrow is moved out exactly once
+		unsafe { core::ptr::read(&this.writer) }
+	}
+}
+
+impl<W: CheckpointJSONWriter> JSONArrayWriter<'_, W> {
+	///
+	/// Writes an array entry with the given closure.
+	///
+	/// If the closure returns an error, everything it has written is removed again
+	/// and the array continues as if the entry had never been started.
+	///
+	/// Panics if the buffer has been flushed inside the closure before it failed.
+	///
+	pub fn try_item<E, F>(&mut self, f: F) -> Result<(), E>
+	where
+		F: FnOnce(&mut Self) -> Result<(), E>,
+	{
+		let checkpoint = self.writer.checkpoint();
+		let empty = self.empty;
+		#[cfg(debug_assertions)]
+		let slot = self.slot;
+		let result = f(self);
+		if result.is_err() {
+			self.writer.rollback(checkpoint);
+			self.empty = empty;
+			#[cfg(debug_assertions)]
+			{
+				self.slot = slot;
+			}
+		}
+		result
 	}
 }
 
 impl<W: JSONWriter> Drop for JSONArrayWriter<'_, W> {
 	#[inline(always)]
 	fn drop(&mut self) {
+		if thread_panicking() {
+			self.writer.json_poison();
+			return;
+		}
+		#[cfg(debug_assertions)]
+		self.slot.check(
+			self.writer,
+			"JSONArrayWriter dropped with a slot that is still awaiting its value",
+		);
 		self.writer.json_end_array(self.empty);
 	}
 }
 
-impl JSONArrayWriter<'_, String> {
+impl<W: BufferedJSONWriter> JSONArrayWriter<'_, W> {
 	///
 	/// Writes the entire buffer to given writer and clears entire buffer on success.
 	///
 	#[inline(always)]
+	#[cfg(feature = "std")]
 	pub fn output_buffered_data<Writer: std::io::Write>(
 		&mut self,
 		writer: &mut Writer,
 	) -> Result<usize, std::io::Error> {
-		output_buffer_to(self.writer, writer)
+		#[cfg(debug_assertions)]
+		{
+			self.slot = SlotState::Complete;
+		}
+		self.writer.take_buffer(writer)
 	}
 
 	///
@@ -403,7 +1455,46 @@ impl JSONArrayWriter<'_, String> {
 	///
 	#[inline(always)]
 	pub fn buffer_len(&self) -> usize {
-		self.writer.len()
+		self.writer.buffer_len()
+	}
+}
+
+impl<W: JSONWriter + ?Sized> JSONWriter for &mut W {
+	forward_json_writer!(|this| (**this);
+		json_null, json_bool, json_string, json_pre_escaped_string, json_escaped_string,
+		json_string_part, json_number_f64, json_numbers_f64, json_number_str, json_number_i64,
+		json_number_u64, json_number_i128, json_number_u128, json_begin_object, json_end_object,
+		json_begin_array, json_end_array, json_begin_string, json_end_string, json_begin_array_value,
+		json_object_key, json_pre_escaped_object_key, json_fragment, json_poison, json_reserve,
+		json_debug_len,
+	);
+}
+
+impl<W: JSONWriter + ?Sized> JSONWriter for Box<W> {
+	forward_json_writer!(|this| (**this);
+		json_null, json_bool, json_string, json_pre_escaped_string, json_escaped_string,
+		json_string_part, json_number_f64, json_numbers_f64, json_number_str, json_number_i64,
+		json_number_u64, json_number_i128, json_number_u128, json_begin_object, json_end_object,
+		json_begin_array, json_end_array, json_begin_string, json_end_string, json_begin_array_value,
+		json_object_key, json_pre_escaped_object_key, json_fragment, json_poison, json_reserve,
+		json_debug_len,
+	);
+}
+
+/// Writes `values` as array items with one `ryu::Buffer`, for writers with the default formatting
+#[inline(always)]
+fn write_f64_items<W: JSONWriter>(writer: &mut W, values: &[f64], first: bool) {
+	let mut buffer = ryu::Buffer::new();
+	for (index, &value) in values.iter().enumerate() {
+		if !first || index > 0 {
+			writer.json_fragment(",");
+		}
+		if value.is_finite() {
+			let result = buffer.format_finite(value);
+			writer.json_fragment(result.strip_suffix(".0").unwrap_or(result));
+		} else {
+			writer.json_number_f64(value);
+		}
 	}
 }
 
@@ -413,6 +1504,19 @@ impl JSONWriter for String {
 		write_string(self, value);
 	}
 
+	#[inline(always)]
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.reserve(value.len() + 2);
+		self.push('"');
+		self.push_str(value);
+		self.push('"');
+	}
+
+	#[inline(always)]
+	fn json_escaped_string(&mut self, _value: &str, escaped: &str) {
+		self.push_str(escaped);
+	}
+
 	#[inline(always)]
 	fn json_string_part(&mut self, value: &str) {
 		write_part_of_string(self, value);
@@ -423,6 +1527,21 @@ impl JSONWriter for String {
 		self.push_str(value);
 	}
 
+	#[inline]
+	fn json_numbers_f64(&mut self, values: &[f64], first: bool) {
+		write_f64_items(self, values, first);
+	}
+
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.len())
+	}
+
+	#[inline(always)]
+	fn json_reserve(&mut self, additional: usize) {
+		self.reserve(additional);
+	}
+
 	/// Called at the start of writing an object.
 	#[inline(always)]
 	fn json_begin_object(&mut self) {
@@ -470,6 +1589,15 @@ impl JSONWriter for String {
 		self.push(':');
 	}
 
+	#[inline(always)]
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		if !first {
+			self.push(',');
+		}
+		self.json_pre_escaped_string(key);
+		self.push(':');
+	}
+
 	#[inline(always)]
 	fn json_begin_string(&mut self) {
 		self.push('"');
@@ -481,22 +1609,302 @@ impl JSONWriter for String {
 	}
 }
 
-/// Formats JSON in a human-readable format with whitespace, newlines, and indentation.
-pub struct PrettyJSONWriter<'a> {
-	/// Result
-	pub buffer: &'a mut String,
-	indent: &'a str,
-	depth: usize,
-}
+impl JSONWriter for Vec<u8> {
+	#[inline(always)]
+	fn json_string(&mut self, value: &str) {
+		self.push(b'"');
+		write_part_of_string_impl(self, value);
+		self.push(b'"');
+	}
 
-impl PrettyJSONWriter<'_> {
-	/// Creates a new human-readable formatter with two spaces for indentation.
-	pub fn new(buffer: &mut String) -> PrettyJSONWriter<'_> {
-		// Same default as serde_json::ser::PrettyFormatter
+	#[inline(always)]
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.reserve(value.len() + 2);
+		self.push(b'"');
+		self.extend_from_slice(value.as_bytes());
+		self.push(b'"');
+	}
+
+	#[inline(always)]
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		if !first {
+			self.push(b',');
+		}
+		self.json_pre_escaped_string(key);
+		self.push(b':');
+	}
+
+	#[inline(always)]
+	fn json_escaped_string(&mut self, _value: &str, escaped: &str) {
+		self.extend_from_slice(escaped.as_bytes());
+	}
+
+	#[inline(always)]
+	fn json_string_part(&mut self, value: &str) {
+		write_part_of_string_impl(self, value);
+	}
+
+	#[inline(always)]
+	fn json_fragment(&mut self, value: &str) {
+		self.extend_from_slice(value.as_bytes());
+	}
+
+	#[inline]
+	fn json_numbers_f64(&mut self, values: &[f64], first: bool) {
+		write_f64_items(self, values, first);
+	}
+
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.len())
+	}
+
+	#[inline(always)]
+	fn json_reserve(&mut self, additional: usize) {
+		self.reserve(additional);
+	}
+}
+
+///
+/// Writers that collect the output in a buffer that can be flushed to a `std::io::Write`.
+///
+/// Used by `output_buffered_data` and `buffer_len` for writing large files.
+///
+pub trait BufferedJSONWriter: JSONWriter {
+	/// Returns buffer length in bytes
+	fn buffer_len(&self) -> usize;
+
+	/// Writes the entire buffer to `out` and clears the buffer on success.
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error>;
+}
+
+impl BufferedJSONWriter for String {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		output_buffer_to(self, out)
+	}
+}
+
+impl BufferedJSONWriter for Vec<u8> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		output_bytes_to(self, out)
+	}
+}
+
+impl<I: PrettyIndent> BufferedJSONWriter for PrettyJSONWriter<'_, I> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.buffer.len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		output_buffer_to(self.buffer, out)
+	}
+}
+
+impl<W: BufferedJSONWriter + ?Sized> BufferedJSONWriter for &mut W {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		(**self).buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		(**self).take_buffer(out)
+	}
+}
+
+///
+/// Position in the output buffer that can be restored with `CheckpointJSONWriter::rollback`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+	len: usize,
+	/// Value of `FLUSHES` when the checkpoint was taken
+	flushes: u64,
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+	/// Number of `String` and `Vec<u8>` buffers flushed on this thread, so that rollbacks across a flush can be rejected
+	static FLUSHES: core::cell::Cell<u64> = const { core::cell::Cell::new(0) };
+}
+
+///
+/// Returns the number of buffers flushed on this thread, always 0 without the `std` feature where buffers can't be flushed
+///
+#[inline(always)]
+fn flush_count() -> u64 {
+	#[cfg(feature = "std")]
+	return FLUSHES.with(core::cell::Cell::get);
+	#[cfg(not(feature = "std"))]
+	return 0;
+}
+
+impl Checkpoint {
+	#[inline(always)]
+	fn new(len: usize) -> Checkpoint {
+		Checkpoint {
+			len,
+			flushes: flush_count(),
+		}
+	}
+
+	/// Panics if a buffer has been flushed since the checkpoint was taken
+	fn check(&self, len: usize) {
+		assert!(
+			self.len <= len && self.flushes == flush_count(),
+			"rollback to a checkpoint that has already been flushed"
+		);
+	}
+}
+
+///
+/// Writers that can undo everything written after a checkpoint.
+///
+pub trait CheckpointJSONWriter: JSONWriter {
+	/// Returns the current position in the output buffer
+	fn checkpoint(&self) -> Checkpoint;
+
+	/// Removes everything written after `checkpoint`.
+	///
+	/// Panics if the buffer has been flushed since the checkpoint was taken.
+	/// `String` and `Vec<u8>` can't tell their own flushes from those of other buffers,
+	/// so they panic if any `String` or `Vec<u8>` buffer has been flushed on this thread since then.
+	fn rollback(&mut self, checkpoint: Checkpoint);
+}
+
+impl CheckpointJSONWriter for String {
+	#[inline(always)]
+	fn checkpoint(&self) -> Checkpoint {
+		Checkpoint::new(self.len())
+	}
+
+	fn rollback(&mut self, checkpoint: Checkpoint) {
+		checkpoint.check(self.len());
+		self.truncate(checkpoint.len);
+	}
+}
+
+impl CheckpointJSONWriter for Vec<u8> {
+	#[inline(always)]
+	fn checkpoint(&self) -> Checkpoint {
+		Checkpoint::new(self.len())
+	}
+
+	fn rollback(&mut self, checkpoint: Checkpoint) {
+		checkpoint.check(self.len());
+		self.truncate(checkpoint.len);
+	}
+}
+
+/// Formats JSON in a human-readable format with whitespace, newlines, and indentation.
+pub struct PrettyJSONWriter<'a, I: PrettyIndent = &'a str> {
+	/// Result
+	pub buffer: &'a mut String,
+	layout: PrettyLayout<I>,
+}
+
+///
+/// Computes the indentation of the `PrettyJSONWriter` for each nesting level.
+///
+pub trait PrettyIndent {
+	/// Returns the indentation added by nesting level `level`, starting at 1.
+	///
+	/// A line at depth `n` is indented with the indentations of levels `1..=n`.
+	fn indent(&self, level: usize) -> &str;
+}
+
+impl PrettyIndent for &str {
+	#[inline(always)]
+	fn indent(&self, _level: usize) -> &str {
+		self
+	}
+}
+
+///
+/// Indentation computed by a closure from the nesting level, see `PrettyJSONWriter::with_indent_fn`
+///
+#[derive(Debug, Copy, Clone)]
+pub struct IndentFn<F>(pub F);
+
+impl<F: Fn(usize) -> &'static str> PrettyIndent for IndentFn<F> {
+	#[inline(always)]
+	fn indent(&self, level: usize) -> &str {
+		(self.0)(level)
+	}
+}
+
+///
+/// Line breaks and indentation of the `PrettyJSONWriter`, also used by `prettify_json` and `JsonPretty`
+/// to produce the same layout
+///
+struct PrettyLayout<I: PrettyIndent> {
+	indent: I,
+	depth: usize,
+	/// Indentation of the current depth, grows and shrinks with the nesting
+	indentation: String,
+}
+
+impl<I: PrettyIndent> PrettyLayout<I> {
+	fn new(indent: I) -> PrettyLayout<I> {
+		PrettyLayout {
+			indent,
+			depth: 0,
+			indentation: String::new(),
+		}
+	}
+
+	/// Writes the opening bracket of an object or array and indents its content
+	fn begin<B: EscapeBuffer + ?Sized>(&mut self, output: &mut B, bracket: &str) {
+		self.depth += 1;
+		self.indentation.push_str(self.indent.indent(self.depth));
+		output.push_str(bracket);
+	}
+
+	/// Writes the closing bracket of an object or array, on its own line unless the container is empty
+	fn end<B: EscapeBuffer + ?Sized>(&mut self, output: &mut B, empty: bool, bracket: &str) {
+		let len = self.indentation.len() - self.indent.indent(self.depth).len();
+		self.indentation.truncate(len);
+		self.depth -= 1;
+		if !empty {
+			output.push_str("\n");
+			output.push_str(&self.indentation);
+		}
+		output.push_str(bracket);
+	}
+
+	/// Starts the line of an array value or object key, after a comma unless it is the first one
+	fn line_start<B: EscapeBuffer + ?Sized>(&self, output: &mut B, first: bool) {
+		output.push_str(if first { "\n" } else { ",\n" });
+		output.push_str(&self.indentation);
+	}
+}
+
+impl PrettyJSONWriter<'_> {
+	/// Creates a new human-readable formatter with two spaces for indentation.
+	pub fn new(buffer: &mut String) -> PrettyJSONWriter<'_> {
+		// Same default as serde_json::ser::PrettyFormatter
 		PrettyJSONWriter {
 			buffer,
-			indent: "  ",
-			depth: 0,
+			layout: PrettyLayout::new("  "),
 		}
 	}
 
@@ -504,63 +1912,87 @@ impl PrettyJSONWriter<'_> {
 	pub fn with_indent<'a>(buffer: &'a mut String, indent: &'a str) -> PrettyJSONWriter<'a> {
 		PrettyJSONWriter {
 			buffer,
-			indent,
-			depth: 0,
+			layout: PrettyLayout::new(indent),
 		}
 	}
 
-	fn write_indent(&mut self) {
-		for _ in 0..self.depth {
-			self.buffer.push_str(self.indent);
+	/// Creates a new formatter that calls `indent` with the nesting level (starting at 1)
+	/// to get the indentation added by that level.
+	pub fn with_indent_fn<'a, F: Fn(usize) -> &'static str>(
+		buffer: &'a mut String,
+		indent: F,
+	) -> PrettyJSONWriter<'a, IndentFn<F>> {
+		PrettyJSONWriter {
+			buffer,
+			layout: PrettyLayout::new(IndentFn(indent)),
 		}
 	}
 }
 
-impl JSONWriter for PrettyJSONWriter<'_> {
+impl<I: PrettyIndent> PrettyJSONWriter<'_, I> {
+	///
+	/// Returns up to the last `n` bytes written to the buffer, for debugging.
+	///
+	/// Fewer bytes are returned if the `n`th last byte is inside a multi byte character.
+	///
+	pub fn tail(&self, n: usize) -> &str {
+		buffer_tail(self.buffer, n)
+	}
+}
+
+impl<I: PrettyIndent> AsRef<str> for PrettyJSONWriter<'_, I> {
+	/// Returns the buffer
+	#[inline(always)]
+	fn as_ref(&self) -> &str {
+		self.buffer
+	}
+}
+
+impl<I: PrettyIndent> JSONWriter for PrettyJSONWriter<'_, I> {
 	fn json_begin_object(&mut self) {
-		self.depth += 1;
-		self.buffer.push('{');
+		self.layout.begin(self.buffer, "{");
 	}
 
 	fn json_end_object(&mut self, empty: bool) {
-		self.depth -= 1;
-		if !empty {
-			self.buffer.push('\n');
-			self.write_indent();
-		}
-		self.buffer.push('}');
+		self.layout.end(self.buffer, empty, "}");
 	}
 
 	fn json_begin_array(&mut self) {
-		self.depth += 1;
-		self.buffer.push('[');
+		self.layout.begin(self.buffer, "[");
 	}
 
 	fn json_end_array(&mut self, empty: bool) {
-		self.depth -= 1;
-		if !empty {
-			self.buffer.push('\n');
-			self.write_indent();
-		}
-		self.buffer.push(']');
+		self.layout.end(self.buffer, empty, "]");
 	}
 
 	fn json_begin_array_value(&mut self, first: bool) {
-		self.buffer.push_str(if first { "\n" } else { ",\n" });
-		self.write_indent();
+		self.layout.line_start(self.buffer, first);
 	}
 
 	fn json_object_key(&mut self, key: &str, first: bool) {
-		self.buffer.push_str(if first { "\n" } else { ",\n" });
-		self.write_indent();
+		self.layout.line_start(self.buffer, first);
 		write_string(self.buffer, key);
 		self.buffer.push_str(": ");
 	}
 
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		self.layout.line_start(self.buffer, first);
+		self.buffer.json_pre_escaped_string(key);
+		self.buffer.push_str(": ");
+	}
+
 	fn json_string(&mut self, value: &str) {
 		write_string(self.buffer, value);
 	}
 
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.buffer.json_pre_escaped_string(value);
+	}
+
+	fn json_escaped_string(&mut self, _value: &str, escaped: &str) {
+		self.buffer.push_str(escaped);
+	}
+
 	fn json_string_part(&mut self, value: &str) {
 		write_part_of_string(self.buffer, value);
 	}
@@ -568,6 +2000,14 @@ impl JSONWriter for PrettyJSONWriter<'_> {
 	fn json_fragment(&mut self, value: &str) {
 		self.buffer.push_str(value);
 	}
+
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.buffer.len())
+	}
+
+	fn json_reserve(&mut self, additional: usize) {
+		self.buffer.reserve(additional);
+	}
 }
 
 ///
@@ -580,6 +2020,75 @@ pub trait JSONWriterValue {
 	fn write_json<W: JSONWriter>(self, writer: &mut W);
 }
 
+///
+/// Types whose shared references can be converted to JSON.
+///
+/// Implemented for every `T` where `&T` implements `JSONWriterValue`.
+/// Useful as a bound, because the compiler can infer it in cases where
+/// `for<'b> &'b T: JSONWriterValue` fails with a recursion overflow.
+///
+pub trait JSONWriterValueRef {
+	///
+	/// Appends a JSON representation of self to the output buffer
+	///
+	fn write_json_ref<W: JSONWriter>(&self, writer: &mut W);
+}
+
+impl<T: ?Sized> JSONWriterValueRef for T
+where
+	for<'b> &'b T: JSONWriterValue,
+{
+	#[inline(always)]
+	fn write_json_ref<W: JSONWriter>(&self, writer: &mut W) {
+		self.write_json(writer);
+	}
+}
+
+///
+/// Types whose fields can be written into another object, like `#[serde(flatten)]`.
+///
+/// ```rust
+/// use json_writer::{JSONObjectFields, JSONObjectWriter, JSONWriter};
+/// struct Metadata {
+///     version: u32,
+///     author: &'static str,
+/// }
+///
+/// impl JSONObjectFields for Metadata {
+///     fn write_fields<W: JSONWriter>(&self, object: &mut JSONObjectWriter<'_, W>) {
+///         object.value("version", self.version);
+///         object.value("author", self.author);
+///     }
+/// }
+///
+/// let metadata = Metadata { version: 2, author: "ann" };
+/// let mut buffer = String::new();
+/// let mut object = JSONObjectWriter::new(&mut buffer);
+/// object.value("id", 1u32);
+/// object.flatten(&metadata);
+/// object.end();
+/// assert_eq!(buffer, r#"{"id":1,"version":2,"author":"ann"}"#);
+/// ```
+///
+pub trait JSONObjectFields {
+	///
+	/// Writes the fields as key:value pairs to `object`
+	///
+	fn write_fields<W: JSONWriter>(&self, object: &mut JSONObjectWriter<'_, W>);
+}
+
+impl JSONObjectFields for () {
+	#[inline(always)]
+	fn write_fields<W: JSONWriter>(&self, _object: &mut JSONObjectWriter<'_, W>) {}
+}
+
+impl<T: JSONObjectFields + ?Sized> JSONObjectFields for &T {
+	#[inline(always)]
+	fn write_fields<W: JSONWriter>(&self, object: &mut JSONObjectWriter<'_, W>) {
+		(**self).write_fields(object);
+	}
+}
+
 impl JSONWriterValue for &str {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
@@ -587,7 +2096,7 @@ impl JSONWriterValue for &str {
 	}
 }
 
-impl JSONWriterValue for &std::borrow::Cow<'_, str> {
+impl JSONWriterValue for &alloc::borrow::Cow<'_, str> {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
 		writer.json_string(AsRef::as_ref(self));
@@ -618,318 +2127,3195 @@ impl JSONWriterValue for f32 {
 impl JSONWriterValue for u128 {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		writer.json_number_u128(self);
 	}
 }
 
 impl JSONWriterValue for i128 {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		writer.json_number_i128(self);
 	}
 }
 
 impl JSONWriterValue for u64 {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		writer.json_number_u64(self);
 	}
 }
 
 impl JSONWriterValue for i64 {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		writer.json_number_i64(self);
 	}
 }
 
 impl JSONWriterValue for u32 {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		writer.json_number_u64(self as u64);
 	}
 }
 
 impl JSONWriterValue for i32 {
 	#[inline(always)]
 	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		writer.json_number_i64(self as i64);
+	}
+}
+
+impl JSONWriterValue for u16 {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self as u64);
+	}
+}
+
+impl JSONWriterValue for i16 {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self as i64);
+	}
+}
+
+impl JSONWriterValue for u8 {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self as u64);
+	}
+}
+
+impl JSONWriterValue for i8 {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self as i64);
+	}
+}
+
+impl JSONWriterValue for bool {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_bool(self);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicU64 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self.load(core::sync::atomic::Ordering::Relaxed));
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicI64 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self.load(core::sync::atomic::Ordering::Relaxed));
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicU32 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self.load(core::sync::atomic::Ordering::Relaxed) as u64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicI32 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self.load(core::sync::atomic::Ordering::Relaxed) as i64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicU16 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self.load(core::sync::atomic::Ordering::Relaxed) as u64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicI16 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self.load(core::sync::atomic::Ordering::Relaxed) as i64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicU8 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self.load(core::sync::atomic::Ordering::Relaxed) as u64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicI8 {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self.load(core::sync::atomic::Ordering::Relaxed) as i64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicUsize {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_u64(self.load(core::sync::atomic::Ordering::Relaxed) as u64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicIsize {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_i64(self.load(core::sync::atomic::Ordering::Relaxed) as i64);
+	}
+}
+
+impl JSONWriterValue for &core::sync::atomic::AtomicBool {
+	/// Writes the current value, loaded with `Ordering::Relaxed`
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_bool(self.load(core::sync::atomic::Ordering::Relaxed));
+	}
+}
+
+impl JSONWriterValue for Null {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_null();
+	}
+}
+
+impl JSONWriterValue for fmt::Arguments<'_> {
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		match self.as_str() {
+			Some(value) => writer.json_string(value),
+			None => {
+				// Errors of Display impls leave the string with the content written so far
+				let _ = fmt::Write::write_fmt(&mut JSONStringWriter::new(writer), self);
+			}
+		}
+	}
+}
+
+impl JSONWriterValue for () {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_null();
+	}
+}
+
+impl<A: JSONWriterValue, B: JSONWriterValue> JSONWriterValue for (A, B) {
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		array.value(self.0);
+		array.value(self.1);
+	}
+}
+
+impl<A: JSONWriterValue, B: JSONWriterValue, C: JSONWriterValue> JSONWriterValue for (A, B, C) {
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		array.value(self.0);
+		array.value(self.1);
+		array.value(self.2);
+	}
+}
+
+impl JSONWriterValue for EmptyObject {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		JSONObjectWriter::new(writer).end();
+	}
+}
+
+///
+/// Writes a string value that is known to contain no characters that need escaping, e.g. hex ids
+/// or enum names, without scanning it.
+///
+/// The string must not contain `"`, `\`, `/` or control characters. Otherwise the output is invalid JSON
+/// or differs from writing the string normally. This is checked with a debug assertion.
+/// Unlike `JSONWriter::json_fragment`, the value is quoted and written as string, so writers that
+/// don't write plain JSON, like `ConfiguredWriter` with an escaping option, still handle it correctly.
+///
+/// ```rust
+/// use json_writer::{to_json_string, PreEscaped};
+/// assert_eq!(to_json_string(PreEscaped("5f2b9c")), "\"5f2b9c\"");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct PreEscaped<'a>(pub &'a str);
+
+impl JSONWriterValue for PreEscaped<'_> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		debug_assert!(
+			self.0.bytes().all(|b| REPLACEMENTS[b as usize] == 0),
+			"PreEscaped: {:?} contains characters that need escaping",
+			self.0
+		);
+		writer.json_pre_escaped_string(self.0);
+	}
+}
+
+///
+/// Writes a matrix of floats, given as slice of rows, as array of arrays.
+///
+/// Rows can be anything that derefs to a float slice, like `Vec<f64>` or `&[f64]`.
+/// The output is identical to writing the nested slices directly.
+///
+/// ```rust
+/// use json_writer::{to_json_string, Matrix};
+/// let rows = vec![vec![1.0, 0.5], vec![-2.0, f64::NAN]];
+/// assert_eq!(to_json_string(Matrix(&rows)), "[[1,0.5],[-2,null]]");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Matrix<'a, R>(pub &'a [R]);
+
+impl<R: AsRef<[f64]>> JSONWriterValue for Matrix<'_, R> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		for row in self.0 {
+			array.array().values_f64(row.as_ref());
+		}
+	}
+}
+
+///
+/// Writes a slice as array in reverse order, without copying it.
+///
+/// ```rust
+/// use json_writer::{to_json_string, Reversed};
+/// assert_eq!(to_json_string(Reversed(&[1, 2, 3])), "[3,2,1]");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Reversed<'a, T>(pub &'a [T]);
+
+impl<T> JSONWriterValue for Reversed<'_, T>
+where
+	for<'b> &'b T: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		for item in self.0.iter().rev() {
+			array.value(item);
+		}
+	}
+}
+
+///
+/// Writes a map, or any iterator of key value pairs, as JSON array of `[key, value]` arrays.
+///
+/// Unlike objects, this works for keys that are not strings, like numbers, tuples or other composite values.
+/// The pairs are written in iteration order, which is sorted for `BTreeMap` and arbitrary for `HashMap`.
+///
+/// ```rust
+/// use json_writer::{to_json_string, MapAsPairs};
+/// let map = std::collections::BTreeMap::from([((1, 2), "a"), ((0, 5), "b")]);
+/// assert_eq!(to_json_string(MapAsPairs(&map)), "[[[0,5],\"b\"],[[1,2],\"a\"]]");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct MapAsPairs<M>(pub M);
+
+impl<M, K, V> JSONWriterValue for MapAsPairs<M>
+where
+	M: IntoIterator<Item = (K, V)>,
+	K: JSONWriterValue,
+	V: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		for pair in self.0 {
+			array.value(pair);
+		}
+	}
+}
+
+///
+/// Writes a slice of key value pairs as JSON object, in slice order and including duplicate keys.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct AssocObject<'a, V>(pub &'a [(&'a str, V)]);
+
+impl<V> JSONWriterValue for AssocObject<'_, V>
+where
+	for<'b> &'b V: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut obj = JSONObjectWriter::new(writer);
+		for (key, value) in self.0 {
+			obj.value(key, value);
+		}
+	}
+}
+
+///
+/// Writes an externally tagged enum variant as an object with the variant name as only key, e.g. `{"Circle":{"radius":1.5}}`.
+///
+/// Unit variants are usually written as plain string instead, see `JSONEnumString`.
+/// See `InternallyTagged` and `AdjacentlyTagged` for the other representations of serde.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct ExternallyTagged<'a, T>(pub &'a str, pub T);
+
+impl<T: JSONWriterValue> JSONWriterValue for ExternallyTagged<'_, T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		JSONObjectWriter::new(writer).value(self.0, self.1);
+	}
+}
+
+///
+/// Writes an internally tagged enum variant as an object with the tag followed by the fields of the variant,
+/// e.g. `{"type":"Circle","radius":1.5}`, like `#[serde(tag = "type")]`.
+///
+/// Unit variants have no fields, use `()` as `fields` to write only the tag.
+///
+/// ```rust
+/// use json_writer::{to_json_string, InternallyTagged};
+/// let unit = InternallyTagged { tag_key: "type", tag: "Unit", fields: () };
+/// assert_eq!(to_json_string(unit), "{\"type\":\"Unit\"}");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct InternallyTagged<'a, T> {
+	/// Key of the tag, e.g. `"type"`
+	pub tag_key: &'a str,
+	/// Name of the variant
+	pub tag: &'a str,
+	/// Fields of the variant, written after the tag
+	pub fields: T,
+}
+
+impl<T: JSONObjectFields> JSONWriterValue for InternallyTagged<'_, T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut obj = JSONObjectWriter::new(writer);
+		obj.tagged(self.tag_key, self.tag);
+		obj.flatten(&self.fields);
+	}
+}
+
+///
+/// Writes an adjacently tagged enum variant as an object with the tag and the content under separate keys,
+/// e.g. `{"t":"Circle","c":{"radius":1.5}}`, like `#[serde(tag = "t", content = "c")]`.
+///
+/// Unit variants have no content key, write them with `InternallyTagged` and `()` as `fields`.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct AdjacentlyTagged<'a, T> {
+	/// Key of the tag, e.g. `"t"`
+	pub tag_key: &'a str,
+	/// Key of the content, e.g. `"c"`
+	pub content_key: &'a str,
+	/// Name of the variant
+	pub tag: &'a str,
+	/// Content of the variant
+	pub value: T,
+}
+
+impl<T: JSONWriterValue> JSONWriterValue for AdjacentlyTagged<'_, T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut obj = JSONObjectWriter::new(writer);
+		obj.tagged(self.tag_key, self.tag);
+		obj.value(self.content_key, self.value);
+	}
+}
+
+///
+/// Writes the fields of a `JSONObjectFields` type as an object of their own.
+///
+/// Inside another object, use `JSONObjectWriter::flatten` to write the fields inline instead.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Flatten<T>(pub T);
+
+impl<T: JSONObjectFields> JSONWriterValue for Flatten<T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		self.0.write_fields(&mut JSONObjectWriter::new(writer));
+	}
+}
+
+///
+/// Implemented by enums that are written as a JSON string, usually the name of their variant.
+///
+/// Use `#[derive(JSONEnumString)]` with the `derive` feature or `json_enum_string!` to implement it together
+/// with `JSONWriterValue`, or wrap values in `EnumAsString` to write a type that implements it by hand.
+/// The derive writes the variant names, optionally converted with `#[json(rename_all = "SCREAMING_SNAKE_CASE")]`
+/// or replaced with `#[json(rename = "...")]`, and rejects variants with fields.
+///
+pub trait JSONEnumString {
+	///
+	/// Returns the string the value is written as.
+	///
+	fn as_json_str(&self) -> &'static str;
+}
+
+///
+/// Writes a `JSONEnumString` as JSON string.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct EnumAsString<T>(pub T);
+
+impl<T: JSONEnumString> JSONWriterValue for EnumAsString<T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_string(self.0.as_json_str());
+	}
+}
+
+///
+/// Implements `JSONEnumString` and `JSONWriterValue` for an enum with unit variants, writing each variant
+/// as the given string.
+///
+/// If the enum is `Copy`, references to it and therefore `Option`s, slices and maps of it can be written as well:
+///
+/// ```rust
+/// use json_writer::{json_enum_string, to_json_string};
+/// #[derive(Copy, Clone)]
+/// enum Level {
+///     Info,
+///     Warn,
+/// }
+/// json_enum_string!(Level {
+///     Info => "INFO",
+///     Warn => "WARN",
+/// });
+/// assert_eq!(to_json_string(&[Level::Info, Level::Warn][..]), "[\"INFO\",\"WARN\"]");
+/// assert_eq!(to_json_string(None::<Level>), "null");
+/// ```
+///
+/// Variants with fields are rejected, as the patterns only match unit variants:
+///
+/// ```compile_fail
+/// use json_writer::json_enum_string;
+/// enum Shape {
+///     Point,
+///     Circle(f64),
+/// }
+/// json_enum_string!(Shape {
+///     Point => "point",
+///     Circle => "circle",
+/// });
+/// ```
+///
+#[macro_export]
+macro_rules! json_enum_string {
+	($type:ty { $($variant:ident => $name:expr),* $(,)? }) => {
+		impl $crate::JSONEnumString for $type {
+			fn as_json_str(&self) -> &'static str {
+				match self {
+					$(Self::$variant => $name,)*
+				}
+			}
+		}
+
+		impl $crate::JSONWriterValue for $type {
+			#[inline(always)]
+			fn write_json<W: $crate::JSONWriter>(self, writer: &mut W) {
+				writer.json_string($crate::JSONEnumString::as_json_str(&self));
+			}
+		}
+	};
+}
+
+///
+/// Writes fields of a struct or local variables to a `JSONObjectWriter`, using their names as keys.
+///
+/// `write_fields!(object, row.{ id, name })` writes `object.value("id", &row.id)` and `object.value("name", &row.name)`,
+/// `write_fields!(object, { count, total })` writes the local variables `count` and `total`.
+/// The base can be a path of fields like `self.inner`. Each field can be
+///
+/// - renamed with `field as "key"`,
+/// - marked with `field?` to skip it if it is `None` and write the contained value otherwise,
+/// - both, as `field as "key"?`.
+///
+/// The values are written by reference, so they must implement `JSONWriterValue` for references.
+/// The object is used once per field, so it should be a variable rather than an expression with side effects.
+///
+/// ```rust
+/// use json_writer::{write_fields, JSONObjectWriter};
+/// struct User {
+///     id: u32,
+///     name: String,
+///     email: Option<String>,
+/// }
+///
+/// let user = User { id: 7, name: "Ann".to_owned(), email: None };
+/// let version = 2;
+/// let mut buffer = String::new();
+/// let mut object = JSONObjectWriter::new(&mut buffer);
+/// write_fields!(object, user.{ id, name as "displayName", email? });
+/// write_fields!(object, { version });
+/// object.end();
+/// assert_eq!(buffer, r#"{"id":7,"displayName":"Ann","version":2}"#);
+/// ```
+///
+#[macro_export]
+macro_rules! write_fields {
+	($object:expr, { $($fields:tt)* }) => {
+		$crate::write_fields!(@fields $object, [], $($fields)*);
+	};
+	($object:expr, $($base:ident).+ . { $($fields:tt)* }) => {
+		$crate::write_fields!(@fields $object, [$($base .)+], $($fields)*);
+	};
+	(@fields $object:expr, [$($base:tt)*], ) => {};
+	(@fields $object:expr, [$($base:tt)*], $field:ident as $key:literal ? $(, $($rest:tt)*)?) => {
+		if let ::core::option::Option::Some(value) = &$($base)* $field {
+			$object.value($key, value);
+		}
+		$crate::write_fields!(@fields $object, [$($base)*], $($($rest)*)?);
+	};
+	(@fields $object:expr, [$($base:tt)*], $field:ident as $key:literal $(, $($rest:tt)*)?) => {
+		$object.value($key, &$($base)* $field);
+		$crate::write_fields!(@fields $object, [$($base)*], $($($rest)*)?);
+	};
+	(@fields $object:expr, [$($base:tt)*], $field:ident ? $(, $($rest:tt)*)?) => {
+		if let ::core::option::Option::Some(value) = &$($base)* $field {
+			$object.value(::core::stringify!($field), value);
+		}
+		$crate::write_fields!(@fields $object, [$($base)*], $($($rest)*)?);
+	};
+	(@fields $object:expr, [$($base:tt)*], $field:ident $(, $($rest:tt)*)?) => {
+		$object.value(::core::stringify!($field), &$($base)* $field);
+		$crate::write_fields!(@fields $object, [$($base)*], $($($rest)*)?);
+	};
+}
+
+///
+/// Writes the `Some` values of an iterator or slice of `Option`s as JSON array and skips the `None`s.
+///
+/// Writing a `Vec<Option<T>>` directly writes `null` for each `None` instead.
+///
+/// ```rust
+/// use json_writer::{to_json_string, SkipNone};
+/// let values = vec![None, Some(1), None, Some(2), None];
+/// assert_eq!(to_json_string(SkipNone(&values)), "[1,2]");
+/// assert_eq!(to_json_string(SkipNone(values.into_iter().map(|x| x.filter(|&x| x > 1)))), "[2]");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct SkipNone<I>(pub I);
+
+impl<I> JSONWriterValue for SkipNone<I>
+where
+	I: IntoIterator,
+	I::Item: OptionValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		JSONArrayWriter::new(writer).values_flatten(self.0);
+	}
+}
+
+///
+/// An `Option` or a reference to one, for `SkipNone` and `JSONArrayWriter::values_flatten`
+///
+pub trait OptionValue {
+	/// Type of the contained value
+	type Value: JSONWriterValue;
+
+	///
+	/// Returns the contained value as `Option`
+	///
+	fn into_option(self) -> Option<Self::Value>;
+}
+
+impl<T: JSONWriterValue> OptionValue for Option<T> {
+	type Value = T;
+
+	#[inline(always)]
+	fn into_option(self) -> Option<T> {
+		self
+	}
+}
+
+impl<'a, T> OptionValue for &'a Option<T>
+where
+	&'a T: JSONWriterValue,
+{
+	type Value = &'a T;
+
+	#[inline(always)]
+	fn into_option(self) -> Option<&'a T> {
+		self.as_ref()
+	}
+}
+
+///
+/// Writes the items of a borrowed iterator as JSON array, without consuming it.
+///
+/// The iterator is cloned for writing, which is why `I` must be `Clone`. Adapters like `map` over ranges
+/// or slice iterators are cheap to clone, so values can be transformed without allocating and the source
+/// stays intact.
+///
+/// ```rust
+/// use json_writer::{to_json_string, ByRefIter};
+/// let doubled = (0..5).map(|x| x * 2);
+/// assert_eq!(to_json_string(ByRefIter(&doubled)), "[0,2,4,6,8]");
+/// assert_eq!(doubled.sum::<i32>(), 20);
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct ByRefIter<'a, I>(pub &'a I);
+
+impl<I> JSONWriterValue for ByRefIter<'_, I>
+where
+	I: IntoIterator + Clone,
+	I::Item: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		for item in self.0.clone() {
+			array.value(item);
+		}
+	}
+}
+
+///
+/// Writes the chars produced by an iterator as a single JSON string, without collecting them first.
+///
+#[derive(Debug, Clone)]
+pub struct CharsValue<I: Iterator<Item = char>>(pub I);
+
+impl<I: Iterator<Item = char>> JSONWriterValue for CharsValue<I> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		// Collect the chars in small batches to avoid escaping them one by one
+		let mut batch = [0u8; 128];
+		let mut batch_len = 0;
+		writer.json_begin_string();
+		for c in self.0 {
+			if batch_len + c.len_utf8() > batch.len() {
+				// Checks can be omitted here: the batch only contains complete utf-8 sequences
+				writer.json_string_part(unsafe {
+					core::str::from_utf8_unchecked(&batch[..batch_len])
+				});
+				batch_len = 0;
+			}
+			batch_len += c.encode_utf8(&mut batch[batch_len..]).len();
+		}
+		// Checks can be omitted here: the batch only contains complete utf-8 sequences
+		writer.json_string_part(unsafe { core::str::from_utf8_unchecked(&batch[..batch_len]) });
+		writer.json_end_string();
+	}
+}
+
+///
+/// Writes a duration as integer number of milliseconds, truncating fractions.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ElapsedMillis(pub core::time::Duration);
+
+impl JSONWriterValue for ElapsedMillis {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		self.0.as_millis().write_json(writer);
+	}
+}
+
+///
+/// Writes a duration as integer number of microseconds, truncating fractions.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ElapsedMicros(pub core::time::Duration);
+
+impl JSONWriterValue for ElapsedMicros {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		self.0.as_micros().write_json(writer);
+	}
+}
+
+///
+/// Writes a bool as number, `1` for true and `0` for false, for consumers that do not support JSON booleans.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BoolAsInt(pub bool);
+
+impl JSONWriterValue for BoolAsInt {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_str(if self.0 { "1" } else { "0" });
+	}
+}
+
+///
+/// Writes an integer with an explicit sign, e.g. `+42`, `-42` and `+0`, like `%+d` of printf.
+///
+/// **Note**: A leading `+` is not valid JSON. Only use this for consumers with a lenient parser that requires it.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SignedNumber(pub i64);
+
+impl JSONWriterValue for SignedNumber {
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut buf = itoa::Buffer::new();
+		let digits = buf.format(self.0);
+		if self.0 < 0 {
+			writer.json_number_str(digits);
+		} else {
+			let mut signed = [0u8; 20];
+			signed[0] = b'+';
+			signed[1..=digits.len()].copy_from_slice(digits.as_bytes());
+			// Checks can be omitted here: '+' and the digits are ascii
+			writer.json_number_str(unsafe {
+				core::str::from_utf8_unchecked(&signed[..=digits.len()])
+			});
+		}
+	}
+}
+
+///
+/// Writes a value with a closure, so hand-written writing code can be passed to `value` like any other value.
+///
+/// Closures can't be generic, so the closure gets the writer as `&mut dyn JSONWriter`. It must write exactly one value.
+/// Wrap it in `Option` to write null instead of calling the closure.
+///
+/// ```rust
+/// use json_writer::{JSONObjectWriter, JSONWriter, WriteWith};
+/// fn write_payload<W: JSONWriter>(writer: &mut W) {
+///     JSONObjectWriter::new(writer).value("legacy", true);
+/// }
+///
+/// let mut buffer = String::new();
+/// let mut object = JSONObjectWriter::new(&mut buffer);
+/// object.value("payload", WriteWith(|mut writer| write_payload(&mut writer)));
+/// object.end();
+/// assert_eq!(buffer, "{\"payload\":{\"legacy\":true}}");
+/// ```
+///
+pub struct WriteWith<F: FnOnce(&mut dyn JSONWriter)>(pub F);
+
+impl<F: FnOnce(&mut dyn JSONWriter)> JSONWriterValue for WriteWith<F> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		(self.0)(writer);
+	}
+}
+
+///
+/// Writes the placeholder string `"[REDACTED]"` instead of the wrapped value, e.g. for card numbers or passwords.
+///
+/// The wrapped value is never read, so it does not need to implement `JSONWriterValue`.
+///
+/// ```rust
+/// use json_writer::{JSONObjectWriter, Redacted};
+/// let password = "hunter2";
+/// let mut buffer = String::new();
+/// let mut object = JSONObjectWriter::new(&mut buffer);
+/// object.value("password", Redacted(&password));
+/// object.value("token", Redacted(&password).with_placeholder("***"));
+/// object.end();
+/// assert_eq!(buffer, "{\"password\":\"[REDACTED]\",\"token\":\"***\"}");
+/// ```
+///
+#[derive(Copy, Clone)]
+pub struct Redacted<T>(pub T);
+
+impl<T> fmt::Debug for Redacted<T> {
+	/// Prints the placeholder, not the wrapped value
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("Redacted([REDACTED])")
+	}
+}
+
+impl<T> Redacted<T> {
+	///
+	/// Writes `placeholder` instead of `"[REDACTED]"`
+	///
+	#[inline(always)]
+	pub fn with_placeholder(self, placeholder: &str) -> RedactedWith<'_, T> {
+		RedactedWith {
+			value: self.0,
+			placeholder,
+		}
+	}
+}
+
+impl<T> JSONWriterValue for Redacted<T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_string("[REDACTED]");
+	}
+}
+
+///
+/// A `Redacted` value with a custom placeholder, see `Redacted::with_placeholder`.
+///
+#[derive(Copy, Clone)]
+pub struct RedactedWith<'a, T> {
+	/// The value that is hidden
+	pub value: T,
+	/// The string that is written instead
+	pub placeholder: &'a str,
+}
+
+impl<T> fmt::Debug for RedactedWith<'_, T> {
+	/// Prints the placeholder, not the hidden value
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RedactedWith")
+			.field("placeholder", &self.placeholder)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<T> JSONWriterValue for RedactedWith<'_, T> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_string(self.placeholder);
+	}
+}
+
+///
+/// Writes only the last `keep_last` characters of `value`, prefixed with `"****"`, e.g. `"****1234"` for a card number.
+///
+/// The prefix has the same length for all values, so it does not reveal the length of `value`.
+/// If `value` has no more than `keep_last` characters, only `"****"` is written.
+///
+#[derive(Copy, Clone)]
+pub struct RedactedPartial<'a> {
+	/// The value to redact
+	pub value: &'a str,
+	/// Number of chars kept at the end
+	pub keep_last: usize,
+}
+
+impl<'a> RedactedPartial<'a> {
+	/// Returns the kept end of the value, which is empty if all chars would be kept
+	fn kept(&self) -> &'a str {
+		// Byte index of the first kept char, or None if all chars would be kept
+		let start = if self.keep_last == 0 {
+			Some(self.value.len())
+		} else {
+			self.value
+				.char_indices()
+				.rev()
+				.nth(self.keep_last)
+				.map(|(index, c)| index + c.len_utf8())
+		};
+		start.map_or("", |start| &self.value[start..])
+	}
+}
+
+impl fmt::Debug for RedactedPartial<'_> {
+	/// Prints the redacted value as it is written
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RedactedPartial")
+			.field(
+				"value",
+				&format_args!("\"****{}\"", self.kept().escape_debug()),
+			)
+			.field("keep_last", &self.keep_last)
+			.finish()
+	}
+}
+
+impl JSONWriterValue for RedactedPartial<'_> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_begin_string();
+		writer.json_string_part("****");
+		writer.json_string_part(self.kept());
+		writer.json_end_string();
+	}
+}
+
+///
+/// Writes at most the first `n` chars of a string, followed by `"…"` if anything was cut off, e.g. for log fields.
+///
+/// The string is only cut at char boundaries, so no UTF-8 sequence is split. Strings with up to `n` chars
+/// are written unchanged.
+///
+/// ```rust
+/// use json_writer::{to_json_string, Truncated};
+/// assert_eq!(to_json_string(Truncated("abcdef", 3)), "\"abc…\"");
+/// assert_eq!(to_json_string(Truncated("abcdef", 3).with_suffix("...")), "\"abc...\"");
+/// assert_eq!(to_json_string(Truncated("abc", 3)), "\"abc\"");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Truncated<'a>(pub &'a str, pub usize);
+
+impl<'a> Truncated<'a> {
+	///
+	/// Appends `suffix` instead of `"…"` to truncated strings
+	///
+	#[inline(always)]
+	pub fn with_suffix(self, suffix: &'a str) -> TruncatedWith<'a> {
+		TruncatedWith {
+			value: self.0,
+			max_chars: self.1,
+			suffix,
+		}
+	}
+}
+
+impl JSONWriterValue for Truncated<'_> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		self.with_suffix("…").write_json(writer);
+	}
+}
+
+///
+/// A `Truncated` string with a custom suffix, see `Truncated::with_suffix`.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct TruncatedWith<'a> {
+	/// The string to write
+	pub value: &'a str,
+	/// Maximum number of chars written from `value`
+	pub max_chars: usize,
+	/// Appended if `value` has more than `max_chars` chars
+	pub suffix: &'a str,
+}
+
+impl JSONWriterValue for TruncatedWith<'_> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		match self.value.char_indices().nth(self.max_chars) {
+			None => writer.json_string(self.value),
+			Some((end, _)) => {
+				writer.json_begin_string();
+				writer.json_string_part(&self.value[..end]);
+				writer.json_string_part(self.suffix);
+				writer.json_end_string();
+			}
+		}
+	}
+}
+
+///
+/// Writes an unsigned big-endian integer of any width, like a 256-bit `[u8; 32]`, in decimal as quoted string.
+///
+/// Strings keep all digits in JavaScript, where numbers beyond 2^53 lose precision.
+/// Use `as_number` to write an unquoted number instead.
+///
+/// ```rust
+/// use json_writer::{to_json_string, BigUintBytes};
+/// let mut bytes = [0u8; 32];
+/// bytes[15] = 1;
+/// assert_eq!(to_json_string(BigUintBytes(&bytes)), "\"340282366920938463463374607431768211456\"");
+/// assert_eq!(to_json_string(BigUintBytes(&[1, 0]).as_number()), "256");
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct BigUintBytes<'a>(pub &'a [u8]);
+
+impl<'a> BigUintBytes<'a> {
+	///
+	/// Writes the integer as number instead of string
+	///
+	#[inline(always)]
+	pub fn as_number(self) -> BigUintNumber<'a> {
+		BigUintNumber(self.0)
+	}
+}
+
+impl JSONWriterValue for BigUintBytes<'_> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut digits = String::new();
+		write_big_uint(self.0, &mut digits);
+		writer.json_string(&digits);
+	}
+}
+
+///
+/// Writes an unsigned big-endian integer of any width as JSON number, see `BigUintBytes::as_number`.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct BigUintNumber<'a>(pub &'a [u8]);
+
+impl JSONWriterValue for BigUintNumber<'_> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut digits = String::new();
+		write_big_uint(self.0, &mut digits);
+		writer.json_number_str(&digits);
+	}
+}
+
+///
+/// Writes a number of any precision given as string, e.g. a decimal with many significant digits, losslessly as JSON number.
+///
+/// The string is checked against the JSON number grammar when the value is created, so invalid numbers
+/// can't end up in the output.
+///
+/// ```rust
+/// use json_writer::{to_json_string, BigNumber};
+/// let number = BigNumber::new("-12345678901234567890.000000000000000000001e-3")?;
+/// assert_eq!(to_json_string(number), "-12345678901234567890.000000000000000000001e-3");
+/// assert!(BigNumber::new("1.").is_err());
+/// # Ok::<(), json_writer::InvalidNumber>(())
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct BigNumber<'a>(&'a str);
+
+impl<'a> BigNumber<'a> {
+	///
+	/// Returns an error if `number` doesn't match the JSON number grammar, e.g. for `+1`, `01`, `.5` or `NaN`
+	///
+	pub fn new(number: &'a str) -> Result<BigNumber<'a>, InvalidNumber> {
+		if reformat::is_number(number.as_bytes()) {
+			Ok(BigNumber(number))
+		} else {
+			Err(InvalidNumber)
+		}
+	}
+
+	///
+	/// Returns the number as string
+	///
+	#[inline(always)]
+	pub fn as_str(&self) -> &'a str {
+		self.0
+	}
+}
+
+impl JSONWriterValue for BigNumber<'_> {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		writer.json_number_str(self.0);
+	}
+}
+
+///
+/// Error of `BigNumber::new` for strings that are not a JSON number
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidNumber;
+
+impl fmt::Display for InvalidNumber {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("string doesn't match the JSON number grammar")
+	}
+}
+
+impl core::error::Error for InvalidNumber {}
+
+/// Appends the decimal digits of the unsigned big-endian integer `bytes` to `output`
+fn write_big_uint(bytes: &[u8], output: &mut String) {
+	let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+	let bytes = &bytes[start..];
+	if bytes.len() <= 16 {
+		let value = bytes
+			.iter()
+			.fold(0u128, |value, &b| (value << 8) | b as u128);
+		output.push_str(itoa::Buffer::new().format(value));
+		return;
+	}
+
+	// Big-endian 32 bit limbs, divided by 10^9 repeatedly to get 9 digits at a time
+	const CHUNK: u64 = 1_000_000_000;
+	let mut limbs: Vec<u32> = Vec::with_capacity(bytes.len() / 4 + 1);
+	let head = bytes.len() % 4;
+	if head > 0 {
+		limbs.push(
+			bytes[..head]
+				.iter()
+				.fold(0, |limb, &b| (limb << 8) | b as u32),
+		);
+	}
+	for word in bytes[head..].chunks_exact(4) {
+		limbs.push(u32::from_be_bytes([word[0], word[1], word[2], word[3]]));
+	}
+	let mut chunks = Vec::with_capacity(bytes.len() * 8 / 29 + 1);
+	let mut first = 0;
+	while first < limbs.len() {
+		let mut remainder = 0u64;
+		for limb in &mut limbs[first..] {
+			let current = (remainder << 32) | *limb as u64;
+			*limb = (current / CHUNK) as u32;
+			remainder = current % CHUNK;
+		}
+		chunks.push(remainder as u32);
+		while first < limbs.len() && limbs[first] == 0 {
+			first += 1;
+		}
+	}
+
+	let mut buf = itoa::Buffer::new();
+	let mut chunks = chunks.iter().rev();
+	output.push_str(buf.format(*chunks.next().unwrap()));
+	for &chunk in chunks {
+		let digits = buf.format(chunk);
+		for _ in digits.len()..9 {
+			output.push('0');
+		}
+		output.push_str(digits);
+	}
+}
+
+///
+/// Writes `sentinel` instead of `value` if `value` is NaN, e.g. `-999` for missing values in legacy formats.
+///
+/// Infinite values are written like any other `f64`, i.e. as null unless the writer has a different policy.
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NanAsSentinel {
+	/// The number
+	pub value: f64,
+	/// Number written if `value` is NaN
+	pub sentinel: f64,
+}
+
+impl JSONWriterValue for NanAsSentinel {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		if self.value.is_nan() {
+			writer.json_number_f64(self.sentinel);
+		} else {
+			writer.json_number_f64(self.value);
+		}
+	}
+}
+
+///
+/// Writes an integer as JSON string, right-aligned to `width` characters with `fill`, e.g. for tables in reports.
+///
+/// Numbers wider than `width` are written in full. With `'0'` as fill, the padding goes after the sign like in `format!("{:05}", -42)`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PaddedNumber {
+	/// The number
+	pub value: i64,
+	/// Minimum number of characters
+	pub width: usize,
+	/// Character used for padding
+	pub fill: char,
+}
+
+impl JSONWriterValue for PaddedNumber {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut buffer = itoa::Buffer::new();
+		let number = buffer.format(self.value);
+		let (sign, digits) = if self.fill == '0' && self.value < 0 {
+			number.split_at(1)
+		} else {
+			("", number)
+		};
+		let mut fill = [0u8; 4];
+		let fill: &str = self.fill.encode_utf8(&mut fill);
+		writer.json_begin_string();
+		writer.json_string_part(sign);
+		for _ in number.len()..self.width {
+			writer.json_string_part(fill);
+		}
+		writer.json_string_part(digits);
+		writer.json_end_string();
+	}
+}
+
+impl<T: JSONWriterValue + Copy> JSONWriterValue for &T {
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		(*self).write_json(writer);
+	}
+}
+
+impl<T: JSONWriterValue> JSONWriterValue for Option<T> {
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		match self {
+			None => {
+				writer.json_null();
+			}
+			Some(value) => {
+				value.write_json(writer);
+			}
+		}
+	}
+}
+
+impl<T> JSONWriterValue for &alloc::rc::Weak<T>
+where
+	for<'b> &'b T: JSONWriterValue,
+{
+	/// Writes the referenced value, or null if it has already been dropped
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		match self.upgrade() {
+			None => writer.json_null(),
+			Some(value) => (&*value).write_json(writer),
+		}
+	}
+}
+
+impl<T> JSONWriterValue for &alloc::sync::Weak<T>
+where
+	for<'b> &'b T: JSONWriterValue,
+{
+	/// Writes the referenced value, or null if it has already been dropped
+	#[inline]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		match self.upgrade() {
+			None => writer.json_null(),
+			Some(value) => (&*value).write_json(writer),
+		}
+	}
+}
+
+impl<Item> JSONWriterValue for &Vec<Item>
+where
+	for<'b> &'b Item: JSONWriterValue,
+{
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		self.as_slice().write_json(writer);
+	}
+}
+
+impl<Item: Clone> JSONWriterValue for &alloc::borrow::Cow<'_, [Item]>
+where
+	for<'b> &'b Item: JSONWriterValue,
+{
+	#[inline(always)]
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		AsRef::<[Item]>::as_ref(self).write_json(writer);
+	}
+}
+
+impl<Item> JSONWriterValue for &[Item]
+where
+	for<'b> &'b Item: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		// Rough estimate: at least one character and a comma per item
+		writer.json_reserve(self.len() * 2 + 2);
+		let mut array = JSONArrayWriter::new(writer);
+		for item in self.iter() {
+			array.value(item);
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<Key: AsRef<str>, Item> JSONWriterValue for &std::collections::HashMap<Key, Item>
+where
+	for<'b> &'b Item: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		// Rough estimate: quotes, colon, comma and one character of value per entry
+		writer.json_reserve(self.len() * 5 + 2);
+		let mut obj = JSONObjectWriter::new(writer);
+		for (key, value) in self.iter() {
+			obj.value(key.as_ref(), value);
+		}
+	}
+}
+
+impl<Key: AsRef<str>, Item> JSONWriterValue for &alloc::collections::BTreeMap<Key, Item>
+where
+	for<'b> &'b Item: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		// Rough estimate: quotes, colon, comma and one character of value per entry
+		writer.json_reserve(self.len() * 5 + 2);
+		let mut obj = JSONObjectWriter::new(writer);
+		for (key, value) in self.iter() {
+			obj.value(key.as_ref(), value);
+		}
+	}
+}
+
+///
+/// Writes the entries of a map as object, using a closure to get the key of each entry.
+///
+/// Created by `object_from_map_with_key`.
+///
+#[derive(Debug, Clone)]
+pub struct MapWithKey<M, F> {
+	map: M,
+	key: F,
+}
+
+///
+/// Writes a map as object, calling `key` to get the string of each key.
+///
+/// Useful for maps with keys that do not implement `AsRef<str>`, e.g. newtypes.
+/// Works with anything that iterates over `(&Key, &Value)` pairs, like `&HashMap` and `&BTreeMap`.
+///
+/// ```rust
+/// use json_writer::{object_from_map_with_key, to_json_string};
+/// struct Lang(String);
+/// let mut map = std::collections::BTreeMap::new();
+/// map.insert(0, "x");
+/// let names = [Lang("en".to_owned())];
+/// let value = object_from_map_with_key(&map, |k| names[*k as usize].0.as_str());
+/// assert_eq!(to_json_string(value), "{\"en\":\"x\"}");
+/// ```
+///
+#[inline(always)]
+pub fn object_from_map_with_key<'a, Key: 'a, Item: 'a, M, F>(map: M, key: F) -> MapWithKey<M, F>
+where
+	M: IntoIterator<Item = (&'a Key, &'a Item)>,
+	F: Fn(&'a Key) -> &'a str,
+{
+	MapWithKey { map, key }
+}
+
+impl<'a, Key: 'a, Item: 'a, M, F> JSONWriterValue for MapWithKey<M, F>
+where
+	M: IntoIterator<Item = (&'a Key, &'a Item)>,
+	F: Fn(&'a Key) -> &'a str,
+	&'a Item: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut obj = JSONObjectWriter::new(writer);
+		for (key, value) in self.map {
+			obj.value((self.key)(key), value);
+		}
+	}
+}
+
+/// Maps with up to this many entries are sorted on the stack by `SortedKeys`
+#[cfg(feature = "std")]
+const SORTED_KEYS_STACK_LEN: usize = 16;
+
+///
+/// Writes a map as object with the keys in sorted order, for output that does not depend on the hash order.
+///
+/// Maps with up to 16 entries are sorted in a fixed size array on the stack without allocating,
+/// larger maps are sorted in a temporary `Vec`.
+/// A `BTreeMap` is already sorted and can be written directly.
+///
+#[derive(Debug)]
+#[cfg(feature = "std")]
+pub struct SortedKeys<'a, Key, Item, S>(pub &'a std::collections::HashMap<Key, Item, S>);
+
+#[cfg(feature = "std")]
+impl<Key: AsRef<str>, Item, S> JSONWriterValue for SortedKeys<'_, Key, Item, S>
+where
+	for<'b> &'b Item: JSONWriterValue,
+{
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut obj = JSONObjectWriter::new(writer);
+		if self.0.len() <= SORTED_KEYS_STACK_LEN {
+			let mut entries: [Option<(&str, &Item)>; SORTED_KEYS_STACK_LEN] =
+				[None; SORTED_KEYS_STACK_LEN];
+			for (entry, (key, value)) in entries.iter_mut().zip(self.0.iter()) {
+				*entry = Some((key.as_ref(), value));
+			}
+			let entries = &mut entries[..self.0.len()];
+			// Keys are unique, so an unstable sort gives the same order and never allocates
+			entries.sort_unstable_by_key(|entry| entry.map(|(key, _)| key));
+			for (key, value) in entries.iter().flatten() {
+				obj.value(key, *value);
+			}
+		} else {
+			let mut entries: Vec<(&str, &Item)> = self
+				.0
+				.iter()
+				.map(|(key, value)| (key.as_ref(), value))
+				.collect();
+			entries.sort_unstable_by_key(|(key, _)| *key);
+			for (key, value) in entries {
+				obj.value(key, value);
+			}
+		}
+	}
+}
+
+///
+/// Writes the entries of a map as object ordered by value instead of by key, returned by `object_sorted_by_value`
+///
+#[derive(Debug)]
+pub struct SortedByValue<M, F> {
+	map: M,
+	cmp: F,
+}
+
+///
+/// Writes the entries of `map` as object, sorted by their values with `cmp`, e.g. for "top N" reports.
+///
+/// The entries are collected and sorted in a temporary `Vec`. The sort is stable, so entries with equal values keep
+/// the order of the map. Every entry is written once, so the object only has duplicate keys if the map has them.
+///
+/// ```rust
+/// use json_writer::{object_sorted_by_value, to_json_string};
+/// use std::collections::HashMap;
+/// let hits: HashMap<&str, u32> = [("/a", 3), ("/b", 10), ("/c", 7)].into();
+/// assert_eq!(
+///     to_json_string(object_sorted_by_value(&hits, |a, b| b.cmp(a))),
+///     r#"{"\/b":10,"\/c":7,"\/a":3}"#
+/// );
+/// ```
+///
+pub fn object_sorted_by_value<'a, M, K, V, F>(map: M, cmp: F) -> SortedByValue<M, F>
+where
+	M: IntoIterator<Item = (&'a K, &'a V)>,
+	K: AsRef<str> + 'a,
+	V: JSONWriterValueRef + 'a,
+	F: FnMut(&V, &V) -> core::cmp::Ordering,
+{
+	SortedByValue { map, cmp }
+}
+
+impl<'a, M, K, V, F> JSONWriterValue for SortedByValue<M, F>
+where
+	M: IntoIterator<Item = (&'a K, &'a V)>,
+	K: AsRef<str> + 'a,
+	V: JSONWriterValueRef + 'a,
+	F: FnMut(&V, &V) -> core::cmp::Ordering,
+{
+	fn write_json<W: JSONWriter>(mut self, writer: &mut W) {
+		let mut entries: Vec<(&K, &V)> = self.map.into_iter().collect();
+		entries.sort_by(|(_, a), (_, b)| (self.cmp)(a, b));
+		let mut obj = JSONObjectWriter::new(writer);
+		for (key, value) in entries {
+			obj.with_raw_writer(key.as_ref(), |writer| value.write_json_ref(writer));
+		}
+	}
+}
+
+///
+/// Converts given value to a json string.
+///
+#[inline]
+pub fn to_json_string<T: JSONWriterValue>(v: T) -> String {
+	let mut result = String::new();
+	v.write_json(&mut result);
+	return result;
+}
+
+///
+/// Converts given value to json encoded as UTF-16 little endian, prefixed with the byte order mark `FF FE`.
+///
+/// For consumers that only read UTF-16 with BOM, like some .NET and Windows tools.
+///
+pub fn to_json_utf16le<T: JSONWriterValue>(v: T) -> Vec<u8> {
+	encode_utf16(&to_json_string(v), u16::to_le_bytes)
+}
+
+///
+/// Converts given value to json encoded as UTF-16 big endian, prefixed with the byte order mark `FE FF`.
+///
+pub fn to_json_utf16be<T: JSONWriterValue>(v: T) -> Vec<u8> {
+	encode_utf16(&to_json_string(v), u16::to_be_bytes)
+}
+
+/// Encodes `json` as UTF-16 with a byte order mark, using `to_bytes` for the byte order
+fn encode_utf16(json: &str, to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+	// Every byte of utf-8 takes at most two bytes in utf-16
+	let mut result = Vec::with_capacity(2 + json.len() * 2);
+	result.extend_from_slice(&to_bytes(0xFEFF));
+	for unit in json.encode_utf16() {
+		result.extend_from_slice(&to_bytes(unit));
+	}
+	result
+}
+
+///
+/// Writes the `Ok` values of `items` as array entries and stops at the first `Err`, returning a clone of the error.
+///
+/// The entries written before the error are left in the array.
+/// Use `JSONArrayWriter::try_item` to remove them again on writers that support it.
+///
+pub fn try_write_slice<T: JSONWriterValueRef, E: Clone, W: JSONWriter>(
+	writer: &mut JSONArrayWriter<'_, W>,
+	items: &[Result<T, E>],
+) -> Result<(), E> {
+	for item in items {
+		match item {
+			Ok(value) => {
+				writer.comma();
+				value.write_json_ref(writer.writer);
+			}
+			Err(err) => return Err(err.clone()),
+		}
+	}
+	Ok(())
+}
+
+///
+/// Writes an object with the given key-value pairs to `writer` and flushes the buffer to `sink`
+/// whenever it has grown past `flush_threshold` bytes.
+///
+/// The whole object, including the part that is still buffered after closing it, has been written to `sink`
+/// when this returns `Ok`. On error, the object is closed in the buffer, but not flushed.
+///
+#[cfg(feature = "std")]
+pub fn object_stream<W, K, V, I, S>(
+	writer: &mut W,
+	pairs: I,
+	sink: &mut S,
+	flush_threshold: usize,
+) -> Result<(), std::io::Error>
+where
+	W: BufferedJSONWriter,
+	K: AsRef<str>,
+	V: JSONWriterValue,
+	I: IntoIterator<Item = (K, V)>,
+	S: std::io::Write,
+{
+	let mut object = JSONObjectWriter::new(&mut *writer);
+	for (key, value) in pairs {
+		object.value(key.as_ref(), value);
+		if object.buffer_len() > flush_threshold {
+			object.output_buffered_data(sink)?;
+		}
+	}
+	object.end();
+	writer.take_buffer(sink)?;
+	Ok(())
+}
+
+///
+/// Writes an array with each line of `reader` as a string, without the surrounding whitespace and line ending,
+/// to `writer`, e.g. to convert a large text file without loading it into memory.
+///
+/// The array is buffered and written to `writer` every 64 KiB, and `writer` is flushed at the end.
+/// Stops at the first read or write error, leaving an incomplete array in `writer`.
+/// Lines that are not valid UTF-8 cause an `InvalidData` error.
+///
+/// ```rust
+/// use json_writer::lines_to_json_array;
+/// let mut output = Vec::new();
+/// lines_to_json_array("first\r\n  second \"line\"\n".as_bytes(), &mut output)?;
+/// assert_eq!(output, br#"["first","second \"line\""]"#);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+#[cfg(feature = "std")]
+pub fn lines_to_json_array<R: std::io::BufRead, W: std::io::Write>(
+	mut reader: R,
+	mut writer: W,
+) -> Result<(), std::io::Error> {
+	const FLUSH_THRESHOLD: usize = 64 * 1024;
+	let mut buffer = String::new();
+	let mut line = String::new();
+	let mut array = JSONArrayWriter::new(&mut buffer);
+	while reader.read_line(&mut line)? > 0 {
+		array.value(line.trim());
+		line.clear();
+		if array.buffer_len() > FLUSH_THRESHOLD {
+			array.output_buffered_data(&mut writer)?;
+		}
+	}
+	array.end();
+	writer.write_all(buffer.as_bytes())?;
+	writer.flush()
+}
+
+///
+/// Returns up to the last `n` bytes of `buffer` that start at a char boundary
+///
+fn buffer_tail(buffer: &str, n: usize) -> &str {
+	let mut start = buffer.len().saturating_sub(n);
+	while !buffer.is_char_boundary(start) {
+		start += 1;
+	}
+	&buffer[start..]
+}
+
+#[cfg(feature = "std")]
+fn output_buffer_to<Writer: std::io::Write + ?Sized>(
+	buffer: &mut String,
+	writer: &mut Writer,
+) -> Result<usize, std::io::Error> {
+	match writer.write_all(buffer.as_bytes()) {
+		Ok(_) => {
+			let len = buffer.len();
+			buffer.clear();
+			FLUSHES.with(|flushes| flushes.set(flushes.get() + 1));
+			Ok(len)
+		}
+		Err(err) => Err(err),
+	}
+}
+
+#[cfg(feature = "std")]
+fn output_bytes_to<Writer: std::io::Write + ?Sized>(
+	buffer: &mut Vec<u8>,
+	writer: &mut Writer,
+) -> Result<usize, std::io::Error> {
+	match writer.write_all(buffer) {
+		Ok(_) => {
+			let len = buffer.len();
+			buffer.clear();
+			FLUSHES.with(|flushes| flushes.set(flushes.get() + 1));
+			Ok(len)
+		}
+		Err(err) => Err(err),
+	}
+}
+
+///
+/// Quotes and escapes input and appends result to output buffer
+///
+#[inline(never)]
+pub fn write_string(output_buffer: &mut String, input: &str) {
+	reserve_for_string(output_buffer, input.len() + 2);
+	output_buffer.push('"');
+	write_part_of_string_impl(output_buffer, input);
+	output_buffer.push('"');
+}
+
+///
+/// Escapes input and appends result to output buffer without adding quotes.
+///
+#[inline(never)]
+pub fn write_part_of_string(output_buffer: &mut String, input: &str) {
+	reserve_for_string(output_buffer, input.len());
+	write_part_of_string_impl(output_buffer, input);
+}
+
+/// Strings shorter than this are written without reserving capacity first
+const RESERVE_THRESHOLD: usize = 256;
+
+///
+/// Reserves capacity for a large string of `len` bytes plus some slack for escapes.
+///
+#[inline(always)]
+fn reserve_for_string(output_buffer: &mut String, len: usize) {
+	if len >= RESERVE_THRESHOLD {
+		output_buffer.reserve(len + len / 16);
+	}
+}
+
+const fn get_replacements() -> [u8; 256] {
+	// NOTE: Only characters smaller than 128 are allowed here.
+	// Trying to escape values above 128 would generate invalid utf-8 output
+	// -----
+	// see https://www.json.org/json-en.html
+	let mut result = [0u8; 256];
+	// Escape everything from 0 to 0x1F
+	let mut i = 0;
+	while i < 0x20 {
+		result[i] = b'u';
+		i += 1;
+	}
+	result[b'\"' as usize] = b'"';
+	result[b'\\' as usize] = b'\\';
+	result[b'/' as usize] = b'/';
+	result[8] = b'b';
+	result[0xc] = b'f';
+	result[b'\n' as usize] = b'n';
+	result[b'\r' as usize] = b'r';
+	result[b'\t' as usize] = b't';
+	result[0] = b'u';
+
+	return result;
+}
+
+///
+/// Output buffer of the escaping routines
+///
+trait EscapeBuffer {
+	fn push_str(&mut self, value: &str);
+}
+
+impl EscapeBuffer for String {
+	#[inline(always)]
+	fn push_str(&mut self, value: &str) {
+		String::push_str(self, value);
+	}
+}
+
+impl EscapeBuffer for Vec<u8> {
+	#[inline(always)]
+	fn push_str(&mut self, value: &str) {
+		self.extend_from_slice(value.as_bytes());
+	}
+}
+
+///
+/// Escape output that writes to a JSONWriter as raw fragments
+///
+struct FragmentBuffer<'a, W: JSONWriter + ?Sized>(&'a mut W);
+
+impl<W: JSONWriter + ?Sized> EscapeBuffer for FragmentBuffer<'_, W> {
+	#[inline(always)]
+	fn push_str(&mut self, value: &str) {
+		if !value.is_empty() {
+			self.0.json_fragment(value);
+		}
+	}
+}
+
+static REPLACEMENTS: [u8; 256] = get_replacements();
+static HEX: [u8; 16] = *b"0123456789ABCDEF";
+
+///
+/// Escapes and append part of string
+///
+#[inline(always)]
+fn write_part_of_string_impl<B: EscapeBuffer + ?Sized>(output_buffer: &mut B, input: &str) {
+	// All of the relevant characters are in the ansi range (<128).
+	// This means we can safely ignore any utf-8 characters and iterate over the bytes directly
+	let mut num_bytes_written: usize = 0;
+	let mut index: usize = 0;
+	let bytes = input.as_bytes();
+	while index < bytes.len() {
+		#[cfg(all(any(test, feature = "simd"), target_arch = "x86_64"))]
+		if index + 16 <= bytes.len() && !simd::block_needs_escaping(&bytes[index..index + 16]) {
+			index += 16;
+			continue;
+		}
+		// Skip blocks of 8 bytes that contain nothing to escape, only inspect the others byte by byte
+		let block_end = if index + 8 <= bytes.len() {
+			let mut block = [0u8; 8];
+			block.copy_from_slice(&bytes[index..index + 8]);
+			if !block_needs_escaping(u64::from_le_bytes(block)) {
+				index += 8;
+				continue;
+			}
+			index + 8
+		} else {
+			bytes.len()
+		};
+		while index < block_end {
+			let cur_byte = bytes[index];
+			let replacement = REPLACEMENTS[cur_byte as usize];
+			if replacement != 0 {
+				write_escaped_byte(
+					output_buffer,
+					input,
+					num_bytes_written,
+					index,
+					cur_byte,
+					replacement,
+				);
+				num_bytes_written = index + 1;
+			}
+			index += 1;
+		}
+	}
+	if num_bytes_written < bytes.len() {
+		// Checks can be omitted here:
+		// We know that num_bytes_written is smaller than index
+		// We also know that num_bytes_written not in the middle of an utf-8 multi byte sequence, because those are not escaped
+		output_buffer.push_str(unsafe { input.get_unchecked(num_bytes_written..bytes.len()) });
+	}
+}
+
+const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+///
+/// Returns true if any of the 8 bytes packed into `block` has an entry in the replacement table
+///
+#[inline(always)]
+fn block_needs_escaping(block: u64) -> bool {
+	// A byte of `x - LOW_BITS * n` has its high bit set (and the byte of x not) iff the byte of x is < n
+	#[inline(always)]
+	fn any_byte_less_than(x: u64, n: u8) -> u64 {
+		x.wrapping_sub(LOW_BITS * n as u64) & !x
+	}
+	let control = any_byte_less_than(block, 0x20);
+	let quote = any_byte_less_than(block ^ (LOW_BITS * b'"' as u64), 1);
+	let backslash = any_byte_less_than(block ^ (LOW_BITS * b'\\' as u64), 1);
+	let slash = any_byte_less_than(block ^ (LOW_BITS * b'/' as u64), 1);
+	(control | quote | backslash | slash) & HIGH_BITS != 0
+}
+
+///
+/// SSE2 version of `block_needs_escaping` for blocks of 16 bytes. SSE2 is available on every x86_64 cpu.
+///
+#[cfg(all(any(test, feature = "simd"), target_arch = "x86_64"))]
+mod simd {
+	use core::arch::x86_64::{
+		__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_min_epu8, _mm_movemask_epi8, _mm_or_si128,
+		_mm_set1_epi8,
+	};
+
+	///
+	/// Returns true if any of the 16 bytes of `block` has an entry in the replacement table
+	///
+	#[inline(always)]
+	pub(super) fn block_needs_escaping(block: &[u8]) -> bool {
+		assert!(block.len() >= 16);
+		// Safety: the block is at least 16 bytes long and SSE2 is part of the x86_64 baseline
+		unsafe {
+			let v = _mm_loadu_si128(block.as_ptr().cast::<__m128i>());
+			// min(x, 0x1F) == x iff x <= 0x1F, compared unsigned
+			let control = _mm_cmpeq_epi8(_mm_min_epu8(v, _mm_set1_epi8(0x1F)), v);
+			let quote = _mm_cmpeq_epi8(v, _mm_set1_epi8(b'"' as i8));
+			let backslash = _mm_cmpeq_epi8(v, _mm_set1_epi8(b'\\' as i8));
+			let slash = _mm_cmpeq_epi8(v, _mm_set1_epi8(b'/' as i8));
+			let any = _mm_or_si128(_mm_or_si128(control, quote), _mm_or_si128(backslash, slash));
+			_mm_movemask_epi8(any) != 0
+		}
+	}
+}
+
+///
+/// Writes the unescaped bytes `num_bytes_written..index` followed by the escaped byte at `index`
+///
+#[inline(always)]
+fn write_escaped_byte<B: EscapeBuffer + ?Sized>(
+	output_buffer: &mut B,
+	input: &str,
+	num_bytes_written: usize,
+	index: usize,
+	cur_byte: u8,
+	replacement: u8,
+) {
+	if num_bytes_written < index {
+		// Checks can be omitted here:
+		// We know that index is smaller than the output_buffer length.
+		// We also know that num_bytes_written is smaller than index
+		// We also know that the boundaries are not in the middle of an utf-8 multi byte sequence, because those characters are not escaped
+		output_buffer.push_str(unsafe { input.get_unchecked(num_bytes_written..index) });
+	}
+	if replacement == b'u' {
+		let bytes: [u8; 6] = [
+			b'\\',
+			b'u',
+			b'0',
+			b'0',
+			HEX[((cur_byte / 16) & 0xF) as usize],
+			HEX[(cur_byte & 0xF) as usize],
+		];
+		// Checks can be omitted here: We know bytes is a valid utf-8 string (see above)
+		output_buffer.push_str(unsafe { core::str::from_utf8_unchecked(&bytes) });
+	} else {
+		let bytes: [u8; 2] = [b'\\', replacement];
+		// Checks can be omitted here: We know bytes is a valid utf-8 string, because the replacement table only contains characters smaller than 128
+		output_buffer.push_str(unsafe { core::str::from_utf8_unchecked(&bytes) });
+	}
+}
+
+///
+/// Writes `c` as `\uXXXX` escape sequence, or two of them for characters outside the BMP
+///
+fn write_escaped_char<B: EscapeBuffer + ?Sized>(output_buffer: &mut B, c: char) {
+	for unit in c.encode_utf16(&mut [0; 2]) {
+		let bytes: [u8; 6] = [
+			b'\\',
+			b'u',
+			HEX[(*unit >> 12) as usize & 0xF],
+			HEX[(*unit >> 8) as usize & 0xF],
+			HEX[(*unit >> 4) as usize & 0xF],
+			HEX[*unit as usize & 0xF],
+		];
+		// Checks can be omitted here: the escape sequence only contains ascii characters
+		output_buffer.push_str(unsafe { core::str::from_utf8_unchecked(&bytes) });
+	}
+}
+
+///
+/// Byte by byte reference implementation of `write_part_of_string_impl`
+///
+#[cfg(test)]
+fn write_part_of_string_scalar(output_buffer: &mut String, input: &str) {
+	let mut num_bytes_written: usize = 0;
+	for (index, cur_byte) in input.bytes().enumerate() {
+		let replacement = REPLACEMENTS[cur_byte as usize];
+		if replacement != 0 {
+			write_escaped_byte(
+				output_buffer,
+				input,
+				num_bytes_written,
+				index,
+				cur_byte,
+				replacement,
+			);
+			num_bytes_written = index + 1;
+		}
+	}
+	output_buffer.push_str(&input[num_bytes_written..]);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_array() {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(0u8);
+		array.value(1i32);
+		array.value("2");
+		array.value("\"<script>1/2</script>\"");
+		let mut nested_arr = array.array();
+		nested_arr.value("nested");
+		nested_arr.end();
+		let mut nested_obj = array.object();
+		nested_obj.value("ä\töü", "ä\töü");
+		nested_obj.end();
+		let nested_obj2 = array.object();
+		nested_obj2.end();
+		drop(array);
+
+		assert_eq!(buffer, "[0,1,\"2\",\"\\\"<script>1\\/2<\\/script>\\\"\",[\"nested\"],{\"ä\\töü\":\"ä\\töü\"},{}]");
+	}
+
+	#[test]
+	fn test_array_range() {
+		let bytes = b"ABC";
+		assert_eq!(to_json_string(&bytes[..]), "[65,66,67]");
+
+		let mut v = Vec::<u8>::new();
+		v.extend_from_slice(bytes);
+		assert_eq!(to_json_string(&v), "[65,66,67]");
+	}
+
+	#[test]
+	fn test_cow_slice() {
+		use std::borrow::Cow;
+		let names = ["a", "b\"c"];
+		let borrowed: Cow<'_, [&str]> = Cow::Borrowed(&names[..]);
+		let owned: Cow<'_, [&str]> = Cow::Owned(names.to_vec());
+		assert_eq!(to_json_string(&borrowed), "[\"a\",\"b\\\"c\"]");
+		assert_eq!(to_json_string(&owned), to_json_string(&borrowed));
+
+		let empty: Cow<'_, [u8]> = Cow::Owned(Vec::new());
+		assert_eq!(to_json_string(&empty), "[]");
+	}
+
+	#[test]
+	fn test_elapsed() {
+		use std::time::Duration;
+		assert_eq!(
+			to_json_string(ElapsedMillis(Duration::from_millis(1500))),
+			"1500"
+		);
+		assert_eq!(
+			to_json_string(ElapsedMillis(Duration::from_micros(2999))),
+			"2"
+		);
+		assert_eq!(
+			to_json_string(ElapsedMicros(Duration::from_millis(1500))),
+			"1500000"
+		);
+		assert_eq!(
+			to_json_string(ElapsedMicros(Duration::from_nanos(999))),
+			"0"
+		);
+		assert_eq!(
+			to_json_string(ElapsedMillis(Duration::MAX)),
+			Duration::MAX.as_millis().to_string()
+		);
+	}
+
+	#[test]
+	fn test_nan_as_sentinel() {
+		let sentinel = |value| {
+			to_json_string(NanAsSentinel {
+				value,
+				sentinel: -999.0,
+			})
+		};
+		assert_eq!(sentinel(f64::NAN), "-999");
+		assert_eq!(sentinel(1.5), "1.5");
+		assert_eq!(sentinel(-0.25), "-0.25");
+		assert_eq!(sentinel(f64::INFINITY), "null");
+
+		let mut buffer = String::new();
+		let mut writer = NonFiniteJSONWriter::new(
+			&mut buffer,
+			NonFinite {
+				nan: NonFiniteValue::String,
+				infinity: NonFiniteValue::String,
+			},
+		);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		array.value(NanAsSentinel {
+			value: f64::NAN,
+			sentinel: -1.0,
+		});
+		array.value(NanAsSentinel {
+			value: f64::NEG_INFINITY,
+			sentinel: -1.0,
+		});
+		array.end();
+		assert_eq!(buffer, "[-1,\"-Infinity\"]");
+	}
+
+	struct Metadata {
+		version: u32,
+		tags: &'static [&'static str],
+	}
+
+	impl JSONObjectFields for Metadata {
+		fn write_fields<W: JSONWriter>(&self, object: &mut JSONObjectWriter<'_, W>) {
+			object.value("version", self.version);
+			object.value("tags", self.tags);
+		}
+	}
+
+	struct NoFields;
+
+	impl JSONObjectFields for NoFields {
+		fn write_fields<W: JSONWriter>(&self, _object: &mut JSONObjectWriter<'_, W>) {}
+	}
+
+	#[test]
+	fn test_flatten() {
+		let metadata = Metadata {
+			version: 3,
+			tags: &["a"],
+		};
+		let fields = r#""version":3,"tags":["a"]"#;
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.flatten(&metadata);
+		object.value("id", 1u8);
+		object.end();
+		assert_eq!(buffer, format!("{{{fields},\"id\":1}}"));
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("id", 1u8);
+		object.flatten(&metadata);
+		object.value("name", "x");
+		object.end();
+		assert_eq!(buffer, format!("{{\"id\":1,{fields},\"name\":\"x\"}}"));
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("id", 1u8);
+		object.flatten(&metadata);
+		object.end();
+		assert_eq!(buffer, format!("{{\"id\":1,{fields}}}"));
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.flatten(&NoFields);
+		object.flatten(&metadata);
+		object.flatten(&NoFields);
+		object.flatten(&Metadata {
+			version: 4,
+			tags: &[],
+		});
+		object.end();
+		assert_eq!(buffer, format!("{{{fields},\"version\":4,\"tags\":[]}}"));
+
+		assert_eq!(to_json_string(Flatten(&metadata)), format!("{{{fields}}}"));
+		assert_eq!(to_json_string(Flatten(NoFields)), "{}");
+	}
+
+	#[derive(Copy, Clone)]
+	enum Status {
+		Active,
+		OnHold,
+	}
+
+	json_enum_string!(Status {
+		Active => "ACTIVE",
+		OnHold => "ON_HOLD",
+	});
+
+	enum Manual {
+		Value,
+	}
+
+	impl JSONEnumString for Manual {
+		fn as_json_str(&self) -> &'static str {
+			"value"
+		}
+	}
+
+	#[test]
+	fn test_enum_string() {
+		assert_eq!(Status::OnHold.as_json_str(), "ON_HOLD");
+		assert_eq!(to_json_string(Status::OnHold), "\"ON_HOLD\"");
+		assert_eq!(to_json_string(Some(Status::Active)), "\"ACTIVE\"");
+		assert_eq!(to_json_string(None::<Status>), "null");
+		assert_eq!(
+			to_json_string(&[Status::Active, Status::OnHold][..]),
+			"[\"ACTIVE\",\"ON_HOLD\"]"
+		);
+		assert_eq!(
+			to_json_string(&std::collections::BTreeMap::from([
+				("a", Status::Active),
+				("b", Status::OnHold),
+			])),
+			"{\"a\":\"ACTIVE\",\"b\":\"ON_HOLD\"}"
+		);
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("status", Status::Active);
+		object.value("manual", EnumAsString(Manual::Value));
+		object.end();
+		assert_eq!(buffer, "{\"status\":\"ACTIVE\",\"manual\":\"value\"}");
+	}
+
+	enum Shape {
+		Point,
+		Rect { x: u32, y: u32 },
+	}
+
+	impl JSONObjectFields for Shape {
+		fn write_fields<W: JSONWriter>(&self, object: &mut JSONObjectWriter<'_, W>) {
+			match self {
+				Shape::Point => {}
+				Shape::Rect { x, y } => {
+					object.value("x", *x);
+					object.value("y", *y);
+				}
+			}
+		}
+	}
+
+	impl Shape {
+		fn name(&self) -> &'static str {
+			match self {
+				Shape::Point => "Point",
+				Shape::Rect { .. } => "Rect",
+			}
+		}
+	}
+
+	#[test]
+	fn test_tagged() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.tagged("type", "A");
+		object.value("x", 1);
+		object.end();
+		assert_eq!(buffer, "{\"type\":\"A\",\"x\":1}");
+
+		let rect = Shape::Rect { x: 1, y: 2 };
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.tagged("type", rect.name());
+		object.flatten(&rect);
+		object.end();
+		assert_eq!(buffer, "{\"type\":\"Rect\",\"x\":1,\"y\":2}");
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.tagged("t", Shape::Point.name());
+		object.value("c", Flatten(&Shape::Point));
+		object.end();
+		assert_eq!(buffer, "{\"t\":\"Point\",\"c\":{}}");
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.object("A").value("x", 1);
+		object.end();
+		assert_eq!(buffer, "{\"A\":{\"x\":1}}");
+
+		assert_eq!(
+			to_json_string(ExternallyTagged(rect.name(), Flatten(&rect))),
+			"{\"Rect\":{\"x\":1,\"y\":2}}"
+		);
+		assert_eq!(
+			to_json_string(&[ExternallyTagged("A", 1), ExternallyTagged("B", 2)][..]),
+			"[{\"A\":1},{\"B\":2}]"
+		);
+	}
+
+	#[test]
+	fn test_enum_tagging_matches_serde() {
+		#[derive(serde::Serialize)]
+		enum External {
+			Point,
+			Rect { x: u32, y: u32 },
+		}
+
+		#[derive(serde::Serialize)]
+		#[serde(tag = "type")]
+		enum Internal {
+			Point,
+			Rect { x: u32, y: u32 },
+		}
+
+		#[derive(serde::Serialize)]
+		#[serde(tag = "t", content = "c")]
+		enum Adjacent {
+			Point,
+			Rect { x: u32, y: u32 },
+		}
+
+		let rect = Shape::Rect { x: 1, y: 2 };
+		assert_eq!(
+			to_json_string(ExternallyTagged(rect.name(), Flatten(&rect))),
+			serde_json::to_string(&External::Rect { x: 1, y: 2 }).unwrap()
+		);
+		assert_eq!(
+			to_json_string(Shape::Point.name()),
+			serde_json::to_string(&External::Point).unwrap()
+		);
+
+		assert_eq!(
+			to_json_string(InternallyTagged {
+				tag_key: "type",
+				tag: rect.name(),
+				fields: &rect,
+			}),
+			serde_json::to_string(&Internal::Rect { x: 1, y: 2 }).unwrap()
+		);
+		assert_eq!(
+			to_json_string(InternallyTagged {
+				tag_key: "type",
+				tag: Shape::Point.name(),
+				fields: (),
+			}),
+			serde_json::to_string(&Internal::Point).unwrap()
+		);
+
+		assert_eq!(
+			to_json_string(AdjacentlyTagged {
+				tag_key: "t",
+				content_key: "c",
+				tag: rect.name(),
+				value: Flatten(&rect),
+			}),
+			serde_json::to_string(&Adjacent::Rect { x: 1, y: 2 }).unwrap()
+		);
+		assert_eq!(
+			to_json_string(InternallyTagged {
+				tag_key: "t",
+				tag: Shape::Point.name(),
+				fields: (),
+			}),
+			serde_json::to_string(&Adjacent::Point).unwrap()
+		);
+		assert_eq!(
+			to_json_string(AdjacentlyTagged {
+				tag_key: "t",
+				content_key: "c",
+				tag: "Id",
+				value: 7,
+			}),
+			r#"{"t":"Id","c":7}"#
+		);
+	}
+
+	#[test]
+	fn test_skip_none() {
+		let inputs: [&[Option<u32>]; 7] = [
+			&[],
+			&[None],
+			&[None, None, None],
+			&[None, Some(1), Some(2)],
+			&[Some(1), Some(2), None],
+			&[Some(1), None, None, Some(2)],
+			&[None, Some(1), None, None, Some(2), None],
+		];
+		for input in inputs {
+			let expected = to_json_string(&input.iter().flatten().collect::<Vec<_>>());
+			assert_eq!(to_json_string(SkipNone(input)), expected);
+			assert_eq!(to_json_string(SkipNone(input.to_vec())), expected);
+			assert_eq!(to_json_string(SkipNone(input.iter().copied())), expected);
+		}
+		assert_eq!(to_json_string(SkipNone(&[None::<u32>, None])), "[]");
+		assert_eq!(
+			to_json_string(SkipNone(&vec![None, Some("a"), None, Some("b")])),
+			"[\"a\",\"b\"]"
+		);
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.values_flatten([None::<u8>, None]);
+		array.value(1);
+		array.values_flatten([None, Some(2), None]);
+		array.values_flatten([Some(3), None].iter());
+		array.values_flatten(Vec::<Option<u8>>::new());
+		array.end();
+		assert_eq!(buffer, "[1,2,3]");
+	}
+
+	#[test]
+	fn test_map_as_pairs() {
+		let map = std::collections::BTreeMap::from([
+			((2u32, 0u32), "c".to_string()),
+			((1, 5), "b".to_string()),
+			((1, 2), "a\"".to_string()),
+		]);
+		assert_eq!(
+			to_json_string(MapAsPairs(&map)),
+			r#"[[[1,2],"a\""],[[1,5],"b"],[[2,0],"c"]]"#
+		);
+
+		let empty = std::collections::BTreeMap::<(u32, u32), String>::new();
+		assert_eq!(to_json_string(MapAsPairs(&empty)), "[]");
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("pairs", MapAsPairs(&map));
+		object.value("empty", MapAsPairs(&empty));
+		object.value(
+			"iter",
+			MapAsPairs([1u8, 2].iter().map(|&x| ((x, x * 2, true), None::<u8>))),
+		);
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"pairs":[[[1,2],"a\""],[[1,5],"b"],[[2,0],"c"]],"empty":[],"iter":[[[1,2,true],null],[[2,4,true],null]]}"#
+		);
+
+		let map = std::collections::HashMap::from([(1u32, 2u32)]);
+		assert_eq!(to_json_string(MapAsPairs(&map)), "[[1,2]]");
+	}
+
+	#[test]
+	fn test_signed_number() {
+		assert_eq!(to_json_string(SignedNumber(42)), "+42");
+		assert_eq!(to_json_string(SignedNumber(-42)), "-42");
+		assert_eq!(to_json_string(SignedNumber(0)), "+0");
+		assert_eq!(
+			to_json_string(SignedNumber(i64::MAX)),
+			"+9223372036854775807"
+		);
+		assert_eq!(
+			to_json_string(SignedNumber(i64::MIN)),
+			"-9223372036854775808"
+		);
+		assert_eq!(
+			to_json_string(&[SignedNumber(1), SignedNumber(-1)][..]),
+			"[+1,-1]"
+		);
+	}
+
+	#[test]
+	fn test_checked_raw_value() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.checked_raw_value("a", "{\"a\":1}").unwrap();
+		object.checked_raw_value("b", "[]").unwrap();
+		object
+			.checked_raw_value("c", "\t[{\"x\":\"]}\\\"[\"}, [ ]]\r\n")
+			.unwrap();
+		for (raw, error) in [
+			("", RawError::Empty),
+			(" \n", RawError::Empty),
+			("[}", RawError::NotContainer),
+			("\"{}\"", RawError::NotContainer),
+			("1", RawError::NotContainer),
+			("{\"a\":[1}", RawError::Unbalanced),
+			("{}{}", RawError::Unbalanced),
+			("[1]]", RawError::Unbalanced),
+			("[\"]", RawError::Unbalanced),
+			("{\"a\":\"}\\\"}", RawError::Unbalanced),
+			("{\"a\":1", RawError::Unbalanced),
+			("{", RawError::Unbalanced),
+			("[", RawError::Unbalanced),
+			("{\"a\":\"}", RawError::Unbalanced),
+			("[1,2", RawError::Unbalanced),
+		] {
+			assert_eq!(object.checked_raw_value("x", raw), Err(error), "{raw}");
+		}
+		object.end();
+		assert_eq!(buffer, r#"{"a":{"a":1},"b":[],"c":[{"x":"]}\"["}, [ ]]}"#);
+	}
+
+	#[test]
+	fn test_redacted() {
+		struct Card;
+		assert_eq!(to_json_string(Redacted(&Card)), "\"[REDACTED]\"");
+		assert_eq!(
+			to_json_string(Redacted(&Card).with_placeholder("<hidden \"card\">")),
+			r#""<hidden \"card\">""#
+		);
+
+		let partial = |value, keep_last| to_json_string(RedactedPartial { value, keep_last });
+		assert_eq!(partial("4111111111111234", 4), "\"****1234\"");
+		assert_eq!(partial("4111111111111234", 0), "\"****\"");
+		assert_eq!(partial("1234", 4), "\"****\"");
+		assert_eq!(partial("", 4), "\"****\"");
+		assert_eq!(partial("x1234", 4), "\"****1234\"");
+		assert_eq!(partial("äöü€😀", 2), "\"****€😀\"");
+		assert_eq!(partial("äöü€😀", 4), "\"****öü€😀\"");
+		assert_eq!(partial("äöü€😀", 5), "\"****\"");
+		assert_eq!(partial("ab\"c", 2), r#""****\"c""#);
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(Redacted(1u8));
+		array.value(Redacted(()).with_placeholder("-"));
+		array.value(RedactedPartial {
+			value: "secret",
+			keep_last: 1,
+		});
+		array.end();
+		assert_eq!(buffer, r#"["[REDACTED]","-","****t"]"#);
+
+		// Debug output doesn't leak the value either
+		assert_eq!(format!("{:?}", Redacted("hunter2")), "Redacted([REDACTED])");
+		assert_eq!(
+			format!("{:?}", Redacted("hunter2").with_placeholder("***")),
+			r#"RedactedWith { placeholder: "***", .. }"#
+		);
+		assert_eq!(
+			format!(
+				"{:?}",
+				RedactedPartial {
+					value: "4111111111111234",
+					keep_last: 4
+				}
+			),
+			r#"RedactedPartial { value: "****1234", keep_last: 4 }"#
+		);
+	}
+
+	#[test]
+	fn test_truncated() {
+		assert_eq!(to_json_string(Truncated("äöü", 2)), "\"äö…\"");
+		assert_eq!(to_json_string(Truncated("a中b", 2)), "\"a中…\"");
+		assert_eq!(to_json_string(Truncated("a中b", 1)), "\"a…\"");
+		assert_eq!(to_json_string(Truncated("exact", 5)), "\"exact\"");
+		assert_eq!(to_json_string(Truncated("exact!", 5)), "\"exact…\"");
+		assert_eq!(to_json_string(Truncated("", 0)), "\"\"");
+		assert_eq!(to_json_string(Truncated("x", 0)), "\"…\"");
+		assert_eq!(to_json_string(Truncated("😀😃😄😁", 4)), "\"😀😃😄😁\"");
+		assert_eq!(to_json_string(Truncated("😀😃😄😁", 3)), "\"😀😃😄…\"");
+		assert_eq!(
+			to_json_string(Truncated("👍🏽👍🏽", 1).with_suffix(" [more]")),
+			"\"👍 [more]\""
+		);
+		assert_eq!(
+			to_json_string(Truncated("a\"b\\c/d", 4).with_suffix("\"")),
+			r#""a\"b\\\"""#
+		);
+	}
+
+	#[test]
+	fn test_format_args() {
+		let (host, port) = ("a\"b/c", 8080u16);
+		assert_eq!(
+			to_json_string(format_args!("{host}:{port}")),
+			to_json_string(format!("{host}:{port}").as_str())
+		);
+		assert_eq!(
+			to_json_string(format_args!("{host}:{port}")),
+			r#""a\"b\/c:8080""#
+		);
+		assert_eq!(to_json_string(format_args!("plain \n")), "\"plain \\n\"");
+		assert_eq!(
+			to_json_string(format_args!(
+				"[{:>6.2}|{:<4}|{:^5}|{:08.3}]",
+				2.34567f32, 'x', "\t", -1.5
+			)),
+			to_json_string(
+				format!("[{:>6.2}|{:<4}|{:^5}|{:08.3}]", 2.34567f32, 'x', "\t", -1.5).as_str()
+			)
+		);
+		assert_eq!(
+			to_json_string(format_args!("{:?}", ("q\"", 1))),
+			r#""(\"q\\\"\", 1)""#
+		);
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value_fmt("addr", format_args!("{host}:{port}"));
+		let mut array = object.array("items");
+		array.push_fmt(format_args!("{:03}", 7));
+		array.push_fmt(format_args!(""));
+		array.end();
+		object.end();
+		assert_eq!(buffer, r#"{"addr":"a\"b\/c:8080","items":["007",""]}"#);
+	}
+
+	#[test]
+	fn test_utf16() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("a", "é中😀");
+		object.end();
+		let decode = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+			let units: Vec<u16> = bytes
+				.chunks_exact(2)
+				.map(|pair| from_bytes([pair[0], pair[1]]))
+				.collect();
+			String::from_utf16(&units).unwrap()
+		};
+
+		let le = to_json_utf16le(&buffer[..]);
+		assert_eq!(&le[..6], b"\xFF\xFE\"\0{\0");
+		assert_eq!(
+			decode(&le[2..], u16::from_le_bytes),
+			to_json_string(&buffer[..])
+		);
+
+		let be = to_json_utf16be(&buffer[..]);
+		assert_eq!(&be[..6], b"\xFE\xFF\0\"\0{");
+		assert_eq!(
+			decode(&be[2..], u16::from_be_bytes),
+			to_json_string(&buffer[..])
+		);
+
+		let le = to_json_utf16le(&["é中😀"][..]);
+		assert_eq!(le.len(), 2 + 2 * "[\"é中\"]".chars().count() + 4);
+		assert_eq!(decode(&le[2..], u16::from_le_bytes), "[\"é中😀\"]");
+		assert_eq!(to_json_utf16be(()), b"\xFE\xFF\0n\0u\0l\0l");
+	}
+
+	#[test]
+	fn test_big_uint_bytes() {
+		let decimal = |bytes: &[u8]| to_json_string(BigUintBytes(bytes).as_number());
+		assert_eq!(to_json_string(BigUintBytes(&[])), "\"0\"");
+		assert_eq!(to_json_string(BigUintBytes(&[0; 32])), "\"0\"");
+		assert_eq!(to_json_string(BigUintBytes(&[255])), "\"255\"");
+		assert_eq!(decimal(&[0, 0, 255]), "255");
+		assert_eq!(decimal(&[0xff; 16]), u128::MAX.to_string());
+		assert_eq!(
+			decimal(&[0xff; 32]),
+			"115792089237316195423570985008687907853269984665640564039457584007913129639935"
+		);
+		let mut bytes = [0u8; 32];
+		bytes[0] = 1;
+		assert_eq!(
+			decimal(&bytes),
+			"452312848583266388373324160190187140051835877600158453279131187530910662656"
+		);
+		// 10^27 has chunks that are all zeros
+		for value in [
+			10u128.pow(27),
+			u128::MAX / 3,
+			1 << 100,
+			12345678901234567890123456789,
+		] {
+			let mut bytes = [0u8; 20];
+			bytes[4..].copy_from_slice(&value.to_be_bytes());
+			assert_eq!(decimal(&bytes), value.to_string());
+		}
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_lines_to_json_array() {
+		let mut text = String::from("plain\n\n  \"quoted\"\t\r\nünïcödé\\\r\nno newline");
+		let mut output = Vec::new();
+		lines_to_json_array(text.as_bytes(), &mut output).unwrap();
+		let parsed: Vec<String> = serde_json::from_slice(&output).unwrap();
+		assert_eq!(
+			parsed,
+			["plain", "", "\"quoted\"", "ünïcödé\\", "no newline"]
+		);
+
+		let mut output = Vec::new();
+		lines_to_json_array("".as_bytes(), &mut output).unwrap();
+		assert_eq!(output, b"[]");
+
+		// Larger than the flush threshold
+		text.clear();
+		for i in 0..20_000 {
+			text.push_str(&format!("line {i}\n"));
+		}
+		let mut output = Vec::new();
+		lines_to_json_array(
+			std::io::BufReader::with_capacity(100, text.as_bytes()),
+			&mut output,
+		)
+		.unwrap();
+		let parsed: Vec<String> = serde_json::from_slice(&output).unwrap();
+		assert_eq!(parsed.len(), 20_000);
+		assert_eq!(parsed[12_345], "line 12345");
+
+		let error = lines_to_json_array(&b"a\n\xff\n"[..], &mut Vec::new()).unwrap_err();
+		assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+	}
+
+	/// Writes all integers up to 64 bits as strings, like JavaScript consumers need for ids
+	struct QuotedIntegers<'a>(&'a mut String);
+
+	impl JSONWriter for QuotedIntegers<'_> {
+		fn json_string(&mut self, value: &str) {
+			self.0.json_string(value);
+		}
+
+		fn json_string_part(&mut self, value: &str) {
+			self.0.json_string_part(value);
+		}
+
+		fn json_fragment(&mut self, value: &str) {
+			self.0.json_fragment(value);
+		}
+
+		fn json_number_i64(&mut self, value: i64) {
+			self.json_string(itoa::Buffer::new().format(value));
+		}
+
+		fn json_number_u64(&mut self, value: u64) {
+			self.json_string(itoa::Buffer::new().format(value));
+		}
+	}
+
+	#[test]
+	fn test_integer_hooks() {
+		let mut buffer = String::new();
+		let mut writer = QuotedIntegers(&mut buffer);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("i8", -8i8);
+		object.value("u16", 16u16);
+		object.value("i32", -32i32);
+		object.value("u64", u64::MAX);
+		object.value("atomic", &std::sync::atomic::AtomicUsize::new(7));
+		object.value("list", [1u8, 2].as_slice());
+		object.value("float", 1.5);
+		object.value("u128", 128u128);
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"i8":"-8","u16":"16","i32":"-32","u64":"18446744073709551615","atomic":"7","list":["1","2"],"float":1.5,"u128":128}"#
+		);
+
+		// Wrappers forward the hooks to the writer they wrap
+		let mut buffer = String::new();
+		let mut writer = QuotedIntegers(&mut buffer);
+		let mut document = JsonDocument::new(&mut writer);
+		JSONArrayWriter::new(&mut document).value(-1i64);
+		assert_eq!(buffer, r#"["-1"]"#);
+
+		let mut buffer = String::new();
+		let mut writer = QuotedIntegers(&mut buffer);
+		let config = WriterConfig::JS_SAFE;
+		let mut configured = ConfiguredWriter::new(&mut writer, &config);
+		JSONArrayWriter::new(&mut configured).value(1u32);
+		assert_eq!(buffer, r#"["1"]"#);
+	}
+
+	/// Records which integer hook wrote each number
+	#[derive(Default)]
+	struct IntegerHooks {
+		output: String,
+		calls: Vec<&'static str>,
+	}
+
+	impl JSONWriter for IntegerHooks {
+		fn json_string(&mut self, value: &str) {
+			self.output.json_string(value);
+		}
+
+		fn json_string_part(&mut self, value: &str) {
+			self.output.json_string_part(value);
+		}
+
+		fn json_fragment(&mut self, value: &str) {
+			self.output.json_fragment(value);
+		}
+
+		fn json_number_i64(&mut self, value: i64) {
+			self.calls.push("i64");
+			self.output.json_number_i64(value);
+		}
+
+		fn json_number_u64(&mut self, value: u64) {
+			self.calls.push("u64");
+			self.output.json_number_u64(value);
+		}
+
+		fn json_number_i128(&mut self, value: i128) {
+			self.calls.push("i128");
+			self.output.json_number_i128(value);
+		}
+
+		fn json_number_u128(&mut self, value: u128) {
+			self.calls.push("u128");
+			self.output.json_number_u128(value);
+		}
+	}
+
+	#[test]
+	fn test_integer_hook_calls() {
+		let mut writer = IntegerHooks::default();
+		let mut array = JSONArrayWriter::new(&mut writer);
+		array.value(-1i8);
+		array.value(2u32);
+		array.value(i128::MIN);
+		array.value(u128::MAX);
+		array.value(&std::sync::atomic::AtomicI64::new(-5));
+		array.value(SignedNumber(6));
+		array.value(7.0);
+		array.end();
+		assert_eq!(writer.calls, ["i64", "u64", "i128", "u128", "i64"]);
+		assert_eq!(
+			writer.output,
+			format!("[-1,2,{},{},-5,+6,7]", i128::MIN, u128::MAX)
+		);
+
+		let mut writer = IntegerHooks::default();
+		Serde(&(1u16, -2i64, 3u128)).write_json(&mut writer);
+		assert_eq!(writer.calls, ["u64", "i64", "u128"]);
+		assert_eq!(writer.output, "[1,-2,3]");
+	}
+
+	#[test]
+	fn test_object_sorted_by_value() {
+		let mut scores = std::collections::HashMap::new();
+		scores.insert("ann".to_owned(), 7u32);
+		scores.insert("bob".to_owned(), 12);
+		scores.insert("cy".to_owned(), 3);
+		scores.insert("dee".to_owned(), 9);
+		assert_eq!(
+			to_json_string(object_sorted_by_value(&scores, |a, b| b.cmp(a))),
+			r#"{"bob":12,"dee":9,"ann":7,"cy":3}"#
+		);
+
+		// Ties keep the order of the map
+		let ranks: std::collections::BTreeMap<&str, (u8, &str)> =
+			[("a", (1, "x")), ("b", (2, "y")), ("c", (1, "z"))].into();
+		assert_eq!(
+			to_json_string(object_sorted_by_value(&ranks, |a, b| a.0.cmp(&b.0))),
+			r#"{"a":[1,"x"],"c":[1,"z"],"b":[2,"y"]}"#
+		);
+		assert_eq!(
+			to_json_string(object_sorted_by_value(
+				&std::collections::BTreeMap::<String, u8>::new(),
+				u8::cmp
+			)),
+			"{}"
+		);
+	}
+
+	#[test]
+	fn test_matrix() {
+		let special = [
+			0.0,
+			-0.0,
+			0.1,
+			-1.5,
+			3.0,
+			1e20,
+			1e-300,
+			f64::MAX,
+			f64::MIN_POSITIVE,
+			f64::NAN,
+			f64::INFINITY,
+		];
+		let rows: Vec<Vec<f64>> = vec![
+			special.to_vec(),
+			Vec::new(),
+			(0..10_000).map(|i| i as f64 / 7.0 - 300.0).collect(),
+			vec![f64::NEG_INFINITY],
+		];
+		assert_eq!(to_json_string(Matrix(&rows)), to_json_string(&rows));
+		let slices: Vec<&[f64]> = rows.iter().map(Vec::as_slice).collect();
+		assert_eq!(to_json_string(Matrix(&slices)), to_json_string(&rows));
+		assert_eq!(to_json_string(Matrix::<Vec<f64>>(&[])), "[]");
+		let mut bytes = Vec::new();
+		Matrix(&rows).write_json(&mut bytes);
+		assert_eq!(bytes, to_json_string(&rows).into_bytes());
+
+		let mut pretty = String::new();
+		Matrix(&rows[..2]).write_json(&mut PrettyJSONWriter::new(&mut pretty));
+		let mut expected = String::new();
+		(&rows[..2]).write_json(&mut PrettyJSONWriter::new(&mut expected));
+		assert_eq!(pretty, expected);
+
+		let config = WriterConfig::COMPACT
+			.fraction_digits(2)
+			.non_finite(NonFinite {
+				nan: NonFiniteValue::String,
+				infinity: NonFiniteValue::String,
+			});
+		let mut configured = String::new();
+		Matrix(&rows).write_json(&mut ConfiguredWriter::new(&mut configured, &config));
+		let mut expected = String::new();
+		(&rows).write_json(&mut ConfiguredWriter::new(&mut expected, &config));
+		assert_eq!(configured, expected);
+		assert!(configured.starts_with(r#"[[0,0,0.1,-1.5,3,1e20,0,"#));
+
+		// Floats beyond 2^53 are written like single floats, not quoted like large integers
+		let large = vec![vec![9_007_199_254_740_992.0, 9_007_199_254_740_994.0, 0.5]];
+		let mut js_safe = String::new();
+		Matrix(&large).write_json(&mut ConfiguredWriter::new(
+			&mut js_safe,
+			&WriterConfig::JS_SAFE,
+		));
+		let mut expected = String::new();
+		(&large).write_json(&mut ConfiguredWriter::new(
+			&mut expected,
+			&WriterConfig::JS_SAFE,
+		));
+		assert_eq!(js_safe, expected);
+		assert_eq!(js_safe, "[[9007199254740992,9007199254740994,0.5]]");
+
+		let mut array_values = String::new();
+		let mut array = JSONArrayWriter::new(&mut array_values);
+		array.value(1);
+		array.values_f64(&special);
+		array.values_f64(&[]);
+		array.value(2);
+		array.end();
+		let mut expected = String::new();
+		let mut array = JSONArrayWriter::new(&mut expected);
+		array.value(1);
+		for value in special {
+			array.value(value);
+		}
+		array.value(2);
+		array.end();
+		assert_eq!(array_values, expected);
+	}
+
+	#[test]
+	fn test_object_members() {
+		let rows = [
+			Metadata {
+				version: 1,
+				tags: &["a", "b"],
+			},
+			Metadata {
+				version: 2,
+				tags: &[],
+			},
+		];
+
+		let mut expected = String::new();
+		let mut array = JSONArrayWriter::new(&mut expected);
+		for row in &rows {
+			let mut object = array.object();
+			object.value("version", row.version);
+			object.value("tags", row.tags);
+		}
+		array.end();
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.objects(&rows);
+		array.end();
+		assert_eq!(buffer, expected);
+		assert_eq!(
+			buffer,
+			r#"[{"version":1,"tags":["a","b"]},{"version":2,"tags":[]}]"#
+		);
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(0);
+		array.objects(core::iter::empty::<Metadata>());
+		array.objects(rows.iter().skip(1));
+		array.end();
+		assert_eq!(buffer, r#"[0,{"version":2,"tags":[]}]"#);
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.object_value("first", &rows[0]);
+		object.object_value("empty", &NoFields);
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"first":{"version":1,"tags":["a","b"]},"empty":{}}"#
+		);
+		assert_eq!(
+			to_json_string(Flatten(&rows[1])),
+			r#"{"version":2,"tags":[]}"#
+		);
 	}
-}
 
-impl JSONWriterValue for u16 {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
-	}
-}
+	#[test]
+	fn test_pre_escaped() {
+		let values = ["", "5f2b9c01", "ACTIVE", "ünïcödé 😀", "a b-c_d.e:f"];
+		for value in values {
+			assert_eq!(to_json_string(PreEscaped(value)), to_json_string(value));
+			let mut bytes = Vec::new();
+			PreEscaped(value).write_json(&mut bytes);
+			assert_eq!(bytes, to_json_string(value).as_bytes());
+		}
 
-impl JSONWriterValue for i16 {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+		let mut pre_escaped = String::new();
+		let mut writer = PrettyJSONWriter::new(&mut pre_escaped);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("id", PreEscaped("5f2b9c01"));
+		object.array("tags").value(PreEscaped("a"));
+		object.end();
+		let mut expected = String::new();
+		let mut writer = PrettyJSONWriter::new(&mut expected);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("id", "5f2b9c01");
+		object.array("tags").value("a");
+		object.end();
+		assert_eq!(pre_escaped, expected);
+
+		// Writers without a fast path escape the value as usual
+		let config = WriterConfig::COMPACT.ascii_only(true);
+		let mut buffer = String::new();
+		PreEscaped("é").write_json(&mut ConfiguredWriter::new(&mut buffer, &config));
+		assert_eq!(buffer, "\"\\u00E9\"");
 	}
-}
 
-impl JSONWriterValue for u8 {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "contains characters that need escaping")]
+	fn test_pre_escaped_debug_assertion() {
+		to_json_string(PreEscaped("say \"hi\""));
 	}
-}
 
-impl JSONWriterValue for i8 {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut buf = itoa::Buffer::new();
-		writer.json_number_str(buf.format(self));
-	}
-}
+	#[test]
+	fn test_object_field_hint() {
+		let write = |object: &mut JSONObjectWriter<'_, String>| {
+			object.value("id", 42);
+			object.value("name", "x");
+			object.value("active", true);
+		};
+		let mut expected = String::new();
+		write(&mut JSONObjectWriter::new(&mut expected));
 
-impl JSONWriterValue for bool {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		writer.json_bool(self);
-	}
-}
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::with_field_hint(&mut buffer, 3);
+		write(&mut object);
+		object.end();
+		assert_eq!(buffer, expected);
+		assert!(buffer.capacity() >= 3 * 16);
 
-impl JSONWriterValue for Null {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		writer.json_null();
+		let mut buffer = String::new();
+		JSONObjectWriter::with_field_hint(&mut buffer, 0).end();
+		assert_eq!(buffer, "{}");
+
+		let mut pretty = String::new();
+		let mut writer = PrettyJSONWriter::new(&mut pretty);
+		let mut object = JSONObjectWriter::with_field_hint(&mut writer, 2);
+		object.value("a", 1);
+		object.value("b", 2);
+		object.end();
+		assert_eq!(pretty, "{\n  \"a\": 1,\n  \"b\": 2\n}");
 	}
-}
 
-impl<T: JSONWriterValue + Copy> JSONWriterValue for &T {
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		(*self).write_json(writer);
-	}
-}
+	#[test]
+	fn test_write_with() {
+		let tags = vec!["a".to_owned(), "b".to_owned()];
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value(
+			"nested",
+			WriteWith(|mut writer| {
+				let mut nested = JSONObjectWriter::new(&mut writer);
+				nested.value("x", 1);
+				nested.array("y").value(2);
+			}),
+		);
+		object.value(
+			"tags",
+			WriteWith(move |mut writer| {
+				let mut array = JSONArrayWriter::new(&mut writer);
+				for tag in tags {
+					array.value(&tag);
+				}
+			}),
+		);
+		object.value("string", WriteWith(|writer| writer.json_string("s")));
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"nested":{"x":1,"y":[2]},"tags":["a","b"],"string":"s"}"#
+		);
 
-impl<T: JSONWriterValue> JSONWriterValue for Option<T> {
-	#[inline]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		match self {
-			None => {
-				writer.json_null();
-			}
-			Some(value) => {
-				value.write_json(writer);
-			}
-		}
+		let mut called = false;
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(None::<WriteWith<fn(&mut dyn JSONWriter)>>);
+		array.value(Some(WriteWith(|writer| {
+			called = true;
+			writer.json_bool(true);
+		})));
+		array.end();
+		assert_eq!(buffer, "[null,true]");
+		assert!(called);
 	}
-}
 
-impl<Item> JSONWriterValue for &Vec<Item>
-where
-	for<'b> &'b Item: JSONWriterValue,
-{
-	#[inline(always)]
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		self.as_slice().write_json(writer);
+	#[test]
+	fn test_reversed() {
+		assert_eq!(to_json_string(Reversed(&[1, 2, 3])), "[3,2,1]");
+		assert_eq!(to_json_string(Reversed::<u8>(&[])), "[]");
+		let names = vec!["a".to_owned(), "b".to_owned()];
+		assert_eq!(to_json_string(Reversed(&names)), r#"["b","a"]"#);
+		let nested = [vec![1, 2], vec![3]];
+		assert_eq!(to_json_string(Reversed(&nested)), "[[3],[1,2]]");
 	}
-}
 
-impl<Item> JSONWriterValue for &[Item]
-where
-	for<'b> &'b Item: JSONWriterValue,
-{
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut array = JSONArrayWriter::new(writer);
-		for item in self.iter() {
-			array.value(item);
+	#[test]
+	fn test_big_number() {
+		let digits = "12345678901234567890123456789012345678901234567890";
+		let number = BigNumber::new(digits).unwrap();
+		assert_eq!(number.as_str(), digits);
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("n", number);
+		object.value("m", BigNumber::new("-0.5E+10").unwrap());
+		object.end();
+		assert_eq!(buffer, format!("{{\"n\":{digits},\"m\":-0.5E+10}}"));
+		let parsed: serde_json::Value = serde_json::from_str(&buffer).unwrap();
+		assert!(parsed["n"].is_number());
+
+		for invalid in [
+			"", "-", "+1", "01", "1.", ".5", "1e", "1e+", "0x1A", "NaN", "1 ", "1,2",
+		] {
+			assert_eq!(
+				BigNumber::new(invalid).unwrap_err(),
+				InvalidNumber,
+				"{invalid}"
+			);
 		}
 	}
-}
 
-impl<Key: AsRef<str>, Item> JSONWriterValue for &std::collections::HashMap<Key, Item>
-where
-	for<'b> &'b Item: JSONWriterValue,
-{
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut obj = JSONObjectWriter::new(writer);
-		for (key, value) in self.iter() {
-			obj.value(key.as_ref(), value);
-		}
+	struct Account {
+		id: u32,
+		name: &'static str,
+		email: Option<&'static str>,
+		nickname: Option<&'static str>,
+		inner: AccountInner,
 	}
-}
 
-impl<Key: AsRef<str>, Item> JSONWriterValue for &std::collections::BTreeMap<Key, Item>
-where
-	for<'b> &'b Item: JSONWriterValue,
-{
-	fn write_json<W: JSONWriter>(self, writer: &mut W) {
-		let mut obj = JSONObjectWriter::new(writer);
-		for (key, value) in self.iter() {
-			obj.value(key.as_ref(), value);
-		}
+	struct AccountInner {
+		created_at: u64,
 	}
-}
-
-///
-/// Converts given value to a json string.
-///
-#[inline]
-pub fn to_json_string<T: JSONWriterValue>(v: T) -> String {
-	let mut result = String::new();
-	v.write_json(&mut result);
-	return result;
-}
 
-fn output_buffer_to<Writer: std::io::Write>(
-	buffer: &mut String,
-	writer: &mut Writer,
-) -> Result<usize, std::io::Error> {
-	match writer.write_all(buffer.as_bytes()) {
-		Ok(_) => {
-			let len = buffer.len();
-			buffer.clear();
-			Ok(len)
+	impl Account {
+		fn write(&self, object: &mut JSONObjectWriter<'_>) {
+			write_fields!(object, self.{ id, name as "displayName", email?, nickname as "nick"?, });
+			write_fields!(object, self.inner.{ created_at as "createdAt" });
 		}
-		Err(err) => Err(err),
 	}
-}
 
-///
-/// Quotes and escapes input and appends result to output buffer
-///
-#[inline(never)]
-pub fn write_string(output_buffer: &mut String, input: &str) {
-	output_buffer.push('"');
-	write_part_of_string_impl(output_buffer, input);
-	output_buffer.push('"');
-}
+	#[test]
+	fn test_write_fields() {
+		let mut account = Account {
+			id: 1,
+			name: "Ann",
+			email: None,
+			nickname: Some("a"),
+			inner: AccountInner { created_at: 42 },
+		};
+		let mut buffer = String::new();
+		account.write(&mut JSONObjectWriter::new(&mut buffer));
+		assert_eq!(
+			buffer,
+			r#"{"id":1,"displayName":"Ann","nick":"a","createdAt":42}"#
+		);
 
-///
-/// Escapes input and appends result to output buffer without adding quotes.
-///
-#[inline(never)]
-pub fn write_part_of_string(output_buffer: &mut String, input: &str) {
-	write_part_of_string_impl(output_buffer, input);
-}
+		account.email = Some("ann@example.com");
+		account.nickname = None;
+		let mut buffer = String::new();
+		account.write(&mut JSONObjectWriter::new(&mut buffer));
+		assert_eq!(
+			buffer,
+			r#"{"id":1,"displayName":"Ann","email":"ann@example.com","createdAt":42}"#
+		);
 
-const fn get_replacements() -> [u8; 256] {
-	// NOTE: Only characters smaller than 128 are allowed here.
-	// Trying to escape values above 128 would generate invalid utf-8 output
-	// -----
-	// see https://www.json.org/json-en.html
-	let mut result = [0u8; 256];
-	// Escape everything from 0 to 0x1F
-	let mut i = 0;
-	while i < 0x20 {
-		result[i] = b'u';
-		i += 1;
+		// Locals named like the variables of the macro
+		let value = 1;
+		let object = Some("shadowed");
+		let missing: Option<u8> = None;
+		let mut buffer = String::new();
+		let mut writer = JSONObjectWriter::new(&mut buffer);
+		write_fields!(writer, { value, object?, missing?, });
+		write_fields!(writer, { value as "again" });
+		write_fields!(writer, {});
+		writer.end();
+		assert_eq!(buffer, r#"{"value":1,"object":"shadowed","again":1}"#);
 	}
-	result[b'\"' as usize] = b'"';
-	result[b'\\' as usize] = b'\\';
-	result[b'/' as usize] = b'/';
-	result[8] = b'b';
-	result[0xc] = b'f';
-	result[b'\n' as usize] = b'n';
-	result[b'\r' as usize] = b'r';
-	result[b'\t' as usize] = b't';
-	result[0] = b'u';
-
-	return result;
-}
 
-static REPLACEMENTS: [u8; 256] = get_replacements();
-static HEX: [u8; 16] = *b"0123456789ABCDEF";
+	#[test]
+	fn test_by_ref_iter() {
+		let mapped = (0..5).map(|x| x * 2);
+		assert_eq!(to_json_string(ByRefIter(&mapped)), "[0,2,4,6,8]");
+		assert_eq!(to_json_string(ByRefIter(&mapped)), "[0,2,4,6,8]");
 
-///
-/// Escapes and append part of string
-///
-#[inline(always)]
-fn write_part_of_string_impl(output_buffer: &mut String, input: &str) {
-	// All of the relevant characters are in the ansi range (<128).
-	// This means we can safely ignore any utf-8 characters and iterate over the bytes directly
-	let mut num_bytes_written: usize = 0;
-	let mut index: usize = 0;
-	let bytes = input.as_bytes();
-	while index < bytes.len() {
-		let cur_byte = bytes[index];
-		let replacement = REPLACEMENTS[cur_byte as usize];
-		if replacement != 0 {
-			if num_bytes_written < index {
-				// Checks can be omitted here:
-				// We know that index is smaller than the output_buffer length.
-				// We also know that num_bytes_written is smaller than index
-				// We also know that the boundaries are not in the middle of an utf-8 multi byte sequence, because those characters are not escaped
-				output_buffer.push_str(unsafe { input.get_unchecked(num_bytes_written..index) });
-			}
-			if replacement == b'u' {
-				let bytes: [u8; 6] = [
-					b'\\',
-					b'u',
-					b'0',
-					b'0',
-					HEX[((cur_byte / 16) & 0xF) as usize],
-					HEX[(cur_byte & 0xF) as usize],
-				];
-				// Checks can be omitted here: We know bytes is a valid utf-8 string (see above)
-				output_buffer.push_str(unsafe { std::str::from_utf8_unchecked(&bytes) });
-			} else {
-				let bytes: [u8; 2] = [b'\\', replacement];
-				// Checks can be omitted here: We know bytes is a valid utf-8 string, because the replacement table only contains characters smaller than 128
-				output_buffer.push_str(unsafe { std::str::from_utf8_unchecked(&bytes) });
-			}
-			num_bytes_written = index + 1;
-		}
-		index += 1;
-	}
-	if num_bytes_written < bytes.len() {
-		// Checks can be omitted here:
-		// We know that num_bytes_written is smaller than index
-		// We also know that num_bytes_written not in the middle of an utf-8 multi byte sequence, because those are not escaped
-		output_buffer.push_str(unsafe { input.get_unchecked(num_bytes_written..bytes.len()) });
+		let names = vec!["a", "bc"];
+		let lengths = names.iter().map(|name| name.len() as u32);
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("names", &names);
+		object.value("lengths", ByRefIter(&lengths));
+		object.value("empty", ByRefIter(&(0..0u8)));
+		object.end();
+		assert_eq!(
+			buffer,
+			"{\"names\":[\"a\",\"bc\"],\"lengths\":[1,2],\"empty\":[]}"
+		);
 	}
-}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+	#[test]
+	fn test_bool_as_int() {
+		assert_eq!(to_json_string(BoolAsInt(true)), "1");
+		assert_eq!(to_json_string(BoolAsInt(false)), "0");
+		assert_eq!(
+			to_json_string(&[BoolAsInt(true), BoolAsInt(false)][..]),
+			"[1,0]"
+		);
+		assert_eq!(to_json_string(true), "true");
+	}
 
 	#[test]
-	fn test_array() {
-		let mut buffer = String::new();
-		let mut array = JSONArrayWriter::new(&mut buffer);
-		array.value(0u8);
-		array.value(1i32);
-		array.value("2");
-		array.value("\"<script>1/2</script>\"");
-		let mut nested_arr = array.array();
-		nested_arr.value("nested");
-		nested_arr.end();
-		let mut nested_obj = array.object();
-		nested_obj.value("ä\töü", "ä\töü");
-		nested_obj.end();
-		let nested_obj2 = array.object();
-		nested_obj2.end();
-		drop(array);
+	fn test_padded_number() {
+		let padded = |value, width, fill| to_json_string(PaddedNumber { value, width, fill });
+		assert_eq!(padded(42, 6, ' '), "\"    42\"");
+		assert_eq!(padded(123456, 6, ' '), "\"123456\"");
+		assert_eq!(padded(1234567, 6, ' '), "\"1234567\"");
+		assert_eq!(padded(-42, 5, ' '), "\"  -42\"");
+		assert_eq!(padded(-42, 5, '0'), "\"-0042\"");
+		assert_eq!(padded(7, 3, '"'), "\"\\\"\\\"7\"");
+		assert_eq!(padded(7, 3, '·'), "\"··7\"");
+	}
 
-		assert_eq!(buffer, "[0,1,\"2\",\"\\\"<script>1\\/2<\\/script>\\\"\",[\"nested\"],{\"ä\\töü\":\"ä\\töü\"},{}]");
+	#[test]
+	fn test_assoc_object() {
+		assert_eq!(
+			to_json_string(AssocObject(&[("a", 1), ("b", 2)])),
+			"{\"a\":1,\"b\":2}"
+		);
+		assert_eq!(
+			to_json_string(AssocObject(&[("b", "x"), ("a", "y"), ("b", "z")])),
+			"{\"b\":\"x\",\"a\":\"y\",\"b\":\"z\"}"
+		);
+		assert_eq!(to_json_string(AssocObject::<u8>(&[])), "{}");
 	}
 
+	#[cfg(feature = "std")]
 	#[test]
-	fn test_array_range() {
-		let bytes = b"ABC";
-		assert_eq!(to_json_string(&bytes[..]), "[65,66,67]");
+	fn test_sorted_keys() {
+		for len in [0u32, 1, 4, 16, 17, 100] {
+			let map: std::collections::HashMap<String, u32> =
+				(0..len).map(|i| (format!("key{i:03}"), i)).collect();
+			let mut expected = String::new();
+			let mut object = JSONObjectWriter::new(&mut expected);
+			for i in 0..len {
+				object.value(&format!("key{i:03}"), i);
+			}
+			object.end();
+			assert_eq!(to_json_string(SortedKeys(&map)), expected, "len: {len}");
+		}
+	}
 
-		let mut v = Vec::<u8>::new();
-		v.extend_from_slice(bytes);
-		assert_eq!(to_json_string(&v), "[65,66,67]");
+	#[test]
+	fn test_value_int_key() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value_int_key(0, true);
+		object.value_int_key(42, false);
+		object.end();
+		assert_eq!(buffer, "{\"0\":true,\"42\":false}");
+
+		let mut bytes = Vec::new();
+		let mut object = JSONObjectWriter::new(&mut bytes);
+		object.value_int_key(u64::MAX, 1);
+		object.value("a", 2);
+		object.end();
+		assert_eq!(bytes, br#"{"18446744073709551615":1,"a":2}"#);
+
+		let mut pretty = String::new();
+		let mut writer = PrettyJSONWriter::new(&mut pretty);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("a", 1);
+		object.value_int_key(7, 2);
+		object.end();
+		assert_eq!(pretty, "{\n  \"a\": 1,\n  \"7\": 2\n}");
+
+		// Writers without the default escaping get the key through `json_object_key`
+		let mut configured = String::new();
+		let config = WriterConfig::COMPACT.indent("\t");
+		let mut writer = ConfiguredWriter::new(&mut configured, &config);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value_int_key(3, true);
+		object.end();
+		assert_eq!(configured, "{\n\t\"3\": true\n}");
 	}
 
+	#[cfg(feature = "std")]
 	#[test]
 	fn test_object() {
 		let mut map = std::collections::HashMap::<String, String>::new();
@@ -1018,7 +5404,20 @@ mod tests {
 		assert_eq!(&object_str, "{\"number\":42}");
 	}
 
+	#[test]
+	fn test_unit_value() {
+		assert_eq!(to_json_string(()), "null");
+		assert_eq!(to_json_string(Some(())), "null");
+
+		let mut object_str = String::new();
+		let mut object_writer = JSONObjectWriter::new(&mut object_str);
+		object_writer.value("unit", ());
+		object_writer.end();
+		assert_eq!(&object_str, "{\"unit\":null}");
+	}
+
 	#[allow(clippy::approx_constant)]
+	#[cfg(feature = "std")]
 	#[test]
 	fn test_misc_examples() {
 		// Values
@@ -1101,7 +5500,7 @@ mod tests {
 		assert_eq!(
 			&object_str,
 			r#"{"name":"zenora \"bariella\"","compound":"zenora \"bariella\" : yellow","number":42,"tools":["hammer","air-hammer","air-saw"]}"#
-		)
+		);
 	}
 
 	#[test]
@@ -1116,6 +5515,7 @@ mod tests {
 		assert_eq!(&object_str, "{\"number\":42,\"number\":43}");
 	}
 
+	#[cfg(feature = "std")]
 	#[test]
 	fn test_flush() {
 		// this could also be a file writer.
@@ -1190,4 +5590,848 @@ mod tests {
 }"#
 		);
 	}
+
+	#[test]
+	fn test_manual_key_and_comma() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.write_key("a");
+		object.writer.json_number_str("1");
+		object.write_key("b");
+		object.writer.json_null();
+		let mut array = object.array("c");
+		array.write_comma();
+		array.writer.json_bool(true);
+		array.value(2u8);
+		array.end();
+		object.end();
+		assert_eq!(buffer, "{\"a\":1,\"b\":null,\"c\":[true,2]}");
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "key written while the previous key is still awaiting its value")]
+	fn test_debug_double_key() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.write_key("a");
+		object.value("b", 1u8);
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(
+		expected = "value slot opened while the previous slot is still awaiting its value"
+	)]
+	fn test_debug_double_comma() {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(1u8);
+		array.write_comma();
+		array.value(2u8);
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "dropped with a key that is still awaiting its value")]
+	fn test_debug_dangling_key() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.write_key("a");
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_debug_flush_in_nested_container() {
+		let mut buffer = String::new();
+		let mut sink = Vec::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		let mut array = object.array("items");
+		array.output_buffered_data(&mut sink).unwrap();
+		for value in [10u8, 2, 3, 4] {
+			array.value(value);
+		}
+		array.end();
+		// The buffer now has the same length as right after the "items" key
+		assert_eq!(object.buffer_len(), "{\"items\":".len());
+		object.value("next", 1u8);
+		object.end();
+		sink.extend_from_slice(buffer.as_bytes());
+		assert_eq!(sink, b"{\"items\":[10,2,3,4],\"next\":1}");
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "dropped with a slot that is still awaiting its value")]
+	fn test_debug_dangling_comma() {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.write_comma();
+	}
+
+	#[cfg(not(debug_assertions))]
+	#[test]
+	fn test_release_layout() {
+		// The debug state tracking must not change the size of the writers in release builds
+		let expected = core::mem::size_of::<(&mut String, bool)>();
+		assert_eq!(core::mem::size_of::<JSONObjectWriter<'_>>(), expected);
+		assert_eq!(core::mem::size_of::<JSONArrayWriter<'_>>(), expected);
+	}
+
+	#[test]
+	fn test_weak() {
+		let live = std::rc::Rc::new(vec![1u8, 2u8]);
+		assert_eq!(to_json_string(&std::rc::Rc::downgrade(&live)), "[1,2]");
+		let dead = std::rc::Rc::downgrade(&std::rc::Rc::new(String::from("gone")));
+		assert_eq!(to_json_string(&dead), "null");
+
+		let live = std::sync::Arc::new(String::from("a"));
+		assert_eq!(to_json_string(&std::sync::Arc::downgrade(&live)), "\"a\"");
+		let dead = std::sync::Weak::<String>::new();
+		assert_eq!(to_json_string(&dead), "null");
+	}
+
+	#[test]
+	fn test_panic_poisons_output() {
+		let mut buffer = String::new();
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			let mut object = JSONObjectWriter::new(&mut buffer);
+			object.value("a", 1u8);
+			let mut array = object.array("b");
+			array.value(2u8);
+			panic!("serializer failed");
+		}));
+		assert!(result.is_err());
+		// No closing brackets have been written
+		assert_eq!(buffer, "{\"a\":1,\"b\":[2");
+
+		let mut document = JsonDocument::new(String::new());
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			let mut object = document.root().object();
+			object.write_key("a");
+			panic!("serializer failed");
+		}));
+		assert!(result.is_err());
+		assert!(document.is_poisoned());
+		assert_eq!(document.into_inner(), "{\"a\":");
+	}
+
+	#[test]
+	fn test_writer_created_during_unwinding() {
+		/// Writes a log record when dropped, closing it with `end` while unwinding
+		struct LogOnDrop(std::rc::Rc<std::cell::RefCell<String>>);
+
+		impl Drop for LogOnDrop {
+			fn drop(&mut self) {
+				let mut buffer = self.0.borrow_mut();
+				let mut object = JSONObjectWriter::new(&mut *buffer);
+				object.value("event", "dropped");
+				let mut tags = object.array("tags");
+				let mut tag = tags.string_writer();
+				tag.push_str("unwinding");
+				tag.end();
+				tags.end();
+				object.array("implicit").value(1);
+				object.end();
+			}
+		}
+
+		let buffer = std::rc::Rc::new(std::cell::RefCell::new(String::new()));
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			let _log = LogOnDrop(buffer.clone());
+			panic!("request failed");
+		}));
+		assert!(result.is_err());
+		assert_eq!(
+			*buffer.borrow(),
+			"{\"event\":\"dropped\",\"tags\":[\"unwinding\"],\"implicit\":[1}"
+		);
+	}
+
+	#[test]
+	fn test_try_item() {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		for i in 0..4u32 {
+			let result = array.try_item(|array| {
+				let mut object = array.object();
+				object.value("id", i);
+				if i % 2 == 0 {
+					return Err("even");
+				}
+				object.value("ok", true);
+				Ok(())
+			});
+			assert_eq!(result.is_err(), i % 2 == 0);
+		}
+		array.end();
+		assert_eq!(buffer, "[{\"id\":1,\"ok\":true},{\"id\":3,\"ok\":true}]");
+
+		let mut bytes = Vec::<u8>::new();
+		let mut array = JSONArrayWriter::new(&mut bytes);
+		array.value("a\"");
+		let result = array.try_item(|array| {
+			array.value(1u8);
+			Err(())
+		});
+		assert!(result.is_err());
+		array.value(2u8);
+		array.end();
+		assert_eq!(bytes, b"[\"a\\\"\",2]");
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	#[should_panic(expected = "rollback to a checkpoint that has already been flushed")]
+	fn test_rollback_after_flush() {
+		let mut sink = Vec::<u8>::new();
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(1u8);
+		let _ = array.try_item(|array| {
+			array.value(2u8);
+			array.output_buffered_data(&mut sink).unwrap();
+			Err(())
+		});
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	#[should_panic(expected = "rollback to a checkpoint that has already been flushed")]
+	fn test_rollback_after_flush_and_longer_output() {
+		let mut sink = Vec::<u8>::new();
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value("aaaaaaaaaa");
+		let _ = array.try_item(|array| {
+			array.value(2u8);
+			array.output_buffered_data(&mut sink).unwrap();
+			// The buffer is longer than at the checkpoint again
+			array.value("bbbbbbbbbbb");
+			array.value(3u8);
+			Err(())
+		});
+	}
+
+	#[test]
+	fn test_empty_object() {
+		assert_eq!(to_json_string(EmptyObject), "{}");
+		assert_eq!(to_json_string(Some(EmptyObject)), "{}");
+
+		let mut buffer = String::new();
+		let mut formatter = PrettyJSONWriter::new(&mut buffer);
+		JSONObjectWriter::new(&mut formatter).value("a", EmptyObject);
+		assert_eq!(buffer, "{\n  \"a\": {}\n}");
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_flush_pretty() {
+		fn write_document<W: BufferedJSONWriter>(writer: &mut W, sink: Option<&mut Vec<u8>>) {
+			let mut sink = sink;
+			let mut object = JSONObjectWriter::new(writer);
+			let mut array = object.array("numbers");
+			for i in 0..10000u32 {
+				let mut item = array.object();
+				item.value("i", i);
+				item.value("s", "x\ty");
+				item.end();
+				if let Some(sink) = sink.as_deref_mut() {
+					if array.buffer_len() > 1000 {
+						array.output_buffered_data(sink).unwrap();
+					}
+				}
+			}
+		}
+
+		let mut expected = String::new();
+		write_document(&mut PrettyJSONWriter::new(&mut expected), None);
+
+		let mut sink = Vec::<u8>::new();
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		write_document(&mut pretty, Some(&mut sink));
+		assert!(pretty.buffer_len() < 2000);
+		pretty.take_buffer(&mut sink).unwrap();
+		assert_eq!(sink, expected.as_bytes());
+
+		let mut expected = String::new();
+		write_document(&mut expected, None);
+
+		let mut sink = Vec::<u8>::new();
+		let mut bytes = Vec::<u8>::new();
+		write_document(&mut bytes, Some(&mut sink));
+		sink.extend_from_slice(&bytes);
+		assert_eq!(sink, expected.as_bytes());
+	}
+
+	#[allow(clippy::mut_mut)]
+	#[test]
+	fn test_forwarding_writers() {
+		fn write_item<W: JSONWriter>(writer: &mut W, id: u32) {
+			JSONObjectWriter::new(writer).value("id", id);
+		}
+
+		let mut buffer = String::new();
+		let mut one = &mut buffer;
+		write_item(&mut one, 1);
+		let mut two = &mut one;
+		write_item(&mut two, 2);
+		write_item(&mut &mut &mut buffer, 3);
+		assert_eq!(buffer, "{\"id\":1}{\"id\":2}{\"id\":3}");
+
+		let mut output = String::new();
+		let mut boxed: Box<dyn JSONWriter + '_> = Box::new(&mut output);
+		let mut array = JSONArrayWriter::new(&mut boxed);
+		array.value("a");
+		array.write_comma();
+		write_item(array.writer, 4);
+		array.end();
+		assert_eq!(boxed.json_debug_len(), Some("[\"a\",{\"id\":4}]".len()));
+		drop(boxed);
+		assert_eq!(output, "[\"a\",{\"id\":4}]");
+	}
+
+	#[test]
+	fn test_atomics() {
+		use std::sync::atomic::{AtomicBool, AtomicI8, AtomicU64, AtomicUsize};
+		let requests = AtomicU64::new(u64::MAX);
+		let healthy = AtomicBool::new(true);
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("requests", &requests);
+		object.value("healthy", &healthy);
+		object.value("small", &AtomicI8::new(-8));
+		object.value("size", &AtomicUsize::new(3));
+		object.end();
+		assert_eq!(
+			buffer,
+			"{\"requests\":18446744073709551615,\"healthy\":true,\"small\":-8,\"size\":3}"
+		);
+	}
+
+	#[test]
+	fn test_pretty_indent_fn() {
+		let mut buffer = String::new();
+		let mut formatter =
+			PrettyJSONWriter::with_indent_fn(
+				&mut buffer,
+				|level| if level == 1 { "    " } else { "  " },
+			);
+		let mut writer = JSONObjectWriter::new(&mut formatter);
+		writer.object("a").value("b", &vec![1u8]);
+		writer.value("c", 2u8);
+		writer.end();
+		assert_eq!(
+			formatter.buffer,
+			"{\n    \"a\": {\n      \"b\": [\n        1\n      ]\n    },\n    \"c\": 2\n}"
+		);
+
+		let mut buffer = String::new();
+		let mut formatter =
+			PrettyJSONWriter::with_indent_fn(
+				&mut buffer,
+				|level| {
+					if level == 1 {
+						"\t"
+					} else {
+						" "
+					}
+				},
+			);
+		JSONArrayWriter::new(&mut formatter).array().value(1u8);
+		assert_eq!(buffer, "[\n\t[\n\t 1\n\t]\n]");
+	}
+
+	/// Writes an array of `count` small objects, the document shared by the tests of the writer wrappers
+	pub(crate) fn write_items<W: JSONWriter>(writer: &mut W, count: u32) {
+		let mut array = JSONArrayWriter::new(writer);
+		for i in 0..count {
+			let mut object = array.object();
+			object.value("id", i);
+			object.value("name", "ünïcödé \"x\"");
+			object.value("values", [i, i + 1].as_slice());
+		}
+	}
+
+	/// Small deterministic xorshift generator for randomized tests
+	struct TestRng(u64);
+
+	impl TestRng {
+		fn next(&mut self) -> u64 {
+			self.0 ^= self.0 << 13;
+			self.0 ^= self.0 >> 7;
+			self.0 ^= self.0 << 17;
+			self.0
+		}
+
+		/// Returns a random string biased towards characters that need escaping
+		fn string(&mut self, max_len: usize) -> String {
+			const SPECIAL: [char; 12] = [
+				'"', '\\', '/', '\n', '\r', '\t', '\0', '\u{1f}', '\u{7f}', 'ä', '中', '😀',
+			];
+			let len = self.next() as usize % (max_len + 1);
+			(0..len)
+				.map(|_| match self.next() % 4 {
+					0 => SPECIAL[self.next() as usize % SPECIAL.len()],
+					1 => char::from_u32(self.next() as u32 % 0x80).unwrap(),
+					2 => char::from_u32(self.next() as u32 % 0x800).unwrap_or('x'),
+					_ => 'a',
+				})
+				.collect()
+		}
+	}
+
+	#[test]
+	fn test_escaping_matches_scalar() {
+		let mut rng = TestRng(0x2545_F491_4F6C_DD1D);
+		for _ in 0..20000 {
+			let input = rng.string(40);
+			let mut expected = String::new();
+			write_part_of_string_scalar(&mut expected, &input);
+			let mut actual = String::new();
+			write_part_of_string(&mut actual, &input);
+			assert_eq!(actual, expected, "input: {input:?}");
+		}
+		for byte in 0u8..0x80 {
+			for (base, position) in [
+				("a", 15),
+				("a", 31),
+				("a", 39),
+				("a", 47),
+				("ä", 0),
+				("ä", 16),
+				("ä", 32),
+				("ä", 40),
+				("ä", 48),
+			] {
+				let mut input = base.repeat(48 / base.len());
+				input.insert(position, char::from(byte));
+				let mut expected = String::new();
+				write_part_of_string_scalar(&mut expected, &input);
+				let mut actual = String::new();
+				write_part_of_string(&mut actual, &input);
+				assert_eq!(actual, expected, "input: {input:?}");
+			}
+			for position in 0..17 {
+				let mut input = "a".repeat(16);
+				input.insert(position, char::from(byte));
+				let mut expected = String::new();
+				write_part_of_string_scalar(&mut expected, &input);
+				let mut actual = String::new();
+				write_part_of_string(&mut actual, &input);
+				assert_eq!(actual, expected, "input: {input:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn test_value_if() {
+		for mask in 0..8u32 {
+			let enabled = |bit: u32| mask & (1 << bit) != 0;
+			let mut buffer = String::new();
+			let mut object = JSONObjectWriter::new(&mut buffer);
+			object.value_if(enabled(0), "a", 0u32);
+			object.value_if(enabled(1), "b", 1u32);
+			object.value_if(enabled(2), "c", 2u32);
+			object.end();
+
+			let mut array_buffer = String::new();
+			let mut array = JSONArrayWriter::new(&mut array_buffer);
+			array.value_if(enabled(0), 0u32);
+			array.value_if(enabled(1), 1u32);
+			array.value_if(enabled(2), 2u32);
+			array.end();
+
+			let keys = ["a", "b", "c"];
+			let fields: Vec<String> = (0..3)
+				.filter(|&bit| enabled(bit))
+				.map(|bit| format!("\"{}\":{}", keys[bit as usize], bit))
+				.collect();
+			let values: Vec<String> = (0..3)
+				.filter(|&bit| enabled(bit))
+				.map(|bit| bit.to_string())
+				.collect();
+			assert_eq!(buffer, format!("{{{}}}", fields.join(",")));
+			assert_eq!(array_buffer, format!("[{}]", values.join(",")));
+		}
+	}
+
+	#[test]
+	fn test_containers_if() {
+		for mask in 0..16u32 {
+			let enabled = |bit: u32| mask & (1 << bit) != 0;
+			let mut buffer = String::new();
+			let mut object = JSONObjectWriter::new(&mut buffer);
+			if let Some(mut details) = object.object_if(enabled(0), "details") {
+				details.value("x", 1);
+			}
+			object.value_if(enabled(1), "a", 1);
+			if let Some(mut tags) = object.array_if(enabled(2), "tags") {
+				tags.value("t");
+			}
+			object.object_if(enabled(3), "empty");
+			object.end();
+
+			let mut expected = String::new();
+			let mut object = JSONObjectWriter::new(&mut expected);
+			if enabled(0) {
+				object.object("details").value("x", 1);
+			}
+			if enabled(1) {
+				object.value("a", 1);
+			}
+			if enabled(2) {
+				object.array("tags").value("t");
+			}
+			if enabled(3) {
+				object.object("empty");
+			}
+			object.end();
+			assert_eq!(buffer, expected);
+			dev::assert_valid_json(&buffer);
+
+			let mut buffer = String::new();
+			let mut array = JSONArrayWriter::new(&mut buffer);
+			if let Some(mut object) = array.object_if(enabled(0)) {
+				object.value("x", 1);
+			}
+			array.value_if(enabled(1), 1);
+			if let Some(mut nested) = array.array_if(enabled(2)) {
+				nested.value(2);
+			}
+			array.array_if(enabled(3));
+			array.end();
+
+			let mut expected = String::new();
+			let mut array = JSONArrayWriter::new(&mut expected);
+			if enabled(0) {
+				array.object().value("x", 1);
+			}
+			if enabled(1) {
+				array.value(1);
+			}
+			if enabled(2) {
+				array.array().value(2);
+			}
+			if enabled(3) {
+				array.array();
+			}
+			array.end();
+			assert_eq!(buffer, expected);
+			dev::assert_valid_json(&buffer);
+		}
+	}
+
+	#[test]
+	fn test_reserve() {
+		let large = "a\"".repeat(1000);
+		let mut buffer = String::new();
+		write_string(&mut buffer, &large);
+		assert!(buffer.capacity() >= large.len() + 2);
+		assert_eq!(buffer, to_json_string(large.as_str()));
+
+		let mut bytes = Vec::<u8>::new();
+		(&vec![1u8; 100][..]).write_json(&mut bytes);
+		assert!(bytes.capacity() >= 200);
+	}
+
+	#[test]
+	fn test_pretty_deep() {
+		fn write_nested<W: JSONWriter>(array: &mut JSONArrayWriter<'_, W>, depth: usize) {
+			array.value(depth as u32);
+			if depth > 1 {
+				write_nested(&mut array.array(), depth - 1);
+			}
+			array.value(depth as u32);
+		}
+
+		fn expected_nested(expected: &mut String, indent: &str, depth: usize, level: usize) {
+			expected.push_str("[\n");
+			expected.push_str(&format!("{}{},\n", indent.repeat(level), depth));
+			if depth > 1 {
+				expected.push_str(&indent.repeat(level));
+				expected_nested(expected, indent, depth - 1, level + 1);
+				expected.push_str(",\n");
+			}
+			expected.push_str(&format!("{}{}\n", indent.repeat(level), depth));
+			expected.push_str(&indent.repeat(level - 1));
+			expected.push(']');
+		}
+
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::with_indent(&mut buffer, "\t");
+		let mut outer = JSONArrayWriter::new(&mut pretty);
+		// Go down and back up twice
+		write_nested(&mut outer.array(), 50);
+		write_nested(&mut outer.array(), 50);
+		outer.end();
+
+		let mut expected = String::from("[\n\t");
+		expected_nested(&mut expected, "\t", 50, 2);
+		expected.push_str(",\n\t");
+		expected_nested(&mut expected, "\t", 50, 2);
+		expected.push_str("\n]");
+		assert_eq!(buffer, expected);
+	}
+
+	#[test]
+	fn test_chars_value() {
+		let text = "Hello \"World\"\n</tag> 中文 😀";
+		assert_eq!(
+			to_json_string(CharsValue(text.chars().map(|c| c.to_ascii_lowercase()))),
+			to_json_string(text.to_ascii_lowercase().as_str())
+		);
+		let long: String = text.chars().cycle().take(1000).collect();
+		assert_eq!(
+			to_json_string(CharsValue(long.chars())),
+			to_json_string(long.as_str())
+		);
+		assert_eq!(to_json_string(CharsValue("".chars())), "\"\"");
+	}
+
+	#[test]
+	fn test_string_writer_chunks() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		let mut value = object.string_writer("log");
+		// Escapes and multi byte chars at the chunk edges
+		for chunk in ["line\"", "\n\"quoted", "\\", "中", "文\t", "</", "end"] {
+			value.push_str(chunk);
+		}
+		value.end();
+		object.string_writer("empty");
+		let mut array = object.array("parts");
+		array.string_writer().push_str("a");
+		array.string_writer();
+		array.end();
+		object.value("next", 1u8);
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"log":"line\"\n\"quoted\\中文\t<\/end","empty":"","parts":["a",""],"next":1}"#
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_string_writer_flush() {
+		let line = "a\"b\n中😀";
+		let mut sink = Vec::<u8>::new();
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		let mut value = object.string_writer("report");
+		for _ in 0..10000 {
+			value.push_str(line);
+			if value.buffer_len() > 1000 {
+				value.output_buffered_data(&mut sink).unwrap();
+			}
+		}
+		assert!(value.buffer_len() <= 1100);
+		value.end();
+		object.value("done", true);
+		object.end();
+		sink.extend_from_slice(buffer.as_bytes());
+
+		let mut expected = String::new();
+		let mut object = JSONObjectWriter::new(&mut expected);
+		object.value("report", line.repeat(10000).as_str());
+		object.value("done", true);
+		object.end();
+		assert_eq!(sink, expected.as_bytes());
+	}
+
+	#[test]
+	fn test_tail() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		assert_eq!(object.tail(5), "{");
+		object.value("a", 1u8);
+		object.value("b", "xyz");
+		assert_eq!(object.tail(5), "\"xyz\"");
+		let mut array = object.array("c");
+		array.value("中");
+		// The first two bytes of '中' are skipped
+		assert_eq!(array.tail(2), "\"");
+		assert_eq!(array.tail(4), "中\"");
+		assert_eq!(array.tail(1000), "{\"a\":1,\"b\":\"xyz\",\"c\":[\"中\"");
+		array.end();
+		object.end();
+
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		JSONArrayWriter::new(&mut pretty).value(1u8);
+		assert_eq!(pretty.tail(5), "  1\n]");
+	}
+
+	#[test]
+	fn test_string_writer_format() {
+		use core::fmt::Write;
+
+		#[derive(Debug)]
+		#[allow(dead_code)]
+		struct Event<'a> {
+			name: &'a str,
+			tags: Vec<&'a str>,
+		}
+
+		let event = Event {
+			name: "multi\nline \"quoted\"",
+			tags: vec!["中文", "😀", "</tag>"],
+		};
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		write!(
+			object.string_writer("display"),
+			"{} failed after {} retries",
+			event.name,
+			3
+		)
+		.unwrap();
+		write!(object.string_writer("debug"), "{:?}", event).unwrap();
+		let mut value = object.string_writer("chars");
+		for c in ['"', '\\', '\n', 'ä', '😀'] {
+			value.push_char(c);
+		}
+		value.write_char('\t').unwrap();
+		value.end();
+		object.end();
+
+		let mut expected = String::new();
+		let mut object = JSONObjectWriter::new(&mut expected);
+		object.value(
+			"display",
+			format!("{} failed after {} retries", event.name, 3).as_str(),
+		);
+		object.value("debug", format!("{:?}", event).as_str());
+		object.value("chars", "\"\\\nä😀\t");
+		object.end();
+		assert_eq!(buffer, expected);
+		dev::assert_valid_json(&buffer);
+	}
+
+	#[test]
+	fn test_map_with_key() {
+		#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
+		struct Lang(String);
+
+		let mut map = std::collections::BTreeMap::new();
+		map.insert(Lang("de".to_owned()), "Hallo");
+		map.insert(Lang("en\"".to_owned()), "Hello");
+		assert_eq!(
+			to_json_string(object_from_map_with_key(&map, |k| k.0.as_str())),
+			"{\"de\":\"Hallo\",\"en\\\"\":\"Hello\"}"
+		);
+
+		let mut map = std::collections::HashMap::new();
+		map.insert(Lang("fr".to_owned()), vec![1u8, 2]);
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value(
+			"greetings",
+			object_from_map_with_key(&map, |k| k.0.as_str()),
+		);
+		object.end();
+		assert_eq!(buffer, "{\"greetings\":{\"fr\":[1,2]}}");
+	}
+
+	#[test]
+	fn test_with_raw_writer() {
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.with_raw_writer("raw", |writer| writer.json_fragment("{\"cached\":true}"));
+		object.value("a", 1u8);
+		let mut array = object.array("items");
+		array.with_raw_writer(|writer| writer.json_number_str("1e3"));
+		array.with_raw_writer(|writer| "x".write_json(writer));
+		array.value(2u8);
+		array.end();
+		object.end();
+		assert_eq!(
+			buffer,
+			r#"{"raw":{"cached":true},"a":1,"items":[1e3,"x",2]}"#
+		);
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "with_raw_writer closure did not write a value")]
+	fn test_debug_raw_writer_without_value() {
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.with_raw_writer(|_| {});
+	}
+
+	#[test]
+	fn test_try_write_slice() {
+		let items: Vec<Result<u32, String>> = vec![Ok(1), Ok(2)];
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		assert_eq!(try_write_slice(&mut array, &items), Ok(()));
+		array.end();
+		assert_eq!(buffer, "[1,2]");
+
+		let items: Vec<Result<String, ()>> = vec![Ok("a".to_owned()), Ok("b".to_owned())];
+		assert_eq!(
+			try_write_slice(&mut JSONArrayWriter::new(&mut buffer), &items),
+			Ok(())
+		);
+		assert_eq!(buffer, "[1,2][\"a\",\"b\"]");
+
+		let items: Vec<Result<&str, String>> = vec![Ok("a"), Err("failed".to_owned()), Ok("c")];
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		assert_eq!(
+			try_write_slice(&mut array, &items),
+			Err("failed".to_owned())
+		);
+		array.end();
+		assert_eq!(buffer, "[\"a\"]");
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		array.value(0u8);
+		let result = array.try_item(|array| try_write_slice(&mut array.array(), &items));
+		assert!(result.is_err());
+		array.end();
+		assert_eq!(buffer, "[0]");
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_object_stream() {
+		/// Sink that records the largest buffer flushed at once
+		struct Sink {
+			data: Vec<u8>,
+			largest_write: usize,
+		}
+
+		impl std::io::Write for Sink {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.largest_write = self.largest_write.max(buf.len());
+				self.data.extend_from_slice(buf);
+				Ok(buf.len())
+			}
+
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let mut sink = Sink {
+			data: Vec::new(),
+			largest_write: 0,
+		};
+		let mut buffer = String::new();
+		let pairs = (0..100_000u32).map(|i| (format!("key{i}"), i));
+		object_stream(&mut buffer, pairs, &mut sink, 4096).unwrap();
+		assert!(buffer.is_empty());
+		assert!(sink.largest_write < 4096 + 64);
+
+		let output = String::from_utf8(sink.data).unwrap();
+		dev::assert_valid_json(&output);
+		assert!(output.starts_with("{\"key0\":0,\"key1\":1,"));
+		assert!(output.ends_with(",\"key99999\":99999}"));
+		assert_eq!(output.matches(':').count(), 100_000);
+	}
 }
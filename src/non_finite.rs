@@ -0,0 +1,169 @@
+use crate::{BufferedJSONWriter, JSONWriter};
+use alloc::string::String;
+
+///
+/// How a non-finite float is written
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NonFiniteValue {
+	/// Writes null, like JSON.stringify in browsers
+	Null,
+	/// Writes the quoted strings "NaN", "Infinity" or "-Infinity"
+	String,
+}
+
+///
+/// Separate policies for NaN and infinite floats, see `NonFiniteJSONWriter`
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NonFinite {
+	/// Policy for NaN
+	pub nan: NonFiniteValue,
+	/// Policy for positive and negative infinity
+	pub infinity: NonFiniteValue,
+}
+
+impl NonFinite {
+	/// Writes the NaN or infinite `value` according to the policies
+	pub(crate) fn write_non_finite<W: JSONWriter>(self, writer: &mut W, value: f64) {
+		let (policy, name) = if value.is_nan() {
+			(self.nan, "NaN")
+		} else if value > 0.0 {
+			(self.infinity, "Infinity")
+		} else {
+			(self.infinity, "-Infinity")
+		};
+		match policy {
+			NonFiniteValue::Null => writer.json_null(),
+			NonFiniteValue::String => writer.json_string(name),
+		}
+	}
+}
+
+impl Default for NonFinite {
+	/// Writes null for all non-finite values, same as the default writers
+	fn default() -> Self {
+		NonFinite {
+			nan: NonFiniteValue::Null,
+			infinity: NonFiniteValue::Null,
+		}
+	}
+}
+
+///
+/// Wraps a writer and writes NaN and infinite floats according to the given `NonFinite` policies.
+///
+/// ```rust
+/// use json_writer::{JSONArrayWriter, NonFinite, NonFiniteJSONWriter, NonFiniteValue};
+/// let mut buffer = String::new();
+/// let policy = NonFinite {
+///     nan: NonFiniteValue::Null,
+///     infinity: NonFiniteValue::String,
+/// };
+/// let mut writer = NonFiniteJSONWriter::new(&mut buffer, policy);
+/// let mut array = JSONArrayWriter::new(&mut writer);
+/// array.value(f64::NAN);
+/// array.value(f64::NEG_INFINITY);
+/// array.end();
+/// assert_eq!(buffer, "[null,\"-Infinity\"]");
+/// ```
+///
+pub struct NonFiniteJSONWriter<W: JSONWriter = String> {
+	inner: W,
+	policy: NonFinite,
+}
+
+impl<W: JSONWriter> NonFiniteJSONWriter<W> {
+	///
+	/// Creates a new writer that forwards everything to `inner`, except for non-finite floats.
+	///
+	#[inline(always)]
+	pub fn new(inner: W, policy: NonFinite) -> NonFiniteJSONWriter<W> {
+		NonFiniteJSONWriter { inner, policy }
+	}
+
+	///
+	/// Returns the wrapped writer
+	///
+	#[inline(always)]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W: JSONWriter> JSONWriter for NonFiniteJSONWriter<W> {
+	forward_json_writer!(|this| this.inner;
+		json_null, json_bool, json_string, json_pre_escaped_string, json_escaped_string,
+		json_string_part, json_number_str, json_number_i64, json_number_u64, json_number_i128,
+		json_number_u128, json_begin_object, json_end_object, json_begin_array, json_end_array,
+		json_begin_string, json_end_string, json_begin_array_value, json_object_key,
+		json_pre_escaped_object_key, json_fragment, json_poison, json_reserve, json_debug_len,
+	);
+
+	fn json_number_f64(&mut self, value: f64) {
+		if value.is_finite() {
+			self.inner.json_number_f64(value);
+		} else {
+			self.policy.write_non_finite(&mut self.inner, value);
+		}
+	}
+}
+
+impl<W: BufferedJSONWriter> BufferedJSONWriter for NonFiniteJSONWriter<W> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.inner.take_buffer(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONArrayWriter, JSONObjectWriter, PrettyJSONWriter};
+
+	#[test]
+	fn test_non_finite() {
+		fn write_floats(policy: NonFinite) -> String {
+			let mut buffer = String::new();
+			let mut writer = NonFiniteJSONWriter::new(&mut buffer, policy);
+			let mut array = JSONArrayWriter::new(&mut writer);
+			array.value(f64::NAN);
+			array.value(f64::INFINITY);
+			array.value(f32::NEG_INFINITY);
+			array.value(1.5f64);
+			array.end();
+			buffer
+		}
+
+		let policy = NonFinite {
+			nan: NonFiniteValue::Null,
+			infinity: NonFiniteValue::String,
+		};
+		assert_eq!(
+			write_floats(policy),
+			"[null,\"Infinity\",\"-Infinity\",1.5]"
+		);
+		let policy = NonFinite {
+			nan: NonFiniteValue::String,
+			infinity: NonFiniteValue::Null,
+		};
+		assert_eq!(write_floats(policy), "[\"NaN\",null,null,1.5]");
+		assert_eq!(write_floats(NonFinite::default()), "[null,null,null,1.5]");
+
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		let policy = NonFinite {
+			nan: NonFiniteValue::Null,
+			infinity: NonFiniteValue::String,
+		};
+		let mut writer = NonFiniteJSONWriter::new(&mut pretty, policy);
+		JSONObjectWriter::new(&mut writer).value("max", f64::INFINITY);
+		assert_eq!(buffer, "{\n  \"max\": \"Infinity\"\n}");
+	}
+}
@@ -0,0 +1,172 @@
+use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+use alloc::string::String;
+
+///
+/// Object writer that checks in debug builds that keys are written in a declared order,
+/// e.g. to keep snapshot tests of known structs stable.
+///
+/// Keys may be skipped, so optional fields can be left out, but writing a key that comes earlier
+/// in the declared order than the previous one, or that is not declared at all, panics.
+/// Release builds write the keys without checking them.
+///
+/// ```rust
+/// use json_writer::OrderedObjectWriter;
+/// const USER_KEYS: &[&str] = &["id", "name", "email"];
+/// let mut buffer = String::new();
+/// let mut object = OrderedObjectWriter::new(&mut buffer, USER_KEYS);
+/// object.value("id", 1);
+/// object.value("email", "a@example.com");
+/// object.end();
+/// assert_eq!(buffer, "{\"id\":1,\"email\":\"a@example.com\"}");
+/// ```
+///
+pub struct OrderedObjectWriter<'a, W: JSONWriter = String> {
+	object: JSONObjectWriter<'a, W>,
+	/// Keys that may still be written, in order
+	#[cfg(debug_assertions)]
+	remaining: &'static [&'static str],
+}
+
+impl<'a, W: JSONWriter> OrderedObjectWriter<'a, W> {
+	///
+	/// Creates a new object writer that expects the keys in the order of `keys`. Writes '{' to the buffer immediately.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W, keys: &'static [&'static str]) -> OrderedObjectWriter<'a, W> {
+		OrderedObjectWriter::from_object(JSONObjectWriter::new(writer), keys)
+	}
+
+	///
+	/// Wraps an object writer, e.g. one returned by `JSONObjectWriter::object` or `JSONArrayWriter::object`
+	///
+	#[inline(always)]
+	pub fn from_object(
+		object: JSONObjectWriter<'a, W>,
+		#[cfg_attr(not(debug_assertions), allow(unused_variables))] keys: &'static [&'static str],
+	) -> OrderedObjectWriter<'a, W> {
+		OrderedObjectWriter {
+			object,
+			#[cfg(debug_assertions)]
+			remaining: keys,
+		}
+	}
+
+	#[cfg(debug_assertions)]
+	fn check_key(&mut self, key: &str) {
+		match self.remaining.iter().position(|expected| *expected == key) {
+			Some(index) => self.remaining = &self.remaining[index + 1..],
+			None => panic!(
+				"OrderedObjectWriter: key {key:?} written out of order, expected one of {:?}",
+				self.remaining
+			),
+		}
+	}
+
+	///
+	/// Escapes and appends key:value to the buffer
+	///
+	#[inline(always)]
+	pub fn value<T: JSONWriterValue>(&mut self, key: &str, value: T) {
+		#[cfg(debug_assertions)]
+		self.check_key(key);
+		self.object.value(key, value);
+	}
+
+	///
+	/// Escapes and appends key:value to the buffer if `condition` is true, otherwise writes nothing
+	///
+	#[inline(always)]
+	pub fn value_if<T: JSONWriterValue>(&mut self, condition: bool, key: &str, value: T) {
+		if condition {
+			self.value(key, value);
+		}
+	}
+
+	///
+	/// Starts writing a nested object with given key. The keys of the nested object are not checked.
+	///
+	#[inline(always)]
+	pub fn object(&mut self, key: &str) -> JSONObjectWriter<'_, W> {
+		#[cfg(debug_assertions)]
+		self.check_key(key);
+		self.object.object(key)
+	}
+
+	///
+	/// Starts writing a nested array with given key
+	///
+	#[inline(always)]
+	pub fn array(&mut self, key: &str) -> JSONArrayWriter<'_, W> {
+		#[cfg(debug_assertions)]
+		self.check_key(key);
+		self.object.array(key)
+	}
+
+	///
+	/// Drops the writer.
+	/// Dropping causes '}' to be appended to the buffer.
+	///
+	#[inline(always)]
+	pub fn end(self) {
+		drop(self);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const KEYS: &[&str] = &["id", "name", "tags", "meta"];
+
+	#[test]
+	fn test_ordered_in_order() {
+		let mut buffer = String::new();
+		let mut object = OrderedObjectWriter::new(&mut buffer, KEYS);
+		object.value("id", 1);
+		object.value("name", "x");
+		object.array("tags").value("a");
+		object.object("meta").value("z", 0);
+		object.end();
+		assert_eq!(buffer, r#"{"id":1,"name":"x","tags":["a"],"meta":{"z":0}}"#);
+
+		let mut buffer = String::new();
+		let mut array = JSONArrayWriter::new(&mut buffer);
+		let mut object = OrderedObjectWriter::from_object(array.object(), KEYS);
+		object.value_if(false, "id", 1);
+		object.value("name", "y");
+		object.value("meta", ());
+		object.end();
+		array.end();
+		assert_eq!(buffer, r#"[{"name":"y","meta":null}]"#);
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(
+		expected = "key \"id\" written out of order, expected one of [\"tags\", \"meta\"]"
+	)]
+	fn test_ordered_out_of_order() {
+		let mut buffer = String::new();
+		let mut object = OrderedObjectWriter::new(&mut buffer, KEYS);
+		object.value("name", "x");
+		object.value("id", 1);
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "key \"name\" written out of order")]
+	fn test_ordered_duplicate_key() {
+		let mut buffer = String::new();
+		let mut object = OrderedObjectWriter::new(&mut buffer, KEYS);
+		object.value("name", "x");
+		object.value("name", "y");
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "key \"unknown\" written out of order")]
+	fn test_ordered_unknown_key() {
+		let mut buffer = String::new();
+		OrderedObjectWriter::new(&mut buffer, KEYS).value("unknown", 1);
+	}
+}
@@ -0,0 +1,139 @@
+//!
+//! Parallel serialization of large arrays.
+//!
+//! Enabled with the `rayon` feature.
+//!
+
+use crate::{JSONArrayWriter, JSONWriter, JSONWriterValueRef};
+use rayon::prelude::*;
+
+/// Slices shorter than this are written sequentially, splitting them is not worth the overhead
+const MIN_PARALLEL_LEN: usize = 1024;
+
+impl JSONArrayWriter<'_, String> {
+	///
+	/// Writes all items of the slice as array entries, serializing them on the rayon thread pool.
+	///
+	/// The slice is split into chunks that are serialized into separate buffers in parallel
+	/// and then appended in order. Short slices, and all slices on a single thread pool, are written sequentially.
+	/// The output is identical to writing each item with `value`.
+	///
+	/// Only available for the compact `String` and `Vec<u8>` writers, whose output the chunks can be
+	/// appended to as they are. Formatting or transforming writers like `PrettyJSONWriter` need to see every value.
+	///
+	pub fn par_values<T: JSONWriterValueRef + Sync>(&mut self, items: &[T]) {
+		self.write_par_values(items);
+	}
+}
+
+impl JSONArrayWriter<'_, Vec<u8>> {
+	///
+	/// Writes all items of the slice as array entries, serializing them on the rayon thread pool.
+	///
+	/// See `JSONArrayWriter::<String>::par_values`.
+	///
+	pub fn par_values<T: JSONWriterValueRef + Sync>(&mut self, items: &[T]) {
+		self.write_par_values(items);
+	}
+}
+
+impl<W: JSONWriter> JSONArrayWriter<'_, W> {
+	/// Writes the items in parallel chunks formatted with the compact `String` writer
+	fn write_par_values<T: JSONWriterValueRef + Sync>(&mut self, items: &[T]) {
+		let threads = rayon::current_num_threads();
+		if items.len() < MIN_PARALLEL_LEN || threads == 1 {
+			for item in items {
+				self.comma();
+				item.write_json_ref(self.writer);
+			}
+			return;
+		}
+		// A few chunks per thread to balance items that take longer than others
+		let chunk_len = (items.len() / (threads * 4)).max(MIN_PARALLEL_LEN / 4);
+		let chunks: Vec<String> = items
+			.par_chunks(chunk_len)
+			.map(|chunk| {
+				let mut buffer = String::new();
+				for (index, item) in chunk.iter().enumerate() {
+					if index > 0 {
+						buffer.push(',');
+					}
+					item.write_json_ref(&mut buffer);
+				}
+				buffer
+			})
+			.collect();
+		for chunk in &chunks {
+			self.comma();
+			self.writer.json_fragment(chunk);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+
+	struct Item {
+		id: u64,
+		name: String,
+		score: f64,
+	}
+
+	impl JSONWriterValue for &Item {
+		fn write_json<W: JSONWriter>(self, writer: &mut W) {
+			let mut object = JSONObjectWriter::new(writer);
+			object.value("id", self.id);
+			object.value("name", &self.name);
+			object.value("score", self.score);
+		}
+	}
+
+	#[test]
+	fn test_par_values_matches_sequential() {
+		for len in [0usize, 1, 2, 1023, 1024, 1025, 100_000] {
+			let items: Vec<Item> = (0..len as u64)
+				.map(|id| Item {
+					id,
+					name: format!("item \"{id}\"\n"),
+					score: id as f64 / 3.0,
+				})
+				.collect();
+
+			let mut expected = String::new();
+			let mut array = JSONArrayWriter::new(&mut expected);
+			array.value("first");
+			for item in &items {
+				array.value(item);
+			}
+			array.value("last");
+			array.end();
+
+			let mut actual = String::new();
+			let mut array = JSONArrayWriter::new(&mut actual);
+			array.value("first");
+			array.par_values(&items);
+			array.value("last");
+			array.end();
+			assert_eq!(actual, expected, "len: {len}");
+
+			let mut actual = String::new();
+			let mut array = JSONArrayWriter::new(&mut actual);
+			array.par_values(&items);
+			array.end();
+			assert_eq!(
+				actual,
+				crate::to_json_string(&items[..]),
+				"len: {len} without surrounding values"
+			);
+
+			let mut bytes = Vec::new();
+			let mut array = JSONArrayWriter::new(&mut bytes);
+			array.value("first");
+			array.par_values(&items);
+			array.value("last");
+			array.end();
+			assert_eq!(bytes, expected.as_bytes(), "len: {len} as bytes");
+		}
+	}
+}
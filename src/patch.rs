@@ -0,0 +1,269 @@
+//!
+//! JSON Patch (RFC 6902) and JSON Merge Patch (RFC 7396) documents.
+//!
+
+use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue, JSONWriterValueRef};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::cmp::Ordering;
+
+///
+/// Writes a JSON Patch document, an array of operations.
+///
+/// Paths are given as segments, which are escaped as JSON Pointer (RFC 6901) automatically:
+/// `~` is written as `~0` and `/` as `~1`. An empty slice is the whole document, `""`.
+/// Array elements are addressed with their index as segment, and `"-"` appends to an array.
+///
+/// ```rust
+/// use json_writer::JsonPatchWriter;
+/// let mut buffer = String::new();
+/// let mut patch = JsonPatchWriter::new(&mut buffer);
+/// patch.add(&["tags", "-"], "new");
+/// patch.remove(&["a/b"]);
+/// patch.end();
+/// assert_eq!(
+///     buffer,
+///     r#"[{"op":"add","path":"\/tags\/-","value":"new"},{"op":"remove","path":"\/a~1b"}]"#
+/// );
+/// ```
+///
+pub struct JsonPatchWriter<'a, W: JSONWriter = String> {
+	array: JSONArrayWriter<'a, W>,
+}
+
+impl<'a, W: JSONWriter> JsonPatchWriter<'a, W> {
+	///
+	/// Creates a new JsonPatchWriter that writes to the given buffer. Writes '[' to the buffer immediately.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> JsonPatchWriter<'a, W> {
+		JsonPatchWriter {
+			array: JSONArrayWriter::new(writer),
+		}
+	}
+
+	///
+	/// Adds `value` at `path`
+	///
+	pub fn add<S: AsRef<str>, T: JSONWriterValue>(&mut self, path: &[S], value: T) {
+		let mut object = self.operation("add", path);
+		object.value("value", value);
+	}
+
+	///
+	/// Removes the value at `path`
+	///
+	pub fn remove<S: AsRef<str>>(&mut self, path: &[S]) {
+		self.operation("remove", path);
+	}
+
+	///
+	/// Replaces the value at `path` with `value`
+	///
+	pub fn replace<S: AsRef<str>, T: JSONWriterValue>(&mut self, path: &[S], value: T) {
+		let mut object = self.operation("replace", path);
+		object.value("value", value);
+	}
+
+	///
+	/// Moves the value at `from` to `path`
+	///
+	pub fn move_<S: AsRef<str>, P: AsRef<str>>(&mut self, from: &[S], path: &[P]) {
+		let mut object = self.operation("move", path);
+		write_pointer(&mut object, "from", from);
+	}
+
+	///
+	/// Copies the value at `from` to `path`
+	///
+	pub fn copy<S: AsRef<str>, P: AsRef<str>>(&mut self, from: &[S], path: &[P]) {
+		let mut object = self.operation("copy", path);
+		write_pointer(&mut object, "from", from);
+	}
+
+	///
+	/// Tests that the value at `path` is equal to `value`
+	///
+	pub fn test<S: AsRef<str>, T: JSONWriterValue>(&mut self, path: &[S], value: T) {
+		let mut object = self.operation("test", path);
+		object.value("value", value);
+	}
+
+	///
+	/// Drops the writer.
+	/// Dropping causes ']' to be appended to the buffer.
+	///
+	#[inline(always)]
+	pub fn end(self) {
+		drop(self);
+	}
+
+	/// Starts the object of an operation with its `op` and `path`
+	fn operation<S: AsRef<str>>(&mut self, op: &str, path: &[S]) -> JSONObjectWriter<'_, W> {
+		let mut object = self.array.object();
+		object.value("op", op);
+		write_pointer(&mut object, "path", path);
+		object
+	}
+}
+
+///
+/// Writes a JSON Merge Patch that turns `old` into `new`: an object with the keys that were added or changed
+/// and their new value, and the removed keys with null. Keys are written in sorted order.
+///
+/// The diff is shallow: changed values are written as a whole. In merge patches, null removes a key,
+/// so `V` should not have values that are written as null.
+///
+/// ```rust
+/// use json_writer::write_merge_patch;
+/// use std::collections::BTreeMap;
+/// let old = BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)]);
+/// let new = BTreeMap::from([("b".to_string(), 3), ("c".to_string(), 4)]);
+/// let mut buffer = String::new();
+/// write_merge_patch(&mut buffer, &old, &new);
+/// assert_eq!(buffer, r#"{"a":null,"b":3,"c":4}"#);
+/// ```
+///
+pub fn write_merge_patch<W: JSONWriter, V>(
+	writer: &mut W,
+	old: &BTreeMap<String, V>,
+	new: &BTreeMap<String, V>,
+) where
+	V: PartialEq + JSONWriterValueRef,
+{
+	let mut object = JSONObjectWriter::new(writer);
+	let mut old_entries = old.iter().peekable();
+	let mut new_entries = new.iter().peekable();
+	// Both maps are sorted, so they can be compared in a single pass
+	loop {
+		let order = match (old_entries.peek(), new_entries.peek()) {
+			(None, None) => break,
+			(Some(_), None) => Ordering::Less,
+			(None, Some(_)) => Ordering::Greater,
+			(Some((old_key, _)), Some((new_key, _))) => old_key.cmp(new_key),
+		};
+		match order {
+			Ordering::Less => {
+				let (key, _) = old_entries.next().unwrap();
+				object.value(key, crate::NULL);
+			}
+			Ordering::Greater => {
+				let (key, value) = new_entries.next().unwrap();
+				object.with_raw_writer(key, |writer| value.write_json_ref(writer));
+			}
+			Ordering::Equal => {
+				let (_, old_value) = old_entries.next().unwrap();
+				let (key, value) = new_entries.next().unwrap();
+				if old_value != value {
+					object.with_raw_writer(key, |writer| value.write_json_ref(writer));
+				}
+			}
+		}
+	}
+}
+
+/// Writes `segments` as JSON Pointer string value with the given key
+fn write_pointer<W: JSONWriter, S: AsRef<str>>(
+	object: &mut JSONObjectWriter<'_, W>,
+	key: &str,
+	segments: &[S],
+) {
+	let mut pointer = object.string_writer(key);
+	for segment in segments {
+		pointer.push_str("/");
+		let mut rest = segment.as_ref();
+		while let Some(index) = rest.find(['~', '/']) {
+			pointer.push_str(&rest[..index]);
+			pointer.push_str(if rest.as_bytes()[index] == b'~' {
+				"~0"
+			} else {
+				"~1"
+			});
+			rest = &rest[index + 1..];
+		}
+		pointer.push_str(rest);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_patch_operations() {
+		let mut buffer = String::new();
+		let mut patch = JsonPatchWriter::new(&mut buffer);
+		patch.add(&["a", "b"], 1u8);
+		patch.remove(&["list", "0"]);
+		patch.replace(&["name"], "x");
+		patch.move_(&["from"], &["to"]);
+		patch.copy(&["list", "1"], &["list", "-"]);
+		patch.test(&["ok"], true);
+		patch.end();
+		crate::dev::assert_json_eq(
+			&buffer,
+			r#"[
+				{"op": "add", "path": "/a/b", "value": 1},
+				{"op": "remove", "path": "/list/0"},
+				{"op": "replace", "path": "/name", "value": "x"},
+				{"op": "move", "path": "/to", "from": "/from"},
+				{"op": "copy", "path": "/list/-", "from": "/list/1"},
+				{"op": "test", "path": "/ok", "value": true}
+			]"#,
+		);
+	}
+
+	#[test]
+	fn test_patch_paths() {
+		let mut buffer = String::new();
+		let mut patch = JsonPatchWriter::new(&mut buffer);
+		patch.replace::<&str, _>(&[], crate::NULL);
+		patch.remove(&["a/b", "m~n", "~/", ""]);
+		patch.add(&[String::from("中文\"")], 0u8);
+		patch.end();
+		assert_eq!(
+			buffer,
+			r#"[{"op":"replace","path":"","value":null},{"op":"remove","path":"\/a~1b\/m~0n\/~0~1\/"},{"op":"add","path":"\/中文\"","value":0}]"#
+		);
+	}
+
+	#[test]
+	fn test_merge_patch() {
+		let old = BTreeMap::from([
+			("changed".to_string(), vec![1u8]),
+			("removed".to_string(), vec![2]),
+			("same".to_string(), vec![3]),
+			("z_removed".to_string(), vec![]),
+		]);
+		let new = BTreeMap::from([
+			("a_added".to_string(), vec![]),
+			("added".to_string(), vec![4]),
+			("changed".to_string(), vec![1, 5]),
+			("same".to_string(), vec![3]),
+		]);
+		let mut buffer = String::new();
+		write_merge_patch(&mut buffer, &old, &new);
+		assert_eq!(
+			buffer,
+			r#"{"a_added":[],"added":[4],"changed":[1,5],"removed":null,"z_removed":null}"#
+		);
+
+		let mut buffer = String::new();
+		write_merge_patch(&mut buffer, &old, &old);
+		assert_eq!(buffer, "{}");
+
+		let mut buffer = String::new();
+		write_merge_patch(&mut buffer, &BTreeMap::new(), &new);
+		assert_eq!(
+			buffer,
+			r#"{"a_added":[],"added":[4],"changed":[1,5],"same":[3]}"#
+		);
+
+		let mut buffer = String::new();
+		write_merge_patch(&mut buffer, &old, &BTreeMap::new());
+		assert_eq!(
+			buffer,
+			r#"{"changed":null,"removed":null,"same":null,"z_removed":null}"#
+		);
+	}
+}
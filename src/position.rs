@@ -0,0 +1,425 @@
+use crate::{BufferedJSONWriter, JSONWriter};
+
+///
+/// Position in the output of a `PositionTrackingWriter`
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Position {
+	/// Line number, starting at 1
+	pub line: usize,
+	/// Column in characters (not bytes), starting at 1
+	pub column: usize,
+	/// Offset in bytes from the start of the output, including flushed data
+	pub offset: usize,
+}
+
+impl Default for Position {
+	fn default() -> Self {
+		Position {
+			line: 1,
+			column: 1,
+			offset: 0,
+		}
+	}
+}
+
+///
+/// Wraps a writer and keeps track of the line and column of the output,
+/// e.g. to find the code that produced the JSON a parser complains about.
+///
+/// The output is read back through `AsRef<str>`, which is implemented for `String` and `PrettyJSONWriter`.
+/// Only new output is scanned after each call, so the overhead is linear in the output size.
+/// If `inner` removes output on its own, e.g. by flushing its buffer, the remaining output is scanned from its start.
+///
+/// ```rust
+/// use json_writer::{JSONObjectWriter, PositionTrackingWriter, PrettyJSONWriter};
+/// let mut buffer = String::new();
+/// let mut pretty = PrettyJSONWriter::new(&mut buffer);
+/// let mut keys = Vec::new();
+/// let mut writer = PositionTrackingWriter::with_key_callback(&mut pretty, |key, position| {
+///     keys.push((key.to_owned(), position.line, position.column));
+/// });
+/// JSONObjectWriter::new(&mut writer).value("a", 1u8);
+/// assert_eq!(keys, [("a".to_owned(), 2, 3)]);
+/// ```
+///
+pub struct PositionTrackingWriter<W: JSONWriter + AsRef<str>, F = fn(&str, Position)> {
+	inner: W,
+	position: Position,
+	/// Length of the output of `inner` that has already been scanned
+	scanned: usize,
+	on_key: F,
+}
+
+impl<W: JSONWriter + AsRef<str>> PositionTrackingWriter<W> {
+	///
+	/// Creates a new writer that tracks the position of everything written to `inner`.
+	///
+	/// Output that has already been written to `inner` is counted as well.
+	///
+	pub fn new(inner: W) -> PositionTrackingWriter<W> {
+		PositionTrackingWriter::with_key_callback(inner, |_, _| {})
+	}
+}
+
+impl<W: JSONWriter + AsRef<str>, F: FnMut(&str, Position)> PositionTrackingWriter<W, F> {
+	///
+	/// Creates a new writer that calls `on_key` with every object key and the position of its opening quote.
+	///
+	pub fn with_key_callback(inner: W, on_key: F) -> PositionTrackingWriter<W, F> {
+		let mut writer = PositionTrackingWriter {
+			inner,
+			position: Position::default(),
+			scanned: 0,
+			on_key,
+		};
+		writer.scan();
+		writer
+	}
+
+	///
+	/// Returns the position after the last written character
+	///
+	#[inline(always)]
+	pub fn position(&self) -> Position {
+		self.position
+	}
+
+	///
+	/// Returns the wrapped writer
+	///
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+
+	/// Advances the position over the output written since the last call
+	fn scan(&mut self) {
+		let output = self.inner.as_ref();
+		advance(&mut self.position, unscanned(output, self.scanned));
+		self.scanned = output.len();
+	}
+}
+
+/// Returns the part of `output` after `scanned`, or all of it if the output got shorter in between,
+/// e.g. because `inner` flushed it on its own
+fn unscanned(output: &str, scanned: usize) -> &str {
+	output.get(scanned..).unwrap_or(output)
+}
+
+/// Advances `position` over `text`
+fn advance(position: &mut Position, text: &str) {
+	position.offset += text.len();
+	match text.rfind('\n') {
+		Some(last_newline) => {
+			position.line += text.as_bytes().iter().filter(|&&b| b == b'\n').count();
+			position.column = text[last_newline + 1..].chars().count() + 1;
+		}
+		None => position.column += text.chars().count(),
+	}
+}
+
+impl<W: JSONWriter + AsRef<str>, F: FnMut(&str, Position)> JSONWriter
+	for PositionTrackingWriter<W, F>
+{
+	forward_json_writer!(|this| this.inner; json_reserve, json_debug_len);
+
+	fn json_null(&mut self) {
+		self.inner.json_null();
+		self.scan();
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		self.inner.json_bool(value);
+		self.scan();
+	}
+
+	fn json_string(&mut self, value: &str) {
+		self.inner.json_string(value);
+		self.scan();
+	}
+
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.inner.json_pre_escaped_string(value);
+		self.scan();
+	}
+
+	fn json_escaped_string(&mut self, value: &str, escaped: &str) {
+		self.inner.json_escaped_string(value, escaped);
+		self.scan();
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		self.inner.json_string_part(value);
+		self.scan();
+	}
+
+	fn json_number_f64(&mut self, value: f64) {
+		self.inner.json_number_f64(value);
+		self.scan();
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		self.inner.json_number_str(value);
+		self.scan();
+	}
+
+	fn json_number_i64(&mut self, value: i64) {
+		self.inner.json_number_i64(value);
+		self.scan();
+	}
+
+	fn json_number_u64(&mut self, value: u64) {
+		self.inner.json_number_u64(value);
+		self.scan();
+	}
+
+	fn json_number_i128(&mut self, value: i128) {
+		self.inner.json_number_i128(value);
+		self.scan();
+	}
+
+	fn json_number_u128(&mut self, value: u128) {
+		self.inner.json_number_u128(value);
+		self.scan();
+	}
+
+	fn json_begin_object(&mut self) {
+		self.inner.json_begin_object();
+		self.scan();
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.inner.json_end_object(empty);
+		self.scan();
+	}
+
+	fn json_begin_array(&mut self) {
+		self.inner.json_begin_array();
+		self.scan();
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.inner.json_end_array(empty);
+		self.scan();
+	}
+
+	fn json_begin_string(&mut self) {
+		self.inner.json_begin_string();
+		self.scan();
+	}
+
+	fn json_end_string(&mut self) {
+		self.inner.json_end_string();
+		self.scan();
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		self.inner.json_begin_array_value(first);
+		self.scan();
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		self.inner.json_object_key(key, first);
+		let output = self.inner.as_ref();
+		let written = unscanned(output, self.scanned);
+		// Only separators and whitespace are written before the opening quote of the key
+		let quote = written.find('"').unwrap_or(0);
+		advance(&mut self.position, &written[..quote]);
+		(self.on_key)(key, self.position);
+		advance(&mut self.position, &written[quote..]);
+		self.scanned = output.len();
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		self.inner.json_fragment(value);
+		self.scan();
+	}
+
+	fn json_poison(&mut self) {
+		self.inner.json_poison();
+		self.scan();
+	}
+}
+
+impl<W, F> BufferedJSONWriter for PositionTrackingWriter<W, F>
+where
+	W: BufferedJSONWriter + AsRef<str>,
+	F: FnMut(&str, Position),
+{
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.scan();
+		let result = self.inner.take_buffer(out);
+		self.scanned = self.inner.as_ref().len();
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONArrayWriter, JSONObjectWriter, PrettyJSONWriter};
+
+	#[test]
+	fn test_key_positions_pretty() {
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		let mut keys = Vec::new();
+		let mut writer = PositionTrackingWriter::with_key_callback(&mut pretty, |key, position| {
+			keys.push((key.to_owned(), position));
+		});
+		{
+			let mut object = JSONObjectWriter::new(&mut writer);
+			object.value("name", "中文😀");
+			let mut nested = object.object("nested");
+			nested.value("list", &vec![1u8, 2]);
+			nested.end();
+			object.value("last", true);
+		}
+		let end = writer.position();
+
+		// {
+		//   "name": "中文😀",
+		//   "nested": {
+		//     "list": [
+		//       1,
+		//       2
+		//     ]
+		//   },
+		//   "last": true
+		// }
+		let at = |line, column| {
+			let offset = buffer
+				.split_inclusive('\n')
+				.take(line - 1)
+				.map(str::len)
+				.sum::<usize>()
+				+ column - 1;
+			Position {
+				line,
+				column,
+				offset,
+			}
+		};
+		assert_eq!(
+			keys,
+			[
+				("name".to_owned(), at(2, 3)),
+				("nested".to_owned(), at(3, 3)),
+				("list".to_owned(), at(4, 5)),
+				("last".to_owned(), at(9, 3)),
+			]
+		);
+		assert_eq!(
+			end,
+			Position {
+				line: 10,
+				column: 2,
+				offset: buffer.len(),
+			}
+		);
+	}
+
+	#[test]
+	fn test_multi_byte_columns() {
+		let mut buffer = String::new();
+		let mut writer = PositionTrackingWriter::new(&mut buffer);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		array.value("ä😀\n");
+		array.value("x");
+		array.end();
+		// ["ä😀\n","x"] with the newline escaped
+		let position = writer.position();
+		assert_eq!(position.line, 1);
+		assert_eq!(position.column, "[\"ä😀\\n\",\"x\"]".chars().count() + 1);
+		assert_eq!(position.offset, buffer.len());
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_position_after_flush() {
+		let mut sink = Vec::new();
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		let mut writer = PositionTrackingWriter::new(&mut pretty);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		for i in 0..100u32 {
+			array.value(i);
+			if array.buffer_len() > 50 {
+				array.output_buffered_data(&mut sink).unwrap();
+			}
+		}
+		array.end();
+		let position = writer.position();
+		sink.extend_from_slice(buffer.as_bytes());
+		assert_eq!(position.offset, sink.len());
+		assert_eq!(position.line, 102);
+		assert_eq!(position.column, 2);
+	}
+
+	/// Clears its buffer before writing a number once it holds 16 bytes, like a writer that flushes on its own
+	struct SelfFlushing {
+		buffer: String,
+		written: usize,
+	}
+
+	impl SelfFlushing {
+		fn push(&mut self, value: &str) {
+			self.buffer.push_str(value);
+			self.written += value.len();
+		}
+	}
+
+	impl AsRef<str> for SelfFlushing {
+		fn as_ref(&self) -> &str {
+			&self.buffer
+		}
+	}
+
+	impl JSONWriter for SelfFlushing {
+		fn json_string(&mut self, value: &str) {
+			let mut quoted = String::new();
+			quoted.json_string(value);
+			self.push(&quoted);
+		}
+
+		fn json_string_part(&mut self, value: &str) {
+			let mut escaped = String::new();
+			escaped.json_string_part(value);
+			self.push(&escaped);
+		}
+
+		fn json_fragment(&mut self, value: &str) {
+			self.push(value);
+		}
+
+		fn json_number_str(&mut self, value: &str) {
+			if self.buffer.len() >= 16 {
+				self.buffer.clear();
+			}
+			self.push(value);
+		}
+	}
+
+	#[test]
+	fn test_inner_output_shrinks() {
+		let mut writer = PositionTrackingWriter::new(SelfFlushing {
+			buffer: String::new(),
+			written: 0,
+		});
+		let mut object = JSONObjectWriter::new(&mut writer);
+		for i in 0..20u32 {
+			object.value("key", i);
+			object.value("text", "ä😀");
+		}
+		object.end();
+		let position = writer.position();
+		assert_eq!(position.offset, writer.into_inner().written);
+		assert_eq!(position.line, 1);
+	}
+}
@@ -0,0 +1,327 @@
+use crate::{
+	write_escaped_byte, write_escaped_char, BufferedJSONWriter, EscapeBuffer, FragmentBuffer,
+	JSONWriter, HIGH_BITS, LOW_BITS,
+};
+
+///
+/// String escaping rules that are selected at compile time, see `ProfiledWriter`.
+///
+/// Control characters, `"` and `\` are always escaped, since JSON requires it.
+///
+pub trait EscapeProfile {
+	/// Bytes below 128 that are escaped in addition, `/` as `\/` and the others as `\u00XX`
+	const ESCAPED: &'static [u8];
+
+	/// Whether non-ASCII characters are escaped as `\uXXXX`, using surrogate pairs outside the BMP
+	const ASCII_ONLY: bool = false;
+
+	/// Escape sequence of each byte: 0 if the byte is written as is, `u` for `\u00XX`,
+	/// otherwise the character following the backslash
+	const REPLACEMENTS: [u8; 256] = replacement_table(Self::ESCAPED);
+}
+
+///
+/// Escapes like the default writers, including `/` as `\/`
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DefaultEscaping;
+
+impl EscapeProfile for DefaultEscaping {
+	const ESCAPED: &'static [u8] = b"/";
+}
+
+///
+/// Escapes only what JSON requires, `/` is written as is
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoSlashEscape;
+
+impl EscapeProfile for NoSlashEscape {
+	const ESCAPED: &'static [u8] = b"";
+}
+
+///
+/// Additionally escapes `<`, `>` and `&`, so the output can be embedded in HTML
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HtmlSafe;
+
+impl EscapeProfile for HtmlSafe {
+	const ESCAPED: &'static [u8] = b"/<>&";
+}
+
+///
+/// Additionally escapes all non-ASCII characters, so the output only contains ASCII
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AsciiOnly;
+
+impl EscapeProfile for AsciiOnly {
+	const ESCAPED: &'static [u8] = b"/";
+	const ASCII_ONLY: bool = true;
+}
+
+///
+/// Builds the replacement table of a profile that escapes `escaped` in addition to the required characters
+///
+pub const fn replacement_table(escaped: &[u8]) -> [u8; 256] {
+	let mut result = [0u8; 256];
+	let mut i = 0;
+	while i < 0x20 {
+		result[i] = b'u';
+		i += 1;
+	}
+	result[8] = b'b';
+	result[0xc] = b'f';
+	result[b'\n' as usize] = b'n';
+	result[b'\r' as usize] = b'r';
+	result[b'\t' as usize] = b't';
+	result[b'"' as usize] = b'"';
+	result[b'\\' as usize] = b'\\';
+	let mut i = 0;
+	while i < escaped.len() {
+		let byte = escaped[i];
+		assert!(byte < 0x80, "only ASCII bytes can be escaped");
+		result[byte as usize] = if byte == b'/' { b'/' } else { b'u' };
+		i += 1;
+	}
+	result
+}
+
+///
+/// Wraps a writer and escapes strings and keys with the profile `P`.
+///
+/// The escaping loop is compiled separately for each profile, so no options are checked while escaping.
+/// Use `ConfiguredWriter` to select the escaping at runtime instead.
+/// Keys are written compactly, so the wrapped writer should not be a `PrettyJSONWriter`.
+///
+/// ```rust
+/// use json_writer::{HtmlSafe, JSONObjectWriter, ProfiledWriter};
+/// let mut buffer = String::new();
+/// let mut writer = ProfiledWriter::new(&mut buffer, HtmlSafe);
+/// JSONObjectWriter::new(&mut writer).value("html", "<b>&</b>");
+/// assert_eq!(buffer, r#"{"html":"\u003Cb\u003E\u0026\u003C\/b\u003E"}"#);
+/// ```
+///
+pub struct ProfiledWriter<W: JSONWriter, P: EscapeProfile> {
+	inner: W,
+	profile: P,
+}
+
+impl<W: JSONWriter, P: EscapeProfile> ProfiledWriter<W, P> {
+	///
+	/// Creates a new writer that escapes with `profile` and writes to `inner`
+	///
+	#[inline(always)]
+	pub fn new(inner: W, profile: P) -> ProfiledWriter<W, P> {
+		ProfiledWriter { inner, profile }
+	}
+
+	///
+	/// Returns the wrapped writer and the profile
+	///
+	#[inline(always)]
+	pub fn into_inner(self) -> (W, P) {
+		(self.inner, self.profile)
+	}
+}
+
+impl<W: JSONWriter, P: EscapeProfile> JSONWriter for ProfiledWriter<W, P> {
+	forward_json_writer!(|this| this.inner;
+		json_null, json_bool, json_number_f64, json_numbers_f64, json_number_str, json_number_i64, json_number_u64,
+		json_number_i128, json_number_u128, json_begin_object, json_end_object, json_begin_array,
+		json_end_array, json_begin_string, json_end_string, json_begin_array_value, json_fragment,
+		json_poison, json_reserve, json_debug_len,
+	);
+
+	#[inline(always)]
+	fn json_string(&mut self, value: &str) {
+		self.inner.json_begin_string();
+		write_part_of_string_profiled::<P, _>(&mut FragmentBuffer(&mut self.inner), value);
+		self.inner.json_end_string();
+	}
+
+	#[inline(always)]
+	fn json_string_part(&mut self, value: &str) {
+		write_part_of_string_profiled::<P, _>(&mut FragmentBuffer(&mut self.inner), value);
+	}
+
+	#[inline(always)]
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		if !first {
+			self.inner.json_fragment(",");
+		}
+		self.json_string(key);
+		self.inner.json_fragment(":");
+	}
+}
+
+impl<W: BufferedJSONWriter, P: EscapeProfile> BufferedJSONWriter for ProfiledWriter<W, P> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.inner.take_buffer(out)
+	}
+}
+
+///
+/// Returns true if any of the 8 bytes packed into `block` is escaped by the profile `P`
+///
+#[inline(always)]
+fn block_needs_escaping<P: EscapeProfile>(block: u64) -> bool {
+	// A byte of `x - LOW_BITS * n` has its high bit set (and the byte of x not) iff the byte of x is < n
+	#[inline(always)]
+	fn any_byte_less_than(x: u64, n: u8) -> u64 {
+		x.wrapping_sub(LOW_BITS * n as u64) & !x
+	}
+	#[inline(always)]
+	fn any_byte_equal(x: u64, n: u8) -> u64 {
+		any_byte_less_than(x ^ (LOW_BITS * n as u64), 1)
+	}
+	let mut found = any_byte_less_than(block, 0x20)
+		| any_byte_equal(block, b'"')
+		| any_byte_equal(block, b'\\');
+	for &byte in P::ESCAPED {
+		found |= any_byte_equal(block, byte);
+	}
+	if P::ASCII_ONLY {
+		// The high bit is set in all bytes of multi byte characters
+		found |= block;
+	}
+	found & HIGH_BITS != 0
+}
+
+///
+/// Escapes and appends part of a string with the profile `P`, see `write_part_of_string_impl`
+///
+#[inline(always)]
+fn write_part_of_string_profiled<P: EscapeProfile, B: EscapeBuffer + ?Sized>(
+	output_buffer: &mut B,
+	input: &str,
+) {
+	let replacements: &'static [u8; 256] = &P::REPLACEMENTS;
+	let mut num_bytes_written: usize = 0;
+	let mut index: usize = 0;
+	let bytes = input.as_bytes();
+	while index < bytes.len() {
+		// Skip blocks of 8 bytes that contain nothing to escape, only inspect the others byte by byte
+		let block_end = if index + 8 <= bytes.len() {
+			let mut block = [0u8; 8];
+			block.copy_from_slice(&bytes[index..index + 8]);
+			if !block_needs_escaping::<P>(u64::from_le_bytes(block)) {
+				index += 8;
+				continue;
+			}
+			index + 8
+		} else {
+			bytes.len()
+		};
+		while index < block_end {
+			let cur_byte = bytes[index];
+			if P::ASCII_ONLY && cur_byte >= 0x80 {
+				// Only whole characters are skipped in this mode, so index is at a character boundary
+				output_buffer.push_str(&input[num_bytes_written..index]);
+				let c = input[index..].chars().next().unwrap();
+				write_escaped_char(output_buffer, c);
+				index += c.len_utf8();
+				num_bytes_written = index;
+				continue;
+			}
+			let replacement = replacements[cur_byte as usize];
+			if replacement != 0 {
+				write_escaped_byte(
+					output_buffer,
+					input,
+					num_bytes_written,
+					index,
+					cur_byte,
+					replacement,
+				);
+				num_bytes_written = index + 1;
+			}
+			index += 1;
+		}
+	}
+	if num_bytes_written < bytes.len() {
+		// Checks can be omitted here:
+		// We know that num_bytes_written is smaller than index
+		// We also know that num_bytes_written not in the middle of an utf-8 multi byte sequence, because those are not escaped
+		output_buffer.push_str(unsafe { input.get_unchecked(num_bytes_written..bytes.len()) });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ConfiguredWriter, JSONArrayWriter, JSONObjectWriter, WriterConfig, REPLACEMENTS};
+
+	/// Strings around every escaped byte and at the block boundaries
+	fn inputs() -> Vec<String> {
+		let mut inputs = vec![String::new()];
+		for special in [
+			"\u{0}", "\u{1f}", "\n", "\"", "\\", "/", "<", ">", "&", "é", "中", "😀", "\u{7f}",
+		] {
+			for prefix in 0..18 {
+				let text = format!("{}{special}{}", "a".repeat(prefix), "b".repeat(17 - prefix));
+				inputs.push(text);
+			}
+		}
+		inputs.push("</script> Grüße aus Köln, 中文 \"quoted\"\n😀".repeat(3));
+		inputs
+	}
+
+	fn write_with<W: JSONWriter>(writer: &mut W) {
+		let mut array = JSONArrayWriter::new(writer);
+		for input in inputs() {
+			let mut object = array.object();
+			object.value(&input, input.as_str());
+			let mut string = object.string_writer("parts");
+			for c in input.chars() {
+				string.push_str(c.encode_utf8(&mut [0; 4]));
+			}
+		}
+	}
+
+	fn assert_matches_config<P: EscapeProfile>(profile: P, config: WriterConfig) {
+		let mut expected = String::new();
+		write_with(&mut ConfiguredWriter::new(&mut expected, &config));
+		let mut output = String::new();
+		write_with(&mut ProfiledWriter::new(&mut output, profile));
+		assert_eq!(output, expected);
+		crate::dev::assert_valid_json(&output);
+	}
+
+	#[test]
+	fn test_profiles_match_config() {
+		let mut plain = String::new();
+		write_with(&mut plain);
+		let mut output = String::new();
+		write_with(&mut ProfiledWriter::new(&mut output, DefaultEscaping));
+		assert_eq!(output, plain);
+
+		assert_matches_config(DefaultEscaping, WriterConfig::COMPACT);
+		assert_matches_config(NoSlashEscape, WriterConfig::COMPACT.escape_slash(false));
+		assert_matches_config(HtmlSafe, WriterConfig::COMPACT.escape_html(true));
+		assert_matches_config(AsciiOnly, WriterConfig::COMPACT.ascii_only(true));
+	}
+
+	#[test]
+	fn test_replacement_tables() {
+		assert_eq!(DefaultEscaping::REPLACEMENTS, REPLACEMENTS);
+		assert_eq!(NoSlashEscape::REPLACEMENTS[b'/' as usize], 0);
+		assert_eq!(HtmlSafe::REPLACEMENTS[b'&' as usize], b'u');
+	}
+
+	#[test]
+	fn test_ascii_only() {
+		let mut buffer = String::new();
+		let mut writer = ProfiledWriter::new(&mut buffer, AsciiOnly);
+		JSONObjectWriter::new(&mut writer).value("ä", "aé中😀/");
+		assert_eq!(buffer, r#"{"\u00E4":"a\u00E9\u4E2D\uD83D\uDE00\/"}"#);
+	}
+}
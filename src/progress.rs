@@ -0,0 +1,261 @@
+use crate::{BufferedJSONWriter, JSONWriter};
+
+///
+/// Wraps a buffered writer and reports the number of bytes written so far,
+/// e.g. to drive a progress bar for a long-running export.
+///
+/// The callback is called with the cumulative number of bytes, including data that was flushed
+/// with `output_buffered_data`, every time another `interval` bytes have been written, and once more by `finish`.
+/// Checking the interval only costs a comparison per write.
+///
+/// ```rust
+/// use json_writer::{JSONArrayWriter, ProgressWriter};
+/// let mut reports = Vec::new();
+/// let mut writer = ProgressWriter::new(String::new(), 4, |total| reports.push(total));
+/// JSONArrayWriter::new(&mut writer).value("abc");
+/// let buffer = writer.finish();
+/// assert_eq!(buffer, "[\"abc\"]");
+/// assert_eq!(reports, [6, 7]);
+/// ```
+///
+pub struct ProgressWriter<W: BufferedJSONWriter, F: FnMut(u64)> {
+	inner: W,
+	on_progress: F,
+	interval: u64,
+	/// Bytes that were flushed from the buffer of `inner`
+	flushed: u64,
+	/// Total at which the callback is called next
+	next_report: u64,
+}
+
+impl<W: BufferedJSONWriter, F: FnMut(u64)> ProgressWriter<W, F> {
+	///
+	/// Creates a new writer that calls `on_progress` every `interval` bytes.
+	///
+	/// Output that has already been written to `inner` is counted as well.
+	/// Panics if `interval` is 0.
+	///
+	pub fn new(inner: W, interval: u64, on_progress: F) -> ProgressWriter<W, F> {
+		assert!(interval > 0, "progress interval must not be 0");
+		let mut writer = ProgressWriter {
+			inner,
+			on_progress,
+			interval,
+			flushed: 0,
+			next_report: interval,
+		};
+		writer.report();
+		writer
+	}
+
+	///
+	/// Returns the number of bytes written so far
+	///
+	#[inline(always)]
+	pub fn total(&self) -> u64 {
+		self.flushed + self.inner.buffer_len() as u64
+	}
+
+	///
+	/// Calls the callback with the final total and returns the wrapped writer
+	///
+	pub fn finish(mut self) -> W {
+		let total = self.total();
+		(self.on_progress)(total);
+		self.inner
+	}
+
+	#[inline(always)]
+	fn report(&mut self) {
+		let total = self.total();
+		if total >= self.next_report {
+			(self.on_progress)(total);
+			self.next_report = (total / self.interval + 1) * self.interval;
+		}
+	}
+}
+
+impl<W: BufferedJSONWriter, F: FnMut(u64)> JSONWriter for ProgressWriter<W, F> {
+	forward_json_writer!(|this| this.inner; json_poison, json_reserve, json_debug_len);
+
+	fn json_null(&mut self) {
+		self.inner.json_null();
+		self.report();
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		self.inner.json_bool(value);
+		self.report();
+	}
+
+	fn json_string(&mut self, value: &str) {
+		self.inner.json_string(value);
+		self.report();
+	}
+
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.inner.json_pre_escaped_string(value);
+		self.report();
+	}
+
+	fn json_escaped_string(&mut self, value: &str, escaped: &str) {
+		self.inner.json_escaped_string(value, escaped);
+		self.report();
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		self.inner.json_string_part(value);
+		self.report();
+	}
+
+	fn json_number_f64(&mut self, value: f64) {
+		self.inner.json_number_f64(value);
+		self.report();
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		self.inner.json_number_str(value);
+		self.report();
+	}
+
+	fn json_number_i64(&mut self, value: i64) {
+		self.inner.json_number_i64(value);
+		self.report();
+	}
+
+	fn json_number_u64(&mut self, value: u64) {
+		self.inner.json_number_u64(value);
+		self.report();
+	}
+
+	fn json_number_i128(&mut self, value: i128) {
+		self.inner.json_number_i128(value);
+		self.report();
+	}
+
+	fn json_number_u128(&mut self, value: u128) {
+		self.inner.json_number_u128(value);
+		self.report();
+	}
+
+	fn json_begin_object(&mut self) {
+		self.inner.json_begin_object();
+		self.report();
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.inner.json_end_object(empty);
+		self.report();
+	}
+
+	fn json_begin_array(&mut self) {
+		self.inner.json_begin_array();
+		self.report();
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.inner.json_end_array(empty);
+		self.report();
+	}
+
+	fn json_begin_string(&mut self) {
+		self.inner.json_begin_string();
+		self.report();
+	}
+
+	fn json_end_string(&mut self) {
+		self.inner.json_end_string();
+		self.report();
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		self.inner.json_begin_array_value(first);
+		self.report();
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		self.inner.json_object_key(key, first);
+		self.report();
+	}
+
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		self.inner.json_pre_escaped_object_key(key, first);
+		self.report();
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		self.inner.json_fragment(value);
+		self.report();
+	}
+}
+
+impl<W: BufferedJSONWriter, F: FnMut(u64)> BufferedJSONWriter for ProgressWriter<W, F> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		let written = self.inner.take_buffer(out)?;
+		self.flushed += written as u64;
+		Ok(written)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::write_items;
+	#[cfg(feature = "std")]
+	use crate::{JSONObjectWriter, PrettyJSONWriter};
+
+	#[test]
+	fn test_progress_reports() {
+		let mut expected = String::new();
+		write_items(&mut expected, 1000);
+
+		let mut reports = Vec::new();
+		let mut writer = ProgressWriter::new(String::new(), 1000, |total| reports.push(total));
+		write_items(&mut writer, 1000);
+		let buffer = writer.finish();
+		assert_eq!(buffer, expected);
+
+		// Every write is shorter than the interval, so every boundary is reported exactly once
+		let total = expected.len() as u64;
+		assert_eq!(reports.len() as u64, total / 1000 + 1);
+		assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+		for (index, &report) in reports[..reports.len() - 1].iter().enumerate() {
+			assert!(
+				report >= (index as u64 + 1) * 1000 && report < (index as u64 + 1) * 1000 + 100
+			);
+		}
+		assert_eq!(reports.last(), Some(&total));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_progress_with_flushing() {
+		let mut sink = Vec::new();
+		let mut reports = Vec::new();
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		let mut writer = ProgressWriter::new(&mut pretty, 512, |total| reports.push(total));
+		{
+			let mut object = JSONObjectWriter::new(&mut writer);
+			let mut array = object.array("items");
+			for i in 0..2000u32 {
+				array.value(i);
+				if array.buffer_len() > 300 {
+					array.output_buffered_data(&mut sink).unwrap();
+				}
+			}
+		}
+		writer.finish();
+		sink.extend_from_slice(buffer.as_bytes());
+
+		assert!(reports.len() > 10);
+		assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+		assert_eq!(reports.last(), Some(&(sink.len() as u64)));
+	}
+}
@@ -0,0 +1,447 @@
+//!
+//! Reformatting of existing JSON text without parsing it into values.
+//!
+
+use crate::{JSONWriter, PrettyLayout};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+///
+/// Error returned when the input of `minify_json` or `prettify_json` is not valid JSON
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonSyntaxError {
+	/// Byte offset of the error in the input
+	pub offset: usize,
+	/// Description of the error
+	pub message: &'static str,
+}
+
+impl core::fmt::Display for JsonSyntaxError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{} at offset {}", self.message, self.offset)
+	}
+}
+
+impl core::error::Error for JsonSyntaxError {}
+
+/// What the scanner accepts next
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Expect {
+	Value,
+	/// First value of an array or `]`
+	ValueOrEnd,
+	Key,
+	/// First key of an object or `}`
+	KeyOrEnd,
+	Colon,
+	CommaOrEnd,
+	/// The top-level value is complete
+	Done,
+}
+
+/// Token passed to the callback of `scan` together with its text
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Token {
+	/// `{` or `[`
+	Begin,
+	/// `}` or `]`
+	End {
+		empty: bool,
+	},
+	Comma,
+	Colon,
+	/// Key, string, number or literal
+	Scalar,
+}
+
+///
+/// Removes all insignificant whitespace from a JSON document.
+///
+/// Strings and numbers are copied byte for byte, nothing is parsed or reformatted.
+/// Returns an error with the offset of the first syntax error if `input` is not a single valid JSON document.
+///
+/// ```rust
+/// use json_writer::minify_json;
+/// assert_eq!(minify_json("{\n  \"a b\": [1.50, true]\n}").unwrap(), "{\"a b\":[1.50,true]}");
+/// assert_eq!(minify_json("[1,]").unwrap_err().offset, 3);
+/// ```
+///
+pub fn minify_json(input: &str) -> Result<String, JsonSyntaxError> {
+	let mut output = String::with_capacity(input.len());
+	minify_json_into(input, &mut output)?;
+	Ok(output)
+}
+
+///
+/// Like `minify_json`, but writes the minified document to `writer`,
+/// e.g. with `JSONObjectWriter::with_raw_writer` to embed an existing document.
+///
+/// On error the output is incomplete and the writer is poisoned (see `JsonDocument::is_poisoned`).
+///
+pub fn minify_json_into<W: JSONWriter + ?Sized>(
+	input: &str,
+	writer: &mut W,
+) -> Result<(), JsonSyntaxError> {
+	let result = scan(input, |_, text| writer.json_fragment(text));
+	if result.is_err() {
+		writer.json_poison();
+	}
+	result
+}
+
+///
+/// Reformats a JSON document like `PrettyJSONWriter::with_indent` with the given indentation.
+///
+/// Strings and numbers are copied byte for byte, so escapes and the precision of numbers are kept.
+/// Returns an error with the offset of the first syntax error if `input` is not a single valid JSON document.
+///
+/// ```rust
+/// use json_writer::prettify_json;
+/// assert_eq!(
+///     prettify_json("{\"a\":[1.50,{}]}", "  ").unwrap(),
+///     "{\n  \"a\": [\n    1.50,\n    {}\n  ]\n}"
+/// );
+/// ```
+///
+pub fn prettify_json(input: &str, indent: &str) -> Result<String, JsonSyntaxError> {
+	let mut output = String::with_capacity(input.len() * 2);
+	let mut layout = PrettyLayout::new(indent);
+	// Set after `{`, `[` and `,`, the next value or key starts a new line, the first one if `Some(true)`
+	let mut line_start = None;
+	scan(input, |token, text| match token {
+		Token::Begin | Token::Scalar => {
+			if let Some(first) = line_start.take() {
+				layout.line_start(&mut output, first);
+			}
+			if token == Token::Begin {
+				layout.begin(&mut output, text);
+				line_start = Some(true);
+			} else {
+				output.push_str(text);
+			}
+		}
+		Token::End { empty } => {
+			line_start = None;
+			layout.end(&mut output, empty, text);
+		}
+		Token::Comma => line_start = Some(false),
+		Token::Colon => output.push_str(": "),
+	})?;
+	Ok(output)
+}
+
+///
+/// Checks the syntax of `input` and calls `on_token` with every token in order.
+///
+fn scan<F: FnMut(Token, &str)>(input: &str, mut on_token: F) -> Result<(), JsonSyntaxError> {
+	let bytes = input.as_bytes();
+	let error = |offset, message| Err(JsonSyntaxError { offset, message });
+	// Open containers, b'{' or b'['
+	let mut stack = Vec::new();
+	let mut expect = Expect::Value;
+	let mut pos = 0;
+	while pos < bytes.len() {
+		let byte = bytes[pos];
+		if let b' ' | b'\t' | b'\n' | b'\r' = byte {
+			pos += 1;
+			continue;
+		}
+		if expect == Expect::Done {
+			return error(pos, "trailing characters");
+		}
+		let start = pos;
+		let token = match byte {
+			b'{' | b'[' => {
+				if !matches!(expect, Expect::Value | Expect::ValueOrEnd) {
+					return error(pos, "unexpected character");
+				}
+				stack.push(byte);
+				expect = if byte == b'{' {
+					Expect::KeyOrEnd
+				} else {
+					Expect::ValueOrEnd
+				};
+				pos += 1;
+				Token::Begin
+			}
+			b'}' | b']' => {
+				let (open, allowed) = if byte == b'}' {
+					(b'{', Expect::KeyOrEnd)
+				} else {
+					(b'[', Expect::ValueOrEnd)
+				};
+				if stack.last() != Some(&open)
+					|| !(expect == allowed || expect == Expect::CommaOrEnd)
+				{
+					return error(pos, "unexpected character");
+				}
+				let empty = expect == allowed;
+				stack.pop();
+				expect = after_value(&stack);
+				pos += 1;
+				Token::End { empty }
+			}
+			b',' => {
+				if expect != Expect::CommaOrEnd {
+					return error(pos, "unexpected character");
+				}
+				expect = if stack.last() == Some(&b'{') {
+					Expect::Key
+				} else {
+					Expect::Value
+				};
+				pos += 1;
+				Token::Comma
+			}
+			b':' => {
+				if expect != Expect::Colon {
+					return error(pos, "unexpected character");
+				}
+				expect = Expect::Value;
+				pos += 1;
+				Token::Colon
+			}
+			b'"' => {
+				expect = match expect {
+					Expect::Key | Expect::KeyOrEnd => Expect::Colon,
+					Expect::Value | Expect::ValueOrEnd => after_value(&stack),
+					_ => return error(pos, "unexpected character"),
+				};
+				pos = skip_string(bytes, pos)?;
+				Token::Scalar
+			}
+			_ => {
+				if !matches!(expect, Expect::Value | Expect::ValueOrEnd) {
+					return error(pos, "unexpected character");
+				}
+				while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'+' | b'-' | b'.') =
+					bytes.get(pos)
+				{
+					pos += 1;
+				}
+				let token = &bytes[start..pos];
+				if token.is_empty() {
+					return error(start, "unexpected character");
+				}
+				if !matches!(token, b"true" | b"false" | b"null") && !is_number(token) {
+					return error(start, "invalid value");
+				}
+				expect = after_value(&stack);
+				Token::Scalar
+			}
+		};
+		on_token(token, &input[start..pos]);
+	}
+	if expect != Expect::Done {
+		return error(bytes.len(), "unexpected end of input");
+	}
+	Ok(())
+}
+
+fn after_value(stack: &[u8]) -> Expect {
+	if stack.is_empty() {
+		Expect::Done
+	} else {
+		Expect::CommaOrEnd
+	}
+}
+
+/// Returns the offset after the closing quote of the string starting at `start`
+fn skip_string(bytes: &[u8], start: usize) -> Result<usize, JsonSyntaxError> {
+	let error = |offset, message| Err(JsonSyntaxError { offset, message });
+	let mut pos = start + 1;
+	loop {
+		match bytes.get(pos) {
+			None => return error(start, "unterminated string"),
+			Some(b'"') => return Ok(pos + 1),
+			Some(0..=0x1F) => return error(pos, "unescaped control character"),
+			Some(b'\\') => match bytes.get(pos + 1) {
+				Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => pos += 2,
+				Some(b'u') => {
+					let hex = bytes.get(pos + 2..pos + 6).unwrap_or_default();
+					if hex.len() != 4 || !hex.iter().all(u8::is_ascii_hexdigit) {
+						return error(pos, "invalid unicode escape");
+					}
+					pos += 6;
+				}
+				_ => return error(pos, "invalid escape"),
+			},
+			Some(_) => pos += 1,
+		}
+	}
+}
+
+/// Returns true if `token` matches the JSON number grammar
+pub(crate) fn is_number(token: &[u8]) -> bool {
+	let digits = |pos: &mut usize| {
+		let start = *pos;
+		while token.get(*pos).is_some_and(u8::is_ascii_digit) {
+			*pos += 1;
+		}
+		*pos - start
+	};
+	let mut pos = usize::from(token.first() == Some(&b'-'));
+	match token.get(pos) {
+		Some(b'0') => pos += 1,
+		Some(b'1'..=b'9') => {
+			digits(&mut pos);
+		}
+		_ => return false,
+	}
+	if token.get(pos) == Some(&b'.') {
+		pos += 1;
+		if digits(&mut pos) == 0 {
+			return false;
+		}
+	}
+	if let Some(b'e' | b'E') = token.get(pos) {
+		pos += 1;
+		if let Some(b'+' | b'-') = token.get(pos) {
+			pos += 1;
+		}
+		if digits(&mut pos) == 0 {
+			return false;
+		}
+	}
+	pos == token.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONObjectWriter, JsonDocument, PrettyJSONWriter};
+
+	fn write_document<W: JSONWriter>(writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("name", "中文 \"quoted\"\n");
+		object.value("empty_list", &Vec::<u8>::new());
+		let mut items = object.array("items");
+		for i in 0..5u32 {
+			let mut item = items.object();
+			item.value("id", i);
+			item.value("ratio", i as f64 / 3.0);
+			item.value("tags", &vec!["a", "b"]);
+		}
+		items.end();
+		object.object("empty").end();
+		object.value("none", crate::NULL);
+	}
+
+	#[test]
+	fn test_minify_pretty_output() {
+		let mut compact = String::new();
+		write_document(&mut compact);
+		let mut pretty = String::new();
+		write_document(&mut PrettyJSONWriter::new(&mut pretty));
+		assert_ne!(pretty, compact);
+		assert_eq!(minify_json(&pretty).unwrap(), compact);
+		assert_eq!(minify_json(&compact).unwrap(), compact);
+
+		let crlf = pretty.replace('\n', "\r\n").replace("  ", "\t");
+		assert_eq!(minify_json(&crlf).unwrap(), compact);
+	}
+
+	#[test]
+	fn test_minify_keeps_strings_and_numbers() {
+		let input =
+			" { \"{ } [ ] :\" : \" a\\\" ,\\t\\u00E4 \" ,\n \"n\": [ 1.000e+02 , -0.0, 0E-0 ] } \n";
+		assert_eq!(
+			minify_json(input).unwrap(),
+			"{\"{ } [ ] :\":\" a\\\" ,\\t\\u00E4 \",\"n\":[1.000e+02,-0.0,0E-0]}"
+		);
+		assert_eq!(minify_json(" \"top level\" ").unwrap(), "\"top level\"");
+		assert_eq!(minify_json("\t-12\n").unwrap(), "-12");
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.with_raw_writer("embedded", |writer| {
+			minify_json_into("[ true, null ]", writer).unwrap();
+		});
+		object.end();
+		assert_eq!(buffer, "{\"embedded\":[true,null]}");
+	}
+
+	#[test]
+	fn test_minify_errors() {
+		let cases = [
+			("", 0, "unexpected end of input"),
+			("  ", 2, "unexpected end of input"),
+			("[1, 2", 5, "unexpected end of input"),
+			("{\"a\" 1}", 5, "unexpected character"),
+			("{\"a\": 1,}", 8, "unexpected character"),
+			("[1 2]", 3, "unexpected character"),
+			("[1}", 2, "unexpected character"),
+			("{1: 2}", 1, "unexpected character"),
+			("[01]", 1, "invalid value"),
+			("[1.]", 1, "invalid value"),
+			("[nul]", 1, "invalid value"),
+			("[\"abc]", 1, "unterminated string"),
+			("[\"a\nb\"]", 3, "unescaped control character"),
+			("[\"\\x\"]", 2, "invalid escape"),
+			("[\"\\u12G4\"]", 2, "invalid unicode escape"),
+			("{} {}", 3, "trailing characters"),
+			("[#]", 1, "unexpected character"),
+		];
+		for (input, offset, message) in cases {
+			assert_eq!(
+				minify_json(input),
+				Err(JsonSyntaxError { offset, message }),
+				"input: {input:?}"
+			);
+		}
+
+		let mut document = JsonDocument::new(String::new());
+		let err = minify_json_into("[1,]", &mut document).unwrap_err();
+		assert_eq!(err.to_string(), "unexpected character at offset 3");
+		assert!(document.is_poisoned());
+	}
+
+	#[test]
+	fn test_prettify_matches_pretty_writer() {
+		let mut compact = String::new();
+		write_document(&mut compact);
+		for indent in ["  ", "\t", ""] {
+			let mut expected = String::new();
+			write_document(&mut PrettyJSONWriter::with_indent(&mut expected, indent));
+			assert_eq!(prettify_json(&compact, indent).unwrap(), expected);
+			// Prettifying minified output gives the same result as prettifying the pretty output
+			let minified = minify_json(&expected).unwrap();
+			assert_eq!(minified, compact);
+			assert_eq!(prettify_json(&minified, indent).unwrap(), expected);
+			assert_eq!(prettify_json(&expected, indent).unwrap(), expected);
+		}
+		assert_eq!(prettify_json(" 12 ", "  ").unwrap(), "12");
+		assert_eq!(prettify_json("[[],{}]", " ").unwrap(), "[\n [],\n {}\n]");
+	}
+
+	#[test]
+	fn test_prettify_keeps_strings_and_numbers() {
+		let input = "{\"\\u00e9\\/\\ud83d\\ude00 :\":[123456789012345678901234567890.000000000000000000001e-400,-0,1E+2],\"k\":\"a,b:{}\"}";
+		assert_eq!(
+			prettify_json(input, "  ").unwrap(),
+			"{\n  \"\\u00e9\\/\\ud83d\\ude00 :\": [\n    123456789012345678901234567890.000000000000000000001e-400,\n    -0,\n    1E+2\n  ],\n  \"k\": \"a,b:{}\"\n}"
+		);
+		assert_eq!(
+			minify_json(&prettify_json(input, "\t").unwrap()).unwrap(),
+			input
+		);
+	}
+
+	#[test]
+	fn test_prettify_errors() {
+		assert_eq!(
+			prettify_json("{\"a\":[1,2}", "  "),
+			Err(JsonSyntaxError {
+				offset: 9,
+				message: "unexpected character"
+			})
+		);
+		assert_eq!(
+			prettify_json("[\"x\" \"y\"]", "  ")
+				.unwrap_err()
+				.to_string(),
+			"unexpected character at offset 5"
+		);
+	}
+}
@@ -0,0 +1,175 @@
+use crate::{JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+use alloc::string::String;
+
+///
+/// Entry point for writing exactly one top-level JSON value to the borrowed buffer.
+///
+/// Each method consumes the root, so a second top-level value can not be started by accident.
+/// The returned `JsonRootObject` or `JsonRootArray` is used like a `JSONObjectWriter` or `JSONArrayWriter`,
+/// and `finish()` closes it and returns the buffer.
+///
+/// ```rust
+/// use json_writer::JsonRoot;
+/// let mut buffer = String::new();
+/// let mut object = JsonRoot::new(&mut buffer).object();
+/// object.value("a", 1u32);
+/// assert_eq!(object.finish(), "{\"a\":1}");
+/// ```
+///
+/// ```rust compile_fail
+/// use json_writer::JsonRoot;
+/// let mut buffer = String::new();
+/// let root = JsonRoot::new(&mut buffer);
+/// root.object();
+/// // Compile error: root has already been consumed
+/// root.object();
+/// ```
+///
+pub struct JsonRoot<'a, W: JSONWriter = String> {
+	writer: &'a mut W,
+}
+
+impl<'a, W: JSONWriter> JsonRoot<'a, W> {
+	///
+	/// Creates a new root for the given buffer. Nothing is written until a value is started.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> JsonRoot<'a, W> {
+		JsonRoot { writer }
+	}
+
+	///
+	/// Starts the top-level object. Writes '{' and returns a JsonRootObject
+	///
+	#[inline(always)]
+	pub fn object(self) -> JsonRootObject<'a, W> {
+		JsonRootObject {
+			object: JSONObjectWriter::new(self.writer),
+		}
+	}
+
+	///
+	/// Starts the top-level array. Writes '[' and returns a JsonRootArray
+	///
+	#[inline(always)]
+	pub fn array(self) -> JsonRootArray<'a, W> {
+		JsonRootArray {
+			array: JSONArrayWriter::new(self.writer),
+		}
+	}
+
+	///
+	/// Writes given value as the entire document and returns the borrowed writer.
+	///
+	#[inline(always)]
+	pub fn value<T: JSONWriterValue>(self, value: T) -> &'a mut W {
+		value.write_json(self.writer);
+		self.writer
+	}
+}
+
+///
+/// The top-level object of a `JsonRoot`. Derefs to `JSONObjectWriter`.
+///
+/// Dropping causes '}' to be appended to the buffer like dropping the object writer.
+///
+pub struct JsonRootObject<'a, W: JSONWriter = String> {
+	object: JSONObjectWriter<'a, W>,
+}
+
+impl<'a, W: JSONWriter> JsonRootObject<'a, W> {
+	///
+	/// Closes the object and returns the buffer.
+	///
+	#[inline(always)]
+	pub fn finish(self) -> &'a mut W {
+		self.object.into_writer()
+	}
+}
+
+impl<'a, W: JSONWriter> core::ops::Deref for JsonRootObject<'a, W> {
+	type Target = JSONObjectWriter<'a, W>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		&self.object
+	}
+}
+
+impl<W: JSONWriter> core::ops::DerefMut for JsonRootObject<'_, W> {
+	#[inline(always)]
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.object
+	}
+}
+
+///
+/// The top-level array of a `JsonRoot`. Derefs to `JSONArrayWriter`.
+///
+/// Dropping causes ']' to be appended to the buffer like dropping the array writer.
+///
+pub struct JsonRootArray<'a, W: JSONWriter = String> {
+	array: JSONArrayWriter<'a, W>,
+}
+
+impl<'a, W: JSONWriter> JsonRootArray<'a, W> {
+	///
+	/// Closes the array and returns the buffer.
+	///
+	#[inline(always)]
+	pub fn finish(self) -> &'a mut W {
+		self.array.into_writer()
+	}
+}
+
+impl<'a, W: JSONWriter> core::ops::Deref for JsonRootArray<'a, W> {
+	type Target = JSONArrayWriter<'a, W>;
+
+	#[inline(always)]
+	fn deref(&self) -> &Self::Target {
+		&self.array
+	}
+}
+
+impl<W: JSONWriter> core::ops::DerefMut for JsonRootArray<'_, W> {
+	#[inline(always)]
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.array
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::PrettyJSONWriter;
+
+	#[test]
+	fn test_root_object() {
+		let mut buffer = String::new();
+		let mut object = JsonRoot::new(&mut buffer).object();
+		object.value("a", 1u32);
+		object.array("b").value(true);
+		let buffer = object.finish();
+		assert_eq!(buffer, "{\"a\":1,\"b\":[true]}");
+	}
+
+	#[test]
+	fn test_root_array() {
+		let mut buffer = String::new();
+		let mut array = JsonRoot::new(&mut buffer).array();
+		array.value(1u32);
+		array.object();
+		assert_eq!(array.finish(), "[1,{}]");
+	}
+
+	#[test]
+	fn test_root_value() {
+		let mut buffer = String::new();
+		assert_eq!(JsonRoot::new(&mut buffer).value("a\n"), "\"a\\n\"");
+
+		let mut pretty_buffer = String::new();
+		let mut formatter = PrettyJSONWriter::new(&mut pretty_buffer);
+		JsonRoot::new(&mut formatter).value(&vec![1u8]);
+		assert_eq!(pretty_buffer, "[\n  1\n]");
+	}
+}
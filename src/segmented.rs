@@ -0,0 +1,212 @@
+use crate::{write_part_of_string_impl, BufferedJSONWriter, EscapeBuffer, JSONWriter};
+use alloc::vec::Vec;
+
+/// Default segment size of 1 MiB
+const DEFAULT_SEGMENT_SIZE: usize = 1 << 20;
+
+///
+/// Output buffer that stores the output in a list of fixed-size segments.
+///
+/// Unlike a `String`, growing the buffer never reallocates or copies data that has already been written,
+/// which keeps the peak memory close to the output size for very large documents.
+/// Segments are filled completely, so a multi byte character may be split between two segments.
+///
+#[derive(Debug, Clone)]
+pub struct SegmentedBuffer {
+	segments: Vec<Vec<u8>>,
+	segment_size: usize,
+	len: usize,
+}
+
+impl Default for SegmentedBuffer {
+	fn default() -> Self {
+		SegmentedBuffer::new()
+	}
+}
+
+impl SegmentedBuffer {
+	///
+	/// Creates an empty buffer with segments of 1 MiB
+	///
+	pub fn new() -> SegmentedBuffer {
+		SegmentedBuffer::with_segment_size(DEFAULT_SEGMENT_SIZE)
+	}
+
+	///
+	/// Creates an empty buffer with segments of `segment_size` bytes.
+	///
+	/// Panics if `segment_size` is 0.
+	///
+	pub fn with_segment_size(segment_size: usize) -> SegmentedBuffer {
+		assert!(segment_size > 0, "segment size must not be 0");
+		SegmentedBuffer {
+			segments: Vec::new(),
+			segment_size,
+			len: 0,
+		}
+	}
+
+	///
+	/// Returns the total number of bytes in all segments
+	///
+	#[inline(always)]
+	pub fn total_len(&self) -> usize {
+		self.len
+	}
+
+	///
+	/// Returns an iterator over the segments in output order, e.g. for vectored writes
+	///
+	pub fn segments(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ {
+		self.segments.iter().map(Vec::as_slice)
+	}
+
+	///
+	/// Writes all segments to `writer`
+	///
+	#[cfg(feature = "std")]
+	pub fn write_to<Writer: std::io::Write + ?Sized>(
+		&self,
+		writer: &mut Writer,
+	) -> Result<(), std::io::Error> {
+		for segment in self.segments() {
+			writer.write_all(segment)?;
+		}
+		Ok(())
+	}
+
+	///
+	/// Removes all segments
+	///
+	pub fn clear(&mut self) {
+		self.segments.clear();
+		self.len = 0;
+	}
+
+	fn push_bytes(&mut self, mut bytes: &[u8]) {
+		self.len += bytes.len();
+		while !bytes.is_empty() {
+			let segment = match self.segments.last_mut() {
+				Some(segment) if segment.len() < self.segment_size => segment,
+				_ => {
+					self.segments.push(Vec::with_capacity(self.segment_size));
+					self.segments.last_mut().unwrap()
+				}
+			};
+			let len = bytes.len().min(self.segment_size - segment.len());
+			segment.extend_from_slice(&bytes[..len]);
+			bytes = &bytes[len..];
+		}
+	}
+}
+
+impl EscapeBuffer for SegmentedBuffer {
+	#[inline(always)]
+	fn push_str(&mut self, value: &str) {
+		self.push_bytes(value.as_bytes());
+	}
+}
+
+impl JSONWriter for SegmentedBuffer {
+	#[inline(always)]
+	fn json_string(&mut self, value: &str) {
+		self.push_bytes(b"\"");
+		write_part_of_string_impl(self, value);
+		self.push_bytes(b"\"");
+	}
+
+	#[inline(always)]
+	fn json_string_part(&mut self, value: &str) {
+		write_part_of_string_impl(self, value);
+	}
+
+	#[inline(always)]
+	fn json_fragment(&mut self, value: &str) {
+		self.push_bytes(value.as_bytes());
+	}
+
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.len)
+	}
+}
+
+impl BufferedJSONWriter for SegmentedBuffer {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.len
+	}
+
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.write_to(out)?;
+		let len = self.len;
+		self.clear();
+		Ok(len)
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+	use super::*;
+	use crate::{JSONObjectWriter, JsonDocument};
+
+	fn write_document<W: JSONWriter>(writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		let mut array = object.array("items");
+		for i in 0..2000u32 {
+			let mut item = array.object();
+			item.value("id", i);
+			item.value("name", "中文 \"quoted\" 😀\n");
+			item.value("ratio", i as f64 / 7.0);
+			item.value("tags", &vec!["a", "b/c"]);
+		}
+		array.end();
+		object.value("long", "x\t".repeat(5000).as_str());
+	}
+
+	#[test]
+	fn test_segmented_matches_string() {
+		let mut expected = String::new();
+		write_document(&mut expected);
+
+		for segment_size in [1, 7, 4096, DEFAULT_SEGMENT_SIZE] {
+			let mut document = JsonDocument::new(SegmentedBuffer::with_segment_size(segment_size));
+			write_document(&mut document);
+			let buffer = document.into_inner();
+			assert_eq!(buffer.total_len(), expected.len());
+			assert_eq!(
+				buffer.segments().len(),
+				expected.len().div_ceil(segment_size)
+			);
+			let mut output = Vec::new();
+			buffer.write_to(&mut output).unwrap();
+			assert_eq!(output, expected.as_bytes());
+		}
+	}
+
+	#[test]
+	fn test_segments_are_never_reallocated() {
+		let mut buffer = SegmentedBuffer::with_segment_size(100);
+		let mut seen: Vec<*const u8> = Vec::new();
+		for i in 0..1000u32 {
+			let mut object = JSONObjectWriter::new(&mut buffer);
+			object.value("i", i);
+			object.value("s", "abc\"def".repeat(i as usize % 40).as_str());
+			object.end();
+			for (index, segment) in buffer.segments.iter().enumerate() {
+				assert_eq!(segment.capacity(), 100);
+				match seen.get(index) {
+					Some(&ptr) => assert_eq!(ptr, segment.as_ptr()),
+					None => seen.push(segment.as_ptr()),
+				}
+			}
+		}
+
+		let mut sink = Vec::new();
+		let len = buffer.take_buffer(&mut sink).unwrap();
+		assert_eq!(len, sink.len());
+		assert_eq!(buffer.total_len(), 0);
+		assert_eq!(buffer.segments().len(), 0);
+	}
+}
@@ -0,0 +1,895 @@
+use crate::{JSONArrayWriter, JSONObjectWriter, JSONStringWriter, JSONWriter, JSONWriterValue};
+use serde::ser::{self, Serialize};
+use std::fmt::Write;
+
+///
+/// Error of serializing a `serde::Serialize` value, either raised by the value itself or
+/// because a map key cannot be written as string.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerdeError {
+	message: String,
+}
+
+impl core::fmt::Display for SerdeError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl std::error::Error for SerdeError {}
+
+impl ser::Error for SerdeError {
+	fn custom<T: core::fmt::Display>(msg: T) -> Self {
+		SerdeError {
+			message: msg.to_string(),
+		}
+	}
+}
+
+fn key_must_be_a_string() -> SerdeError {
+	SerdeError {
+		message: "map key must be a string, integer, bool or char".to_string(),
+	}
+}
+
+///
+/// A `serde::Serializer` that writes to a `JSONWriter`, to reuse `#[derive(Serialize)]` types.
+///
+/// The output matches `serde_json::to_string` except for the escaping and number formatting of this crate:
+/// '/' is escaped, control characters use upper case hex digits, floats like `1.0` are written as `1`
+/// and exponents have no '+' sign, e.g. `1e300`.
+/// Like serde_json, integer, bool and char map keys are written as strings.
+///
+/// On error, the output is incomplete. `serde_value` and `Serde` poison the writer in that case.
+///
+pub struct JSONSerializer<'a, W: JSONWriter> {
+	writer: &'a mut W,
+}
+
+impl<'a, W: JSONWriter> JSONSerializer<'a, W> {
+	///
+	/// Creates a new serializer that writes a single value to `writer`
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> JSONSerializer<'a, W> {
+		JSONSerializer { writer }
+	}
+}
+
+///
+/// Serializes `value` into a new String
+///
+pub fn serde_to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerdeError> {
+	let mut result = String::new();
+	value.serialize(JSONSerializer::new(&mut result))?;
+	Ok(result)
+}
+
+impl<W: JSONWriter> JSONObjectWriter<'_, W> {
+	///
+	/// Serializes `value` with `JSONSerializer` and writes it with the given key.
+	///
+	/// On error, e.g. for a map with keys that can't be written as string, the value is incomplete
+	/// and the writer is poisoned, so the document should be discarded.
+	///
+	pub fn serde_value<T: Serialize + ?Sized>(
+		&mut self,
+		key: &str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		self.key(key);
+		serialize_into(self.writer, value)
+	}
+}
+
+impl<W: JSONWriter> JSONArrayWriter<'_, W> {
+	///
+	/// Serializes `value` with `JSONSerializer` and writes it as array entry.
+	///
+	/// On error the writer is poisoned, see `JSONObjectWriter::serde_value`.
+	///
+	pub fn serde_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.comma();
+		serialize_into(self.writer, value)
+	}
+}
+
+/// Serializes `value` to `writer` and poisons it on error
+fn serialize_into<W: JSONWriter, T: Serialize + ?Sized>(
+	writer: &mut W,
+	value: &T,
+) -> Result<(), SerdeError> {
+	let result = value.serialize(JSONSerializer::new(writer));
+	if result.is_err() {
+		writer.json_poison();
+	}
+	result
+}
+
+///
+/// Writes a `serde::Serialize` value, e.g. to use derived types as values of a `JSONObjectWriter`.
+///
+/// If serialization fails, the value is incomplete and the writer is poisoned, like for a writer dropped during
+/// a panic. Use `serde_value` of the object and array writers or `serde_to_string` to get the error instead,
+/// e.g. for maps whose keys may not be writable as string.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct Serde<'a, T: ?Sized>(pub &'a T);
+
+impl<T: Serialize + ?Sized> JSONWriterValue for Serde<'_, T> {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		// The writer is poisoned on error, there is nothing else to report it to
+		serialize_into(writer, self.0).ok();
+	}
+}
+
+fn write_f32<W: JSONWriter>(writer: &mut W, value: f32) {
+	if !value.is_finite() {
+		// Uses the non-finite policy of the writer
+		writer.json_number_f64(value as f64);
+		return;
+	}
+	let mut buf = ryu::Buffer::new();
+	let result = buf.format_finite(value);
+	writer.json_number_str(result.strip_suffix(".0").unwrap_or(result));
+}
+
+impl<'a, W: JSONWriter> ser::Serializer for JSONSerializer<'a, W> {
+	type Ok = ();
+	type Error = SerdeError;
+	type SerializeSeq = Compound<'a, W>;
+	type SerializeTuple = Compound<'a, W>;
+	type SerializeTupleStruct = Compound<'a, W>;
+	type SerializeTupleVariant = Compound<'a, W>;
+	type SerializeMap = Compound<'a, W>;
+	type SerializeStruct = Compound<'a, W>;
+	type SerializeStructVariant = Compound<'a, W>;
+
+	fn serialize_bool(self, v: bool) -> Result<(), SerdeError> {
+		self.writer.json_bool(v);
+		Ok(())
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<(), SerdeError> {
+		self.serialize_i64(v as i64)
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<(), SerdeError> {
+		self.serialize_i64(v as i64)
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<(), SerdeError> {
+		self.serialize_i64(v as i64)
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<(), SerdeError> {
+		self.writer.json_number_i64(v);
+		Ok(())
+	}
+
+	fn serialize_i128(self, v: i128) -> Result<(), SerdeError> {
+		self.writer.json_number_i128(v);
+		Ok(())
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<(), SerdeError> {
+		self.serialize_u64(v as u64)
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<(), SerdeError> {
+		self.serialize_u64(v as u64)
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<(), SerdeError> {
+		self.serialize_u64(v as u64)
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<(), SerdeError> {
+		self.writer.json_number_u64(v);
+		Ok(())
+	}
+
+	fn serialize_u128(self, v: u128) -> Result<(), SerdeError> {
+		self.writer.json_number_u128(v);
+		Ok(())
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<(), SerdeError> {
+		write_f32(self.writer, v);
+		Ok(())
+	}
+
+	fn serialize_f64(self, v: f64) -> Result<(), SerdeError> {
+		self.writer.json_number_f64(v);
+		Ok(())
+	}
+
+	fn serialize_char(self, v: char) -> Result<(), SerdeError> {
+		self.writer.json_string(v.encode_utf8(&mut [0u8; 4]));
+		Ok(())
+	}
+
+	fn serialize_str(self, v: &str) -> Result<(), SerdeError> {
+		self.writer.json_string(v);
+		Ok(())
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<(), SerdeError> {
+		v.write_json(self.writer);
+		Ok(())
+	}
+
+	fn serialize_none(self) -> Result<(), SerdeError> {
+		self.writer.json_null();
+		Ok(())
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerdeError> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<(), SerdeError> {
+		self.writer.json_null();
+		Ok(())
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerdeError> {
+		self.serialize_unit()
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+	) -> Result<(), SerdeError> {
+		self.serialize_str(variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		self.writer.json_begin_object();
+		self.writer.json_object_key(variant, true);
+		value.serialize(JSONSerializer::new(&mut *self.writer))?;
+		self.writer.json_end_object(false);
+		Ok(())
+	}
+
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>, SerdeError> {
+		self.writer.json_begin_array();
+		Ok(Compound::new(self.writer, false))
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>, SerdeError> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<Compound<'a, W>, SerdeError> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+		_len: usize,
+	) -> Result<Compound<'a, W>, SerdeError> {
+		self.writer.json_begin_object();
+		self.writer.json_object_key(variant, true);
+		self.writer.json_begin_array();
+		Ok(Compound::new(self.writer, true))
+	}
+
+	fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>, SerdeError> {
+		self.writer.json_begin_object();
+		Ok(Compound::new(self.writer, false))
+	}
+
+	fn serialize_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<Compound<'a, W>, SerdeError> {
+		self.serialize_map(Some(len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+		_len: usize,
+	) -> Result<Compound<'a, W>, SerdeError> {
+		self.writer.json_begin_object();
+		self.writer.json_object_key(variant, true);
+		self.writer.json_begin_object();
+		Ok(Compound::new(self.writer, true))
+	}
+
+	fn collect_str<T: core::fmt::Display + ?Sized>(self, value: &T) -> Result<(), SerdeError> {
+		// Escapes the formatted chunks directly, without formatting into a temporary String first
+		write!(JSONStringWriter::new(self.writer), "{value}")
+			.map_err(|_| ser::Error::custom("formatting failed"))
+	}
+}
+
+///
+/// State of an array or object that is being serialized by `JSONSerializer`
+///
+pub struct Compound<'a, W: JSONWriter> {
+	writer: &'a mut W,
+	empty: bool,
+	/// The array or object is nested in an object with the variant name as key
+	variant: bool,
+}
+
+impl<'a, W: JSONWriter> Compound<'a, W> {
+	#[inline(always)]
+	fn new(writer: &'a mut W, variant: bool) -> Compound<'a, W> {
+		Compound {
+			writer,
+			empty: true,
+			variant,
+		}
+	}
+
+	#[inline(always)]
+	fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.writer.json_begin_array_value(self.empty);
+		self.empty = false;
+		value.serialize(JSONSerializer::new(&mut *self.writer))
+	}
+
+	#[inline(always)]
+	fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerdeError> {
+		self.writer.json_object_key(key, self.empty);
+		self.empty = false;
+		value.serialize(JSONSerializer::new(&mut *self.writer))
+	}
+
+	#[inline(always)]
+	fn end_array(self) -> Result<(), SerdeError> {
+		self.writer.json_end_array(self.empty);
+		if self.variant {
+			self.writer.json_end_object(false);
+		}
+		Ok(())
+	}
+
+	#[inline(always)]
+	fn end_object(self) -> Result<(), SerdeError> {
+		self.writer.json_end_object(self.empty);
+		if self.variant {
+			self.writer.json_end_object(false);
+		}
+		Ok(())
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeSeq for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_array()
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeTuple for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_array()
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeTupleStruct for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_array()
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeTupleVariant for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_array()
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeMap for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+		key.serialize(KeySerializer {
+			writer: &mut *self.writer,
+			first: self.empty,
+		})?;
+		self.empty = false;
+		Ok(())
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		value.serialize(JSONSerializer::new(&mut *self.writer))
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_object()
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeStruct for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		self.field(key, value)
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_object()
+	}
+}
+
+impl<W: JSONWriter> ser::SerializeStructVariant for Compound<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		self.field(key, value)
+	}
+
+	fn end(self) -> Result<(), SerdeError> {
+		self.end_object()
+	}
+}
+
+/// Writes map keys, which must be strings in JSON
+struct KeySerializer<'a, W: JSONWriter> {
+	writer: &'a mut W,
+	first: bool,
+}
+
+impl<W: JSONWriter> KeySerializer<'_, W> {
+	#[inline(always)]
+	fn key(self, key: &str) -> Result<(), SerdeError> {
+		self.writer.json_object_key(key, self.first);
+		Ok(())
+	}
+}
+
+impl<W: JSONWriter> ser::Serializer for KeySerializer<'_, W> {
+	type Ok = ();
+	type Error = SerdeError;
+	type SerializeSeq = ser::Impossible<(), SerdeError>;
+	type SerializeTuple = ser::Impossible<(), SerdeError>;
+	type SerializeTupleStruct = ser::Impossible<(), SerdeError>;
+	type SerializeTupleVariant = ser::Impossible<(), SerdeError>;
+	type SerializeMap = ser::Impossible<(), SerdeError>;
+	type SerializeStruct = ser::Impossible<(), SerdeError>;
+	type SerializeStructVariant = ser::Impossible<(), SerdeError>;
+
+	fn serialize_bool(self, v: bool) -> Result<(), SerdeError> {
+		self.key(if v { "true" } else { "false" })
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_i128(self, v: i128) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_u128(self, v: u128) -> Result<(), SerdeError> {
+		self.key(itoa::Buffer::new().format(v))
+	}
+
+	fn serialize_f32(self, _v: f32) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_f64(self, _v: f64) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_char(self, v: char) -> Result<(), SerdeError> {
+		self.key(v.encode_utf8(&mut [0u8; 4]))
+	}
+
+	fn serialize_str(self, v: &str) -> Result<(), SerdeError> {
+		self.key(v)
+	}
+
+	fn serialize_bytes(self, _v: &[u8]) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_none(self) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_unit(self) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+	) -> Result<(), SerdeError> {
+		self.key(variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_value: &T,
+	) -> Result<(), SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeTupleStruct, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeTupleVariant, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_struct(
+		self,
+		_name: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeStruct, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeStructVariant, SerdeError> {
+		Err(key_must_be_a_string())
+	}
+
+	fn collect_str<T: core::fmt::Display + ?Sized>(self, value: &T) -> Result<(), SerdeError> {
+		self.key(&value.to_string())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONObjectWriter, PrettyJSONWriter};
+	use serde::Serialize;
+	use std::collections::BTreeMap;
+
+	#[derive(Serialize)]
+	struct Unit;
+
+	#[derive(Serialize)]
+	struct Meters(f64);
+
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	enum Event {
+		Started,
+		Progress(u8),
+		Moved(i32, i32),
+		Renamed { from: String, to: Option<String> },
+	}
+
+	#[derive(Serialize)]
+	struct Record {
+		id: u64,
+		name: String,
+		#[serde(rename = "isActive")]
+		active: bool,
+		score: f64,
+		ratio: f32,
+		tags: Vec<&'static str>,
+		parent: Option<Box<Record>>,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		missing: Option<u8>,
+		unit: Unit,
+		empty: (),
+		distance: Meters,
+		point: (i8, u16),
+		events: Vec<Event>,
+		counts: BTreeMap<u32, i128>,
+		flags: BTreeMap<char, bool>,
+		#[serde(with = "serde_bytes_as_seq")]
+		bytes: Vec<u8>,
+		letter: char,
+	}
+
+	mod serde_bytes_as_seq {
+		pub(super) fn serialize<S: serde::Serializer>(
+			bytes: &[u8],
+			serializer: S,
+		) -> Result<S::Ok, S::Error> {
+			serializer.serialize_bytes(bytes)
+		}
+	}
+
+	fn record() -> Record {
+		Record {
+			id: u64::MAX,
+			name: "ünïcödé \"quoted\" \\ \n\t 😀".to_string(),
+			active: true,
+			score: -12.375,
+			ratio: 0.1,
+			tags: vec!["a", "b c"],
+			parent: Some(Box::new(Record {
+				id: 1,
+				name: String::new(),
+				active: false,
+				score: 123456.789,
+				ratio: 3e-7,
+				tags: Vec::new(),
+				parent: None,
+				missing: None,
+				unit: Unit,
+				empty: (),
+				distance: Meters(0.5),
+				point: (-1, 1),
+				events: Vec::new(),
+				counts: BTreeMap::new(),
+				flags: BTreeMap::new(),
+				bytes: Vec::new(),
+				letter: '"',
+			})),
+			missing: None,
+			unit: Unit,
+			empty: (),
+			distance: Meters(1234.5),
+			point: (i8::MIN, u16::MAX),
+			events: vec![
+				Event::Started,
+				Event::Progress(50),
+				Event::Moved(-3, 4),
+				Event::Renamed {
+					from: "x".to_string(),
+					to: None,
+				},
+			],
+			counts: BTreeMap::from([(1, i128::MIN), (20, 0)]),
+			flags: BTreeMap::from([('ä', true), ('z', false)]),
+			bytes: vec![0, 127, 255],
+			letter: '中',
+		}
+	}
+
+	#[test]
+	fn test_serde_matches_serde_json() {
+		let record = record();
+		let expected = serde_json::to_string(&record).unwrap();
+		assert_eq!(serde_to_string(&record).unwrap(), expected);
+		assert_eq!(serde_to_string(&Vec::<Record>::new()).unwrap(), "[]");
+		assert_eq!(
+			serde_to_string(&BTreeMap::<String, u8>::new()).unwrap(),
+			"{}"
+		);
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("record", Serde(&record));
+		object.value("n", 1);
+		object.end();
+		assert_eq!(buffer, format!("{{\"record\":{expected},\"n\":1}}"));
+	}
+
+	#[test]
+	fn test_serde_pretty() {
+		let record = record();
+		let mut buffer = String::new();
+		Serde(&record).write_json(&mut PrettyJSONWriter::new(&mut buffer));
+		assert_eq!(buffer, serde_json::to_string_pretty(&record).unwrap());
+	}
+
+	#[test]
+	fn test_serde_crate_formatting() {
+		assert_eq!(serde_to_string(&"a/b").unwrap(), "\"a\\/b\"");
+		assert_eq!(serde_to_string(&1.0f64).unwrap(), "1");
+		assert_eq!(serde_to_string(&2.0f32).unwrap(), "2");
+		assert_eq!(serde_to_string(&1e300).unwrap(), "1e300");
+		assert_eq!(serde_to_string(&f64::NAN).unwrap(), "null");
+		assert_eq!(serde_to_string(&f32::INFINITY).unwrap(), "null");
+		assert_eq!(
+			serde_to_string(&format_args!("{}/{}", 1, "\"")).unwrap(),
+			"\"1\\/\\\"\""
+		);
+	}
+
+	#[test]
+	fn test_serde_errors() {
+		let map = BTreeMap::from([(vec![1u8], 1u8)]);
+		let error = serde_to_string(&map).unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"map key must be a string, integer, bool or char"
+		);
+
+		struct Failing;
+		impl Serialize for Failing {
+			fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+				Err(ser::Error::custom("not serializable"))
+			}
+		}
+		assert_eq!(
+			serde_to_string(&Failing).unwrap_err().to_string(),
+			"not serializable"
+		);
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.serde_value("a", &[1u8]).unwrap();
+		let mut array = object.array("b");
+		array.serde_value(&Some("x")).unwrap();
+		array.end();
+		object.end();
+		assert_eq!(buffer, r#"{"a":[1],"b":["x"]}"#);
+
+		#[derive(Serialize)]
+		struct Row {
+			id: u8,
+			data: BTreeMap<Vec<u8>, u8>,
+		}
+		let row = Row { id: 1, data: map };
+		let mut document = crate::JsonDocument::new(String::new());
+		{
+			let mut object = document.root().object();
+			assert_eq!(object.serde_value("row", &row), Err(error));
+		}
+		assert!(document.is_poisoned());
+
+		let mut document = crate::JsonDocument::new(String::new());
+		{
+			let mut array = document.root().array();
+			assert!(array.serde_value(&Failing).is_err());
+		}
+		assert!(document.is_poisoned());
+	}
+
+	#[test]
+	fn test_serde_poisons_on_error() {
+		struct Failing;
+		impl Serialize for Failing {
+			fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+				Err(ser::Error::custom("not serializable"))
+			}
+		}
+		let mut document = crate::JsonDocument::new(String::new());
+		document.root().object().value("a", Serde(&Failing));
+		assert!(document.is_poisoned());
+
+		let mut document = crate::JsonDocument::new(String::new());
+		{
+			let mut array = document.root().array();
+			array.value(Serde(&BTreeMap::from([((1, 2), 3)])));
+			array.value(1);
+		}
+		assert!(document.is_poisoned());
+	}
+}
@@ -0,0 +1,365 @@
+//!
+//! Streaming HTTP response bodies.
+//!
+//! Enabled with the `http-body` feature, the axum `IntoResponse` impl with the `axum` feature.
+//!
+
+use crate::{
+	write_part_of_string_impl, EscapeBuffer, JSONArrayWriter, JSONObjectWriter, JSONWriter,
+};
+use bytes::Bytes;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use http_body::{Body, Frame};
+use std::panic::AssertUnwindSafe;
+use tokio::sync::mpsc;
+
+/// Default chunk size of 64 KiB
+const DEFAULT_CHUNK_SIZE: usize = 64 << 10;
+
+/// Number of full chunks that may wait for the body to be polled before the serialization blocks
+const CHANNEL_CAPACITY: usize = 2;
+
+///
+/// Error of a `JsonStreamBody` whose serialization closure panicked.
+///
+/// The chunks sent before the panic have been delivered already, so the body is truncated.
+/// Returning the error lets the server abort the response instead of ending it normally.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SerializationPanicked;
+
+impl core::fmt::Display for SerializationPanicked {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str("the JSON serialization closure panicked")
+	}
+}
+
+impl std::error::Error for SerializationPanicked {}
+
+///
+/// `http_body::Body` whose content is written by a closure while the body is being sent.
+///
+/// The closure runs on a blocking task of the tokio runtime (`tokio::task::spawn_blocking`) and sends
+/// the output in chunks of a fixed size through a bounded channel. When the channel is full, the closure
+/// blocks until the body is polled again, so at most a few chunks are held in memory regardless of the
+/// size of the document.
+///
+/// The alternative, an incremental pull design that serializes one piece per `poll_frame`, would avoid the
+/// blocking thread, but the serialization could not be written as a plain closure or loop anymore.
+/// Running the closure on its own thread keeps any code that works with a `JSONWriter` usable as is,
+/// at the cost of occupying one thread of the blocking pool per response and a channel handoff per chunk.
+///
+/// Creating a body must happen inside a tokio runtime. When the body is dropped, e.g. because the client
+/// disconnected, the remaining output is discarded; long running closures can stop early by checking
+/// `JsonStreamWriter::is_closed`.
+///
+pub struct JsonStreamBody {
+	receiver: mpsc::Receiver<Result<Bytes, SerializationPanicked>>,
+}
+
+impl JsonStreamBody {
+	///
+	/// Streams the array written by `f` in chunks of 64 KiB
+	///
+	pub fn array<F>(f: F) -> JsonStreamBody
+	where
+		F: FnOnce(&mut JSONArrayWriter<'_, JsonStreamWriter>) + Send + 'static,
+	{
+		JsonStreamBody::new(DEFAULT_CHUNK_SIZE, move |writer| {
+			f(&mut JSONArrayWriter::new(writer));
+		})
+	}
+
+	///
+	/// Streams the object written by `f` in chunks of 64 KiB
+	///
+	pub fn object<F>(f: F) -> JsonStreamBody
+	where
+		F: FnOnce(&mut JSONObjectWriter<'_, JsonStreamWriter>) + Send + 'static,
+	{
+		JsonStreamBody::new(DEFAULT_CHUNK_SIZE, move |writer| {
+			f(&mut JSONObjectWriter::new(writer));
+		})
+	}
+
+	///
+	/// Streams the output of `f` in chunks of `chunk_size` bytes. Only the last chunk may be shorter.
+	///
+	/// `f` must write exactly one complete value. Chunks are split at byte boundaries,
+	/// so a multi byte character may be split between two chunks.
+	///
+	/// Panics if `chunk_size` is 0 or if called outside of a tokio runtime.
+	///
+	pub fn new<F>(chunk_size: usize, f: F) -> JsonStreamBody
+	where
+		F: FnOnce(&mut JsonStreamWriter) + Send + 'static,
+	{
+		assert!(chunk_size > 0, "chunk size must not be 0");
+		let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+		tokio::task::spawn_blocking(move || {
+			let mut writer = JsonStreamWriter {
+				chunk: Vec::with_capacity(chunk_size),
+				chunk_size,
+				len: 0,
+				sender: Some(sender),
+			};
+			let result = std::panic::catch_unwind(AssertUnwindSafe(|| f(&mut writer)));
+			writer.send_chunk();
+			if result.is_err() {
+				writer.send(Err(SerializationPanicked));
+			}
+		});
+		JsonStreamBody { receiver }
+	}
+}
+
+impl Body for JsonStreamBody {
+	type Data = Bytes;
+	type Error = SerializationPanicked;
+
+	fn poll_frame(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Frame<Bytes>, SerializationPanicked>>> {
+		self.receiver
+			.poll_recv(cx)
+			.map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+	}
+}
+
+#[cfg(any(test, feature = "axum"))]
+impl axum_core::response::IntoResponse for JsonStreamBody {
+	fn into_response(self) -> axum_core::response::Response {
+		(
+			[(http::header::CONTENT_TYPE, "application/json")],
+			axum_core::body::Body::new(self),
+		)
+			.into_response()
+	}
+}
+
+///
+/// Writer of a `JsonStreamBody` that sends the output to the body whenever a chunk is full.
+///
+pub struct JsonStreamWriter {
+	chunk: Vec<u8>,
+	chunk_size: usize,
+	/// Total number of bytes written
+	len: usize,
+	/// None once the body has been dropped
+	sender: Option<mpsc::Sender<Result<Bytes, SerializationPanicked>>>,
+}
+
+impl JsonStreamWriter {
+	///
+	/// Returns true if the body has been dropped, e.g. because the client disconnected.
+	///
+	/// Everything written afterwards is discarded.
+	///
+	#[inline(always)]
+	pub fn is_closed(&self) -> bool {
+		self.sender.is_none()
+	}
+
+	fn push_bytes(&mut self, mut bytes: &[u8]) {
+		self.len += bytes.len();
+		while !bytes.is_empty() {
+			let len = bytes.len().min(self.chunk_size - self.chunk.len());
+			self.chunk.extend_from_slice(&bytes[..len]);
+			bytes = &bytes[len..];
+			if self.chunk.len() == self.chunk_size {
+				self.send_chunk();
+			}
+		}
+	}
+
+	fn send_chunk(&mut self) {
+		if self.chunk.is_empty() {
+			return;
+		}
+		if self.sender.is_none() {
+			self.chunk.clear();
+			return;
+		}
+		let chunk = core::mem::replace(&mut self.chunk, Vec::with_capacity(self.chunk_size));
+		self.send(Ok(Bytes::from(chunk)));
+	}
+
+	/// Blocks until the body has room for another chunk
+	fn send(&mut self, item: Result<Bytes, SerializationPanicked>) {
+		if let Some(sender) = &self.sender {
+			if sender.blocking_send(item).is_err() {
+				self.sender = None;
+			}
+		}
+	}
+}
+
+impl EscapeBuffer for JsonStreamWriter {
+	#[inline(always)]
+	fn push_str(&mut self, value: &str) {
+		self.push_bytes(value.as_bytes());
+	}
+}
+
+impl JSONWriter for JsonStreamWriter {
+	#[inline(always)]
+	fn json_string(&mut self, value: &str) {
+		self.push_bytes(b"\"");
+		write_part_of_string_impl(self, value);
+		self.push_bytes(b"\"");
+	}
+
+	#[inline(always)]
+	fn json_string_part(&mut self, value: &str) {
+		write_part_of_string_impl(self, value);
+	}
+
+	#[inline(always)]
+	fn json_fragment(&mut self, value: &str) {
+		self.push_bytes(value.as_bytes());
+	}
+
+	#[inline(always)]
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.len)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use axum_core::response::IntoResponse;
+	use http_body_util::{BodyExt, Empty};
+	use hyper_util::rt::TokioIo;
+	use std::sync::atomic::{AtomicU64, Ordering};
+	use std::sync::Arc;
+
+	#[tokio::test]
+	async fn test_hyper_response() {
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let address = listener.local_addr().unwrap();
+		tokio::spawn(async move {
+			let (stream, _) = listener.accept().await.unwrap();
+			let service = hyper::service::service_fn(|_request| async {
+				let body = JsonStreamBody::new(16, |writer| {
+					let mut array = JSONArrayWriter::new(writer);
+					for i in 0..100u32 {
+						let mut object = array.object();
+						object.value("id", i);
+						object.value("name", "中文 \"x\"");
+					}
+				});
+				Ok::<_, core::convert::Infallible>(hyper::Response::new(body))
+			});
+			hyper::server::conn::http1::Builder::new()
+				.serve_connection(TokioIo::new(stream), service)
+				.await
+				.unwrap();
+		});
+
+		let stream = tokio::net::TcpStream::connect(address).await.unwrap();
+		let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+			.await
+			.unwrap();
+		tokio::spawn(connection);
+		let request = hyper::Request::get("/")
+			.body(Empty::<Bytes>::new())
+			.unwrap();
+		let response = sender.send_request(request).await.unwrap();
+		let body = response.into_body().collect().await.unwrap().to_bytes();
+
+		let parsed: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+		assert_eq!(parsed.len(), 100);
+		assert_eq!(parsed[99]["id"], 99);
+		assert_eq!(parsed[99]["name"], "中文 \"x\"");
+	}
+
+	#[tokio::test]
+	async fn test_into_response() {
+		let response = JsonStreamBody::object(|object| {
+			object.value("ok", true);
+		})
+		.into_response();
+		assert_eq!(
+			response.headers()[http::header::CONTENT_TYPE],
+			"application/json"
+		);
+		let body = response.into_body().collect().await.unwrap().to_bytes();
+		assert_eq!(body, "{\"ok\":true}");
+	}
+
+	#[tokio::test]
+	async fn test_bounded_memory() {
+		const CHUNK_SIZE: usize = 1024;
+		const ROWS: u64 = 4_000_000;
+		let produced = Arc::new(AtomicU64::new(0));
+		let mut body = JsonStreamBody::new(CHUNK_SIZE, {
+			let produced = produced.clone();
+			move |writer| {
+				let mut array = JSONArrayWriter::new(writer);
+				// Every row is written as 8 digits and a comma
+				for row in (10_000_000..).take(ROWS as usize) {
+					array.value(row as u64);
+					produced.fetch_add(1, Ordering::Relaxed);
+				}
+			}
+		});
+
+		let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+		assert_eq!(first.len(), CHUNK_SIZE);
+		tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+		// Chunks in the channel, the one being sent and the one being filled
+		let max_rows = ((CHANNEL_CAPACITY + 3) * CHUNK_SIZE / 9) as u64;
+		assert!(produced.load(Ordering::Relaxed) <= max_rows);
+
+		let mut total = first.len();
+		let mut last = first;
+		while let Some(frame) = body.frame().await {
+			let chunk = frame.unwrap().into_data().unwrap();
+			assert_eq!(last.len(), CHUNK_SIZE);
+			total += chunk.len();
+			last = chunk;
+		}
+		assert_eq!(produced.load(Ordering::Relaxed), ROWS);
+		assert_eq!(total, ROWS as usize * 9 + 1);
+		assert!(last.ends_with(b",13999999]"));
+	}
+
+	#[tokio::test]
+	async fn test_panic() {
+		let mut body = JsonStreamBody::new(4, |writer| {
+			let mut array = JSONArrayWriter::new(writer);
+			array.value("abcdef");
+			panic!("failed to load rows");
+		});
+		let mut output = Vec::new();
+		let error = loop {
+			match body.frame().await.unwrap() {
+				Ok(frame) => output.extend_from_slice(&frame.into_data().unwrap()),
+				Err(error) => break error,
+			}
+		};
+		assert_eq!(error, SerializationPanicked);
+		assert_eq!(output, b"[\"abcdef\"");
+		assert!(body.frame().await.is_none());
+	}
+
+	#[tokio::test]
+	async fn test_dropped_body() {
+		let (done, finished) = tokio::sync::oneshot::channel();
+		let body = JsonStreamBody::new(8, move |writer| {
+			let mut array = JSONArrayWriter::new(writer);
+			let mut rows = 0u32;
+			while !array.writer.is_closed() {
+				array.value(rows);
+				rows += 1;
+			}
+			drop(array);
+			done.send(rows).unwrap();
+		});
+		drop(body);
+		assert!(finished.await.unwrap() < 1000);
+	}
+}
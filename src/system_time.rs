@@ -0,0 +1,101 @@
+use crate::{JSONWriter, JSONWriterValue};
+use chrono::{DateTime, Datelike, SecondsFormat, Utc};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+///
+/// Writes a `SystemTime` as RFC 3339 string in UTC, e.g. `"2009-02-13T23:31:30Z"`, instead of a Unix timestamp.
+///
+/// Fractional seconds are written with 3, 6 or 9 digits as needed and omitted for whole seconds.
+/// RFC 3339 only allows the years 0000 to 9999; times outside of that range are written as `null`.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct SystemTimeRfc3339(pub SystemTime);
+
+impl SystemTimeRfc3339 {
+	/// Returns the time as `DateTime` if its year can be written in RFC 3339
+	fn date_time(self) -> Option<DateTime<Utc>> {
+		let (secs, nanos) = match self.0.duration_since(UNIX_EPOCH) {
+			Ok(since) => (i64::try_from(since.as_secs()).ok()?, since.subsec_nanos()),
+			Err(err) => {
+				let before = err.duration();
+				let secs = i64::try_from(before.as_secs()).ok()?;
+				match before.subsec_nanos() {
+					0 => (-secs, 0),
+					nanos => (-secs - 1, 1_000_000_000 - nanos),
+				}
+			}
+		};
+		DateTime::from_timestamp(secs, nanos).filter(|time| (0..=9999).contains(&time.year()))
+	}
+}
+
+impl JSONWriterValue for SystemTimeRfc3339 {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		match self.date_time() {
+			Some(time) => writer.json_string(&time.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+			None => writer.json_null(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{to_json_string, JSONObjectWriter};
+	use std::time::{Duration, UNIX_EPOCH};
+
+	#[test]
+	fn test_system_time_rfc3339() {
+		let time = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(time)),
+			"\"2009-02-13T23:31:30Z\""
+		);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(time + Duration::from_millis(500))),
+			"\"2009-02-13T23:31:30.500Z\""
+		);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(time + Duration::from_nanos(1))),
+			"\"2009-02-13T23:31:30.000000001Z\""
+		);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(UNIX_EPOCH - Duration::from_secs(1))),
+			"\"1969-12-31T23:59:59Z\""
+		);
+
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(UNIX_EPOCH - Duration::from_millis(1))),
+			"\"1969-12-31T23:59:59.999Z\""
+		);
+
+		// The years 0000 and 9999 are the limits of RFC 3339
+		let last = UNIX_EPOCH + Duration::from_secs(253_402_300_799);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(last)),
+			"\"9999-12-31T23:59:59Z\""
+		);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(last + Duration::from_secs(1))),
+			"null"
+		);
+		let first = UNIX_EPOCH - Duration::from_secs(62_167_219_200);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(first)),
+			"\"0000-01-01T00:00:00Z\""
+		);
+		assert_eq!(
+			to_json_string(SystemTimeRfc3339(first - Duration::from_nanos(1))),
+			"null"
+		);
+		// Out of range for chrono
+		let far = UNIX_EPOCH + Duration::from_secs(1 << 50);
+		assert_eq!(to_json_string(SystemTimeRfc3339(far)), "null");
+
+		let mut buffer = String::new();
+		let mut object = JSONObjectWriter::new(&mut buffer);
+		object.value("created", SystemTimeRfc3339(UNIX_EPOCH));
+		object.end();
+		assert_eq!(buffer, r#"{"created":"1970-01-01T00:00:00Z"}"#);
+	}
+}
@@ -0,0 +1,319 @@
+//!
+//! JSON log lines for `tracing` events.
+//!
+//! Enabled with the `tracing` feature.
+//!
+
+use crate::{JSONObjectWriter, JSONWriter, JSONWriterValue};
+use core::fmt::Write;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Record};
+use tracing::Event;
+
+///
+/// Writes visited `tracing` fields into a JSON object.
+///
+/// Strings, numbers and bools are written as JSON values, everything else is formatted with `Debug`
+/// straight into an escaped JSON string, without a temporary `String`.
+///
+pub struct JsonVisitor<'a, 'b, W: JSONWriter> {
+	object: &'a mut JSONObjectWriter<'b, W>,
+}
+
+impl<'a, 'b, W: JSONWriter> JsonVisitor<'a, 'b, W> {
+	///
+	/// Creates a visitor that writes every field as key:value to `object`
+	///
+	pub fn new(object: &'a mut JSONObjectWriter<'b, W>) -> JsonVisitor<'a, 'b, W> {
+		JsonVisitor { object }
+	}
+}
+
+impl<W: JSONWriter> Visit for JsonVisitor<'_, '_, W> {
+	fn record_f64(&mut self, field: &Field, value: f64) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_i64(&mut self, field: &Field, value: i64) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_u64(&mut self, field: &Field, value: u64) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_i128(&mut self, field: &Field, value: i128) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_u128(&mut self, field: &Field, value: u128) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_bool(&mut self, field: &Field, value: bool) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.object.value(field.name(), value);
+	}
+
+	fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+		let mut string = self.object.string_writer(field.name());
+		// Writing to a JSONStringWriter never fails
+		let _ = write!(string, "{value}");
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+		let mut string = self.object.string_writer(field.name());
+		// Writing to a JSONStringWriter never fails
+		let _ = write!(string, "{value:?}");
+	}
+}
+
+///
+/// Name and fields of a span, serialized once when the span is created
+/// and stored e.g. in the extensions of a `tracing-subscriber` registry.
+///
+/// Written as `{"name":"<name>","fields":{...}}`.
+///
+#[derive(Debug, Clone)]
+pub struct SpanFields {
+	name: &'static str,
+	/// Names of the fields with their values as JSON, in the order they were first recorded
+	fields: Vec<(&'static str, String)>,
+}
+
+impl SpanFields {
+	///
+	/// Serializes the fields of a new span
+	///
+	pub fn new(attributes: &Attributes<'_>) -> SpanFields {
+		let mut fields = Vec::new();
+		attributes.record(&mut FieldValues(&mut fields));
+		SpanFields {
+			name: attributes.metadata().name(),
+			fields,
+		}
+	}
+
+	///
+	/// Adds values recorded after the span was created.
+	///
+	/// A field that is recorded again keeps its position and gets the new value.
+	///
+	pub fn record(&mut self, values: &Record<'_>) {
+		values.record(&mut FieldValues(&mut self.fields));
+	}
+
+	///
+	/// Returns the name of the span
+	///
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+
+	///
+	/// Returns the names of the fields with their values as JSON
+	///
+	pub fn fields(&self) -> impl Iterator<Item = (&'static str, &str)> {
+		self.fields
+			.iter()
+			.map(|(name, value)| (*name, value.as_str()))
+	}
+}
+
+impl JSONWriterValue for &SpanFields {
+	fn write_json<W: JSONWriter>(self, writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("name", self.name);
+		let mut fields = object.object("fields");
+		for (name, value) in &self.fields {
+			fields.with_raw_writer(name, |writer| writer.json_fragment(value));
+		}
+	}
+}
+
+/// Serializes every visited field on its own, replacing the value of a field that was recorded before
+struct FieldValues<'a>(&'a mut Vec<(&'static str, String)>);
+
+impl FieldValues<'_> {
+	fn set<T: JSONWriterValue>(&mut self, field: &Field, value: T) {
+		let name = field.name();
+		let index = self
+			.0
+			.iter()
+			.position(|(existing, _)| *existing == name)
+			.unwrap_or_else(|| {
+				self.0.push((name, String::new()));
+				self.0.len() - 1
+			});
+		let json = &mut self.0[index].1;
+		json.clear();
+		value.write_json(json);
+	}
+}
+
+impl Visit for FieldValues<'_> {
+	fn record_f64(&mut self, field: &Field, value: f64) {
+		self.set(field, value);
+	}
+
+	fn record_i64(&mut self, field: &Field, value: i64) {
+		self.set(field, value);
+	}
+
+	fn record_u64(&mut self, field: &Field, value: u64) {
+		self.set(field, value);
+	}
+
+	fn record_i128(&mut self, field: &Field, value: i128) {
+		self.set(field, value);
+	}
+
+	fn record_u128(&mut self, field: &Field, value: u128) {
+		self.set(field, value);
+	}
+
+	fn record_bool(&mut self, field: &Field, value: bool) {
+		self.set(field, value);
+	}
+
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.set(field, value);
+	}
+
+	fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+		self.set(field, format_args!("{value}"));
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+		self.set(field, format_args!("{value:?}"));
+	}
+}
+
+///
+/// Writes an event as a single JSON object:
+///
+/// `{"timestamp":...,"level":"INFO","target":"app","spans":[...],"fields":{"message":"...",...}}`
+///
+/// `spans` are the spans the event is in, from the outermost to the innermost.
+/// The `spans` key is omitted when there are none.
+///
+pub fn format_event<'a, W, T, S>(writer: &mut W, timestamp: T, spans: S, event: &Event<'_>)
+where
+	W: JSONWriter,
+	T: JSONWriterValue,
+	S: IntoIterator<Item = &'a SpanFields>,
+{
+	let metadata = event.metadata();
+	let mut object = JSONObjectWriter::new(writer);
+	object.value("timestamp", timestamp);
+	object.value("level", metadata.level().as_str());
+	object.value("target", metadata.target());
+	let mut spans = spans.into_iter().peekable();
+	if spans.peek().is_some() {
+		let mut array = object.array("spans");
+		for span in spans {
+			array.value(span);
+		}
+	}
+	let mut fields = object.object("fields");
+	event.record(&mut JsonVisitor::new(&mut fields));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{Arc, Mutex};
+	use tracing::span;
+	use tracing_subscriber::layer::{Context, SubscriberExt};
+	use tracing_subscriber::registry::LookupSpan;
+	use tracing_subscriber::Layer;
+
+	/// Collects formatted events with a fixed timestamp
+	struct JsonLayer {
+		lines: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for JsonLayer {
+		fn on_new_span(&self, attributes: &Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+			let span = ctx.span(id).unwrap();
+			span.extensions_mut().insert(SpanFields::new(attributes));
+		}
+
+		fn on_record(&self, id: &span::Id, values: &Record<'_>, ctx: Context<'_, S>) {
+			let span = ctx.span(id).unwrap();
+			span.extensions_mut()
+				.get_mut::<SpanFields>()
+				.unwrap()
+				.record(values);
+		}
+
+		fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+			let mut line = String::new();
+			let spans: Vec<SpanFields> = ctx
+				.event_scope(event)
+				.into_iter()
+				.flat_map(|scope| scope.from_root())
+				.map(|span| span.extensions().get::<SpanFields>().unwrap().clone())
+				.collect();
+			format_event(&mut line, 1234u32, &spans, event);
+			self.lines.lock().unwrap().push(line);
+		}
+	}
+
+	#[derive(Debug)]
+	#[allow(dead_code)]
+	struct Point {
+		x: i32,
+		label: &'static str,
+	}
+
+	#[test]
+	fn test_format_event() {
+		let lines = Arc::new(Mutex::new(Vec::new()));
+		let subscriber = tracing_subscriber::registry().with(JsonLayer {
+			lines: lines.clone(),
+		});
+		tracing::subscriber::with_default(subscriber, || {
+			tracing::info!(target: "app", "no spans");
+			let outer = tracing::info_span!(
+				"request",
+				id = 7u64,
+				path = "/a\"b",
+				user = tracing::field::Empty,
+				attempt = tracing::field::Empty
+			);
+			let _outer = outer.enter();
+			outer.record("attempt", 1u8);
+			outer.record("user", "ann");
+			outer.record("attempt", 2u8);
+			// Records nothing
+			outer.record("user", tracing::field::Empty);
+			let inner = tracing::debug_span!("step");
+			let _inner = inner.enter();
+			tracing::warn!(
+				target: "app::db",
+				count = -3i64,
+				ok = true,
+				ratio = 0.5f64,
+				name = "x\ny",
+				point = ?Point { x: 1, label: "q\"" },
+				"done {} of {}", 1, 2
+			);
+		});
+		let lines = lines.lock().unwrap();
+		assert_eq!(
+			lines[0],
+			r#"{"timestamp":1234,"level":"INFO","target":"app","fields":{"message":"no spans"}}"#
+		);
+		assert_eq!(
+			lines[1],
+			r#"{"timestamp":1234,"level":"WARN","target":"app::db","spans":[{"name":"request","fields":{"id":7,"path":"\/a\"b","attempt":2,"user":"ann"}},{"name":"step","fields":{}}],"fields":{"message":"done 1 of 2","count":-3,"ok":true,"ratio":0.5,"name":"x\ny","point":"Point { x: 1, label: \"q\\\"\" }"}}"#
+		);
+		for line in lines.iter() {
+			crate::dev::assert_valid_json(line);
+		}
+	}
+}
@@ -0,0 +1,313 @@
+use crate::{BufferedJSONWriter, JSONWriter};
+
+///
+/// Error returned by `ValidatingJSONWriter::check_complete`
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IncompleteDocument {
+	/// Number of objects, arrays and strings that were opened but not closed
+	pub depth: usize,
+	/// Number of complete top-level values
+	pub values: usize,
+}
+
+impl core::fmt::Display for IncompleteDocument {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		if self.depth > 0 {
+			write!(f, "{} unclosed containers", self.depth)
+		} else {
+			write!(
+				f,
+				"expected a single top-level value, found {}",
+				self.values
+			)
+		}
+	}
+}
+
+impl core::error::Error for IncompleteDocument {}
+
+///
+/// Wraps a writer and keeps track of the nesting depth and the number of top-level values,
+/// e.g. to catch a writer that was leaked (e.g. with `mem::forget`) instead of being ended.
+///
+/// ```rust
+/// use json_writer::{JSONObjectWriter, ValidatingJSONWriter};
+/// let mut writer = ValidatingJSONWriter::new(String::new());
+/// JSONObjectWriter::new(&mut writer).value("a", 1u8);
+/// writer.checkpoint().unwrap();
+/// assert_eq!(writer.into_inner(), "{\"a\":1}");
+/// ```
+///
+pub struct ValidatingJSONWriter<W: JSONWriter> {
+	inner: W,
+	depth: usize,
+	values: usize,
+}
+
+impl<W: JSONWriter> ValidatingJSONWriter<W> {
+	///
+	/// Creates a new writer that validates everything written to `inner`.
+	///
+	/// Output that has already been written to `inner` is not taken into account.
+	///
+	pub fn new(inner: W) -> ValidatingJSONWriter<W> {
+		ValidatingJSONWriter {
+			inner,
+			depth: 0,
+			values: 0,
+		}
+	}
+
+	///
+	/// Returns the number of objects, arrays and strings that are currently open
+	///
+	#[inline(always)]
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	///
+	/// Returns an error unless all containers have been closed and exactly one top-level value was written.
+	///
+	pub fn check_complete(&self) -> Result<(), IncompleteDocument> {
+		if self.depth == 0 && self.values == 1 {
+			Ok(())
+		} else {
+			Err(IncompleteDocument {
+				depth: self.depth,
+				values: self.values,
+			})
+		}
+	}
+
+	///
+	/// Like `check_complete`, but panics instead of returning the error in debug builds.
+	///
+	#[track_caller]
+	pub fn checkpoint(&self) -> Result<(), IncompleteDocument> {
+		let result = self.check_complete();
+		#[cfg(debug_assertions)]
+		if let Err(err) = result {
+			panic!("incomplete JSON document: {err}");
+		}
+		result
+	}
+
+	///
+	/// Returns the wrapped writer
+	///
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+
+	#[inline(always)]
+	fn open(&mut self) {
+		self.depth += 1;
+	}
+
+	#[inline(always)]
+	fn close(&mut self) {
+		self.depth = self.depth.saturating_sub(1);
+		self.complete_value();
+	}
+
+	#[inline(always)]
+	fn complete_value(&mut self) {
+		if self.depth == 0 {
+			self.values += 1;
+		}
+	}
+}
+
+impl<W: JSONWriter> JSONWriter for ValidatingJSONWriter<W> {
+	forward_json_writer!(|this| this.inner;
+		json_string_part, json_begin_array_value, json_object_key, json_poison, json_reserve,
+		json_debug_len,
+	);
+
+	fn json_null(&mut self) {
+		self.inner.json_null();
+		self.complete_value();
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		self.inner.json_bool(value);
+		self.complete_value();
+	}
+
+	fn json_string(&mut self, value: &str) {
+		self.inner.json_string(value);
+		self.complete_value();
+	}
+
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		self.inner.json_pre_escaped_string(value);
+		self.complete_value();
+	}
+
+	fn json_escaped_string(&mut self, value: &str, escaped: &str) {
+		self.inner.json_escaped_string(value, escaped);
+		self.complete_value();
+	}
+
+	fn json_number_f64(&mut self, value: f64) {
+		self.inner.json_number_f64(value);
+		self.complete_value();
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		self.inner.json_number_str(value);
+		self.complete_value();
+	}
+
+	fn json_number_i64(&mut self, value: i64) {
+		self.inner.json_number_i64(value);
+		self.complete_value();
+	}
+
+	fn json_number_u64(&mut self, value: u64) {
+		self.inner.json_number_u64(value);
+		self.complete_value();
+	}
+
+	fn json_number_i128(&mut self, value: i128) {
+		self.inner.json_number_i128(value);
+		self.complete_value();
+	}
+
+	fn json_number_u128(&mut self, value: u128) {
+		self.inner.json_number_u128(value);
+		self.complete_value();
+	}
+
+	fn json_begin_object(&mut self) {
+		self.inner.json_begin_object();
+		self.open();
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.inner.json_end_object(empty);
+		self.close();
+	}
+
+	fn json_begin_array(&mut self) {
+		self.inner.json_begin_array();
+		self.open();
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.inner.json_end_array(empty);
+		self.close();
+	}
+
+	fn json_begin_string(&mut self) {
+		self.inner.json_begin_string();
+		self.open();
+	}
+
+	fn json_end_string(&mut self) {
+		self.inner.json_end_string();
+		self.close();
+	}
+
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		self.inner.json_pre_escaped_object_key(key, first);
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		self.inner.json_fragment(value);
+		// Fragments outside of the hooks above are raw values or, between concatenated values, whitespace
+		if !value.trim_start_matches([' ', '\t', '\n', '\r']).is_empty() {
+			self.complete_value();
+		}
+	}
+}
+
+impl<W: BufferedJSONWriter> BufferedJSONWriter for ValidatingJSONWriter<W> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.inner.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.inner.take_buffer(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		ConcatJSONWriter, JSONArrayWriter, JSONObjectWriter, JSONStringWriter, PrettyJSONWriter,
+		NULL,
+	};
+	use std::mem::ManuallyDrop;
+
+	#[test]
+	fn test_complete_document() {
+		let mut buffer = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut buffer);
+		let mut writer = ValidatingJSONWriter::new(&mut pretty);
+		assert_eq!(
+			writer.check_complete(),
+			Err(IncompleteDocument {
+				depth: 0,
+				values: 0
+			})
+		);
+		{
+			let mut object = JSONObjectWriter::new(&mut writer);
+			let mut array = object.array("list");
+			array.value(1u8);
+			array.value("two");
+			let mut text = array.string_writer();
+			text.push_str("three");
+			text.end();
+			array.end();
+			object.value("empty", &Vec::<u8>::new());
+		}
+		assert_eq!(writer.depth(), 0);
+		assert_eq!(writer.checkpoint(), Ok(()));
+	}
+
+	#[test]
+	fn test_unclosed_object() {
+		let mut writer = ValidatingJSONWriter::new(String::new());
+		{
+			// Leaked writers never close their objects
+			let mut object = ManuallyDrop::new(JSONObjectWriter::new(&mut writer));
+			let _nested = ManuallyDrop::new(object.object("nested"));
+		}
+		assert_eq!(writer.depth(), 2);
+		let err = writer.check_complete().unwrap_err();
+		assert_eq!(
+			err,
+			IncompleteDocument {
+				depth: 2,
+				values: 0
+			}
+		);
+		assert_eq!(err.to_string(), "2 unclosed containers");
+		let result = std::panic::catch_unwind(|| writer.checkpoint());
+		if cfg!(debug_assertions) {
+			assert!(result.is_err());
+		} else {
+			assert!(result.unwrap().is_err());
+		}
+	}
+
+	#[test]
+	fn test_multiple_top_level_values() {
+		let mut writer = ValidatingJSONWriter::new(String::new());
+		JSONArrayWriter::new(&mut writer).value(1u8);
+		JSONStringWriter::new(&mut writer).push_str("second");
+		ConcatJSONWriter::new(&mut writer).write_value(NULL);
+		assert_eq!(
+			writer.check_complete().unwrap_err().to_string(),
+			"expected a single top-level value, found 3"
+		);
+	}
+}
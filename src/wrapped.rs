@@ -0,0 +1,491 @@
+use crate::{BufferedJSONWriter, JSONArrayWriter, JSONObjectWriter, JSONWriter, JSONWriterValue};
+use alloc::string::String;
+
+///
+/// Writes a single top-level value between a fixed prefix and suffix, e.g. an anti-XSSI prefix like `)]}',\n`
+/// or a JSONP callback call.
+///
+/// The prefix is written when the value is started and the suffix when the document is finished or dropped,
+/// exactly once and only if a value was started. Like the other writers, nothing is appended when dropped during a panic.
+/// Prefix and suffix are restricted to printable ASCII without '<' but otherwise written as is, so they must not
+/// contain untrusted input; use `jsonp` for callback names.
+///
+/// ```rust
+/// use json_writer::WrappedDocument;
+/// let mut buffer = String::new();
+/// let mut document = WrappedDocument::new(&mut buffer).prefix(")]}',\n");
+/// document.object().value("a", 1);
+/// document.finish();
+/// assert_eq!(buffer, ")]}',\n{\"a\":1}");
+/// ```
+///
+pub struct WrappedDocument<'a, W: JSONWriter = String> {
+	/// `None` once the document is finished
+	writer: Option<WrappedWriter<'a, W>>,
+	prefix: &'a str,
+	/// JSONP callback, written after the prefix as `/**/callback(`
+	callback: Option<&'a str>,
+	suffix: &'a str,
+	started: bool,
+}
+
+///
+/// Writer for the value of a `WrappedDocument`.
+///
+/// Passes everything on to the borrowed writer. In JSONP documents, U+2028 and U+2029 are escaped as well,
+/// since JavaScript engines before ES2019 don't allow them in string literals. Object keys that contain them
+/// are written without the layout of the borrowed writer, e.g. without the indentation of `PrettyJSONWriter`.
+///
+pub struct WrappedWriter<'a, W: JSONWriter = String> {
+	writer: &'a mut W,
+	escape_line_terminators: bool,
+}
+
+///
+/// Error of `WrappedDocument::jsonp` for callback names that are not a dotted path of JavaScript identifiers
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidCallbackName;
+
+impl core::fmt::Display for InvalidCallbackName {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str("JSONP callback name must be a dotted path of identifiers like `app.onData`")
+	}
+}
+
+impl core::error::Error for InvalidCallbackName {}
+
+/// Returns true if `name` only consists of identifiers made of ASCII letters, digits, '_' and '$', separated by '.'
+fn is_safe_callback_name(name: &str) -> bool {
+	name.split('.').all(|identifier| {
+		let mut bytes = identifier.bytes();
+		matches!(bytes.next(), Some(b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'$'))
+			&& bytes.all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'$')
+	})
+}
+
+impl<'a, W: JSONWriter> WrappedDocument<'a, W> {
+	///
+	/// Creates a new document without prefix and suffix. Nothing is written until a value is started.
+	///
+	#[inline(always)]
+	pub fn new(writer: &'a mut W) -> WrappedDocument<'a, W> {
+		WrappedDocument {
+			writer: Some(WrappedWriter {
+				writer,
+				escape_line_terminators: false,
+			}),
+			prefix: "",
+			callback: None,
+			suffix: "",
+			started: false,
+		}
+	}
+
+	///
+	/// Creates a new document that wraps the value in a JSONP call of `callback`, e.g. `/**/onData({...});`.
+	///
+	/// Returns an error unless `callback` is a dotted path of identifiers made of ASCII letters, digits, '_' and '$',
+	/// which prevents injecting script through the callback name. The leading comment prevents the response
+	/// from being interpreted as another content type by some plugins, and is written after a custom prefix.
+	/// U+2028 and U+2029 in strings are escaped, so the output is valid JavaScript in engines before ES2019.
+	///
+	/// ```rust
+	/// use json_writer::WrappedDocument;
+	/// let mut buffer = String::new();
+	/// WrappedDocument::jsonp(&mut buffer, "app.onData").unwrap().value([1, 2].as_slice());
+	/// assert_eq!(buffer, "/**/app.onData([1,2]);");
+	/// assert!(WrappedDocument::jsonp(&mut buffer, "alert(1);f").is_err());
+	/// ```
+	///
+	pub fn jsonp(
+		writer: &'a mut W,
+		callback: &'a str,
+	) -> Result<WrappedDocument<'a, W>, InvalidCallbackName> {
+		if !is_safe_callback_name(callback) {
+			return Err(InvalidCallbackName);
+		}
+		Ok(WrappedDocument {
+			writer: Some(WrappedWriter {
+				writer,
+				escape_line_terminators: true,
+			}),
+			prefix: "",
+			callback: Some(callback),
+			suffix: "",
+			started: false,
+		})
+	}
+
+	///
+	/// Sets the text written before the value, and before the JSONP callback.
+	///
+	/// Panics unless `prefix` only contains printable ASCII characters and whitespace other than '<',
+	/// so it cannot start a tag in an HTML context.
+	///
+	#[inline(always)]
+	pub fn prefix(mut self, prefix: &'a str) -> WrappedDocument<'a, W> {
+		assert_safe_text(prefix);
+		self.prefix = prefix;
+		self
+	}
+
+	///
+	/// Sets the text written after the value, and after the closing `);` of the JSONP callback.
+	///
+	/// Panics unless `suffix` only contains printable ASCII characters and whitespace other than '<'.
+	///
+	#[inline(always)]
+	pub fn suffix(mut self, suffix: &'a str) -> WrappedDocument<'a, W> {
+		assert_safe_text(suffix);
+		self.suffix = suffix;
+		self
+	}
+
+	/// Writes the prefix and the start of the JSONP call and returns the writer for the value
+	fn start(&mut self) -> &mut WrappedWriter<'a, W> {
+		assert!(!self.started, "WrappedDocument: value started twice");
+		self.started = true;
+		let wrapped = self.writer.as_mut().unwrap();
+		wrapped.writer.json_fragment(self.prefix);
+		if let Some(callback) = self.callback {
+			wrapped.writer.json_fragment("/**/");
+			wrapped.writer.json_fragment(callback);
+			wrapped.writer.json_fragment("(");
+		}
+		wrapped
+	}
+
+	///
+	/// Writes the prefix and starts the object. The suffix is written when the document is finished or dropped.
+	///
+	/// Panics if a value has already been started.
+	///
+	pub fn object(&mut self) -> JSONObjectWriter<'_, WrappedWriter<'a, W>> {
+		JSONObjectWriter::new(self.start())
+	}
+
+	///
+	/// Writes the prefix and starts the array. The suffix is written when the document is finished or dropped.
+	///
+	/// Panics if a value has already been started.
+	///
+	pub fn array(&mut self) -> JSONArrayWriter<'_, WrappedWriter<'a, W>> {
+		JSONArrayWriter::new(self.start())
+	}
+
+	///
+	/// Writes the prefix, the value and the suffix and returns the borrowed writer.
+	///
+	/// Panics if a value has already been started.
+	///
+	pub fn value<T: JSONWriterValue>(mut self, value: T) -> &'a mut W {
+		value.write_json(self.start());
+		self.finish()
+	}
+
+	///
+	/// Writes the suffix if a value was started and returns the borrowed writer.
+	///
+	pub fn finish(mut self) -> &'a mut W {
+		let writer = self.writer.take().unwrap().writer;
+		self.write_suffix(writer);
+		writer
+	}
+
+	#[inline(always)]
+	fn write_suffix(&self, writer: &mut W) {
+		if self.started {
+			if self.callback.is_some() {
+				writer.json_fragment(");");
+			}
+			writer.json_fragment(self.suffix);
+		}
+	}
+}
+
+/// Panics unless `text` only contains printable ASCII characters and whitespace other than '<'
+fn assert_safe_text(text: &str) {
+	assert!(
+		text.bytes()
+			.all(|b| (b.is_ascii_graphic() || b.is_ascii_whitespace()) && b != b'<'),
+		"WrappedDocument: prefix and suffix must be printable ASCII without '<'"
+	);
+}
+
+impl<W: JSONWriter> Drop for WrappedDocument<'_, W> {
+	fn drop(&mut self) {
+		// Nothing to do once finished
+		if let Some(wrapped) = self.writer.take() {
+			if crate::thread_panicking() {
+				wrapped.writer.json_poison();
+				return;
+			}
+			self.write_suffix(wrapped.writer);
+		}
+	}
+}
+
+/// Returns true if `value` contains U+2028 or U+2029
+#[inline(always)]
+fn has_line_terminator(value: &str) -> bool {
+	// Both are encoded as E2 80 A8 and E2 80 A9, other text rarely contains E2
+	value.as_bytes().contains(&0xE2) && value.contains(['\u{2028}', '\u{2029}'])
+}
+
+impl<W: JSONWriter> WrappedWriter<'_, W> {
+	#[inline(always)]
+	fn needs_escaping(&self, value: &str) -> bool {
+		self.escape_line_terminators && has_line_terminator(value)
+	}
+
+	/// Writes `value` with `write`, replacing U+2028 and U+2029 by their escape sequences
+	fn write_escaped(&mut self, value: &str, write: fn(&mut W, &str)) {
+		let mut rest = value;
+		while let Some(index) = rest.find(['\u{2028}', '\u{2029}']) {
+			write(self.writer, &rest[..index]);
+			self.writer
+				.json_fragment(if rest[index..].starts_with('\u{2028}') {
+					"\\u2028"
+				} else {
+					"\\u2029"
+				});
+			rest = &rest[index + '\u{2028}'.len_utf8()..];
+		}
+		write(self.writer, rest);
+	}
+
+	fn write_string(&mut self, value: &str) {
+		self.writer.json_begin_string();
+		self.write_escaped(value, W::json_string_part);
+		self.writer.json_end_string();
+	}
+}
+
+impl<W: JSONWriter> JSONWriter for WrappedWriter<'_, W> {
+	forward_json_writer!(|this| this.writer;
+		json_null, json_bool, json_number_f64, json_numbers_f64, json_number_str, json_number_i64, json_number_u64,
+		json_number_i128, json_number_u128, json_begin_object, json_end_object, json_begin_array,
+		json_end_array, json_begin_string, json_end_string, json_begin_array_value, json_poison,
+		json_reserve, json_debug_len,
+	);
+
+	#[inline(always)]
+	fn json_string(&mut self, value: &str) {
+		if self.needs_escaping(value) {
+			self.write_string(value);
+		} else {
+			self.writer.json_string(value);
+		}
+	}
+
+	#[inline(always)]
+	fn json_pre_escaped_string(&mut self, value: &str) {
+		if self.needs_escaping(value) {
+			self.write_string(value);
+		} else {
+			self.writer.json_pre_escaped_string(value);
+		}
+	}
+
+	#[inline(always)]
+	fn json_escaped_string(&mut self, value: &str, escaped: &str) {
+		if self.needs_escaping(value) {
+			self.write_string(value);
+		} else {
+			self.writer.json_escaped_string(value, escaped);
+		}
+	}
+
+	#[inline(always)]
+	fn json_string_part(&mut self, value: &str) {
+		if self.needs_escaping(value) {
+			self.write_escaped(value, W::json_string_part);
+		} else {
+			self.writer.json_string_part(value);
+		}
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		if self.needs_escaping(key) {
+			if !first {
+				self.writer.json_fragment(",");
+			}
+			self.write_string(key);
+			self.writer.json_fragment(":");
+		} else {
+			self.writer.json_object_key(key, first);
+		}
+	}
+
+	#[inline(always)]
+	fn json_pre_escaped_object_key(&mut self, key: &str, first: bool) {
+		if self.needs_escaping(key) {
+			self.json_object_key(key, first);
+		} else {
+			self.writer.json_pre_escaped_object_key(key, first);
+		}
+	}
+
+	/// Raw JSON only contains U+2028 and U+2029 inside of strings, where the escape sequences have the same meaning
+	#[inline(always)]
+	fn json_fragment(&mut self, value: &str) {
+		if self.needs_escaping(value) {
+			self.write_escaped(value, W::json_fragment);
+		} else {
+			self.writer.json_fragment(value);
+		}
+	}
+}
+
+impl<W: BufferedJSONWriter> BufferedJSONWriter for WrappedWriter<'_, W> {
+	#[inline(always)]
+	fn buffer_len(&self) -> usize {
+		self.writer.buffer_len()
+	}
+
+	#[inline(always)]
+	#[cfg(feature = "std")]
+	fn take_buffer(&mut self, out: &mut dyn std::io::Write) -> Result<usize, std::io::Error> {
+		self.writer.take_buffer(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_jsonp() {
+		let mut buffer = String::new();
+		{
+			let mut document = WrappedDocument::jsonp(&mut buffer, "$jq_1.cb").unwrap();
+			let mut object = document.object();
+			object.value("a", "x</script>");
+			object.array("b").value(1);
+		}
+		let call = buffer.strip_prefix("/**/$jq_1.cb(").unwrap();
+		let argument = call.strip_suffix(");").unwrap();
+		let parsed: serde_json::Value = serde_json::from_str(argument).unwrap();
+		assert_eq!(parsed["a"], "x</script>");
+		assert_eq!(parsed["b"][0], 1);
+		// Escaping '/' keeps the value from closing an inline script tag
+		assert!(!argument.contains("</"));
+	}
+
+	#[test]
+	fn test_prefix_and_suffix() {
+		let mut buffer = String::new();
+		let mut document = WrappedDocument::new(&mut buffer).prefix(")]}',\n");
+		document.array().value(true);
+		assert_eq!(document.finish(), ")]}',\n[true]");
+
+		let mut buffer = String::new();
+		WrappedDocument::new(&mut buffer)
+			.prefix("(")
+			.suffix(")\n")
+			.value(crate::NULL);
+		assert_eq!(buffer, "(null)\n");
+
+		// Nothing is written without a value
+		let mut buffer = String::new();
+		WrappedDocument::new(&mut buffer).prefix("(").suffix(")");
+		assert_eq!(buffer, "");
+	}
+
+	#[test]
+	fn test_jsonp_prefix_and_suffix() {
+		// The comment and the call stay intact with a custom prefix and suffix
+		let mut buffer = String::new();
+		WrappedDocument::jsonp(&mut buffer, "cb")
+			.unwrap()
+			.prefix(")]}'\n")
+			.suffix("\n")
+			.value(1);
+		assert_eq!(buffer, ")]}'\n/**/cb(1);\n");
+	}
+
+	#[test]
+	#[should_panic(expected = "printable ASCII without '<'")]
+	fn test_prefix_with_tag() {
+		let mut buffer = String::new();
+		drop(WrappedDocument::new(&mut buffer).prefix("<script>"));
+	}
+
+	#[test]
+	#[should_panic(expected = "printable ASCII without '<'")]
+	fn test_suffix_with_control_character() {
+		let mut buffer = String::new();
+		drop(WrappedDocument::new(&mut buffer).suffix("\u{0}"));
+	}
+
+	#[test]
+	fn test_jsonp_line_terminators() {
+		let text = "a\u{2028}b\u{2029}c\u{2028}";
+		let mut buffer = String::new();
+		{
+			let mut document = WrappedDocument::jsonp(&mut buffer, "cb").unwrap();
+			let mut object = document.object();
+			object.value("value", text);
+			object.value(text, 1);
+			object.string_writer("part").push_str(text);
+			object.checked_raw_value("raw", "[\"\u{2029}\"]").unwrap();
+			object.array("items").value(text);
+		}
+		assert!(!buffer.contains(['\u{2028}', '\u{2029}']), "{buffer}");
+		let argument = buffer
+			.strip_prefix("/**/cb(")
+			.and_then(|call| call.strip_suffix(");"))
+			.unwrap();
+		assert!(argument.starts_with("{\"value\":\"a\\u2028b\\u2029c\\u2028\""));
+		let parsed: serde_json::Value = serde_json::from_str(argument).unwrap();
+		assert_eq!(parsed["value"], text);
+		assert_eq!(parsed[text], 1);
+		assert_eq!(parsed["part"], text);
+		assert_eq!(parsed["raw"][0], "\u{2029}");
+		assert_eq!(parsed["items"][0], text);
+
+		// Plain JSON may contain them as is
+		let mut buffer = String::new();
+		WrappedDocument::new(&mut buffer).value(text);
+		assert_eq!(buffer, format!("\"{text}\""));
+	}
+
+	#[test]
+	fn test_invalid_callback_names() {
+		let mut buffer = String::new();
+		for name in [
+			"",
+			"1cb",
+			"cb)",
+			"alert(1);cb",
+			"a b",
+			"a..b",
+			".a",
+			"a.",
+			"cb//",
+			"<script>",
+			"ünïcödé",
+			"a\u{2028}b",
+		] {
+			assert_eq!(
+				WrappedDocument::jsonp(&mut buffer, name).err(),
+				Some(InvalidCallbackName),
+				"{name}"
+			);
+		}
+		assert_eq!(buffer, "");
+		for name in ["cb", "_", "$", "a.b.c", "jQuery123_456", "A$1"] {
+			assert!(WrappedDocument::jsonp(&mut buffer, name).is_ok(), "{name}");
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "value started twice")]
+	fn test_value_started_twice() {
+		let mut buffer = String::new();
+		let mut document = WrappedDocument::new(&mut buffer);
+		document.object();
+		document.array();
+	}
+}
@@ -0,0 +1,389 @@
+use crate::{write_part_of_string, JSONWriter};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+///
+/// Writes block-style YAML instead of JSON, so the same code can generate both formats.
+///
+/// Non-empty objects and arrays are written as block mappings and sequences indented by two spaces,
+/// empty ones as `{}` and `[]`. Strings and keys are written as plain scalars unless they could be
+/// read as something else, e.g. `yes`, `null`, `1e2` or `a: b`, in which case they are written as
+/// double-quoted scalars with JSON escapes, except for '/' which is not escaped. The decision is conservative:
+/// every string starting with a digit, a sign or an indicator character is quoted, as is the merge key `<<`.
+/// Keys longer than 1024 characters, the limit of implicit keys, are written as explicit `? key` entries.
+///
+/// A newline is written after each top-level value. Raw fragments are written as is,
+/// so JSON fragments end up as YAML flow collections.
+///
+pub struct YamlWriter<'a> {
+	/// Result
+	pub buffer: &'a mut String,
+	/// Open mappings and sequences
+	stack: Vec<Frame>,
+	/// What has been written in front of the next value
+	slot: Slot,
+	/// Content of the string written with `json_begin_string` and `json_string_part`
+	string: String,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Slot {
+	/// Start of the document
+	Root,
+	/// `key:`
+	Key,
+	/// `- `
+	Item,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Frame {
+	/// Column of the keys or dashes of the entries
+	indent: usize,
+	/// Slot in which the mapping or sequence is the value
+	slot: Slot,
+}
+
+/// Maximum number of characters of a key written as `key: value`, including quotes and escapes.
+/// Longer keys need an explicit `? key`.
+const MAX_IMPLICIT_KEY_LEN: usize = 1024;
+
+/// Returns true if `value` can be written as plain scalar and is read back as the same string
+fn is_plain_safe(value: &str) -> bool {
+	let Some(first) = value.chars().next() else {
+		return false;
+	};
+	if first.is_ascii_digit()
+		|| first.is_whitespace()
+		|| "-+.?:,[]{}#&*!|>'\"%@`~".contains(first)
+		|| value.ends_with(char::is_whitespace)
+		|| value.ends_with(':')
+		|| value.contains(": ")
+		|| value.contains(" #")
+		// Merge key of YAML 1.1
+		|| value == "<<"
+		|| value
+			.chars()
+			.any(|c| c.is_control() || matches!(c, '\u{85}' | '\u{2028}' | '\u{2029}' | '\u{feff}'))
+	{
+		return false;
+	}
+	// Booleans and null of YAML 1.1 and 1.2
+	const RESERVED: &[&str] = &["null", "true", "false", "yes", "no", "on", "off", "y", "n"];
+	!RESERVED
+		.iter()
+		.any(|reserved| value.eq_ignore_ascii_case(reserved))
+}
+
+impl YamlWriter<'_> {
+	/// Creates a new YAML writer that appends to `buffer`
+	pub fn new(buffer: &mut String) -> YamlWriter<'_> {
+		YamlWriter {
+			buffer,
+			stack: Vec::new(),
+			slot: Slot::Root,
+			string: String::new(),
+		}
+	}
+
+	fn write_indent(&mut self, indent: usize) {
+		self.buffer.push('\n');
+		for _ in 0..indent {
+			self.buffer.push(' ');
+		}
+	}
+
+	fn write_scalar_string(&mut self, value: &str) {
+		if is_plain_safe(value) {
+			self.buffer.push_str(value);
+		} else {
+			// YAML allows '/' in double-quoted scalars, it does not need the escape of JSON
+			self.buffer.push('"');
+			for (index, part) in value.split('/').enumerate() {
+				if index > 0 {
+					self.buffer.push('/');
+				}
+				write_part_of_string(self.buffer, part);
+			}
+			self.buffer.push('"');
+		}
+	}
+
+	/// Writes the separator between the key and the value
+	fn begin_value(&mut self) {
+		if let Slot::Key = self.slot {
+			self.buffer.push(' ');
+		}
+	}
+
+	/// Ends the line after a top-level value
+	fn end_value(&mut self) {
+		if self.stack.is_empty() {
+			self.buffer.push('\n');
+			self.slot = Slot::Root;
+		}
+	}
+
+	/// Writes the line break and indentation in front of the key or dash of an entry
+	fn begin_entry(&mut self, first: bool) {
+		let frame = *self
+			.stack
+			.last()
+			.expect("YamlWriter: entry outside of a mapping or sequence");
+		match (first, frame.slot) {
+			// The first entry is written on the same line as the parent's dash, or at the start of the document
+			(true, Slot::Root | Slot::Item) => {}
+			_ => self.write_indent(frame.indent),
+		}
+	}
+
+	fn begin_container(&mut self) {
+		let indent = match self.stack.last() {
+			Some(parent) => parent.indent + 2,
+			None => 0,
+		};
+		self.stack.push(Frame {
+			indent,
+			slot: self.slot,
+		});
+	}
+
+	fn end_container(&mut self, empty: bool, flow: &str) {
+		let frame = self
+			.stack
+			.pop()
+			.expect("YamlWriter: unbalanced end of mapping or sequence");
+		if empty {
+			self.slot = frame.slot;
+			self.begin_value();
+			self.buffer.push_str(flow);
+		}
+		self.end_value();
+	}
+}
+
+impl JSONWriter for YamlWriter<'_> {
+	fn json_null(&mut self) {
+		self.json_fragment("null");
+	}
+
+	fn json_bool(&mut self, value: bool) {
+		self.json_fragment(if value { "true" } else { "false" });
+	}
+
+	fn json_string(&mut self, value: &str) {
+		self.begin_value();
+		self.write_scalar_string(value);
+		self.end_value();
+	}
+
+	fn json_string_part(&mut self, value: &str) {
+		self.string.push_str(value);
+	}
+
+	fn json_number_str(&mut self, value: &str) {
+		self.json_fragment(value);
+	}
+
+	fn json_begin_object(&mut self) {
+		self.begin_container();
+	}
+
+	fn json_end_object(&mut self, empty: bool) {
+		self.end_container(empty, "{}");
+	}
+
+	fn json_begin_array(&mut self) {
+		self.begin_container();
+	}
+
+	fn json_end_array(&mut self, empty: bool) {
+		self.end_container(empty, "[]");
+	}
+
+	fn json_begin_string(&mut self) {
+		self.string.clear();
+	}
+
+	fn json_end_string(&mut self) {
+		let string = core::mem::take(&mut self.string);
+		self.json_string(&string);
+		self.string = string;
+	}
+
+	fn json_begin_array_value(&mut self, first: bool) {
+		self.begin_entry(first);
+		self.buffer.push_str("- ");
+		self.slot = Slot::Item;
+	}
+
+	fn json_object_key(&mut self, key: &str, first: bool) {
+		self.begin_entry(first);
+		let start = self.buffer.len();
+		self.write_scalar_string(key);
+		// The limit includes quotes and escapes
+		let written = &self.buffer[start..];
+		if written.len() > MAX_IMPLICIT_KEY_LEN && written.chars().count() > MAX_IMPLICIT_KEY_LEN {
+			self.buffer.insert_str(start, "? ");
+			let indent = self.stack.last().map_or(0, |frame| frame.indent);
+			self.write_indent(indent);
+		}
+		self.buffer.push(':');
+		self.slot = Slot::Key;
+	}
+
+	fn json_fragment(&mut self, value: &str) {
+		self.begin_value();
+		self.buffer.push_str(value);
+		self.end_value();
+	}
+
+	fn json_debug_len(&self) -> Option<usize> {
+		Some(self.buffer.len())
+	}
+
+	fn json_reserve(&mut self, additional: usize) {
+		self.buffer.reserve(additional);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{JSONArrayWriter, JSONObjectWriter};
+
+	fn write_document<W: JSONWriter>(writer: &mut W) {
+		let mut object = JSONObjectWriter::new(writer);
+		object.value("name", "json-writer");
+		object.value("version", 4);
+		object.value("ratio", 0.5);
+		object.value("missing", ());
+		object.value("enabled", false);
+		object.value("multi\nline key", "a\tb\n\"c\"");
+		let mut list = object.array("list");
+		list.value(1);
+		list.value("two");
+		list.array().value("nested");
+		list.object();
+		let mut item = list.object();
+		item.value("a", 1);
+		item.object("b").value("c", [1, 2].as_slice());
+		item.value("d", "e");
+		item.end();
+		list.array();
+		list.end();
+		let mut tricky = object.array("tricky");
+		for value in [
+			"",
+			"yes",
+			"No",
+			"ON",
+			"y",
+			"null",
+			"~",
+			"true",
+			"1e2",
+			"0x10",
+			"-1",
+			".5",
+			"1.0",
+			"a: b",
+			"a:b",
+			"key:",
+			"a #b",
+			"a#b",
+			"#a",
+			"- a",
+			"[a]",
+			"{a}",
+			"*a",
+			"&a",
+			"!a",
+			"|",
+			">",
+			"'a'",
+			"\"a\"",
+			"%a",
+			"@a",
+			"`a",
+			" a",
+			"a ",
+			"ünïcödé",
+			"a\u{2028}b",
+			"\u{feff}a",
+		] {
+			tricky.value(value);
+		}
+		tricky.end();
+		object.value("empty", "");
+		object.object("yes").value("on", "off");
+		object.value("<<", "a/b");
+		object.value("/", "\"/\"");
+		object.value(&"k".repeat(1024), 1);
+		object.value(&"k".repeat(1025), 2);
+		object.value(&"\n".repeat(600), 3);
+		object
+			.array(&"l".repeat(2000))
+			.object()
+			.value(&"n".repeat(1500), [1].as_slice());
+		let mut string = object.string_writer("streamed");
+		string.push_str("1");
+		string.push_str("e2");
+		string.end();
+	}
+
+	#[test]
+	fn test_yaml_matches_json() {
+		let mut json = String::new();
+		write_document(&mut json);
+		let mut yaml = String::new();
+		write_document(&mut YamlWriter::new(&mut yaml));
+
+		let expected: serde_json::Value = serde_json::from_str(&json).unwrap();
+		let parsed: serde_json::Value = serde_yaml::from_str(&yaml).unwrap();
+		assert_eq!(parsed, expected, "{yaml}");
+	}
+
+	#[test]
+	fn test_yaml_layout() {
+		let mut buffer = String::new();
+		let mut writer = YamlWriter::new(&mut buffer);
+		let mut array = JSONArrayWriter::new(&mut writer);
+		array.array().value(1);
+		let mut nested = array.array();
+		nested.value("a");
+		nested.value("b");
+		nested.end();
+		let mut object = array.object();
+		object.value("a", 1);
+		object.array("b").value(2);
+		object.object("c").value("d", 3);
+		object.end();
+		array.end();
+		assert_eq!(
+			buffer,
+			"- - 1\n- - a\n  - b\n- a: 1\n  b:\n    - 2\n  c:\n    d: 3\n"
+		);
+
+		let mut buffer = String::new();
+		let mut writer = YamlWriter::new(&mut buffer);
+		JSONObjectWriter::new(&mut writer);
+		JSONArrayWriter::new(&mut writer);
+		crate::JSONWriterValue::write_json("yes", &mut writer);
+		crate::JSONWriterValue::write_json(1.5, &mut writer);
+		assert_eq!(buffer, "{}\n[]\n\"yes\"\n1.5\n");
+
+		let mut buffer = String::new();
+		let mut writer = YamlWriter::new(&mut buffer);
+		let mut object = JSONObjectWriter::new(&mut writer);
+		object.value("<<", "/a/");
+		object.value("a/b", "'/'");
+		object.object(&"k".repeat(1025)).value("a", 1);
+		object.end();
+		let long_key = "k".repeat(1025);
+		assert_eq!(
+			buffer,
+			format!("\"<<\": /a/\na/b: \"'/'\"\n? {long_key}\n:\n  a: 1\n")
+		);
+	}
+}
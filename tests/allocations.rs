@@ -0,0 +1,77 @@
+//! Tests that count heap allocations, in a separate binary because they replace the global allocator.
+
+use json_writer::{JSONWriterValue, KeyCase, SortedKeys};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+struct CountingAllocator;
+
+thread_local! {
+	static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.set(ALLOCATIONS.get() + 1);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout);
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.set(ALLOCATIONS.get() + 1);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations of the current thread while running `f`
+fn count_allocations(f: impl FnOnce()) -> usize {
+	let before = ALLOCATIONS.get();
+	f();
+	ALLOCATIONS.get() - before
+}
+
+#[test]
+fn test_sorted_keys_small_map_does_not_allocate() {
+	let mut map = HashMap::new();
+	map.insert("delta", 4u32);
+	map.insert("alpha", 1);
+	map.insert("charlie", 3);
+	map.insert("bravo", 2);
+
+	let mut buffer = String::with_capacity(100);
+	let allocations = count_allocations(|| SortedKeys(&map).write_json(&mut buffer));
+	assert_eq!(
+		buffer,
+		"{\"alpha\":1,\"bravo\":2,\"charlie\":3,\"delta\":4}"
+	);
+	assert_eq!(allocations, 0);
+
+	let large: HashMap<String, u32> = (0..100).map(|i| (i.to_string(), i)).collect();
+	let mut buffer = String::with_capacity(2000);
+	let allocations = count_allocations(|| SortedKeys(&large).write_json(&mut buffer));
+	assert_eq!(allocations, 1);
+}
+
+#[test]
+fn test_key_case_convert_borrows_without_allocating() {
+	let allocations = count_allocations(|| {
+		assert_eq!(KeyCase::CamelCase.convert("userId"), "userId");
+		assert_eq!(KeyCase::SnakeCase.convert("http_server"), "http_server");
+		assert_eq!(
+			KeyCase::KebabCase.convert("ünïcödé-straße"),
+			"ünïcödé-straße"
+		);
+	});
+	assert_eq!(allocations, 0);
+	let allocations = count_allocations(|| {
+		assert_eq!(KeyCase::CamelCase.convert("user_id"), "userId");
+	});
+	assert_eq!(allocations, 1);
+}
@@ -0,0 +1,52 @@
+//! Tests of `#[derive(JSONEnumString)]`, run with `--features derive`.
+
+use json_writer::{to_json_string, JSONEnumString, JSONObjectWriter};
+use std::collections::BTreeMap;
+
+#[derive(Copy, Clone, Debug, JSONEnumString)]
+#[json(rename_all = "SCREAMING_SNAKE_CASE")]
+enum Level {
+	Info,
+	WarnOnce,
+	#[json(rename = "err")]
+	Error,
+}
+
+#[derive(Copy, Clone, JSONEnumString)]
+enum Plain {
+	KeepAsIs,
+}
+
+#[test]
+fn test_renamed_variants() {
+	assert_eq!(Level::Info.as_json_str(), "INFO");
+	assert_eq!(Level::WarnOnce.as_json_str(), "WARN_ONCE");
+	assert_eq!(Level::Error.as_json_str(), "err");
+	assert_eq!(to_json_string(Level::WarnOnce), "\"WARN_ONCE\"");
+	assert_eq!(to_json_string(Plain::KeepAsIs), "\"KeepAsIs\"");
+}
+
+#[test]
+fn test_composes_with_containers() {
+	let mut levels = BTreeMap::new();
+	levels.insert("db", Level::Error);
+	levels.insert("http", Level::Info);
+	assert_eq!(to_json_string(&levels), r#"{"db":"err","http":"INFO"}"#);
+
+	let list = [Level::Info, Level::WarnOnce];
+	assert_eq!(to_json_string(&list[..]), r#"["INFO","WARN_ONCE"]"#);
+	assert_eq!(to_json_string(None::<Level>), "null");
+
+	let mut buffer = String::new();
+	let mut object = JSONObjectWriter::new(&mut buffer);
+	object.value("level", Some(Level::Error));
+	object.value("http", levels.get("http"));
+	object.end();
+	assert_eq!(buffer, r#"{"level":"err","http":"INFO"}"#);
+}
+
+#[test]
+fn test_compile_errors() {
+	let tests = trybuild::TestCases::new();
+	tests.compile_fail("tests/ui/*.rs");
+}
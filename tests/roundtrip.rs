@@ -0,0 +1,89 @@
+//! Property tests that parse the written strings back with serde_json.
+
+use json_writer::{to_json_string, CharsValue, JSONObjectWriter, PrettyJSONWriter};
+use proptest::prelude::*;
+
+/// Characters close to the edges of the escaping rules
+const SPECIAL_CHARS: &[char] = &[
+	'\0', '\u{8}', '\t', '\n', '\u{c}', '\r', '\u{1f}', ' ', '"', '/', '\\', '\u{7f}', '\u{80}',
+	'é', '\u{7ff}', '\u{800}', '中', '\u{2028}', '\u{2029}', '\u{feff}', '\u{ffff}', '😀',
+];
+
+/// Arbitrary strings with a high share of characters that are escaped or encoded with several bytes
+fn json_string() -> impl Strategy<Value = String> {
+	prop_oneof![
+		any::<String>(),
+		prop::collection::vec(
+			prop_oneof![
+				3 => prop::sample::select(SPECIAL_CHARS),
+				1 => prop::char::range('a', 'z'),
+				1 => any::<char>(),
+			],
+			0..80,
+		)
+		.prop_map(String::from_iter),
+	]
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(2000))]
+
+	#[test]
+	fn string_value_roundtrip(value in json_string()) {
+		let json = to_json_string(value.as_str());
+		prop_assert_eq!(serde_json::from_str::<String>(&json).unwrap(), value);
+	}
+
+	#[test]
+	fn bytes_string_value_roundtrip(value in json_string()) {
+		let mut json = Vec::new();
+		let mut object = JSONObjectWriter::new(&mut json);
+		object.value(&value, value.as_str());
+		object.end();
+		let parsed: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&json).unwrap();
+		prop_assert_eq!(parsed.get(&value), Some(&serde_json::Value::String(value.clone())));
+	}
+
+	#[test]
+	fn pretty_string_value_roundtrip(value in json_string()) {
+		let mut json = String::new();
+		let mut pretty = PrettyJSONWriter::new(&mut json);
+		let mut object = JSONObjectWriter::new(&mut pretty);
+		object.value(&value, value.as_str());
+		object.end();
+		let parsed: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json).unwrap();
+		prop_assert_eq!(parsed.get(&value), Some(&serde_json::Value::String(value.clone())));
+	}
+
+	#[test]
+	fn chunked_string_value_roundtrip(value in json_string(), splits in prop::collection::vec(any::<prop::sample::Index>(), 0..8)) {
+		// Splits the string at arbitrary char boundaries, including right before and after escaped characters
+		let boundaries: Vec<usize> = value.char_indices().map(|(index, _)| index).collect();
+		let mut splits: Vec<usize> = if boundaries.is_empty() {
+			Vec::new()
+		} else {
+			splits.iter().map(|split| boundaries[split.index(boundaries.len())]).collect()
+		};
+		splits.sort_unstable();
+		splits.push(value.len());
+
+		let mut json = String::new();
+		let mut object = JSONObjectWriter::new(&mut json);
+		let mut string = object.string_writer("value");
+		let mut start = 0;
+		for split in splits {
+			string.push_str(&value[start..split]);
+			start = split;
+		}
+		drop(string);
+		object.end();
+		let parsed: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json).unwrap();
+		prop_assert_eq!(&parsed["value"], &serde_json::Value::String(value.clone()));
+	}
+
+	#[test]
+	fn chars_value_roundtrip(value in json_string()) {
+		let json = to_json_string(CharsValue(value.chars()));
+		prop_assert_eq!(serde_json::from_str::<String>(&json).unwrap(), value);
+	}
+}
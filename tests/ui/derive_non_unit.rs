@@ -0,0 +1,9 @@
+use json_writer::JSONEnumString;
+
+#[derive(JSONEnumString)]
+enum Shape {
+	Point,
+	Circle(f64),
+}
+
+fn main() {}
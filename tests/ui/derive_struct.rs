@@ -0,0 +1,8 @@
+use json_writer::JSONEnumString;
+
+#[derive(JSONEnumString)]
+struct Level {
+	name: &'static str,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use json_writer::JSONEnumString;
+
+#[derive(JSONEnumString)]
+#[json(rename_all = "Title Case")]
+enum Level {
+	Info,
+}
+
+fn main() {}